@@ -0,0 +1,199 @@
+use crate::io;
+use crate::reconstruction::Kernel;
+use anyhow::{anyhow, Context};
+use clap::{value_parser, ValueEnum};
+use log::{info, warn};
+use splashsurf_lib::nalgebra::Vector3;
+use splashsurf_lib::neighborhood_search::{self, compute_neigborhood_stats};
+use splashsurf_lib::{density_map, profile, Aabb3d, ParticleProperties};
+use std::path::PathBuf;
+
+/// Command line arguments for the `info` subcommand
+#[derive(Clone, Debug, clap::Parser)]
+pub struct InfoSubcommandArgs {
+    /// Path to the input file with particles to analyze (supported formats: .vtk, .vtu, .bgeo, .ply, .xyz, .json)
+    #[arg(value_parser = value_parser!(PathBuf))]
+    input_file: PathBuf,
+    /// The particle radius used for the neighborhood and density diagnostics (by default the suggested radius based on the estimated particle spacing)
+    #[arg(short = 'r', long)]
+    particle_radius: Option<f64>,
+    /// The smoothing length used for the neighborhood and density diagnostics in multiplies of the particle radius (by default the generic default smoothing length)
+    #[arg(short = 'l', long)]
+    smoothing_length: Option<f64>,
+    /// The SPH kernel function used for the density diagnostics, should match the kernel of the reconstruction
+    #[arg(
+        long,
+        default_value = "cubic-spline",
+        value_name = "cubic-spline|wendland-c2|wendland-c4|wendland-c6|poly6|spiky|gaussian",
+        ignore_case = true,
+        require_equals = true
+    )]
+    kernel: Kernel,
+    /// The rest density of the fluid
+    #[arg(long, default_value = "1000.0")]
+    rest_density: f64,
+}
+
+/// Suggested particle radius in multiplies of half the particle spacing (see the recommended settings in the README)
+const SUGGESTED_RADIUS_FACTOR: f64 = 1.5;
+/// Generic default smoothing length in multiplies of the particle radius (independent of the particle data)
+const DEFAULT_SMOOTHING_LENGTH: f64 = 1.2;
+/// Generic default marching cubes cube size in multiplies of the particle radius (independent of the particle data)
+const DEFAULT_CUBE_SIZE: f64 = 0.5;
+/// Suggested iso-surface threshold as fraction of the median particle density of the diagnostics
+const SUGGESTED_SURFACE_THRESHOLD_FRACTION: f64 = 0.6;
+
+/// Executes the `info` subcommand
+pub fn info_subcommand(cmd_args: &InfoSubcommandArgs) -> Result<(), anyhow::Error> {
+    profile!("particle info cli");
+
+    let io_params = io::FormatParameters::default();
+    let input_file = &cmd_args.input_file;
+
+    let particle_positions: Vec<Vector3<f64>> =
+        io::read_particle_positions(input_file.as_path(), &io_params.input).with_context(|| {
+            format!(
+                "Failed to load particle positions from file \"{}\"",
+                input_file.as_path().display()
+            )
+        })?;
+
+    if particle_positions.is_empty() {
+        return Err(anyhow!("The input file does not contain any particles"));
+    }
+
+    // Attributes are only listed, so a file with unsupported attributes should not abort the analysis
+    let attribute_names =
+        match splashsurf_lib::io::point_attribute_names_from_file(input_file.as_path()) {
+            Ok(names) => names,
+            Err(err) => {
+                warn!(
+                    "Failed to read the attribute names of the input file: {}",
+                    err
+                );
+                Vec::new()
+            }
+        };
+
+    let aabb = Aabb3d::par_from_points(&particle_positions);
+
+    println!("Particles:             {}", particle_positions.len());
+    println!(
+        "Bounding box:          [{}, {}, {}] to [{}, {}, {}]",
+        aabb.min().x,
+        aabb.min().y,
+        aabb.min().z,
+        aabb.max().x,
+        aabb.max().y,
+        aabb.max().z
+    );
+    if attribute_names.is_empty() {
+        println!("Attributes:            -");
+    } else {
+        println!("Attributes:            {}", attribute_names.join(", "));
+    }
+
+    let particle_spacing =
+        neighborhood_search::estimate_particle_spacing::<i64, f64>(&particle_positions, true);
+    let particle_spacing = match particle_spacing {
+        Ok(particle_spacing) => particle_spacing,
+        Err(err) => {
+            println!("Particle spacing:      - ({})", err);
+            return Ok(());
+        }
+    };
+    println!(
+        "Particle spacing:      {} (median distance to the nearest neighbor)",
+        particle_spacing
+    );
+
+    let suggested_radius = SUGGESTED_RADIUS_FACTOR * 0.5 * particle_spacing;
+    let particle_radius = cmd_args.particle_radius.unwrap_or(suggested_radius);
+    let smoothing_length = cmd_args
+        .smoothing_length
+        .unwrap_or(DEFAULT_SMOOTHING_LENGTH);
+    let compact_support_radius = 2.0 * smoothing_length * particle_radius;
+
+    let kernel_name = cmd_args
+        .kernel
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default();
+
+    // Neighborhood and density diagnostics for the selected parameters
+    info!(
+        "Computing neighborhood and density diagnostics with particle radius {}, smoothing length {} and kernel {}...",
+        particle_radius, smoothing_length, kernel_name
+    );
+    let mut domain = aabb.clone();
+    domain.grow_uniformly(compact_support_radius);
+    let neighbor_lists = neighborhood_search::search::<i64, f64>(
+        &domain,
+        &particle_positions,
+        compact_support_radius,
+        true,
+    );
+    let neighborhood_stats = compute_neigborhood_stats(&neighbor_lists);
+
+    let particle_rest_volume = (4.0 / 3.0) * std::f64::consts::PI * particle_radius.powi(3);
+    let particle_rest_mass = particle_rest_volume * cmd_args.rest_density;
+    let particle_densities = density_map::compute_particle_densities::<i64, f64>(
+        &particle_positions,
+        &neighbor_lists,
+        compact_support_radius,
+        cmd_args.kernel.into(),
+        particle_rest_mass,
+        ParticleProperties::uniform(),
+        true,
+    );
+    let mut relative_densities = particle_densities
+        .iter()
+        .map(|density| density / cmd_args.rest_density)
+        .collect::<Vec<_>>();
+    relative_densities.sort_unstable_by(|a, b| a.total_cmp(b));
+    let quantile =
+        |q: f64| relative_densities[((relative_densities.len() - 1) as f64 * q).round() as usize];
+
+    println!(
+        "Diagnostics for particle radius {}, smoothing length {} and kernel {}:",
+        particle_radius, smoothing_length, kernel_name
+    );
+    println!(
+        "  Neighbors:           avg: {:.2}, max: {}, particles without neighbors: {}",
+        neighborhood_stats.avg_neighbors,
+        neighborhood_stats.max_neighbors,
+        particle_positions.len() - neighborhood_stats.particles_with_neighbors
+    );
+    println!(
+        "  Density / rest:      min: {:.3}, 5%: {:.3}, 25%: {:.3}, median: {:.3}, 75%: {:.3}, 95%: {:.3}, max: {:.3}",
+        quantile(0.0),
+        quantile(0.05),
+        quantile(0.25),
+        quantile(0.5),
+        quantile(0.75),
+        quantile(0.95),
+        quantile(1.0)
+    );
+
+    // The reconstructed density of the fluid interior is close to the median particle density, so the
+    // threshold is chosen relative to it instead of the rest density (e.g. if the radius does not match the spacing)
+    let suggested_surface_threshold = SUGGESTED_SURFACE_THRESHOLD_FRACTION * quantile(0.5);
+
+    println!("Suggested parameters:");
+    println!(
+        "  --particle-radius={}    ({} times half the particle spacing)",
+        suggested_radius, SUGGESTED_RADIUS_FACTOR
+    );
+    println!(
+        "  --smoothing-length={}    (generic default)",
+        DEFAULT_SMOOTHING_LENGTH
+    );
+    println!("  --cube-size={}    (generic default)", DEFAULT_CUBE_SIZE);
+    println!(
+        "  --surface-threshold={:.3}    ({} times the median density of the diagnostics)",
+        suggested_surface_threshold, SUGGESTED_SURFACE_THRESHOLD_FRACTION
+    );
+    println!("  --kernel={}", kernel_name);
+
+    Ok(())
+}
@@ -1,6 +1,5 @@
-use crate::io::vtk_format::VtkFile;
 use anyhow::{anyhow, Context};
-use log::{info, warn};
+use log::info;
 use splashsurf_lib::mesh::MeshAttribute;
 use splashsurf_lib::nalgebra::Vector3;
 use splashsurf_lib::Real;
@@ -9,7 +8,6 @@ use splashsurf_lib::{
     mesh::{Mesh3d, MeshWithData, TriMesh3d},
     vtkio::model::DataSet,
 };
-use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::Path;
@@ -78,8 +76,36 @@ pub fn read_particle_positions_with_attributes<R: Real, P: AsRef<Path>>(
     attribute_names: &[String],
     format_params: &InputFormatParameters,
 ) -> Result<(Vec<Vector3<R>>, Vec<MeshAttribute<R>>), anyhow::Error> {
-    if attribute_names.is_empty() {
-        return read_particle_positions(input_file, format_params).map(|p| (p, Vec::new()));
+    read_particle_positions_with_optional_attributes(
+        input_file,
+        attribute_names,
+        &[],
+        format_params,
+    )
+    .map(|(particle_positions, attributes, _)| (particle_positions, attributes))
+}
+
+/// Tries to read particle positions, attributes with the given names and all optional attributes that exist in the specified file
+///
+/// In contrast to the attributes in `attribute_names`, optional attributes that are missing in the file are skipped.
+/// Files in formats without support for attributes are loaded without any optional attributes.
+/// Returns the positions, the attributes and the optional attributes that were found in the file.
+pub fn read_particle_positions_with_optional_attributes<R: Real, P: AsRef<Path>>(
+    input_file: P,
+    attribute_names: &[String],
+    optional_attribute_names: &[String],
+    format_params: &InputFormatParameters,
+) -> Result<
+    (
+        Vec<Vector3<R>>,
+        Vec<MeshAttribute<R>>,
+        Vec<MeshAttribute<R>>,
+    ),
+    anyhow::Error,
+> {
+    if attribute_names.is_empty() && optional_attribute_names.is_empty() {
+        return read_particle_positions(input_file, format_params)
+            .map(|p| (p, Vec::new(), Vec::new()));
     }
 
     let input_file = input_file.as_ref();
@@ -88,73 +114,30 @@ pub fn read_particle_positions_with_attributes<R: Real, P: AsRef<Path>>(
         input_file.display()
     );
 
-    profile!("loading particle positions and attributes");
-
-    // Check file extension: only VTK is supported for reading attributes at the moment
-    {
-        let extension = input_file.extension().ok_or(anyhow!(
-            "Unable to detect file format of particle input file (file name has to end with supported extension)",
-        ))?.to_str().ok_or(anyhow!("Invalid extension of input file"))?.to_lowercase();
-
-        match extension.as_str() {
-            "vtk" | "vtu" => {}
-            _ => {
-                return Err(anyhow!(
-                    "Unsupported file format extension \"{}\" for reading particles and attributes",
-                    extension
-                ));
-            }
-        }
-    }
-
-    let vtk_pieces = VtkFile::load_file(input_file)
-        .map(|f| f.into_pieces())
-        .with_context(|| format!("Failed to load particle positions from file"))?;
-
-    if vtk_pieces.len() > 1 {
-        warn!("VTK file contains more than one \"piece\". Only the first one will be loaded.");
-    }
-
-    let first_piece = vtk_pieces
-        .into_iter()
-        .next()
-        .ok_or(anyhow!("VTK file does not contain a supported \"piece\"."))?;
-
-    // Load particles
-    let particle_positions = first_piece.load_as_particles()?;
-
-    // Load attributes that should be interpolated
-    let attributes = {
-        // Check if all attributes to interpolate are present in the input file
-        {
-            let attributes_to_interpolate = attribute_names.iter().cloned().collect::<HashSet<_>>();
-            let attributes = first_piece
-                .point_attribute_names()
-                .into_iter()
-                .collect::<HashSet<_>>();
-
-            let missing_attributes = attributes_to_interpolate
-                .difference(&attributes)
-                .cloned()
-                .collect::<Vec<_>>();
-            if !missing_attributes.is_empty() {
-                return Err(anyhow!(
-                    "Missing attribute(s) \"{}\" in input file",
-                    missing_attributes.join("\", \""),
-                ));
-            }
-        }
-
-        first_piece.load_point_attributes::<R>(attribute_names)
-    }?;
+    let (particle_positions, mut attributes) = {
+        profile!("loading particle positions and attributes");
+        io::particles_with_optional_attributes_from_file(
+            input_file,
+            attribute_names,
+            optional_attribute_names,
+        )
+        .with_context(|| format!("Failed to load particle positions and attributes from file"))?
+    };
+    let optional_attributes = attributes.split_off(attribute_names.len());
 
     info!(
-        "Successfully loaded point {} attribute(s): \"{}\"",
-        attributes.len(),
-        attribute_names.join("\", \"")
+        "Successfully loaded {} particle positions and {} point attribute(s): \"{}\"",
+        particle_positions.len(),
+        attributes.len() + optional_attributes.len(),
+        attributes
+            .iter()
+            .chain(optional_attributes.iter())
+            .map(|attribute| attribute.name.as_str())
+            .collect::<Vec<_>>()
+            .join("\", \"")
     );
 
-    Ok((particle_positions, attributes))
+    Ok((particle_positions, attributes, optional_attributes))
 }
 
 /// Writes particles positions to the given file path, automatically detects the file format
@@ -162,6 +145,16 @@ pub fn write_particle_positions<R: Real, P: AsRef<Path>>(
     particles: &[Vector3<R>],
     output_file: P,
     format_params: &OutputFormatParameters,
+) -> Result<(), anyhow::Error> {
+    write_particle_positions_with_attributes(particles, &[], output_file, format_params)
+}
+
+/// Writes particles positions together with point attributes to the given file path, automatically detects the file format
+pub fn write_particle_positions_with_attributes<R: Real, P: AsRef<Path>>(
+    particles: &[Vector3<R>],
+    attributes: &[MeshAttribute<R>],
+    output_file: P,
+    format_params: &OutputFormatParameters,
 ) -> Result<(), anyhow::Error> {
     let output_file = output_file.as_ref();
     info!(
@@ -178,13 +171,18 @@ pub fn write_particle_positions<R: Real, P: AsRef<Path>>(
             .ok_or(anyhow!("Invalid extension of output file"))?;
 
         match extension.to_lowercase().as_str() {
-            "vtk" => vtk_format::particles_to_vtk(particles, &output_file),
-            "bgeo" => bgeo_format::particles_to_bgeo(
+            "vtk" => {
+                vtk_format::particles_with_attributes_to_vtk(particles, attributes, output_file)
+            }
+            "bgeo" => bgeo_format::particles_with_attributes_to_bgeo(
                 particles,
-                &output_file,
+                attributes,
+                output_file,
                 format_params.enable_compression,
             ),
-            "json" => json_format::particles_to_json(particles, &output_file),
+            "json" => {
+                json_format::particles_with_attributes_to_json(particles, attributes, output_file)
+            }
             _ => Err(anyhow!(
                 "Unsupported file format extension \"{}\" for writing particles",
                 extension
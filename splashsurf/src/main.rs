@@ -1,6 +1,8 @@
 mod convert;
+mod info;
 mod io;
 mod reconstruction;
+mod stats;
 #[macro_use]
 mod allocator;
 mod logging;
@@ -52,6 +54,12 @@ enum Subcommand {
     /// Convert particle or mesh files between different file formats
     #[command(help_template = HELP_TEMPLATE)]
     Convert(convert::ConvertSubcommandArgs),
+    /// Print information about a particle file and suggest reconstruction parameters
+    #[command(help_template = HELP_TEMPLATE)]
+    Info(info::InfoSubcommandArgs),
+    /// Print quality metrics and topological properties of a surface mesh
+    #[command(help_template = HELP_TEMPLATE)]
+    Stats(stats::StatsSubcommandArgs),
 }
 
 fn main() -> Result<(), anyhow::Error> {
@@ -85,6 +93,8 @@ fn run_splashsurf() -> Result<(), anyhow::Error> {
     match &cmd_args.subcommand {
         Subcommand::Reconstruct(cmd_args) => reconstruction::reconstruct_subcommand(cmd_args)?,
         Subcommand::Convert(cmd_args) => convert::convert_subcommand(cmd_args)?,
+        Subcommand::Info(cmd_args) => info::info_subcommand(cmd_args)?,
+        Subcommand::Stats(cmd_args) => stats::stats_subcommand(cmd_args)?,
     }
 
     // Write coarse_prof stats using log::info
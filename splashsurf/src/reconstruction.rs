@@ -1,19 +1,42 @@
 use crate::{io, logging};
 use anyhow::{anyhow, Context};
 use arguments::{
-    ReconstructionRunnerArgs, ReconstructionRunnerPathCollection, ReconstructionRunnerPaths,
+    ReconstructionRunnerArgs, ReconstructionRunnerClassificationArgs,
+    ReconstructionRunnerDiffuseArgs, ReconstructionRunnerMotionVectorArgs,
+    ReconstructionRunnerMultiphaseArgs, ReconstructionRunnerPathCollection,
+    ReconstructionRunnerPaths, ReconstructionRunnerPostprocessingArgs,
+    ReconstructionRunnerTrackingArgs,
 };
 use clap::value_parser;
 use indicatif::{ProgressBar, ProgressStyle};
-use log::info;
+use log::{info, warn};
 use rayon::prelude::*;
-use splashsurf_lib::mesh::{AttributeData, Mesh3d, MeshAttribute, MeshWithData, PointCloud3d};
+use splashsurf_lib::boundary::BoundaryHandling;
+use splashsurf_lib::diffuse_particles::generate_diffuse_particles_with_parameters;
+use splashsurf_lib::mesh::{
+    AttributeData, Mesh3d, MeshAttribute, MeshWithData, PointCloud3d, TriMesh3d,
+};
+use splashsurf_lib::multiphase::{
+    phase_ids_from_attribute, reconstruct_multiphase_surfaces, PhaseReconstructionMode,
+};
 use splashsurf_lib::nalgebra::{Unit, Vector3};
+use splashsurf_lib::neighborhood_search::estimate_particle_radius;
+use splashsurf_lib::particle_classification::classify_particles_with_parameters;
+use splashsurf_lib::postprocessing::{
+    compute_mesh_components, convert_tris_to_quads, decimate_mesh, filter_mesh_components,
+    smooth_mesh, smooth_mesh_weighted, snap_vertices_to_grid, LaplacianWeights,
+};
 use splashsurf_lib::profile;
 use splashsurf_lib::sph_interpolation::SphInterpolator;
-use splashsurf_lib::{density_map, Index, Real};
+use splashsurf_lib::surface_tracking::{SurfaceTracker, SurfaceTrackingParameters};
+use splashsurf_lib::temporal::{
+    filter_particle_frames, finite_difference_velocities, ParticleFrame,
+};
+use splashsurf_lib::{
+    density_map, Index, IsoSurfaceExtraction, KernelType, ParticleProperties, Real, UniformGrid,
+};
 use std::convert::TryFrom;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 // TODO: Detect smallest index type (i.e. check if ok to use i32 as index)
 
@@ -23,6 +46,8 @@ static ARGS_ADV: &str = "Advanced parameters";
 static ARGS_OCTREE: &str = "Octree (domain decomposition) parameters";
 static ARGS_DEBUG: &str = "Debug options";
 static ARGS_INTERP: &str = "Interpolation";
+static ARGS_DIFFUSE: &str = "Diffuse particles";
+static ARGS_POSTPROC: &str = "Postprocessing";
 static ARGS_OTHER: &str = "Remaining options";
 
 /// Command line arguments for the `reconstruct` subcommand
@@ -45,22 +70,164 @@ pub struct ReconstructSubcommandArgs {
     /// Index of the last input file to process when processing a sequence of files (default: highest index of the sequence)
     #[arg(help_heading = ARGS_IO, short = 'e', long)]
     pub end_index: Option<usize>,
+    /// Optional filename for writing the particles with their classification into interior (0), surface (1) and isolated (2) particles as "class" attribute to disk (supported formats: VTK, BGEO, JSON), has to include "{}" as placeholder for the frame index when processing a sequence
+    #[arg(help_heading = ARGS_IO, long, value_parser = value_parser!(PathBuf))]
+    pub output_classified_particles: Option<PathBuf>,
+    /// Threshold for the color field gradient norm (normalized by the compact support radius) above which a particle is classified as surface particle
+    #[arg(
+        help_heading = ARGS_IO,
+        long,
+        default_value = "0.5",
+        requires = "output_classified_particles"
+    )]
+    pub classification_surface_threshold: f64,
+    /// Particles with at most this number of neighbors are classified as isolated particles
+    #[arg(
+        help_heading = ARGS_IO,
+        long,
+        default_value = "5",
+        requires = "output_classified_particles"
+    )]
+    pub classification_max_isolated_neighbors: usize,
 
-    /// The particle radius of the input data
+    /// The particle radius of the input data (required unless --auto-radius is enabled)
     #[arg(help_heading = ARGS_BASIC, short = 'r', long)]
-    pub particle_radius: f64,
+    pub particle_radius: Option<f64>,
+    /// Whether to estimate the particle radius from the nearest neighbor distances of the particles of the (first) input file instead of specifying it with --particle-radius
+    #[arg(
+        help_heading = ARGS_BASIC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub auto_radius: Switch,
+    /// Factor for the particle radius estimated by --auto-radius (in multiplies of half the median nearest neighbor distance), a radius slightly larger than the one of the simulation is recommended
+    #[arg(help_heading = ARGS_BASIC, long, default_value = "1.5")]
+    pub auto_radius_factor: f64,
     /// The rest density of the fluid
     #[arg(help_heading = ARGS_BASIC, long, default_value = "1000.0")]
     pub rest_density: f64,
     /// The smoothing length radius used for the SPH kernel, the kernel compact support radius will be twice the smoothing length (in multiplies of the particle radius)
     #[arg(help_heading = ARGS_BASIC, short = 'l', long)]
     pub smoothing_length: f64,
+    /// The SPH kernel function used for the density computation and interpolation, should match the kernel of the simulation
+    #[arg(
+        help_heading = ARGS_BASIC,
+        long,
+        default_value = "cubic-spline",
+        value_name = "cubic-spline|wendland-c2|wendland-c4|wendland-c6|poly6|spiky|gaussian",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub kernel: Kernel,
     /// The cube edge length used for marching cubes in multiplies of the particle radius, corresponds to the cell size of the implicit background grid
     #[arg(help_heading = ARGS_BASIC, short = 'c', long)]
     pub cube_size: f64,
     /// The iso-surface threshold for the density, i.e. the normalized value of the reconstructed density level that indicates the fluid surface (in multiplies of the rest density)
     #[arg(help_heading = ARGS_BASIC, short = 't', long, default_value = "0.6")]
     pub surface_threshold: f64,
+    /// Method used to extract the surface mesh from the density values on the background grid (surface nets require the subdomain grid or a global reconstruction, i.e. no octree decomposition)
+    #[arg(
+        help_heading = ARGS_ADV,
+        long,
+        default_value = "marching-cubes",
+        value_name = "marching-cubes|surface-nets",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub surface_extraction: SurfaceExtraction,
+    /// Whether to use anisotropic kernels that are stretched along the particle distribution for a sharper reconstruction of thin sheets and streams (not supported with the octree decomposition without the subdomain grid)
+    #[arg(
+        help_heading = ARGS_ADV,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub anisotropic_kernels: Switch,
+    /// Factor in [0, 1] for smoothing the kernel centers of anisotropic kernels towards the mean position of their neighbors
+    #[arg(help_heading = ARGS_ADV, long, default_value = "0.5")]
+    pub anisotropy_smoothing_factor: f64,
+    /// Maximum ratio between the largest and the smallest eigenvalue of the neighborhood covariance, limits the stretching of anisotropic kernels
+    #[arg(help_heading = ARGS_ADV, long, default_value = "4.0")]
+    pub anisotropy_max_eigenvalue_ratio: f64,
+    /// Minimum number of neighbors of a particle to use an anisotropic kernel, particles with fewer neighbors use isotropic kernels
+    #[arg(help_heading = ARGS_ADV, long, default_value = "10")]
+    pub anisotropy_min_neighbors: usize,
+    /// Whether to adaptively refine the background grid close to strongly curved parts of the surface, e.g. at thin sheets and droplets (only supported by the global reconstruction with surface nets, i.e. requires --surface-extraction=surface-nets, --octree-decomposition=off and --subdomain-grid=off)
+    #[arg(
+        help_heading = ARGS_ADV,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub adaptive_resolution: Switch,
+    /// Maximum number of subdivisions of a cell of the background grid with adaptive resolution, the smallest cells have an edge length of cube-size / 2^level
+    #[arg(help_heading = ARGS_ADV, long, default_value = "2")]
+    pub adaptive_max_level: usize,
+    /// Cells are subdivided if the density at their center deviates by more than this value (relative to the surface threshold) from the trilinear interpolation of their corners
+    #[arg(help_heading = ARGS_ADV, long, default_value = "0.1")]
+    pub adaptive_tolerance: f64,
+    /// Name of an integer point attribute of the input file with per particle phase ids, enables the reconstruction of one surface per phase that is written to "{output_name}_phase{k}" (requires a global reconstruction, i.e. no octree decomposition and no subdomain grid)
+    #[arg(help_heading = ARGS_ADV, long)]
+    pub phase_attribute: Option<String>,
+    /// Whether the surface of each phase is reconstructed separately (ignoring all other phases) or against all other phases (surfaces of adjacent phases share their interface, best used with a surface threshold of 0.5)
+    #[arg(
+        help_heading = ARGS_ADV,
+        long,
+        default_value = "separate",
+        value_name = "separate|against-others",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub phase_mode: PhaseMode,
+    /// Path to a surface mesh file (VTK or PLY) of a solid boundary (e.g. tank walls) with normals pointing into the fluid, the reconstructed surface is clipped and closed at the boundary (not supported with the octree decomposition without the subdomain grid)
+    #[arg(
+        help_heading = ARGS_ADV,
+        long,
+        value_parser = value_parser!(PathBuf),
+        conflicts_with = "boundary_particles"
+    )]
+    pub boundary_mesh: Option<PathBuf>,
+    /// Path to a particle file with boundary particles describing a solid boundary (e.g. tank walls), the reconstructed surface is clipped and closed at the boundary (not supported with the octree decomposition without the subdomain grid)
+    #[arg(help_heading = ARGS_ADV, long, value_parser = value_parser!(PathBuf))]
+    pub boundary_particles: Option<PathBuf>,
+    /// Radius of the boundary particles (in multiplies of the particle radius)
+    #[arg(help_heading = ARGS_ADV, long, default_value = "1.0")]
+    pub boundary_particle_radius: f64,
+    /// How the density field is modified at the solid boundary: cut at the boundary, extended into the solid by mirroring particles close to the boundary, or both
+    #[arg(
+        help_heading = ARGS_ADV,
+        long,
+        default_value = "extend-and-cut",
+        value_name = "cut|extend|extend-and-cut",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub boundary_handling: BoundaryMode,
+    /// Whether the boundary particles contribute to the densities of the fluid particles and to the density field (wall density correction), no surface is generated around the boundary particles on their own (best combined with --boundary-handling=cut, as mirrored particles compensate the density deficiency at the boundary as well)
+    #[arg(
+        help_heading = ARGS_ADV,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true,
+        requires = "boundary_particles"
+    )]
+    pub boundary_density_correction: Switch,
+    /// Name of a scalar attribute of the boundary particles with their volumes for the wall density correction, if not provided the volumes are estimated from the sampling of the boundary particles
+    #[arg(
+        help_heading = ARGS_ADV,
+        long,
+        requires = "boundary_density_correction"
+    )]
+    pub boundary_volume_attribute: Option<String>,
 
     /// Whether to enable the use of double precision for all computations
     #[arg(
@@ -93,6 +260,17 @@ pub struct ReconstructSubcommandArgs {
         requires = "domain_min",
     )]
     pub domain_max: Option<Vec<f64>>,
+    /// Whether to close the surface with caps where the fluid is cut by the boundary of the domain (see domain-min/domain-max), resulting in a closed mesh (requires a global reconstruction with marching cubes, i.e. no octree decomposition and no subdomain grid)
+    #[arg(
+        help_heading = ARGS_BASIC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true,
+        requires = "domain_min",
+    )]
+    pub cap_domain_boundary: Switch,
 
     /// Flag to enable multi-threading to process multiple input files in parallel
     #[arg(
@@ -117,6 +295,29 @@ pub struct ReconstructSubcommandArgs {
     /// Set the number of threads for the worker thread pool
     #[arg(help_heading = ARGS_ADV, long, short = 'n')]
     pub num_threads: Option<usize>,
+    /// Number of neighboring frames on each side of a frame of a sequence whose particle positions are blended with a binomial filter before the reconstruction to reduce flickering of small features. The particles of the frames are matched by their ids (see --temporal-filter-id-attribute), without ids all frames have to contain the same particles in the same order. Close to the first and last frame of the sequence, the window is shrunk so that it stays centered, i.e. the first and last frame are not filtered
+    #[arg(help_heading = ARGS_ADV, long)]
+    pub temporal_filter_radius: Option<usize>,
+    /// Name of an integer point attribute with unique particle ids that is used to match the particles of the frames of the temporal filter window. If the attribute does not exist, the particles are matched by their order in the files, i.e. the particle order has to be stable over the sequence
+    #[arg(help_heading = ARGS_ADV, long, default_value = "id")]
+    pub temporal_filter_id_attribute: String,
+    /// Whether to track the surface mesh over the frames of a sequence by advecting the mesh of the previous frame with the particle velocities and projecting it onto the new surface, components of the mesh that change their topology are re-meshed. The output meshes keep their vertices where possible and carry a "velocity" point attribute (requires --tracking-time-step, not supported with --mt-files, anisotropic kernels, solid boundaries, caps at the domain boundary, multiple phases and post-processing steps that modify the mesh connectivity)
+    #[arg(
+        help_heading = ARGS_ADV,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true,
+        requires = "tracking_time_step"
+    )]
+    pub surface_tracking: Switch,
+    /// Time between two consecutive frames of the sequence, used to advect the surface mesh with the particle velocities for the surface tracking
+    #[arg(help_heading = ARGS_ADV, long)]
+    pub tracking_time_step: Option<f64>,
+    /// Name of the vector point attribute of the input files with the particle velocities for the surface tracking
+    #[arg(help_heading = ARGS_ADV, long, default_value = "velocity")]
+    pub tracking_velocity_attribute: String,
 
     /// Whether to enable spatial decomposition using a regular grid-based approach
     #[arg(
@@ -201,9 +402,203 @@ pub struct ReconstructSubcommandArgs {
         require_equals = true
     )]
     pub sph_normals: Switch,
-    /// List of point attribute field names from the input file that should be interpolated to the reconstructed surface. Currently this is only supported for VTK and VTU input files.
+    /// List of point attribute field names from the input file that should be interpolated to the reconstructed surface. This is supported for VTK, VTU, BGEO, PLY and JSON input files (see the README for the supported layouts of the attributes).
     #[arg(help_heading = ARGS_INTERP, long)]
     pub interpolate_attributes: Vec<String>,
+    /// Method used to transfer integer attributes (e.g. phase or object ids) of --interpolate-attributes to the surface, either the value of the nearest particle or the value with the largest total SPH weight of the particles in the kernel support
+    #[arg(
+        help_heading = ARGS_INTERP,
+        long,
+        default_value = "majority",
+        value_name = "nearest|majority",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub integer_attribute_interpolation: IntegerInterpolation,
+    /// Whether to write per-vertex velocities (motion vectors, e.g. for motion blur) of the surface as "velocity" point attribute. The particle velocities are read from the first attribute of --motion-vector-attributes that exists in the input file, otherwise they are computed by finite differences of the particle positions of the previous and next frame of the sequence
+    #[arg(
+        help_heading = ARGS_INTERP,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true,
+        conflicts_with = "surface_tracking"
+    )]
+    pub motion_vectors: Switch,
+    /// Comma separated names of point attributes that may contain the particle velocities for the motion vectors, the first attribute that exists in an input file is used
+    #[arg(
+        help_heading = ARGS_INTERP,
+        long,
+        value_delimiter = ',',
+        default_value = "v,velocity"
+    )]
+    pub motion_vector_attributes: Vec<String>,
+    /// Name of an integer point attribute with unique particle ids that is used to match the particles of consecutive frames for the finite difference velocities. If the attribute does not exist, all frames have to contain the same particles in the same order
+    #[arg(help_heading = ARGS_INTERP, long, default_value = "id")]
+    pub motion_vector_id_attribute: String,
+    /// Time between two consecutive frames of the sequence that is used for the finite difference velocities of the motion vectors, by default the velocities are given as displacement per frame
+    #[arg(help_heading = ARGS_INTERP, long, default_value = "1.0")]
+    pub motion_vector_time_step: f64,
+
+    /// Optional filename for writing diffuse particles (spray, foam and air bubbles) generated from the trapped air and wave crest potentials of the fluid particles to disk (supported formats: VTK, BGEO, JSON). The particles carry a "velocity" and a "type" (spray: 0, foam: 1, bubble: 2) attribute. Has to include "{}" as placeholder for the frame index when processing a sequence (requires --diffuse-time-step)
+    #[arg(
+        help_heading = ARGS_DIFFUSE,
+        long,
+        value_parser = value_parser!(PathBuf),
+        requires = "diffuse_time_step"
+    )]
+    pub output_diffuse_particles: Option<PathBuf>,
+    /// Time step of the simulation that is used to determine the number of generated diffuse particles and their sampling volume
+    #[arg(help_heading = ARGS_DIFFUSE, long, requires = "output_diffuse_particles")]
+    pub diffuse_time_step: Option<f64>,
+    /// Name of the vector point attribute of the input files with the particle velocities for the generation of diffuse particles
+    #[arg(
+        help_heading = ARGS_DIFFUSE,
+        long,
+        default_value = "velocity",
+        requires = "output_diffuse_particles"
+    )]
+    pub diffuse_velocity_attribute: String,
+    /// Range of the scaled velocity difference of the fluid particles that is mapped to the trapped air potential
+    #[arg(
+        help_heading = ARGS_DIFFUSE,
+        long,
+        number_of_values = 2,
+        value_names = ["MIN", "MAX"],
+        default_values = ["5.0", "20.0"],
+        requires = "output_diffuse_particles"
+    )]
+    pub diffuse_trapped_air_range: Vec<f64>,
+    /// Range of the surface curvature of the fluid particles that is mapped to the wave crest potential
+    #[arg(
+        help_heading = ARGS_DIFFUSE,
+        long,
+        number_of_values = 2,
+        value_names = ["MIN", "MAX"],
+        default_values = ["2.0", "8.0"],
+        requires = "output_diffuse_particles"
+    )]
+    pub diffuse_wave_crest_range: Vec<f64>,
+    /// Range of the kinetic energy per unit mass of the fluid particles that is mapped to the kinetic energy potential
+    #[arg(
+        help_heading = ARGS_DIFFUSE,
+        long,
+        number_of_values = 2,
+        value_names = ["MIN", "MAX"],
+        default_values = ["5.0", "50.0"],
+        requires = "output_diffuse_particles"
+    )]
+    pub diffuse_kinetic_energy_range: Vec<f64>,
+    /// Maximum number of diffuse particles generated per fluid particle and time unit due to trapped air
+    #[arg(
+        help_heading = ARGS_DIFFUSE,
+        long,
+        default_value = "4000.0",
+        requires = "output_diffuse_particles"
+    )]
+    pub diffuse_trapped_air_rate: f64,
+    /// Maximum number of diffuse particles generated per fluid particle and time unit at wave crests
+    #[arg(
+        help_heading = ARGS_DIFFUSE,
+        long,
+        default_value = "50000.0",
+        requires = "output_diffuse_particles"
+    )]
+    pub diffuse_wave_crest_rate: f64,
+
+    /// Snap marching cubes vertices that are closer than this distance to a grid point onto the point and remove the resulting degenerate triangles (in multiplies of the cube size, should be smaller than 0.5, applied before all other post-processing steps, requires marching cubes)
+    #[arg(help_heading = ARGS_POSTPROC, long)]
+    pub mesh_cleanup_snap_distance: Option<f64>,
+    /// Remove connected components of the mesh (e.g. small droplets) with fewer triangles (applied after vertex snapping and before all other post-processing steps)
+    #[arg(help_heading = ARGS_POSTPROC, long)]
+    pub mesh_cleanup_min_component_triangles: Option<usize>,
+    /// Remove connected components of the mesh with a smaller surface area (in multiplies of the squared particle radius)
+    #[arg(help_heading = ARGS_POSTPROC, long)]
+    pub mesh_cleanup_min_component_area: Option<f64>,
+    /// Only keep this number of connected components of the mesh with the largest surface area
+    #[arg(help_heading = ARGS_POSTPROC, long)]
+    pub mesh_cleanup_keep_largest_components: Option<usize>,
+    /// Whether to write the index of the connected component of every triangle of the final mesh as "component" cell attribute (not supported with --generate-quads)
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub mesh_component_ids: Switch,
+    /// Number of smoothing iterations applied to the reconstructed surface mesh before it is written to disk (no smoothing is applied if not specified)
+    #[arg(help_heading = ARGS_POSTPROC, long)]
+    pub mesh_smoothing_iters: Option<usize>,
+    /// Weighting scheme of the neighbor vertices in the discrete Laplacian used for mesh smoothing
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "uniform",
+        value_name = "uniform|cotangent",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub mesh_smoothing_weights: MeshSmoothingWeights,
+    /// Step size (lambda) of each mesh smoothing step
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "0.5")]
+    pub mesh_smoothing_lambda: f64,
+    /// Negative step size (mu) of the inflation step performed after every smoothing step, enables Taubin smoothing instead of plain Laplacian smoothing (should satisfy mu < -lambda, e.g. -0.53 for lambda 0.5)
+    #[arg(help_heading = ARGS_POSTPROC, long, allow_negative_numbers = true)]
+    pub mesh_smoothing_mu: Option<f64>,
+    /// Whether to scale the smoothing step size per vertex by the distance of the vertex to the closest particle (vertices close to particles are fixed, reduces shrinking of thin sheets and droplets)
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub mesh_smoothing_weighted: Switch,
+    /// Vertices closer than this distance to a particle are not moved by the weighted mesh smoothing (in multiplies of the particle radius)
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "1.0")]
+    pub mesh_smoothing_fixed_distance: f64,
+    /// Vertices farther away than this distance from all particles are smoothed with the full step size by the weighted mesh smoothing (in multiplies of the particle radius)
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "2.0")]
+    pub mesh_smoothing_full_distance: f64,
+    /// Whether to restore the volume enclosed by the mesh after smoothing by offsetting the vertices along their normals
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub mesh_smoothing_preserve_volume: Switch,
+    /// Simplify the mesh using quadric error metric edge collapses until it has at most this number of triangles (applied after smoothing)
+    #[arg(help_heading = ARGS_POSTPROC, long)]
+    pub mesh_decimation_target: Option<usize>,
+    /// Maximum geometric error allowed for the edge collapses of the mesh simplification (in multiplies of the particle radius, applied after smoothing)
+    #[arg(help_heading = ARGS_POSTPROC, long)]
+    pub mesh_decimation_max_error: Option<f64>,
+    /// Whether to convert the final mesh into a quad-dominant mesh by merging pairs of adjacent triangles into quads (applied after all other post-processing steps, the output mesh consists of triangles and quads)
+    #[arg(
+        help_heading = ARGS_POSTPROC,
+        long,
+        default_value = "off",
+        value_name = "off|on",
+        ignore_case = true,
+        require_equals = true
+    )]
+    pub generate_quads: Switch,
+    /// Maximum angle between the normals of two triangles that are merged into a quad (in degrees)
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "10.0")]
+    pub quad_max_normal_angle: f64,
+    /// Maximum interior angle at the corners of a quad resulting from the merge of two triangles (in degrees, has to be smaller than 180)
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "135.0")]
+    pub quad_max_interior_angle: f64,
+    /// Maximum ratio of the lengths of the longest and the shortest edge of a quad resulting from the merge of two triangles
+    #[arg(help_heading = ARGS_POSTPROC, long, default_value = "1.75")]
+    pub quad_max_edge_ratio: f64,
 
     /// Optional filename for writing the point cloud representation of the intermediate density map to disk
     #[arg(help_heading = ARGS_DEBUG, long, value_parser = value_parser!(PathBuf))]
@@ -241,6 +636,102 @@ impl Switch {
     }
 }
 
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum SurfaceExtraction {
+    MarchingCubes,
+    SurfaceNets,
+}
+
+impl From<SurfaceExtraction> for IsoSurfaceExtraction {
+    fn from(extraction: SurfaceExtraction) -> Self {
+        match extraction {
+            SurfaceExtraction::MarchingCubes => IsoSurfaceExtraction::MarchingCubes,
+            SurfaceExtraction::SurfaceNets => IsoSurfaceExtraction::SurfaceNets,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum Kernel {
+    CubicSpline,
+    #[value(name = "wendland-c2")]
+    WendlandC2,
+    #[value(name = "wendland-c4")]
+    WendlandC4,
+    #[value(name = "wendland-c6")]
+    WendlandC6,
+    Poly6,
+    Spiky,
+    Gaussian,
+}
+
+impl From<Kernel> for KernelType {
+    fn from(kernel: Kernel) -> Self {
+        match kernel {
+            Kernel::CubicSpline => KernelType::CubicSpline,
+            Kernel::WendlandC2 => KernelType::WendlandC2,
+            Kernel::WendlandC4 => KernelType::WendlandC4,
+            Kernel::WendlandC6 => KernelType::WendlandC6,
+            Kernel::Poly6 => KernelType::Poly6,
+            Kernel::Spiky => KernelType::Spiky,
+            Kernel::Gaussian => KernelType::Gaussian,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum PhaseMode {
+    Separate,
+    AgainstOthers,
+}
+
+impl From<PhaseMode> for PhaseReconstructionMode {
+    fn from(mode: PhaseMode) -> Self {
+        match mode {
+            PhaseMode::Separate => PhaseReconstructionMode::Separate,
+            PhaseMode::AgainstOthers => PhaseReconstructionMode::AgainstOthers,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum BoundaryMode {
+    Cut,
+    Extend,
+    ExtendAndCut,
+}
+
+impl From<BoundaryMode> for BoundaryHandling {
+    fn from(mode: BoundaryMode) -> Self {
+        match mode {
+            BoundaryMode::Cut => BoundaryHandling::Cut,
+            BoundaryMode::Extend => BoundaryHandling::Extend,
+            BoundaryMode::ExtendAndCut => BoundaryHandling::ExtendAndCut,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum IntegerInterpolation {
+    Nearest,
+    Majority,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, clap::ValueEnum)]
+pub enum MeshSmoothingWeights {
+    Uniform,
+    Cotangent,
+}
+
+impl From<MeshSmoothingWeights> for LaplacianWeights {
+    fn from(weights: MeshSmoothingWeights) -> Self {
+        match weights {
+            MeshSmoothingWeights::Uniform => LaplacianWeights::Uniform,
+            MeshSmoothingWeights::Cotangent => LaplacianWeights::Cotangent,
+        }
+    }
+}
+
 /// Executes the `reconstruct` subcommand
 pub fn reconstruct_subcommand(cmd_args: &ReconstructSubcommandArgs) -> Result<(), anyhow::Error> {
     profile!("reconstruct subcommand");
@@ -248,8 +739,12 @@ pub fn reconstruct_subcommand(cmd_args: &ReconstructSubcommandArgs) -> Result<()
     let paths = ReconstructionRunnerPathCollection::try_from(cmd_args)
         .context("Failed parsing input file path(s) from command line")?
         .collect();
-    let args = ReconstructionRunnerArgs::try_from(cmd_args)
+    // The particle radius is estimated from the input using the same format parameters as the reconstruction
+    let io_params = io::FormatParameters::default();
+    let cmd_args = &with_estimated_particle_radius(cmd_args, &paths, &io_params.input)?;
+    let mut args = ReconstructionRunnerArgs::try_from(cmd_args)
         .context("Failed processing parameters from command line")?;
+    args.io_params = io_params;
 
     let _pb = if paths.len() > 1 {
         let pb = ProgressBar::new(paths.len() as u64);
@@ -262,7 +757,9 @@ pub fn reconstruct_subcommand(cmd_args: &ReconstructSubcommandArgs) -> Result<()
         None
     };
 
-    let result = if cmd_args.parallelize_over_files.into_bool() {
+    let result = if let Some(tracking) = &args.tracking {
+        tracking_pipeline(&paths, &args, tracking)
+    } else if cmd_args.parallelize_over_files.into_bool() {
         paths.par_iter().try_for_each(|path| {
             reconstruction_pipeline(path, &args)
                 .with_context(|| {
@@ -302,15 +799,96 @@ pub fn reconstruct_subcommand(cmd_args: &ReconstructSubcommandArgs) -> Result<()
     result
 }
 
+/// Returns the command line arguments with the particle radius estimated from the first input file if `--auto-radius` is enabled
+fn with_estimated_particle_radius(
+    cmd_args: &ReconstructSubcommandArgs,
+    paths: &[ReconstructionRunnerPaths],
+    input_format_params: &io::InputFormatParameters,
+) -> Result<ReconstructSubcommandArgs, anyhow::Error> {
+    let mut cmd_args = cmd_args.clone();
+    match (cmd_args.auto_radius.into_bool(), cmd_args.particle_radius) {
+        (false, Some(_)) => return Ok(cmd_args),
+        (false, None) => {
+            return Err(anyhow!(
+                "Either the particle radius (--particle-radius) has to be specified or its estimation has to be enabled (--auto-radius=on)"
+            ))
+        }
+        (true, Some(_)) => {
+            return Err(anyhow!(
+                "The particle radius (--particle-radius) cannot be specified together with --auto-radius=on"
+            ))
+        }
+        (true, None) => {}
+    }
+
+    if !(cmd_args.auto_radius_factor > 0.0) {
+        return Err(anyhow!(
+            "The factor for the estimated particle radius (--auto-radius-factor) has to be positive (got {})",
+            cmd_args.auto_radius_factor
+        ));
+    }
+
+    profile!("estimate particle radius");
+
+    let input_file = &paths
+        .first()
+        .ok_or(anyhow!(
+            "No input file to estimate the particle radius from"
+        ))?
+        .input_file;
+    let particle_positions: Vec<Vector3<f64>> =
+        io::read_particle_positions(input_file, input_format_params).with_context(|| {
+            format!(
+            "Failed to load particle positions from file \"{}\" to estimate the particle radius",
+            input_file.display()
+        )
+        })?;
+
+    let estimated_radius = estimate_particle_radius::<i64, f64>(
+        &particle_positions,
+        cmd_args.parallelize_over_particles.into_bool(),
+    )
+    .with_context(|| {
+        format!(
+            "Unable to estimate the particle radius from file \"{}\"",
+            input_file.display()
+        )
+    })?;
+    let particle_radius = estimated_radius * cmd_args.auto_radius_factor;
+    info!(
+        "Estimated particle radius {} from the nearest neighbor distances of the particles of \"{}\", using particle radius {} (factor {}).",
+        estimated_radius,
+        input_file.display(),
+        particle_radius,
+        cmd_args.auto_radius_factor
+    );
+
+    cmd_args.particle_radius = Some(particle_radius);
+    Ok(cmd_args)
+}
+
 /// Conversion and validation of command line arguments
 mod arguments {
-    use super::ReconstructSubcommandArgs;
+    use super::{IntegerInterpolation, ReconstructSubcommandArgs};
     use crate::io;
     use anyhow::{anyhow, Context};
     use log::info;
     use regex::{escape, Regex};
+    use splashsurf_lib::boundary::BoundaryGeometry;
+    use splashsurf_lib::diffuse_particles::DiffuseParticleParameters;
+    use splashsurf_lib::mesh::{AttributeData, MeshAttribute};
+    use splashsurf_lib::multiphase::PhaseReconstructionMode;
     use splashsurf_lib::nalgebra::Vector3;
-    use splashsurf_lib::{Aabb3d, ParticleDensityComputationStrategy};
+    use splashsurf_lib::particle_classification::ClassificationParameters;
+    use splashsurf_lib::postprocessing::{
+        ComponentFilterParameters, DecimationParameters, ParticleDistanceWeighting,
+        QuadConversionParameters, SmoothingParameters,
+    };
+    use splashsurf_lib::surface_tracking::SurfaceTrackingParameters;
+    use splashsurf_lib::{
+        Aabb3d, AdaptiveResolutionParameters, AnisotropyParameters, BoundaryParameters,
+        BoundaryParticles, IsoSurfaceExtraction, ParticleDensityComputationStrategy,
+    };
     use std::convert::TryFrom;
     use std::fs;
     use std::path::{Path, PathBuf};
@@ -323,6 +901,65 @@ mod arguments {
         pub use_double_precision: bool,
         pub check_mesh: bool,
         pub io_params: io::FormatParameters,
+        pub postprocessing: ReconstructionRunnerPostprocessingArgs,
+        pub multiphase: Option<ReconstructionRunnerMultiphaseArgs>,
+        pub tracking: Option<ReconstructionRunnerTrackingArgs>,
+    }
+
+    /// Parameters for the reconstruction of one surface per phase of multiphase particle data
+    #[derive(Clone, Debug)]
+    pub struct ReconstructionRunnerMultiphaseArgs {
+        pub phase_attribute: String,
+        pub mode: PhaseReconstructionMode,
+    }
+
+    /// Parameters for tracking the surface mesh over the frames of a sequence
+    #[derive(Clone, Debug)]
+    pub struct ReconstructionRunnerTrackingArgs {
+        pub velocity_attribute: String,
+        pub parameters: SurfaceTrackingParameters<f64>,
+    }
+
+    /// Parameters for the per-vertex motion vectors of the surface
+    #[derive(Clone, Debug)]
+    pub struct ReconstructionRunnerMotionVectorArgs {
+        /// Names of the point attributes that may contain the particle velocities, the first one that exists in a file is used
+        pub velocity_attributes: Vec<String>,
+        /// Name of the point attribute with particle ids that is used to match the particles of consecutive frames
+        pub id_attribute: String,
+        /// Time between two consecutive frames of the sequence
+        pub time_step: f64,
+    }
+
+    /// Parameters for the generation of diffuse particles
+    #[derive(Clone, Debug)]
+    pub struct ReconstructionRunnerDiffuseArgs {
+        /// File for writing the diffuse particles, contains a "{}" placeholder for the frame index when processing a sequence
+        pub output_file: PathBuf,
+        /// Name of the point attribute with the particle velocities
+        pub velocity_attribute: String,
+        pub parameters: DiffuseParticleParameters<f64>,
+    }
+
+    /// Parameters for the classification of the particles into interior, surface and isolated particles
+    #[derive(Clone, Debug)]
+    pub struct ReconstructionRunnerClassificationArgs {
+        /// File for writing the classified particles, contains a "{}" placeholder for the frame index when processing a sequence
+        pub output_file: PathBuf,
+        pub parameters: ClassificationParameters<f64>,
+    }
+
+    /// Post-processing steps that are applied to the reconstructed mesh before it is written to disk
+    #[derive(Clone, Debug, Default)]
+    pub struct ReconstructionRunnerPostprocessingArgs {
+        pub snap_distance: Option<f64>,
+        pub component_filter: Option<ComponentFilterParameters<f64>>,
+        pub smoothing: Option<SmoothingParameters<f64>>,
+        pub smoothing_weighting: Option<ParticleDistanceWeighting<f64>>,
+        pub decimation: Option<DecimationParameters<f64>>,
+        pub quad_conversion: Option<QuadConversionParameters<f64>>,
+        /// Whether to write the connected component of every triangle as cell attribute
+        pub component_ids: bool,
     }
 
     // Convert raw command line arguments to more useful types
@@ -330,6 +967,10 @@ mod arguments {
         type Error = anyhow::Error;
 
         fn try_from(args: &ReconstructSubcommandArgs) -> Result<Self, Self::Error> {
+            let particle_radius = args.particle_radius.ok_or(anyhow!(
+                "The particle radius has to be specified (--particle-radius) or estimated (--auto-radius=on)"
+            ))?;
+
             // Convert domain args to aabb
             let domain_aabb = match (&args.domain_min, &args.domain_max) {
                 (Some(domain_min), Some(domain_max)) => {
@@ -360,8 +1001,8 @@ mod arguments {
             };
 
             // Scale kernel radius and cube size by particle radius
-            let compact_support_radius = args.particle_radius * 2.0 * args.smoothing_length;
-            let cube_size = args.particle_radius * args.cube_size;
+            let compact_support_radius = particle_radius * 2.0 * args.smoothing_length;
+            let cube_size = particle_radius * args.cube_size;
 
             let spatial_decomposition = if !args.octree_decomposition.into_bool() {
                 None
@@ -396,24 +1037,311 @@ mod arguments {
                 })
             };
 
-            // Assemble all parameters for the surface reconstruction
-            let params = splashsurf_lib::Parameters {
-                particle_radius: args.particle_radius,
-                rest_density: args.rest_density,
-                compact_support_radius,
-                cube_size,
-                iso_surface_threshold: args.surface_threshold,
-                domain_aabb,
-                subdomain_num_cubes_per_dim: args
-                    .subdomain_grid
-                    .into_bool()
-                    .then_some(args.subdomain_cubes),
-                enable_multi_threading: args.parallelize_over_particles.into_bool(),
-                spatial_decomposition,
-            };
+            let iso_surface_extraction = args.surface_extraction.into();
+            if iso_surface_extraction != IsoSurfaceExtraction::MarchingCubes
+                && spatial_decomposition.is_some()
+                && !args.subdomain_grid.into_bool()
+            {
+                return Err(anyhow!("Surface extraction using {:?} is not supported with octree decomposition. Enable the subdomain grid (--subdomain-grid=on) or disable the octree decomposition (--octree-decomposition=off).", iso_surface_extraction));
+            }
 
-            // Optionally initialize thread pool
-            if let Some(num_threads) = args.num_threads {
+            let anisotropy = if args.anisotropic_kernels.into_bool() {
+                if spatial_decomposition.is_some() && !args.subdomain_grid.into_bool() {
+                    return Err(anyhow!("Anisotropic kernels are not supported with octree decomposition. Enable the subdomain grid (--subdomain-grid=on) or disable the octree decomposition (--octree-decomposition=off)."));
+                }
+                if !(0.0..=1.0).contains(&args.anisotropy_smoothing_factor) {
+                    return Err(anyhow!(
+                        "The anisotropy smoothing factor has to be in the range [0, 1] (got {})",
+                        args.anisotropy_smoothing_factor
+                    ));
+                }
+                if args.anisotropy_max_eigenvalue_ratio < 1.0 {
+                    return Err(anyhow!(
+                        "The anisotropy max. eigenvalue ratio has to be at least 1 (got {})",
+                        args.anisotropy_max_eigenvalue_ratio
+                    ));
+                }
+
+                Some(AnisotropyParameters {
+                    position_smoothing_factor: args.anisotropy_smoothing_factor,
+                    max_eigenvalue_ratio: args.anisotropy_max_eigenvalue_ratio,
+                    min_neighbors: args.anisotropy_min_neighbors,
+                })
+            } else {
+                None
+            };
+
+            let multiphase = if let Some(phase_attribute) = &args.phase_attribute {
+                if spatial_decomposition.is_some() || args.subdomain_grid.into_bool() {
+                    return Err(anyhow!("The reconstruction of multiple phases is only supported with a global reconstruction. Disable the octree decomposition (--octree-decomposition=off) and the subdomain grid (--subdomain-grid=off)."));
+                }
+
+                Some(ReconstructionRunnerMultiphaseArgs {
+                    phase_attribute: phase_attribute.clone(),
+                    mode: args.phase_mode.into(),
+                })
+            } else {
+                None
+            };
+
+            if args.cap_domain_boundary.into_bool() {
+                if spatial_decomposition.is_some() || args.subdomain_grid.into_bool() {
+                    return Err(anyhow!("Caps at the domain boundary are only supported with a global reconstruction. Disable the octree decomposition (--octree-decomposition=off) and the subdomain grid (--subdomain-grid=off)."));
+                }
+                if iso_surface_extraction != IsoSurfaceExtraction::MarchingCubes {
+                    return Err(anyhow!("Caps at the domain boundary are only supported with marching cubes (--surface-extraction=marching-cubes)."));
+                }
+            }
+
+            let adaptive_resolution = if args.adaptive_resolution.into_bool() {
+                if spatial_decomposition.is_some() || args.subdomain_grid.into_bool() {
+                    return Err(anyhow!("Adaptive resolution is only supported with a global reconstruction. Disable the octree decomposition (--octree-decomposition=off) and the subdomain grid (--subdomain-grid=off)."));
+                }
+                if iso_surface_extraction != IsoSurfaceExtraction::SurfaceNets {
+                    return Err(anyhow!("Adaptive resolution is only supported with surface nets (--surface-extraction=surface-nets)."));
+                }
+                if anisotropy.is_some()
+                    || multiphase.is_some()
+                    || args.boundary_mesh.is_some()
+                    || args.boundary_particles.is_some()
+                {
+                    return Err(anyhow!("Adaptive resolution is not supported with anisotropic kernels, multiple phases or solid boundaries"));
+                }
+
+                let adaptive_resolution = AdaptiveResolutionParameters {
+                    max_refinement_level: args.adaptive_max_level,
+                    refinement_tolerance: args.adaptive_tolerance,
+                };
+                adaptive_resolution.validate()?;
+                Some(adaptive_resolution)
+            } else {
+                None
+            };
+
+            // Clap does not enforce the requirement if the conflicting boundary mesh is given instead
+            if args.boundary_density_correction.into_bool() && args.boundary_particles.is_none() {
+                return Err(anyhow!(
+                    "The wall density correction (--boundary-density-correction=on) requires boundary particles (--boundary-particles)"
+                ));
+            }
+
+            let mut boundary_particles = None;
+            let boundary_geometry = if let Some(boundary_mesh) = &args.boundary_mesh {
+                let mesh =
+                    io::read_surface_mesh(boundary_mesh, &io::InputFormatParameters::default())
+                        .context("Failed to load the boundary mesh")?;
+                Some(BoundaryGeometry::Mesh(mesh.mesh))
+            } else if let Some(boundary_particles_path) = &args.boundary_particles {
+                if args.boundary_particle_radius <= 0.0 {
+                    return Err(anyhow!(
+                        "The boundary particle radius has to be positive (got {})",
+                        args.boundary_particle_radius
+                    ));
+                }
+                let volume_attribute = args
+                    .boundary_volume_attribute
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<_>>();
+                let (positions, attributes) = io::read_particle_positions_with_attributes(
+                    boundary_particles_path,
+                    &volume_attribute,
+                    &io::InputFormatParameters::default(),
+                )
+                .context("Failed to load the boundary particles")?;
+
+                if args.boundary_density_correction.into_bool() {
+                    let particles = match attributes.into_iter().next() {
+                        Some(MeshAttribute {
+                            data: AttributeData::ScalarReal(volumes),
+                            ..
+                        }) => BoundaryParticles::new(positions.clone(), volumes),
+                        Some(attribute) => {
+                            return Err(anyhow!(
+                                "The boundary particle volume attribute \"{}\" has to be a real scalar attribute",
+                                attribute.name
+                            ));
+                        }
+                        None => BoundaryParticles::with_estimated_volumes(
+                            positions.clone(),
+                            compact_support_radius,
+                            args.kernel.into(),
+                        ),
+                    };
+                    particles.validate()?;
+                    boundary_particles = Some(particles);
+                }
+
+                Some(BoundaryGeometry::Particles {
+                    positions,
+                    radius: particle_radius * args.boundary_particle_radius,
+                })
+            } else {
+                None
+            };
+
+            let boundary = if let Some(geometry) = boundary_geometry {
+                if spatial_decomposition.is_some() && !args.subdomain_grid.into_bool() {
+                    return Err(anyhow!("Solid boundaries are not supported with octree decomposition. Enable the subdomain grid (--subdomain-grid=on) or disable the octree decomposition (--octree-decomposition=off)."));
+                }
+
+                Some(BoundaryParameters {
+                    geometry,
+                    handling: args.boundary_handling.into(),
+                })
+            } else {
+                None
+            };
+
+            // Assemble all parameters for the surface reconstruction
+            let params = splashsurf_lib::Parameters {
+                particle_radius,
+                rest_density: args.rest_density,
+                compact_support_radius,
+                cube_size,
+                iso_surface_threshold: args.surface_threshold,
+                domain_aabb,
+                subdomain_num_cubes_per_dim: args
+                    .subdomain_grid
+                    .into_bool()
+                    .then_some(args.subdomain_cubes),
+                enable_multi_threading: args.parallelize_over_particles.into_bool(),
+                spatial_decomposition,
+                iso_surface_extraction,
+                adaptive_resolution,
+                kernel: args.kernel.into(),
+                anisotropy,
+                boundary,
+                boundary_particles,
+                cap_domain_boundary: args.cap_domain_boundary.into_bool(),
+            };
+
+            if let Some(snap_distance) = args.mesh_cleanup_snap_distance {
+                if !(snap_distance > 0.0 && snap_distance < 0.5) {
+                    return Err(anyhow!("The mesh cleanup snap distance has to be larger than 0 and smaller than 0.5 (got {})", snap_distance));
+                }
+                // Only marching cubes vertices lie on the edges of the background grid (surface nets and adaptive resolution place vertices inside of cells)
+                if iso_surface_extraction != IsoSurfaceExtraction::MarchingCubes {
+                    return Err(anyhow!("Snapping vertices to grid points (--mesh-cleanup-snap-distance) is only supported with marching cubes (--surface-extraction=marching-cubes)."));
+                }
+            }
+
+            let component_filter = (args.mesh_cleanup_min_component_triangles.is_some()
+                || args.mesh_cleanup_min_component_area.is_some()
+                || args.mesh_cleanup_keep_largest_components.is_some())
+            .then_some(ComponentFilterParameters {
+                min_triangles: args.mesh_cleanup_min_component_triangles,
+                min_area: args
+                    .mesh_cleanup_min_component_area
+                    .map(|a| a * particle_radius * particle_radius),
+                keep_largest: args.mesh_cleanup_keep_largest_components,
+            });
+
+            let smoothing = args
+                .mesh_smoothing_iters
+                .map(|iterations| SmoothingParameters {
+                    weights: args.mesh_smoothing_weights.into(),
+                    lambda: args.mesh_smoothing_lambda,
+                    mu: args.mesh_smoothing_mu,
+                    iterations,
+                    preserve_volume: args.mesh_smoothing_preserve_volume.into_bool(),
+                });
+
+            let smoothing_weighting =
+                args.mesh_smoothing_weighted
+                    .into_bool()
+                    .then_some(ParticleDistanceWeighting {
+                        fixed_distance: particle_radius * args.mesh_smoothing_fixed_distance,
+                        full_smoothing_distance: particle_radius
+                            * args.mesh_smoothing_full_distance,
+                    });
+
+            if smoothing_weighting.is_some() && smoothing.is_none() {
+                return Err(anyhow!("Weighted mesh smoothing was enabled, but mesh smoothing is not enabled. Specify the number of smoothing iterations to enable it."));
+            }
+
+            if smoothing_weighting.is_some()
+                && args.mesh_smoothing_fixed_distance > args.mesh_smoothing_full_distance
+            {
+                return Err(anyhow!("The fixed distance of the weighted mesh smoothing cannot be larger than the full smoothing distance (fixed distance: {}, full distance: {})", args.mesh_smoothing_fixed_distance, args.mesh_smoothing_full_distance));
+            }
+
+            if let Some(mu) = args.mesh_smoothing_mu {
+                if smoothing.is_none() {
+                    return Err(anyhow!("A value for mesh smoothing mu was specified, but mesh smoothing is not enabled. Specify the number of smoothing iterations to enable it."));
+                }
+                if mu >= -args.mesh_smoothing_lambda {
+                    return Err(anyhow!("Mesh smoothing mu has to be smaller than the negative of lambda for Taubin smoothing (mu: {}, lambda: {})", mu, args.mesh_smoothing_lambda));
+                }
+            }
+
+            let decimation = (args.mesh_decimation_target.is_some()
+                || args.mesh_decimation_max_error.is_some())
+            .then_some(DecimationParameters {
+                target_triangle_count: args.mesh_decimation_target,
+                max_error: args.mesh_decimation_max_error.map(|e| e * particle_radius),
+            });
+
+            if args.quad_max_interior_angle >= 180.0 {
+                return Err(anyhow!("The maximum interior angle of quads has to be smaller than 180 degrees (got {})", args.quad_max_interior_angle));
+            }
+
+            let quad_conversion =
+                args.generate_quads
+                    .into_bool()
+                    .then_some(QuadConversionParameters {
+                        max_normal_angle: args.quad_max_normal_angle.to_radians(),
+                        max_interior_angle: args.quad_max_interior_angle.to_radians(),
+                        max_edge_ratio: args.quad_max_edge_ratio,
+                    });
+
+            if args.mesh_component_ids.into_bool() && quad_conversion.is_some() {
+                return Err(anyhow!("Writing the connected components of the mesh as cell attribute is not supported for quad-dominant meshes (--generate-quads)"));
+            }
+
+            let tracking = if args.surface_tracking.into_bool() {
+                if !args.input_file_or_sequence.to_string_lossy().contains("{}") {
+                    return Err(anyhow!("The surface tracking requires an input sequence, use \"{{}}\" in the input filename as a placeholder for the frame index"));
+                }
+                if args.parallelize_over_files.into_bool() {
+                    return Err(anyhow!("The surface tracking processes the frames of a sequence one after another and cannot be combined with --mt-files=on"));
+                }
+                if params.anisotropy.is_some()
+                    || params.boundary.is_some()
+                    || params.boundary_particles.is_some()
+                    || params.cap_domain_boundary
+                    || multiphase.is_some()
+                {
+                    return Err(anyhow!("The surface tracking is not supported with anisotropic kernels, solid boundaries, caps at the domain boundary or multiple phases"));
+                }
+                if args.mesh_cleanup_snap_distance.is_some()
+                    || component_filter.is_some()
+                    || decimation.is_some()
+                    || quad_conversion.is_some()
+                {
+                    return Err(anyhow!("The surface tracking is not supported with post-processing steps that modify the connectivity of the mesh (vertex snapping, removal of components, decimation, quad conversion)"));
+                }
+
+                let time_step = args.tracking_time_step.ok_or_else(|| {
+                    anyhow!("The surface tracking requires the time between two frames (--tracking-time-step)")
+                })?;
+                if time_step <= 0.0 {
+                    return Err(anyhow!(
+                        "The tracking time step has to be positive (got {})",
+                        time_step
+                    ));
+                }
+
+                Some(ReconstructionRunnerTrackingArgs {
+                    velocity_attribute: args.tracking_velocity_attribute.clone(),
+                    parameters: SurfaceTrackingParameters::with_time_step(time_step),
+                })
+            } else {
+                None
+            };
+
+            // Optionally initialize thread pool
+            if let Some(num_threads) = args.num_threads {
                 splashsurf_lib::initialize_thread_pool(num_threads)?;
             }
 
@@ -422,6 +1350,17 @@ mod arguments {
                 use_double_precision: args.double_precision.into_bool(),
                 check_mesh: args.check_mesh.into_bool(),
                 io_params: io::FormatParameters::default(),
+                postprocessing: ReconstructionRunnerPostprocessingArgs {
+                    snap_distance: args.mesh_cleanup_snap_distance,
+                    component_filter,
+                    smoothing,
+                    smoothing_weighting,
+                    decimation,
+                    quad_conversion,
+                    component_ids: args.mesh_component_ids.into_bool(),
+                },
+                multiphase,
+                tracking,
             })
         }
     }
@@ -441,6 +1380,18 @@ mod arguments {
         sph_normals: bool,
         /// Additional attributes to load and interpolate to surface
         attributes: Vec<String>,
+        /// Interpolation method for integer attributes
+        integer_interpolation: IntegerInterpolation,
+        /// Number of neighboring frames on each side of a frame used for temporal filtering of the particle positions
+        temporal_filter_radius: usize,
+        /// Name of the point attribute with particle ids that is used to match the particles of the temporal filter window
+        temporal_filter_id_attribute: String,
+        /// Parameters for the motion vectors of all files (if requested)
+        motion_vectors: Option<ReconstructionRunnerMotionVectorArgs>,
+        /// Parameters for the particle classification of all files (if requested)
+        classification: Option<ReconstructionRunnerClassificationArgs>,
+        /// Parameters for the diffuse particles of all files (if requested)
+        diffuse: Option<ReconstructionRunnerDiffuseArgs>,
     }
 
     impl ReconstructionRunnerPathCollection {
@@ -456,6 +1407,12 @@ mod arguments {
             compute_normals: bool,
             sph_normals: bool,
             attributes: Vec<String>,
+            integer_interpolation: IntegerInterpolation,
+            temporal_filter_radius: usize,
+            temporal_filter_id_attribute: String,
+            motion_vectors: Option<ReconstructionRunnerMotionVectorArgs>,
+            classification: Option<ReconstructionRunnerClassificationArgs>,
+            diffuse: Option<ReconstructionRunnerDiffuseArgs>,
         ) -> Result<Self, anyhow::Error> {
             let input_file = input_file.into();
             let output_base_path = output_base_path.map(|p| p.into());
@@ -503,6 +1460,20 @@ mod arguments {
                     compute_normals,
                     sph_normals,
                     attributes,
+                    integer_interpolation,
+                    temporal_filter_radius,
+                    temporal_filter_id_attribute,
+                    motion_vectors,
+                    classification: classification.map(|classification| {
+                        ReconstructionRunnerClassificationArgs {
+                            output_file: output_base_path.join(classification.output_file),
+                            ..classification
+                        }
+                    }),
+                    diffuse: diffuse.map(|diffuse| ReconstructionRunnerDiffuseArgs {
+                        output_file: output_base_path.join(diffuse.output_file),
+                        ..diffuse
+                    }),
                 })
             } else {
                 Ok(Self {
@@ -516,6 +1487,12 @@ mod arguments {
                     compute_normals,
                     sph_normals,
                     attributes,
+                    integer_interpolation,
+                    temporal_filter_radius,
+                    temporal_filter_id_attribute,
+                    motion_vectors,
+                    classification,
+                    diffuse,
                 })
             }
         }
@@ -554,7 +1531,8 @@ mod arguments {
                     input_root.display()
                 );
 
-                let mut paths = Vec::new();
+                // All files of the sequence, including files outside of the range to process that are used for temporal filtering
+                let mut sequence_files = Vec::new();
 
                 for entry in WalkDir::new(input_root)
                     .max_depth(1)
@@ -576,36 +1554,88 @@ mod arguments {
                         let index_usize =
                             usize::from_str(index).expect("index should be convertible to usize");
 
-                        if let Some(start) = self.sequence_range.0 {
-                            if index_usize < start {
-                                continue;
-                            }
+                        let input_filename_i = entry_name.as_ref();
+                        let input_file_i = input_dir.join(input_filename_i);
+
+                        sequence_files.push((index_usize, index.to_string(), input_file_i));
+                    }
+                }
+
+                let mut paths = Vec::new();
+
+                for (i, (index_usize, index, input_file_i)) in sequence_files.iter().enumerate() {
+                    if let Some(start) = self.sequence_range.0 {
+                        if *index_usize < start {
+                            continue;
                         }
+                    }
 
-                        if let Some(end) = self.sequence_range.1 {
-                            if index_usize > end {
-                                continue;
-                            }
+                    if let Some(end) = self.sequence_range.1 {
+                        if *index_usize > end {
+                            continue;
                         }
+                    }
 
-                        let input_filename_i = entry_name.as_ref();
-                        let input_file_i = input_dir.join(input_filename_i);
+                    let output_filename_i = output_pattern.replace("{}", index);
+                    let output_file_i = output_dir.join(output_filename_i);
+
+                    // Shrink the filter window at the ends of the sequence such that it stays centered
+                    let temporal_filter_window = if self.temporal_filter_radius > 0 {
+                        let radius = self
+                            .temporal_filter_radius
+                            .min(i)
+                            .min(sequence_files.len() - 1 - i);
+                        sequence_files[i - radius..=i + radius]
+                            .iter()
+                            .map(|(_, _, input_file)| input_file.clone())
+                            .collect()
+                    } else {
+                        Vec::new()
+                    };
 
-                        let output_filename_i = output_pattern.replace("{}", index);
-                        let output_file_i = output_dir.join(output_filename_i);
-
-                        paths.push(ReconstructionRunnerPaths::new(
-                            input_file_i,
-                            output_file_i,
-                            // Don't write density maps etc. when processing a sequence of files
-                            None,
-                            None,
-                            None,
-                            self.compute_normals,
-                            self.sph_normals,
-                            self.attributes.clone(),
-                        ));
-                    }
+                    // The neighboring frames are used for finite difference velocities of the motion vectors
+                    let neighbor_frames = if self.motion_vectors.is_some() {
+                        (
+                            i.checked_sub(1).map(|j| sequence_files[j].2.clone()),
+                            sequence_files.get(i + 1).map(|(_, _, f)| f.clone()),
+                        )
+                    } else {
+                        (None, None)
+                    };
+
+                    // The classified and diffuse particles of every frame are written to separate files
+                    let classification = self.classification.as_ref().map(|classification| {
+                        ReconstructionRunnerClassificationArgs {
+                            output_file: sequence_file(&classification.output_file, index),
+                            parameters: classification.parameters.clone(),
+                        }
+                    });
+                    let diffuse =
+                        self.diffuse
+                            .as_ref()
+                            .map(|diffuse| ReconstructionRunnerDiffuseArgs {
+                                output_file: sequence_file(&diffuse.output_file, index),
+                                ..diffuse.clone()
+                            });
+
+                    paths.push(ReconstructionRunnerPaths::new(
+                        input_file_i.clone(),
+                        output_file_i,
+                        // Don't write density maps etc. when processing a sequence of files
+                        None,
+                        None,
+                        None,
+                        self.compute_normals,
+                        self.sph_normals,
+                        self.attributes.clone(),
+                        self.integer_interpolation,
+                        temporal_filter_window,
+                        self.temporal_filter_id_attribute.clone(),
+                        self.motion_vectors.clone(),
+                        neighbor_frames,
+                        classification,
+                        diffuse,
+                    ));
                 }
 
                 info!(
@@ -633,6 +1663,13 @@ mod arguments {
                         self.compute_normals,
                         self.sph_normals,
                         self.attributes.clone(),
+                        self.integer_interpolation,
+                        Vec::new(),
+                        self.temporal_filter_id_attribute.clone(),
+                        self.motion_vectors.clone(),
+                        (None, None),
+                        self.classification.clone(),
+                        self.diffuse.clone(),
                     );
                     1
                 ]
@@ -702,6 +1739,12 @@ mod arguments {
             } else {
                 is_sequence = false;
 
+                if args.temporal_filter_radius.is_some() {
+                    return Err(anyhow!(
+                        "Temporal filtering (--temporal-filter-radius) requires an input sequence, use \"{{}}\" in the input filename as a placeholder for the frame index"
+                    ));
+                }
+
                 // Make sure that the input file actually exists
                 if args.input_file_or_sequence.is_file() {
                     // Use the user defined output file name if provided...
@@ -724,6 +1767,105 @@ mod arguments {
                 }
             }
 
+            let motion_vectors = if args.motion_vectors.into_bool() {
+                if args.motion_vector_time_step <= 0.0 {
+                    return Err(anyhow!(
+                        "The time step for the motion vectors (--motion-vector-time-step) has to be positive"
+                    ));
+                }
+                if args.motion_vector_attributes.is_empty() {
+                    return Err(anyhow!(
+                        "At least one attribute name has to be given for the particle velocities of the motion vectors (--motion-vector-attributes)"
+                    ));
+                }
+                if args
+                    .interpolate_attributes
+                    .iter()
+                    .any(|name| name == "velocity")
+                {
+                    return Err(anyhow!(
+                        "The motion vectors are written as \"velocity\" attribute, which cannot be interpolated at the same time (remove \"velocity\" from --interpolate-attributes)"
+                    ));
+                }
+                Some(ReconstructionRunnerMotionVectorArgs {
+                    velocity_attributes: args.motion_vector_attributes.clone(),
+                    id_attribute: args.motion_vector_id_attribute.clone(),
+                    time_step: args.motion_vector_time_step,
+                })
+            } else {
+                None
+            };
+
+            let classification = if let Some(output_file) = &args.output_classified_particles {
+                if args.classification_surface_threshold <= 0.0 {
+                    return Err(anyhow!(
+                        "The surface threshold of the particle classification has to be positive (--classification-surface-threshold)"
+                    ));
+                }
+                if is_sequence && !is_sequence_file(output_file) {
+                    return Err(anyhow!(
+                        "The filename of the classified particles has to include \"{{}}\" as placeholder for the frame index when processing a sequence (--output-classified-particles)"
+                    ));
+                }
+                Some(ReconstructionRunnerClassificationArgs {
+                    output_file: output_file.clone(),
+                    parameters: ClassificationParameters {
+                        surface_threshold: args.classification_surface_threshold,
+                        max_isolated_neighbors: args.classification_max_isolated_neighbors,
+                    },
+                })
+            } else {
+                None
+            };
+
+            let diffuse = if let Some(output_file) = &args.output_diffuse_particles {
+                let time_step = args.diffuse_time_step.ok_or_else(|| {
+                    anyhow!("The generation of diffuse particles requires the time step of the simulation (--diffuse-time-step)")
+                })?;
+                if time_step <= 0.0 {
+                    return Err(anyhow!(
+                        "The time step for the diffuse particles has to be positive (got {})",
+                        time_step
+                    ));
+                }
+                if is_sequence && !is_sequence_file(output_file) {
+                    return Err(anyhow!(
+                        "The filename of the diffuse particles has to include \"{{}}\" as placeholder for the frame index when processing a sequence (--output-diffuse-particles)"
+                    ));
+                }
+                let range = |values: &[f64], name: &str| match values {
+                    &[min, max] if min < max => Ok([min, max]),
+                    _ => Err(anyhow!(
+                        "The lower bound of --{} has to be smaller than the upper bound",
+                        name
+                    )),
+                };
+                Some(ReconstructionRunnerDiffuseArgs {
+                    output_file: output_file.clone(),
+                    velocity_attribute: args.diffuse_velocity_attribute.clone(),
+                    parameters: DiffuseParticleParameters {
+                        trapped_air_range: range(
+                            &args.diffuse_trapped_air_range,
+                            "diffuse-trapped-air-range",
+                        )?,
+                        wave_crest_range: range(
+                            &args.diffuse_wave_crest_range,
+                            "diffuse-wave-crest-range",
+                        )?,
+                        kinetic_energy_range: range(
+                            &args.diffuse_kinetic_energy_range,
+                            "diffuse-kinetic-energy-range",
+                        )?,
+                        trapped_air_rate: args.diffuse_trapped_air_rate,
+                        wave_crest_rate: args.diffuse_wave_crest_rate,
+                        time_step,
+                        ..Default::default()
+                    },
+                })
+            } else {
+                None
+            };
+
             Self::try_new(
                 is_sequence,
                 args.input_file_or_sequence.clone(),
@@ -736,6 +1878,12 @@ mod arguments {
                 args.normals.into_bool(),
                 args.sph_normals.into_bool(),
                 args.interpolate_attributes.clone(),
+                args.integer_attribute_interpolation,
+                args.temporal_filter_radius.unwrap_or(0),
+                args.temporal_filter_id_attribute.clone(),
+                motion_vectors,
+                classification,
+                diffuse,
             )
         }
     }
@@ -754,6 +1902,20 @@ mod arguments {
         pub sph_normals: bool,
         /// Additional attributes to load and interpolate to surface
         pub attributes: Vec<String>,
+        /// Interpolation method for integer attributes
+        pub integer_interpolation: IntegerInterpolation,
+        /// Input files of the frames used for temporal filtering of the particle positions in temporal order, centered at the input file (empty if temporal filtering is disabled)
+        pub temporal_filter_window: Vec<PathBuf>,
+        /// Name of the point attribute with particle ids that is used to match the particles of the temporal filter window
+        pub temporal_filter_id_attribute: String,
+        /// Parameters for the motion vectors (if requested)
+        pub motion_vectors: Option<ReconstructionRunnerMotionVectorArgs>,
+        /// Input files of the previous and next frame of the sequence used for finite difference velocities of the motion vectors
+        pub neighbor_frames: (Option<PathBuf>, Option<PathBuf>),
+        /// Parameters and output file for the particle classification (if requested)
+        pub classification: Option<ReconstructionRunnerClassificationArgs>,
+        /// Parameters and output file for the diffuse particles (if requested)
+        pub diffuse: Option<ReconstructionRunnerDiffuseArgs>,
     }
 
     impl ReconstructionRunnerPaths {
@@ -766,6 +1928,13 @@ mod arguments {
             compute_normals: bool,
             sph_normals: bool,
             attributes: Vec<String>,
+            integer_interpolation: IntegerInterpolation,
+            temporal_filter_window: Vec<PathBuf>,
+            temporal_filter_id_attribute: String,
+            motion_vectors: Option<ReconstructionRunnerMotionVectorArgs>,
+            neighbor_frames: (Option<PathBuf>, Option<PathBuf>),
+            classification: Option<ReconstructionRunnerClassificationArgs>,
+            diffuse: Option<ReconstructionRunnerDiffuseArgs>,
         ) -> Self {
             ReconstructionRunnerPaths {
                 input_file,
@@ -776,9 +1945,32 @@ mod arguments {
                 compute_normals,
                 sph_normals,
                 attributes,
+                integer_interpolation,
+                temporal_filter_window,
+                temporal_filter_id_attribute,
+                motion_vectors,
+                neighbor_frames,
+                classification,
+                diffuse,
             }
         }
     }
+
+    /// Returns whether the filename of the path contains a "{}" placeholder for the frame index of a sequence
+    fn is_sequence_file(path: &Path) -> bool {
+        path.file_name()
+            .is_some_and(|f| f.to_string_lossy().contains("{}"))
+    }
+
+    /// Replaces the "{}" placeholder in the filename of the path by the given frame index
+    fn sequence_file(path: &Path, index: &str) -> PathBuf {
+        let filename = path
+            .file_name()
+            .expect("expected an output path ending in a filename")
+            .to_string_lossy()
+            .replace("{}", index);
+        path.with_file_name(filename)
+    }
 }
 
 /// Calls the reconstruction pipeline for single or double precision depending on the runtime parameters
@@ -792,6 +1984,9 @@ pub(crate) fn reconstruction_pipeline(
             paths,
             &args.params,
             &args.io_params,
+            &args.postprocessing,
+            args.multiphase.as_ref(),
+            None,
             args.check_mesh,
         )?;
     } else {
@@ -802,6 +1997,9 @@ pub(crate) fn reconstruction_pipeline(
                 "Unable to convert surface reconstruction parameters from f64 to f32."
             ))?,
             &args.io_params,
+            &args.postprocessing,
+            args.multiphase.as_ref(),
+            None,
             args.check_mesh,
         )?;
     }
@@ -809,20 +2007,235 @@ pub(crate) fn reconstruction_pipeline(
     Ok(())
 }
 
+/// Runs the reconstruction pipeline for all frames of a sequence one after another while tracking the surface mesh
+fn tracking_pipeline(
+    paths: &[ReconstructionRunnerPaths],
+    args: &ReconstructionRunnerArgs,
+    tracking: &ReconstructionRunnerTrackingArgs,
+) -> Result<(), anyhow::Error> {
+    if args.use_double_precision {
+        info!("Using double precision (f64) for surface reconstruction.");
+        tracking_pipeline_generic::<i64, f64>(
+            paths,
+            &args.params,
+            tracking.parameters.clone(),
+            &tracking.velocity_attribute,
+            args,
+        )
+    } else {
+        info!("Using single precision (f32) for surface reconstruction.");
+        tracking_pipeline_generic::<i64, f32>(
+            paths,
+            &args.params.try_convert().ok_or(anyhow!(
+                "Unable to convert surface reconstruction parameters from f64 to f32."
+            ))?,
+            tracking.parameters.try_convert().ok_or(anyhow!(
+                "Unable to convert surface tracking parameters from f64 to f32."
+            ))?,
+            &tracking.velocity_attribute,
+            args,
+        )
+    }
+}
+
+/// Surface tracker that is passed from frame to frame and the name of the particle velocity attribute
+pub(crate) struct FrameTracking<'a, R: Real> {
+    tracker: &'a mut SurfaceTracker<R>,
+    velocity_attribute: &'a str,
+}
+
+fn tracking_pipeline_generic<I: Index, R: Real>(
+    paths: &[ReconstructionRunnerPaths],
+    params: &splashsurf_lib::Parameters<R>,
+    tracking_parameters: SurfaceTrackingParameters<R>,
+    velocity_attribute: &str,
+    args: &ReconstructionRunnerArgs,
+) -> Result<(), anyhow::Error> {
+    let mut tracker = SurfaceTracker::new(tracking_parameters);
+    paths.iter().try_for_each(|path| {
+        reconstruction_pipeline_generic::<I, R>(
+            path,
+            params,
+            &args.io_params,
+            &args.postprocessing,
+            None,
+            Some(FrameTracking {
+                tracker: &mut tracker,
+                velocity_attribute,
+            }),
+            args.check_mesh,
+        )
+        .with_context(|| {
+            format!(
+                "Error while tracking the surface of input file \"{}\"",
+                path.input_file.display()
+            )
+        })
+        .and_then(|_| {
+            logging::get_progress_bar().map(|pb| pb.inc(1));
+            Ok(())
+        })
+    })
+}
+
+/// Returns the velocities stored in the vector attribute with the given name, the attributes have to be in the order of the attribute names
+fn velocities_from_attributes<R: Real>(
+    attribute_names: &[String],
+    attributes: &[MeshAttribute<R>],
+    velocity_attribute: &str,
+) -> Result<Vec<Vector3<R>>, anyhow::Error> {
+    let velocity_attribute_index = attribute_names
+        .iter()
+        .position(|name| name == velocity_attribute)
+        .unwrap();
+    match &attributes[velocity_attribute_index].data {
+        AttributeData::Vector3Real(velocities) => Ok(velocities.clone()),
+        _ => Err(anyhow!(
+            "The velocity attribute \"{}\" has to be a real vector attribute",
+            velocity_attribute
+        )),
+    }
+}
+
+/// Generates diffuse particles from the fluid particles and writes them with their velocities and types to disk
+fn write_diffuse_particles<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    particle_velocities: &[Vector3<R>],
+    particle_densities: Option<&[R]>,
+    params: &splashsurf_lib::Parameters<R>,
+    diffuse: &ReconstructionRunnerDiffuseArgs,
+    io_params: &io::FormatParameters,
+) -> Result<(), anyhow::Error> {
+    let parameters = diffuse.parameters.try_convert().ok_or(anyhow!(
+        "Unable to convert diffuse particle parameters from f64 to the target precision."
+    ))?;
+    let diffuse_particles = generate_diffuse_particles_with_parameters::<I, R>(
+        particle_positions,
+        particle_velocities,
+        particle_densities,
+        params,
+        &parameters,
+    );
+
+    let attributes = [
+        MeshAttribute::new_real_vector3("velocity", diffuse_particles.velocities),
+        MeshAttribute::new(
+            "type",
+            AttributeData::ScalarU64(
+                diffuse_particles
+                    .types
+                    .iter()
+                    .map(|diffuse_type| diffuse_type.label())
+                    .collect(),
+            ),
+        ),
+    ];
+
+    info!(
+        "Writing diffuse particles to \"{}\"...",
+        diffuse.output_file.display()
+    );
+    io::write_particle_positions_with_attributes(
+        &diffuse_particles.positions,
+        &attributes,
+        &diffuse.output_file,
+        &io_params.output,
+    )
+    .with_context(|| {
+        format!(
+            "Failed to write diffuse particles to output file \"{}\"",
+            diffuse.output_file.display()
+        )
+    })?;
+    info!("Done.");
+
+    Ok(())
+}
+
+/// Classifies the particles into interior, surface and isolated particles and writes them with the class labels to disk
+fn write_classified_particles<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    params: &splashsurf_lib::Parameters<R>,
+    classification: &ReconstructionRunnerClassificationArgs,
+    io_params: &io::FormatParameters,
+) -> Result<(), anyhow::Error> {
+    let parameters = classification.parameters.try_convert().ok_or(anyhow!(
+        "Unable to convert particle classification parameters from f64 to the target precision."
+    ))?;
+    let classes =
+        classify_particles_with_parameters::<I, R>(particle_positions, params, &parameters);
+
+    let class_attribute = MeshAttribute::new(
+        "class".to_string(),
+        AttributeData::ScalarU64(classes.iter().map(|class| class.label()).collect()),
+    );
+
+    info!(
+        "Writing classified particles to \"{}\"...",
+        classification.output_file.display()
+    );
+    io::write_particle_positions_with_attributes(
+        particle_positions,
+        &[class_attribute],
+        &classification.output_file,
+        &io_params.output,
+    )
+    .with_context(|| {
+        format!(
+            "Failed to write classified particles to output file \"{}\"",
+            classification.output_file.display()
+        )
+    })?;
+    info!("Done.");
+
+    Ok(())
+}
+
 /// Wrapper for the reconstruction pipeline: loads input file, runs reconstructions, stores output files
 pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
     paths: &ReconstructionRunnerPaths,
     params: &splashsurf_lib::Parameters<R>,
     io_params: &io::FormatParameters,
+    postprocessing: &ReconstructionRunnerPostprocessingArgs,
+    multiphase: Option<&ReconstructionRunnerMultiphaseArgs>,
+    tracking: Option<FrameTracking<R>>,
     check_mesh: bool,
 ) -> Result<(), anyhow::Error> {
+    if let Some(multiphase) = multiphase {
+        return multiphase_reconstruction_pipeline_generic::<I, R>(
+            paths,
+            params,
+            io_params,
+            postprocessing,
+            multiphase,
+            check_mesh,
+        );
+    }
+
     profile!("surface reconstruction");
 
-    // Load particle positions and attributes to interpolate
-    let (particle_positions, attributes) = io::read_particle_positions_with_attributes(
-        &paths.input_file,
-        &paths.attributes,
-        &io_params.input,
+    // Load particle positions and attributes to interpolate (and the velocities for the surface tracking and the diffuse particles)
+    let tracking_velocity_attribute = tracking
+        .as_ref()
+        .map(|tracking| tracking.velocity_attribute);
+    let diffuse_velocity_attribute = paths
+        .diffuse
+        .as_ref()
+        .map(|diffuse| diffuse.velocity_attribute.as_str());
+    let mut attribute_names = paths.attributes.clone();
+    for name in tracking_velocity_attribute
+        .iter()
+        .chain(diffuse_velocity_attribute.iter())
+    {
+        if !attribute_names.iter().any(|n| n == name) {
+            attribute_names.push(name.to_string());
+        }
+    }
+    let (particle_positions, mut attributes) = read_particles_with_attributes(
+        paths,
+        &attribute_names,
+        io_params,
+        params.enable_multi_threading,
     )
     .with_context(|| {
         format!(
@@ -831,122 +2244,101 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
         )
     })?;
 
+    let particle_velocities = tracking_velocity_attribute
+        .map(|name| velocities_from_attributes(&attribute_names, &attributes, name))
+        .transpose()?;
+    let diffuse_particle_velocities = diffuse_velocity_attribute
+        .map(|name| velocities_from_attributes(&attribute_names, &attributes, name))
+        .transpose()?;
+    // Remove the velocity attributes that were not requested for interpolation
+    attributes.drain(paths.attributes.len()..attribute_names.len());
+
+    // Classify the particles into interior, surface and isolated particles if requested
+    if let Some(classification) = &paths.classification {
+        write_classified_particles::<I, R>(
+            particle_positions.as_slice(),
+            params,
+            classification,
+            io_params,
+        )?;
+    }
+
     // Perform the surface reconstruction
     let reconstruction =
         splashsurf_lib::reconstruct_surface::<I, R>(particle_positions.as_slice(), &params)?;
 
-    let grid = reconstruction.grid();
-    let mesh = reconstruction.mesh();
-
-    // Add normals to mesh if requested
-    let mesh = if paths.compute_normals || !attributes.is_empty() {
-        profile!("compute normals");
-
-        info!(
-            "Constructing global acceleration structure for SPH interpolation to {} vertices...",
-            mesh.vertices.len()
-        );
-
-        let particle_rest_density = params.rest_density;
-        let particle_rest_volume = R::from_f64((4.0 / 3.0) * std::f64::consts::PI).unwrap()
-            * params.particle_radius.powi(3);
-        let particle_rest_mass = particle_rest_volume * particle_rest_density;
-
-        let particle_densities = reconstruction
-            .particle_densities()
-            .ok_or_else(|| anyhow::anyhow!("Particle densities were not returned by surface reconstruction but are required for SPH normal computation"))?
-            .as_slice();
-        assert_eq!(
-            particle_positions.len(),
-            particle_densities.len(),
-            "There has to be one density value per particle"
-        );
-
-        let interpolator = SphInterpolator::new(
-            &particle_positions,
-            particle_densities,
-            particle_rest_mass,
-            params.compact_support_radius,
-        );
-
-        let mut mesh_with_data = MeshWithData::new(mesh.clone());
-
-        // Compute normals if requested
-        if paths.compute_normals {
-            let normals = if paths.sph_normals {
-                info!("Using SPH interpolation to compute surface normals");
+    // Generate diffuse particles using the particle densities of the reconstruction if requested
+    if let (Some(diffuse), Some(particle_velocities)) =
+        (&paths.diffuse, &diffuse_particle_velocities)
+    {
+        write_diffuse_particles::<I, R>(
+            particle_positions.as_slice(),
+            particle_velocities.as_slice(),
+            reconstruction.particle_densities().map(Vec::as_slice),
+            params,
+            diffuse,
+            io_params,
+        )?;
+    }
 
-                let sph_normals = interpolator.interpolate_normals(mesh.vertices());
-                bytemuck::allocation::cast_vec::<Unit<Vector3<R>>, Vector3<R>>(sph_normals)
-            } else {
-                info!("Using area weighted triangle normals for surface normals");
-                profile!("mesh.par_vertex_normals");
-                let tri_normals = mesh.par_vertex_normals();
+    let grid = reconstruction.grid();
 
-                // Convert unit vectors to plain vectors
-                bytemuck::allocation::cast_vec::<Unit<Vector3<R>>, Vector3<R>>(tri_normals)
-            };
+    // Advect the mesh of the previous frame to the current frame if the surface is tracked
+    let tracked_surface = match (tracking, &particle_velocities) {
+        (Some(tracking), Some(particle_velocities)) => Some(tracking.tracker.track_frame(
+            &reconstruction,
+            particle_positions.as_slice(),
+            particle_velocities.as_slice(),
+            ParticleProperties::uniform(),
+            params,
+        )?),
+        _ => None,
+    };
+    let reconstructed_mesh = tracked_surface
+        .as_ref()
+        .map(|tracked| tracked.mesh())
+        .unwrap_or_else(|| reconstruction.mesh());
+
+    // Apply post-processing steps to the mesh if requested
+    let postprocessed_mesh = postprocess_mesh(
+        reconstructed_mesh,
+        grid,
+        particle_positions.as_slice(),
+        postprocessing,
+        params.enable_multi_threading,
+    )?;
+    let mesh = postprocessed_mesh.as_ref().unwrap_or(reconstructed_mesh);
 
-            mesh_with_data.point_attributes.push(MeshAttribute::new(
-                "normals".to_string(),
-                AttributeData::Vector3Real(normals),
+    // Add normals to mesh if requested
+    let mut mesh = mesh_with_point_data(
+        mesh,
+        particle_positions.as_slice(),
+        reconstruction.particle_densities().map(Vec::as_slice),
+        attributes,
+        paths,
+        params,
+    )?;
+
+    // Add the vertex velocities of the tracked surface
+    if let Some(tracked_surface) = &tracked_surface {
+        if !mesh
+            .point_attributes
+            .iter()
+            .any(|attribute| attribute.name == "velocity")
+        {
+            mesh.point_attributes.push(MeshAttribute::new(
+                "velocity".to_string(),
+                AttributeData::Vector3Real(tracked_surface.vertex_velocities().to_vec()),
             ));
         }
+    }
 
-        // Interpolate attributes if requested
-        if !attributes.is_empty() {
-            for attribute in attributes.into_iter() {
-                info!("Interpolating attribute \"{}\"...", attribute.name);
-
-                match attribute.data {
-                    AttributeData::ScalarReal(values) => {
-                        let interpolated_values = interpolator.interpolate_scalar_quantity(
-                            values.as_slice(),
-                            mesh.vertices(),
-                            true,
-                        );
-                        mesh_with_data.point_attributes.push(MeshAttribute::new(
-                            attribute.name,
-                            AttributeData::ScalarReal(interpolated_values),
-                        ));
-                    }
-                    AttributeData::Vector3Real(values) => {
-                        let interpolated_values = interpolator.interpolate_vector_quantity(
-                            values.as_slice(),
-                            mesh.vertices(),
-                            true,
-                        );
-                        mesh_with_data.point_attributes.push(MeshAttribute::new(
-                            attribute.name,
-                            AttributeData::Vector3Real(interpolated_values),
-                        ));
-                    }
-                    _ => unimplemented!("Interpolation of this attribute type not implemented"),
-                }
-            }
-        }
-
-        mesh_with_data
-    } else {
-        MeshWithData::new(mesh.clone())
-    };
+    if postprocessing.component_ids {
+        add_component_ids(&mut mesh);
+    }
 
     // Store the surface mesh
-    {
-        profile!("write surface mesh to file");
-        info!(
-            "Writing surface mesh to \"{}\"...",
-            paths.output_file.display()
-        );
-
-        io::write_mesh(&mesh, paths.output_file.clone(), &io_params.output).with_context(|| {
-            anyhow!(
-                "Failed to write output mesh to file \"{}\"",
-                paths.output_file.display()
-            )
-        })?;
-        info!("Done.");
-    }
+    write_surface_mesh(&mesh, &paths.output_file, postprocessing, io_params)?;
 
     // Store octree leaf nodes as hex cells
     if let Some(output_octree_file) = &paths.output_octree_file {
@@ -1034,3 +2426,727 @@ pub(crate) fn reconstruction_pipeline_generic<I: Index, R: Real>(
 
     Ok(())
 }
+
+/// Wrapper for the multiphase reconstruction pipeline: loads input file, runs reconstructions of all phases, stores one output file per phase
+fn multiphase_reconstruction_pipeline_generic<I: Index, R: Real>(
+    paths: &ReconstructionRunnerPaths,
+    params: &splashsurf_lib::Parameters<R>,
+    io_params: &io::FormatParameters,
+    postprocessing: &ReconstructionRunnerPostprocessingArgs,
+    multiphase: &ReconstructionRunnerMultiphaseArgs,
+    check_mesh: bool,
+) -> Result<(), anyhow::Error> {
+    profile!("multiphase surface reconstruction");
+
+    if paths.output_octree_file.is_some()
+        || paths.output_density_map_points_file.is_some()
+        || paths.output_density_map_grid_file.is_some()
+    {
+        return Err(anyhow!(
+            "Writing the octree or density map is not supported for the reconstruction of multiple phases"
+        ));
+    }
+
+    // Load particle positions, the phase attribute and attributes to interpolate (and the velocities for the diffuse particles)
+    let diffuse_velocity_attribute = paths
+        .diffuse
+        .as_ref()
+        .map(|diffuse| &diffuse.velocity_attribute);
+    let mut attribute_names = paths.attributes.clone();
+    for name in std::iter::once(&multiphase.phase_attribute).chain(diffuse_velocity_attribute) {
+        if !attribute_names.contains(name) {
+            attribute_names.push(name.clone());
+        }
+    }
+    let (particle_positions, mut attributes) = read_particles_with_attributes(
+        paths,
+        &attribute_names,
+        io_params,
+        params.enable_multi_threading,
+    )
+    .with_context(|| {
+        format!(
+            "Failed to load particle positions and phases from file \"{}\"",
+            paths.input_file.display()
+        )
+    })?;
+
+    let phase_attribute_index = attribute_names
+        .iter()
+        .position(|name| *name == multiphase.phase_attribute)
+        .unwrap();
+    let particle_phases = phase_ids_from_attribute(&attributes[phase_attribute_index].data)
+        .with_context(|| {
+            format!(
+                "Failed to use attribute \"{}\" as phase ids",
+                multiphase.phase_attribute
+            )
+        })?;
+    let diffuse_particle_velocities = diffuse_velocity_attribute
+        .map(|name| velocities_from_attributes(&attribute_names, &attributes, name))
+        .transpose()?;
+    // Remove the phase and velocity attributes that were not requested for interpolation
+    attributes.drain(paths.attributes.len()..attribute_names.len());
+
+    // Classify the particles of all phases into interior, surface and isolated particles if requested
+    if let Some(classification) = &paths.classification {
+        write_classified_particles::<I, R>(
+            particle_positions.as_slice(),
+            params,
+            classification,
+            io_params,
+        )?;
+    }
+
+    // Generate diffuse particles from the particles of all phases if requested
+    if let (Some(diffuse), Some(particle_velocities)) =
+        (&paths.diffuse, &diffuse_particle_velocities)
+    {
+        write_diffuse_particles::<I, R>(
+            particle_positions.as_slice(),
+            particle_velocities.as_slice(),
+            None,
+            params,
+            diffuse,
+            io_params,
+        )?;
+    }
+
+    // Perform the surface reconstruction of all phases
+    let reconstruction = reconstruct_multiphase_surfaces::<I, R>(
+        particle_positions.as_slice(),
+        particle_phases.as_slice(),
+        ParticleProperties::uniform(),
+        multiphase.mode,
+        params,
+    )?;
+
+    let grid = reconstruction.grid();
+
+    for phase in reconstruction.phases() {
+        let output_file = phase_output_file(&paths.output_file, phase.phase_id());
+
+        // Particle data of the phase for the post-processing and interpolation
+        let phase_particles = (0..particle_positions.len())
+            .filter(|&i| particle_phases[i] == phase.phase_id())
+            .collect::<Vec<_>>();
+        let phase_positions = phase_particles
+            .iter()
+            .map(|&i| particle_positions[i])
+            .collect::<Vec<_>>();
+        let phase_densities = phase_particles
+            .iter()
+            .map(|&i| reconstruction.particle_densities()[i])
+            .collect::<Vec<_>>();
+        let phase_attributes = attributes
+            .iter()
+            .map(|attribute| select_attribute_values(attribute, &phase_particles))
+            .collect::<Vec<_>>();
+
+        // Apply post-processing steps to the mesh if requested
+        let postprocessed_mesh = postprocess_mesh(
+            phase.mesh(),
+            grid,
+            &phase_positions,
+            postprocessing,
+            params.enable_multi_threading,
+        )?;
+        let mesh = postprocessed_mesh.as_ref().unwrap_or_else(|| phase.mesh());
+
+        // Add normals to mesh if requested
+        let mut mesh = mesh_with_point_data(
+            mesh,
+            &phase_positions,
+            Some(&phase_densities),
+            phase_attributes,
+            paths,
+            params,
+        )?;
+
+        if postprocessing.component_ids {
+            add_component_ids(&mut mesh);
+        }
+
+        // Store the surface mesh of the phase
+        write_surface_mesh(&mesh, &output_file, postprocessing, io_params)?;
+
+        if check_mesh {
+            if let Err(err) =
+                splashsurf_lib::marching_cubes::check_mesh_consistency(grid, &mesh.mesh)
+            {
+                return Err(anyhow!("Phase {}: {}", phase.phase_id(), err));
+            } else {
+                info!(
+                    "Checked mesh of phase {} for problems (holes, etc.), no problems were found.",
+                    phase.phase_id()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Loads the particle positions and the given attributes from the input file, appends the particle velocities as "velocity" attribute if motion vectors are requested and applies the temporal filter to the positions if requested
+///
+/// The velocities are read from the first of the motion vector velocity attributes that exists in the input file, otherwise they
+/// are computed by finite differences of the (unfiltered) particle positions of the previous and next frame of the sequence.
+fn read_particles_with_attributes<R: Real>(
+    paths: &ReconstructionRunnerPaths,
+    attribute_names: &[String],
+    io_params: &io::FormatParameters,
+    enable_multi_threading: bool,
+) -> Result<(Vec<Vector3<R>>, Vec<MeshAttribute<R>>), anyhow::Error> {
+    let enable_temporal_filter = paths.temporal_filter_window.len() > 1;
+    let mut optional_attribute_names = Vec::new();
+    if let Some(motion_vectors) = &paths.motion_vectors {
+        optional_attribute_names.extend(motion_vectors.velocity_attributes.iter().cloned());
+        optional_attribute_names.push(motion_vectors.id_attribute.clone());
+    }
+    if enable_temporal_filter
+        && !optional_attribute_names.contains(&paths.temporal_filter_id_attribute)
+    {
+        optional_attribute_names.push(paths.temporal_filter_id_attribute.clone());
+    }
+
+    let (particle_positions, mut attributes, optional_attributes) =
+        if optional_attribute_names.is_empty() {
+            let (particle_positions, attributes) = io::read_particle_positions_with_attributes(
+                &paths.input_file,
+                attribute_names,
+                &io_params.input,
+            )?;
+            (particle_positions, attributes, Vec::new())
+        } else {
+            io::read_particle_positions_with_optional_attributes(
+                &paths.input_file,
+                attribute_names,
+                &optional_attribute_names,
+                &io_params.input,
+            )?
+        };
+
+    if let Some(motion_vectors) = &paths.motion_vectors {
+        let velocity_attribute = motion_vectors.velocity_attributes.iter().find_map(|name| {
+            optional_attributes
+                .iter()
+                .find(|attribute| attribute.name == *name)
+        });
+        let particle_velocities = if let Some(velocity_attribute) = velocity_attribute {
+            info!(
+                "Using particle velocities of attribute \"{}\" for the motion vectors.",
+                velocity_attribute.name
+            );
+            match &velocity_attribute.data {
+                AttributeData::Vector3Real(velocities) => velocities.clone(),
+                _ => {
+                    return Err(anyhow!(
+                        "The velocity attribute \"{}\" has to be a real vector attribute",
+                        velocity_attribute.name
+                    ))
+                }
+            }
+        } else {
+            profile!("finite difference velocities");
+
+            let particle_ids =
+                particle_ids_from_attributes(&optional_attributes, &motion_vectors.id_attribute)?;
+            let load_frame = |input_file: &PathBuf| {
+                io::read_particle_positions_with_optional_attributes::<R, _>(
+                    input_file,
+                    &[],
+                    &[motion_vectors.id_attribute.clone()],
+                    &io_params.input,
+                )
+                .and_then(|(positions, _, attributes)| {
+                    Ok((
+                        positions,
+                        particle_ids_from_attributes(&attributes, &motion_vectors.id_attribute)?,
+                    ))
+                })
+                .with_context(|| {
+                    format!(
+                        "Failed to load particle positions for the motion vectors from file \"{}\"",
+                        input_file.display()
+                    )
+                })
+            };
+            // Without ids, only frames with the same number of particles can be matched
+            let can_be_matched = |(positions, ids): &(Vec<Vector3<R>>, Option<Vec<u64>>)| {
+                let matched = (particle_ids.is_some() && ids.is_some())
+                    || positions.len() == particle_positions.len();
+                if !matched {
+                    warn!(
+                    "Particle count of \"{}\" differs from a neighboring frame and there are no particle ids (\"{}\" attribute), the neighboring frame is not used for the motion vectors.",
+                    paths.input_file.display(),
+                    motion_vectors.id_attribute
+                );
+                }
+                matched
+            };
+            let previous = paths
+                .neighbor_frames
+                .0
+                .as_ref()
+                .map(load_frame)
+                .transpose()?
+                .filter(can_be_matched);
+            let next = paths
+                .neighbor_frames
+                .1
+                .as_ref()
+                .map(load_frame)
+                .transpose()?
+                .filter(can_be_matched);
+
+            fn frame<R: Real>(
+                (positions, ids): &(Vec<Vector3<R>>, Option<Vec<u64>>),
+            ) -> ParticleFrame<'_, R> {
+                ParticleFrame {
+                    positions: positions.as_slice(),
+                    ids: ids.as_deref(),
+                }
+            }
+            let time_step = R::from_f64(motion_vectors.time_step).ok_or(anyhow!(
+            "Unable to convert the motion vector time step from f64 to the floating point type of the reconstruction."
+        ))?;
+            let velocities = finite_difference_velocities(
+            previous.as_ref().map(frame),
+            ParticleFrame {
+                positions: particle_positions.as_slice(),
+                ids: particle_ids.as_deref(),
+            },
+            next.as_ref().map(frame),
+            time_step,
+            enable_multi_threading,
+        )
+        .with_context(|| {
+            format!(
+                "Failed to compute finite difference velocities for the motion vectors of file \"{}\" (none of the velocity attributes \"{}\" exists)",
+                paths.input_file.display(),
+                motion_vectors.velocity_attributes.join("\", \"")
+            )
+        })?;
+            info!(
+            "Computed particle velocities for the motion vectors by finite differences with {} neighboring frame(s).",
+            previous.iter().chain(next.iter()).count()
+        );
+            velocities
+        };
+
+        attributes.push(MeshAttribute::new(
+            "velocity".to_string(),
+            AttributeData::Vector3Real(particle_velocities),
+        ));
+    }
+
+    let particle_positions = if enable_temporal_filter {
+        let particle_ids = particle_ids_from_attributes(
+            &optional_attributes,
+            &paths.temporal_filter_id_attribute,
+        )?;
+        temporally_filtered_positions(
+            paths,
+            particle_positions,
+            particle_ids,
+            io_params,
+            enable_multi_threading,
+        )?
+    } else {
+        particle_positions
+    };
+
+    Ok((particle_positions, attributes))
+}
+
+/// Returns the particle ids stored in the given id attribute if it was loaded
+fn particle_ids_from_attributes<R: Real>(
+    attributes: &[MeshAttribute<R>],
+    id_attribute_name: &str,
+) -> Result<Option<Vec<u64>>, anyhow::Error> {
+    let id_attribute = match attributes
+        .iter()
+        .find(|attribute| attribute.name == id_attribute_name)
+    {
+        Some(id_attribute) => id_attribute,
+        None => return Ok(None),
+    };
+
+    let ids = match &id_attribute.data {
+        AttributeData::ScalarU64(ids) => ids.clone(),
+        // Some exporters store particle ids as floating point values
+        AttributeData::ScalarReal(ids) => ids
+            .iter()
+            .map(|id| {
+                id.to_u64()
+                    .ok_or_else(|| anyhow!("Cannot convert particle id {} to u64", id))
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        AttributeData::Vector3Real(_)
+        | AttributeData::VectorReal(..)
+        | AttributeData::Matrix3Real(_) => {
+            return Err(anyhow!(
+                "The particle id attribute \"{}\" has to be a scalar attribute",
+                id_attribute_name
+            ))
+        }
+    };
+    Ok(Some(ids))
+}
+
+/// Blends the particle positions of the input file with the positions of the neighboring frames in the temporal filter window of the paths
+///
+/// The particles of the frames are matched by the ids of the temporal filter id attribute if the frames have ids, otherwise by their index.
+/// The window is shrunk (keeping it centered) to the largest window where all frames can be matched with the input file, i.e. without ids
+/// all frames have to contain the same number of particles as the input file.
+fn temporally_filtered_positions<R: Real>(
+    paths: &ReconstructionRunnerPaths,
+    particle_positions: Vec<Vector3<R>>,
+    particle_ids: Option<Vec<u64>>,
+    io_params: &io::FormatParameters,
+    enable_multi_threading: bool,
+) -> Result<Vec<Vector3<R>>, anyhow::Error> {
+    if paths.temporal_filter_window.len() <= 1 {
+        return Ok(particle_positions);
+    }
+
+    profile!("temporal filter");
+
+    let center = paths.temporal_filter_window.len() / 2;
+    let mut frames = Vec::with_capacity(paths.temporal_filter_window.len());
+    for (i, input_file) in paths.temporal_filter_window.iter().enumerate() {
+        if i == center {
+            frames.push((Vec::new(), None));
+            continue;
+        }
+        let frame = io::read_particle_positions_with_optional_attributes::<R, _>(
+            input_file,
+            &[],
+            &[paths.temporal_filter_id_attribute.clone()],
+            &io_params.input,
+        )
+        .and_then(|(positions, _, attributes)| {
+            Ok((
+                positions,
+                particle_ids_from_attributes(&attributes, &paths.temporal_filter_id_attribute)?,
+            ))
+        })
+        .with_context(|| {
+            format!(
+                "Failed to load particle positions for temporal filtering from file \"{}\"",
+                input_file.display()
+            )
+        })?;
+        frames.push(frame);
+    }
+    frames[center] = (particle_positions, particle_ids);
+
+    // Without ids, only frames with the same number of particles can be matched
+    let can_be_matched = |(positions, ids): &(Vec<Vector3<R>>, Option<Vec<u64>>)| {
+        (frames[center].1.is_some() && ids.is_some()) || positions.len() == frames[center].0.len()
+    };
+    let radius = (1..=center)
+        .take_while(|&r| can_be_matched(&frames[center - r]) && can_be_matched(&frames[center + r]))
+        .last()
+        .unwrap_or(0);
+    if radius < center {
+        warn!(
+            "Particle count of \"{}\" differs from neighboring frames and there are no particle ids (\"{}\" attribute), reducing the temporal filter radius from {} to {}.",
+            paths.input_file.display(),
+            paths.temporal_filter_id_attribute,
+            center,
+            radius
+        );
+        if radius == 0 {
+            return Ok(frames.swap_remove(center).0);
+        }
+    }
+
+    let window = frames[center - radius..=center + radius]
+        .iter()
+        .map(|(positions, ids)| ParticleFrame {
+            positions: positions.as_slice(),
+            ids: ids.as_deref(),
+        })
+        .collect::<Vec<_>>();
+    let filtered_positions = filter_particle_frames(&window, enable_multi_threading)?;
+    info!(
+        "Applied temporal filter with a window of {} frames to the particle positions.",
+        window.len()
+    );
+
+    Ok(filtered_positions)
+}
+
+/// Returns the path of the output file of the given phase, i.e. "{name}_phase{k}.{extension}" for the output file "{name}.{extension}"
+fn phase_output_file(output_file: &Path, phase_id: u64) -> PathBuf {
+    let mut file_name = output_file.file_stem().unwrap_or_default().to_os_string();
+    file_name.push(format!("_phase{}", phase_id));
+    if let Some(extension) = output_file.extension() {
+        file_name.push(".");
+        file_name.push(extension);
+    }
+    output_file.with_file_name(file_name)
+}
+
+/// Returns a copy of the attribute that only contains the values of the given particles
+fn select_attribute_values<R: Real>(
+    attribute: &MeshAttribute<R>,
+    particles: &[usize],
+) -> MeshAttribute<R> {
+    fn select<T: Clone>(values: &[T], particles: &[usize]) -> Vec<T> {
+        particles.iter().map(|&i| values[i].clone()).collect()
+    }
+
+    let data = match &attribute.data {
+        AttributeData::ScalarU64(values) => AttributeData::ScalarU64(select(values, particles)),
+        AttributeData::ScalarReal(values) => AttributeData::ScalarReal(select(values, particles)),
+        AttributeData::Vector3Real(values) => AttributeData::Vector3Real(select(values, particles)),
+        AttributeData::VectorReal(num_components, values) => AttributeData::VectorReal(
+            *num_components,
+            particles
+                .iter()
+                .flat_map(|&i| &values[i * num_components..(i + 1) * num_components])
+                .copied()
+                .collect(),
+        ),
+        AttributeData::Matrix3Real(values) => AttributeData::Matrix3Real(select(values, particles)),
+    };
+    MeshAttribute::new(attribute.name.clone(), data)
+}
+
+/// Computes the requested surface normals and interpolates the requested particle attributes to the vertices of the mesh
+fn mesh_with_point_data<R: Real>(
+    mesh: &TriMesh3d<R>,
+    particle_positions: &[Vector3<R>],
+    particle_densities: Option<&[R]>,
+    attributes: Vec<MeshAttribute<R>>,
+    paths: &ReconstructionRunnerPaths,
+    params: &splashsurf_lib::Parameters<R>,
+) -> Result<MeshWithData<R, TriMesh3d<R>>, anyhow::Error> {
+    // Add normals to mesh if requested
+    let mesh = if paths.compute_normals || !attributes.is_empty() {
+        profile!("compute normals");
+
+        info!(
+            "Constructing global acceleration structure for SPH interpolation to {} vertices...",
+            mesh.vertices.len()
+        );
+
+        let particle_rest_density = params.rest_density;
+        let particle_rest_volume = R::from_f64((4.0 / 3.0) * std::f64::consts::PI).unwrap()
+            * params.particle_radius.powi(3);
+        let particle_rest_mass = particle_rest_volume * particle_rest_density;
+
+        let particle_densities = particle_densities
+            .ok_or_else(|| anyhow::anyhow!("Particle densities were not returned by surface reconstruction but are required for SPH normal computation"))?;
+        assert_eq!(
+            particle_positions.len(),
+            particle_densities.len(),
+            "There has to be one density value per particle"
+        );
+
+        let interpolator = SphInterpolator::new(
+            particle_positions,
+            particle_densities,
+            particle_rest_mass,
+            ParticleProperties::uniform(),
+            params.compact_support_radius,
+            params.kernel,
+        );
+
+        let mut mesh_with_data = MeshWithData::new(mesh.clone());
+
+        // Compute normals if requested
+        if paths.compute_normals {
+            let normals = if paths.sph_normals {
+                info!("Using SPH interpolation to compute surface normals");
+
+                let sph_normals = interpolator.interpolate_normals(mesh.vertices());
+                bytemuck::allocation::cast_vec::<Unit<Vector3<R>>, Vector3<R>>(sph_normals)
+            } else {
+                info!("Using area weighted triangle normals for surface normals");
+                profile!("mesh.par_vertex_normals");
+                let tri_normals = mesh.par_vertex_normals();
+
+                // Convert unit vectors to plain vectors
+                bytemuck::allocation::cast_vec::<Unit<Vector3<R>>, Vector3<R>>(tri_normals)
+            };
+
+            mesh_with_data.point_attributes.push(MeshAttribute::new(
+                "normals".to_string(),
+                AttributeData::Vector3Real(normals),
+            ));
+        }
+
+        // Interpolate attributes if requested
+        if !attributes.is_empty() {
+            for attribute in attributes.into_iter() {
+                info!("Interpolating attribute \"{}\"...", attribute.name);
+
+                let interpolated_data = match attribute.data {
+                    AttributeData::ScalarReal(values) => AttributeData::ScalarReal(
+                        interpolator.interpolate_scalar_quantity(&values, mesh.vertices(), true),
+                    ),
+                    AttributeData::Vector3Real(values) => AttributeData::Vector3Real(
+                        interpolator.interpolate_vector_quantity(&values, mesh.vertices(), true),
+                    ),
+                    AttributeData::VectorReal(num_components, values) => AttributeData::VectorReal(
+                        num_components,
+                        interpolator.interpolate_flat_vector_quantity(
+                            &values,
+                            num_components,
+                            mesh.vertices(),
+                            true,
+                        ),
+                    ),
+                    AttributeData::Matrix3Real(values) => AttributeData::Matrix3Real(
+                        interpolator.interpolate_tensor_quantity(&values, mesh.vertices(), true),
+                    ),
+                    // Integer values (e.g. phase or object ids) cannot be averaged
+                    AttributeData::ScalarU64(values) => {
+                        AttributeData::ScalarU64(match paths.integer_interpolation {
+                            IntegerInterpolation::Nearest => {
+                                interpolator.interpolate_nearest_particle(&values, mesh.vertices())
+                            }
+                            IntegerInterpolation::Majority => {
+                                interpolator.interpolate_majority_vote(&values, mesh.vertices())
+                            }
+                        })
+                    }
+                };
+                mesh_with_data
+                    .point_attributes
+                    .push(MeshAttribute::new(attribute.name, interpolated_data));
+            }
+        }
+
+        mesh_with_data
+    } else {
+        MeshWithData::new(mesh.clone())
+    };
+
+    Ok(mesh)
+}
+
+/// Adds the index of the connected component of every triangle as "component" cell attribute to the mesh
+fn add_component_ids<R: Real>(mesh: &mut MeshWithData<R, TriMesh3d<R>>) {
+    profile!("compute mesh components");
+
+    let components = compute_mesh_components(&mesh.mesh);
+    info!(
+        "The surface mesh consists of {} connected components.",
+        components.components.len()
+    );
+
+    mesh.cell_attributes.push(MeshAttribute::new(
+        "component".to_string(),
+        AttributeData::ScalarU64(
+            components
+                .triangle_components
+                .into_iter()
+                .map(|c| c as u64)
+                .collect(),
+        ),
+    ));
+}
+
+/// Writes the surface mesh to the given file, converts it to a quad-dominant mesh before if requested
+fn write_surface_mesh<R: Real>(
+    mesh: &MeshWithData<R, TriMesh3d<R>>,
+    output_file: &Path,
+    postprocessing: &ReconstructionRunnerPostprocessingArgs,
+    io_params: &io::FormatParameters,
+) -> Result<(), anyhow::Error> {
+    profile!("write surface mesh to file");
+    info!("Writing surface mesh to \"{}\"...", output_file.display());
+
+    let write_result = if let Some(quad_conversion) = &postprocessing.quad_conversion {
+        let quad_conversion = quad_conversion.try_convert::<R>().ok_or(anyhow!(
+            "Unable to convert quad conversion parameters from f64 to the floating point type of the reconstruction."
+        ))?;
+
+        // The vertices are not modified by the conversion, so the point attributes stay valid
+        let quad_mesh = MeshWithData {
+            mesh: convert_tris_to_quads(&mesh.mesh, &quad_conversion),
+            point_attributes: mesh.point_attributes.clone(),
+            cell_attributes: Vec::new(),
+        };
+        io::write_mesh(&quad_mesh, output_file, &io_params.output)
+    } else {
+        io::write_mesh(mesh, output_file, &io_params.output)
+    };
+
+    write_result.with_context(|| {
+        anyhow!(
+            "Failed to write output mesh to file \"{}\"",
+            output_file.display()
+        )
+    })?;
+    info!("Done.");
+
+    Ok(())
+}
+
+/// Applies all requested post-processing steps to a copy of the given mesh, returns `None` if no post-processing was requested
+fn postprocess_mesh<I: Index, R: Real>(
+    mesh: &TriMesh3d<R>,
+    grid: &UniformGrid<I, R>,
+    particle_positions: &[Vector3<R>],
+    postprocessing: &ReconstructionRunnerPostprocessingArgs,
+    enable_multi_threading: bool,
+) -> Result<Option<TriMesh3d<R>>, anyhow::Error> {
+    if postprocessing.snap_distance.is_none()
+        && postprocessing.component_filter.is_none()
+        && postprocessing.smoothing.is_none()
+        && postprocessing.decimation.is_none()
+    {
+        return Ok(None);
+    }
+
+    profile!("mesh post-processing");
+    let mut mesh = mesh.clone();
+
+    if let Some(snap_distance) = postprocessing.snap_distance {
+        let snap_distance = R::from_f64(snap_distance).ok_or(anyhow!(
+            "Unable to convert mesh cleanup snap distance from f64 to the floating point type of the reconstruction."
+        ))?;
+        snap_vertices_to_grid(&mut mesh, grid, snap_distance);
+    }
+
+    if let Some(component_filter) = &postprocessing.component_filter {
+        let component_filter = component_filter.try_convert::<R>().ok_or(anyhow!(
+            "Unable to convert mesh component filter parameters from f64 to the floating point type of the reconstruction."
+        ))?;
+        filter_mesh_components(&mut mesh, &component_filter);
+    }
+
+    if let Some(smoothing) = &postprocessing.smoothing {
+        let smoothing = smoothing.try_convert::<R>().ok_or(anyhow!(
+            "Unable to convert mesh smoothing parameters from f64 to the floating point type of the reconstruction."
+        ))?;
+        if let Some(weighting) = &postprocessing.smoothing_weighting {
+            let weighting = weighting.try_convert::<R>().ok_or(anyhow!(
+                "Unable to convert mesh smoothing weighting parameters from f64 to the floating point type of the reconstruction."
+            ))?;
+            smooth_mesh_weighted::<I, R>(
+                &mut mesh,
+                particle_positions,
+                &weighting,
+                &smoothing,
+                enable_multi_threading,
+            );
+        } else {
+            smooth_mesh(&mut mesh, &smoothing, enable_multi_threading);
+        }
+    }
+
+    if let Some(decimation) = &postprocessing.decimation {
+        let decimation = decimation.try_convert::<R>().ok_or(anyhow!(
+            "Unable to convert mesh decimation parameters from f64 to the floating point type of the reconstruction."
+        ))?;
+        decimate_mesh(&mut mesh, &decimation);
+    }
+
+    Ok(Some(mesh))
+}
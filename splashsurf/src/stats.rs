@@ -0,0 +1,150 @@
+use crate::io;
+use anyhow::{anyhow, Context};
+use clap::value_parser;
+use log::info;
+use serde_json::{json, Value};
+use splashsurf_lib::mesh::MeshWithData;
+use splashsurf_lib::mesh_quality::{compute_mesh_quality, MeshQualityStatistics, ValueStatistics};
+use splashsurf_lib::profile;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+
+/// Command line arguments for the `stats` subcommand
+#[derive(Clone, Debug, clap::Parser)]
+pub struct StatsSubcommandArgs {
+    /// Path to the input file with a surface mesh (supported formats: .vtk, .ply)
+    #[arg(value_parser = value_parser!(PathBuf))]
+    input_mesh: PathBuf,
+    /// Print the statistics as JSON instead of a human readable summary (combine with -q to suppress all other output)
+    #[arg(long)]
+    json: bool,
+    /// Optional filename for writing the statistics as JSON file
+    #[arg(short = 'o', value_parser = value_parser!(PathBuf))]
+    output_file: Option<PathBuf>,
+}
+
+/// Executes the `stats` subcommand
+pub fn stats_subcommand(cmd_args: &StatsSubcommandArgs) -> Result<(), anyhow::Error> {
+    profile!("mesh stats cli");
+
+    let io_params = io::FormatParameters::default();
+    let input_file = &cmd_args.input_mesh;
+
+    let mesh: MeshWithData<f64, _> = io::read_surface_mesh(input_file.as_path(), &io_params.input)
+        .with_context(|| {
+            format!(
+                "Failed to load surface mesh from file \"{}\"",
+                input_file.as_path().display()
+            )
+        })?;
+
+    let stats = compute_mesh_quality(&mesh.mesh);
+    let stats_json = stats_to_json(&stats);
+
+    if cmd_args.json {
+        println!("{}", serde_json::to_string_pretty(&stats_json)?);
+    } else {
+        print_stats(&stats);
+    }
+
+    if let Some(output_file) = &cmd_args.output_file {
+        info!(
+            "Writing mesh statistics to \"{}\"...",
+            output_file.display()
+        );
+        let file = File::create(output_file).with_context(|| {
+            anyhow!("Failed to create output file \"{}\"", output_file.display())
+        })?;
+        serde_json::to_writer_pretty(BufWriter::new(file), &stats_json)
+            .context("Failed to write mesh statistics to JSON file")?;
+        info!("Done.");
+    }
+
+    Ok(())
+}
+
+/// Prints a human readable summary of the mesh statistics to stdout
+fn print_stats(stats: &MeshQualityStatistics<f64>) {
+    let print_value_stats = |name: &str, value_stats: &Option<ValueStatistics<f64>>| {
+        if let Some(value_stats) = value_stats {
+            println!(
+                "{:<22} min: {:.6}, max: {:.6}, mean: {:.6}",
+                name, value_stats.min, value_stats.max, value_stats.mean
+            );
+        } else {
+            println!("{:<22} -", name);
+        }
+    };
+
+    println!(
+        "Vertices:              {} ({} unreferenced)",
+        stats.num_vertices, stats.num_unreferenced_vertices
+    );
+    println!(
+        "Triangles:             {} ({} degenerate)",
+        stats.num_triangles, stats.num_degenerate_triangles
+    );
+    println!("Edges:                 {}", stats.num_edges);
+    print_value_stats("Edge lengths:", &stats.edge_length);
+    print_value_stats("Aspect ratios:", &stats.aspect_ratio);
+    print_value_stats("Min. angles (deg):", &stats.min_angle);
+    print_value_stats("Max. angles (deg):", &stats.max_angle);
+    println!("Area:                  {}", stats.area);
+    println!("Volume:                {}", stats.volume);
+    if let Some(aabb) = &stats.aabb {
+        println!(
+            "Bounding box:          [{}, {}, {}] to [{}, {}, {}]",
+            aabb.min().x,
+            aabb.min().y,
+            aabb.min().z,
+            aabb.max().x,
+            aabb.max().y,
+            aabb.max().z
+        );
+    }
+    println!("Connected components:  {}", stats.num_components);
+    println!("Euler characteristic:  {}", stats.euler_characteristic);
+    match stats.genus {
+        Some(genus) => println!("Genus:                 {}", genus),
+        None => println!("Genus:                 - (non-manifold mesh)"),
+    }
+    println!(
+        "Boundary:              {} edges in {} loops",
+        stats.num_boundary_edges, stats.num_boundary_loops
+    );
+    println!("Non-manifold edges:    {}", stats.num_non_manifold_edges);
+}
+
+/// Converts the mesh statistics to a JSON object
+fn stats_to_json(stats: &MeshQualityStatistics<f64>) -> Value {
+    let value_stats_to_json = |value_stats: &Option<ValueStatistics<f64>>| {
+        value_stats
+            .map(|s| json!({ "min": s.min, "max": s.max, "mean": s.mean }))
+            .unwrap_or(Value::Null)
+    };
+
+    json!({
+        "num_vertices": stats.num_vertices,
+        "num_unreferenced_vertices": stats.num_unreferenced_vertices,
+        "num_triangles": stats.num_triangles,
+        "num_edges": stats.num_edges,
+        "num_degenerate_triangles": stats.num_degenerate_triangles,
+        "edge_length": value_stats_to_json(&stats.edge_length),
+        "aspect_ratio": value_stats_to_json(&stats.aspect_ratio),
+        "min_angle": value_stats_to_json(&stats.min_angle),
+        "max_angle": value_stats_to_json(&stats.max_angle),
+        "area": stats.area,
+        "volume": stats.volume,
+        "aabb": stats.aabb.as_ref().map(|aabb| json!({
+            "min": [aabb.min().x, aabb.min().y, aabb.min().z],
+            "max": [aabb.max().x, aabb.max().y, aabb.max().z],
+        })),
+        "num_components": stats.num_components,
+        "euler_characteristic": stats.euler_characteristic,
+        "genus": stats.genus,
+        "num_boundary_edges": stats.num_boundary_edges,
+        "num_boundary_loops": stats.num_boundary_loops,
+        "num_non_manifold_edges": stats.num_non_manifold_edges,
+    })
+}
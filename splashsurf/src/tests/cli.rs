@@ -1,4 +1,7 @@
-use crate::reconstruction::Switch;
+use crate::reconstruction::{
+    BoundaryMode, IntegerInterpolation, Kernel, MeshSmoothingWeights, PhaseMode, SurfaceExtraction,
+    Switch,
+};
 use crate::Subcommand;
 use std::path::PathBuf;
 
@@ -20,6 +23,18 @@ fn verify_convert_cli() {
     crate::convert::ConvertSubcommandArgs::command().debug_assert()
 }
 
+#[test]
+fn verify_info_cli() {
+    use clap::CommandFactory;
+    crate::info::InfoSubcommandArgs::command().debug_assert()
+}
+
+#[test]
+fn verify_stats_cli() {
+    use clap::CommandFactory;
+    crate::stats::StatsSubcommandArgs::command().debug_assert()
+}
+
 #[test]
 fn test_main_cli() {
     use clap::Parser;
@@ -116,8 +131,45 @@ fn test_main_cli() {
     {
         assert_eq!(rec_args.domain_min, Some(vec![-1.0, 1.0, -1.0]));
         assert_eq!(rec_args.domain_max, Some(vec![-2.0, 2.0, -2.0]));
+        assert_eq!(rec_args.cap_domain_boundary, Switch::Off);
+    };
+
+    // Test domain min/max: caps at the domain boundary
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--domain-min",
+        "-1.0",
+        "1.0",
+        "-1.0",
+        "--domain-max",
+        "-2.0",
+        "2.0",
+        "-2.0",
+        "--cap-domain-boundary=on",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.cap_domain_boundary, Switch::On);
     };
 
+    // Test domain min/max: caps require a domain
+    assert!(crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--cap-domain-boundary=on",
+    ])
+    .is_err());
+
     // Test domain min/max: too many values
     assert_eq!(
         crate::CommandlineArgs::try_parse_from([
@@ -141,4 +193,684 @@ fn test_main_cli() {
         .kind(),
         clap::error::ErrorKind::UnknownArgument
     );
+
+    // Test mesh smoothing arguments
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--mesh-smoothing-iters=10",
+        "--mesh-smoothing-weights=cotangent",
+        "--mesh-smoothing-mu",
+        "-0.53",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.mesh_smoothing_iters, Some(10));
+        assert_eq!(
+            rec_args.mesh_smoothing_weights,
+            MeshSmoothingWeights::Cotangent
+        );
+        assert_eq!(rec_args.mesh_smoothing_lambda, 0.5);
+        assert_eq!(rec_args.mesh_smoothing_mu, Some(-0.53));
+        assert_eq!(rec_args.mesh_smoothing_weighted, Switch::Off);
+        assert_eq!(rec_args.mesh_smoothing_fixed_distance, 1.0);
+        assert_eq!(rec_args.mesh_smoothing_full_distance, 2.0);
+        assert_eq!(rec_args.mesh_smoothing_preserve_volume, Switch::Off);
+        assert_eq!(rec_args.mesh_decimation_target, None);
+    };
+
+    // Test weighted mesh smoothing arguments
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--mesh-smoothing-iters=10",
+        "--mesh-smoothing-weighted=on",
+        "--mesh-smoothing-fixed-distance=0.5",
+        "--mesh-smoothing-full-distance=1.5",
+        "--mesh-smoothing-preserve-volume=on",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.mesh_smoothing_iters, Some(10));
+        assert_eq!(rec_args.mesh_smoothing_weighted, Switch::On);
+        assert_eq!(rec_args.mesh_smoothing_fixed_distance, 0.5);
+        assert_eq!(rec_args.mesh_smoothing_full_distance, 1.5);
+        assert_eq!(rec_args.mesh_smoothing_preserve_volume, Switch::On);
+    };
+
+    // Test mesh decimation arguments
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--mesh-decimation-target=5000",
+        "--mesh-decimation-max-error=0.1",
+        "--mesh-cleanup-snap-distance=0.05",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.mesh_decimation_target, Some(5000));
+        assert_eq!(rec_args.mesh_decimation_max_error, Some(0.1));
+        assert_eq!(rec_args.mesh_cleanup_snap_distance, Some(0.05));
+    };
+
+    // Vertex snapping is rejected with surface nets because their vertices do not lie on the edges of the grid
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "../data/cube_8_particles.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--octree-decomposition=off",
+        "--surface-extraction=surface-nets",
+        "--mesh-cleanup-snap-distance=0.05",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        let err = crate::reconstruction::reconstruct_subcommand(&rec_args)
+            .expect_err("vertex snapping is not supported with surface nets");
+        assert!(format!("{:#}", err).contains("--mesh-cleanup-snap-distance"));
+    };
+
+    // Test surface extraction argument
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(
+            rec_args.surface_extraction,
+            SurfaceExtraction::MarchingCubes
+        );
+    };
+
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--surface-extraction=surface-nets",
+        "--subdomain-grid=on",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.surface_extraction, SurfaceExtraction::SurfaceNets);
+    };
+
+    // Test kernel argument
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.kernel, Kernel::CubicSpline);
+    };
+
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--kernel=wendland-c4",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.kernel, Kernel::WendlandC4);
+    };
+
+    // Test quad generation arguments
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--generate-quads=on",
+        "--quad-max-normal-angle=15",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.generate_quads, Switch::On);
+        assert_eq!(rec_args.quad_max_normal_angle, 15.0);
+        assert_eq!(rec_args.quad_max_interior_angle, 135.0);
+        assert_eq!(rec_args.quad_max_edge_ratio, 1.75);
+    };
+
+    // Test anisotropic kernel arguments
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--anisotropic-kernels=on",
+        "--anisotropy-smoothing-factor=0.9",
+        "--anisotropy-min-neighbors=25",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.anisotropic_kernels, Switch::On);
+        assert_eq!(rec_args.anisotropy_smoothing_factor, 0.9);
+        assert_eq!(rec_args.anisotropy_max_eigenvalue_ratio, 4.0);
+        assert_eq!(rec_args.anisotropy_min_neighbors, 25);
+    };
+
+    // Test multiphase arguments
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.bgeo",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.phase_attribute, None);
+        assert_eq!(rec_args.phase_mode, PhaseMode::Separate);
+    };
+
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.bgeo",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--octree-decomposition=off",
+        "--phase-attribute",
+        "phase",
+        "--phase-mode=against-others",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.phase_attribute, Some("phase".to_string()));
+        assert_eq!(rec_args.phase_mode, PhaseMode::AgainstOthers);
+    };
+
+    // Test boundary arguments
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.boundary_mesh, None);
+        assert_eq!(rec_args.boundary_particles, None);
+        assert_eq!(rec_args.boundary_particle_radius, 1.0);
+        assert_eq!(rec_args.boundary_handling, BoundaryMode::ExtendAndCut);
+        assert_eq!(rec_args.boundary_density_correction, Switch::Off);
+        assert_eq!(rec_args.boundary_volume_attribute, None);
+    };
+
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--boundary-particles",
+        "walls.bgeo",
+        "--boundary-particle-radius",
+        "0.5",
+        "--boundary-handling=cut",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(
+            rec_args.boundary_particles,
+            Some(PathBuf::from("walls.bgeo"))
+        );
+        assert_eq!(rec_args.boundary_particle_radius, 0.5);
+        assert_eq!(rec_args.boundary_handling, BoundaryMode::Cut);
+    };
+
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--boundary-particles",
+        "walls.bgeo",
+        "--boundary-density-correction=on",
+        "--boundary-volume-attribute",
+        "volume",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.boundary_density_correction, Switch::On);
+        assert_eq!(
+            rec_args.boundary_volume_attribute,
+            Some("volume".to_string())
+        );
+    };
+
+    // The wall density correction requires boundary particles
+    assert!(crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--boundary-density-correction=on",
+    ])
+    .is_err());
+
+    // A boundary mesh and boundary particles cannot be used at the same time
+    assert!(crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--boundary-mesh",
+        "walls.ply",
+        "--boundary-particles",
+        "walls.bgeo",
+    ])
+    .is_err());
+
+    // Temporal filtering of sequences
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test_{}.vtk",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--mt-files=on",
+        "--temporal-filter-radius=2",
+        "--temporal-filter-id-attribute=pid",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.parallelize_over_files, Switch::On);
+        assert_eq!(rec_args.temporal_filter_radius, Some(2));
+        assert_eq!(rec_args.temporal_filter_id_attribute, "pid");
+    };
+
+    // Surface tracking of sequences
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test_{}.bgeo",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--surface-tracking=on",
+        "--tracking-time-step=0.01",
+        "--tracking-velocity-attribute=v",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.surface_tracking, Switch::On);
+        assert_eq!(rec_args.tracking_time_step, Some(0.01));
+        assert_eq!(rec_args.tracking_velocity_attribute, "v");
+    };
+
+    // Surface tracking requires the time step between the frames
+    assert!(crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test_{}.bgeo",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--surface-tracking=on",
+    ])
+    .is_err());
+
+    // Motion vectors with the default attribute names
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test_{}.bgeo",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--motion-vectors=on",
+        "--motion-vector-time-step=0.04",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.motion_vectors, Switch::On);
+        assert_eq!(
+            rec_args.motion_vector_attributes,
+            vec!["v".to_string(), "velocity".to_string()]
+        );
+        assert_eq!(rec_args.motion_vector_id_attribute, "id");
+        assert_eq!(rec_args.motion_vector_time_step, 0.04);
+    };
+
+    // Motion vectors and surface tracking both write the vertex velocities
+    assert!(crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test_{}.bgeo",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--motion-vectors=on",
+        "--motion-vector-attributes=vel",
+        "--surface-tracking=on",
+        "--tracking-time-step=0.01",
+    ])
+    .is_err());
+
+    // Integer attributes are interpolated by majority vote by default
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.bgeo",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--interpolate-attributes=phase",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(
+            rec_args.integer_attribute_interpolation,
+            IntegerInterpolation::Majority
+        );
+    };
+
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.bgeo",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--interpolate-attributes=phase",
+        "--integer-attribute-interpolation=nearest",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(
+            rec_args.integer_attribute_interpolation,
+            IntegerInterpolation::Nearest
+        );
+    };
+
+    // Particle classification
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.bgeo",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--output-classified-particles=classes.bgeo",
+        "--classification-surface-threshold=0.8",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(
+            rec_args.output_classified_particles,
+            Some(PathBuf::from("classes.bgeo"))
+        );
+        assert_eq!(rec_args.classification_surface_threshold, 0.8);
+        assert_eq!(rec_args.classification_max_isolated_neighbors, 5);
+    };
+
+    // The classification parameters require an output file for the classified particles
+    assert!(crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.bgeo",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--classification-max-isolated-neighbors=3",
+    ])
+    .is_err());
+
+    // Diffuse particles
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.bgeo",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--output-diffuse-particles=diffuse.bgeo",
+        "--diffuse-time-step=0.01",
+        "--diffuse-trapped-air-range",
+        "2.0",
+        "10.0",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(
+            rec_args.output_diffuse_particles,
+            Some(PathBuf::from("diffuse.bgeo"))
+        );
+        assert_eq!(rec_args.diffuse_time_step, Some(0.01));
+        assert_eq!(rec_args.diffuse_velocity_attribute, "velocity");
+        assert_eq!(rec_args.diffuse_trapped_air_range, vec![2.0, 10.0]);
+        assert_eq!(rec_args.diffuse_wave_crest_range, vec![2.0, 8.0]);
+    };
+
+    // The diffuse particles require the time step of the simulation
+    assert!(crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.bgeo",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--output-diffuse-particles=diffuse.bgeo",
+    ])
+    .is_err());
+
+    // Removal of small mesh fragments
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.bgeo",
+        "--particle-radius=0.05",
+        "--smoothing-length=3.0",
+        "--cube-size=0.75",
+        "--mesh-cleanup-min-component-triangles=100",
+        "--mesh-cleanup-keep-largest-components=2",
+        "--mesh-component-ids=on",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.mesh_cleanup_min_component_triangles, Some(100));
+        assert_eq!(rec_args.mesh_cleanup_min_component_area, None);
+        assert_eq!(rec_args.mesh_cleanup_keep_largest_components, Some(2));
+        assert_eq!(rec_args.mesh_component_ids, Switch::On);
+    };
+
+    // Mesh statistics
+    assert!(matches!(
+        crate::CommandlineArgs::try_parse_from(["splashsurf", "stats", "mesh.vtk", "--json"])
+            .expect("this command is supposed to work")
+            .subcommand,
+        Subcommand::Stats(_)
+    ));
+
+    // Particle file information
+    assert!(matches!(
+        crate::CommandlineArgs::try_parse_from(["splashsurf", "info", "test.bgeo", "-r", "0.025"])
+            .expect("this command is supposed to work")
+            .subcommand,
+        Subcommand::Info(_)
+    ));
+    assert!(matches!(
+        crate::CommandlineArgs::try_parse_from([
+            "splashsurf",
+            "info",
+            "test.bgeo",
+            "--kernel=wendland-c2"
+        ])
+        .expect("this command is supposed to work")
+        .subcommand,
+        Subcommand::Info(_)
+    ));
+
+    // Automatic particle radius
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.bgeo",
+        "--auto-radius=on",
+        "--smoothing-length=2.0",
+        "--cube-size=0.5",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.particle_radius, None);
+        assert_eq!(rec_args.auto_radius, Switch::On);
+        assert_eq!(rec_args.auto_radius_factor, 1.5);
+    };
+
+    // Automatic particle radius with a non-positive factor is rejected before reading any input
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "../data/cube_8_particles.vtk",
+        "--auto-radius=on",
+        "--auto-radius-factor=0.0",
+        "--smoothing-length=2.0",
+        "--cube-size=0.5",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        let err = crate::reconstruction::reconstruct_subcommand(&rec_args)
+            .expect_err("a non-positive radius factor is supposed to fail");
+        assert!(format!("{:#}", err).contains("--auto-radius-factor"));
+    };
+
+    // Adaptive resolution
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.bgeo",
+        "-r=0.025",
+        "-l=2.0",
+        "-c=0.5",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.adaptive_resolution, Switch::Off);
+        assert_eq!(rec_args.adaptive_max_level, 2);
+        assert_eq!(rec_args.adaptive_tolerance, 0.1);
+    };
+
+    if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from([
+        "splashsurf",
+        "reconstruct",
+        "test.bgeo",
+        "-r=0.025",
+        "-l=2.0",
+        "-c=0.5",
+        "--surface-extraction=surface-nets",
+        "--adaptive-resolution=on",
+        "--adaptive-max-level=3",
+        "--adaptive-tolerance=0.05",
+    ])
+    .expect("this command is supposed to work")
+    .subcommand
+    {
+        assert_eq!(rec_args.adaptive_resolution, Switch::On);
+        assert_eq!(rec_args.adaptive_max_level, 3);
+        assert_eq!(rec_args.adaptive_tolerance, 0.05);
+    };
+
+    // Adaptive resolution is rejected with the default octree decomposition and with marching cubes before reading any input
+    for (extra_args, expected_error) in [
+        (
+            &["--surface-extraction=surface-nets"][..],
+            "--octree-decomposition=off",
+        ),
+        (
+            &["--octree-decomposition=off", "--subdomain-grid=off"][..],
+            "--surface-extraction=surface-nets",
+        ),
+    ] {
+        let mut args = vec![
+            "splashsurf",
+            "reconstruct",
+            "../data/cube_8_particles.vtk",
+            "-r=0.025",
+            "-l=2.0",
+            "-c=0.5",
+            "--adaptive-resolution=on",
+        ];
+        args.extend_from_slice(extra_args);
+        if let Subcommand::Reconstruct(rec_args) = crate::CommandlineArgs::try_parse_from(args)
+            .expect("this command is supposed to work")
+            .subcommand
+        {
+            let err = crate::reconstruction::reconstruct_subcommand(&rec_args)
+                .expect_err("adaptive resolution is not supported with these arguments");
+            assert!(format!("{:#}", err).contains(expected_error));
+        };
+    }
 }
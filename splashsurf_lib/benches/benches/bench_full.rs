@@ -4,7 +4,7 @@ use splashsurf_lib::io::particles_from_file;
 #[allow(dead_code)]
 use splashsurf_lib::io::vtk_format::write_vtk;
 use splashsurf_lib::{
-    reconstruct_surface, reconstruct_surface_inplace, Parameters,
+    reconstruct_surface, reconstruct_surface_inplace, IsoSurfaceExtraction, KernelType, Parameters,
     ParticleDensityComputationStrategy, SpatialDecompositionParameters, SubdivisionCriterion,
     SurfaceReconstruction,
 };
@@ -28,6 +28,13 @@ pub fn surface_reconstruction_canyon(c: &mut Criterion) {
         domain_aabb: None,
         enable_multi_threading: true,
         spatial_decomposition: None,
+        iso_surface_extraction: IsoSurfaceExtraction::MarchingCubes,
+        adaptive_resolution: None,
+        kernel: KernelType::CubicSpline,
+        anisotropy: None,
+        boundary: None,
+        boundary_particles: None,
+        cap_domain_boundary: false,
     };
 
     let mut group = c.benchmark_group("full surface reconstruction");
@@ -105,6 +112,13 @@ pub fn surface_reconstruction_dam_break(c: &mut Criterion) {
         enable_multi_threading: true,
         subdomain_num_cubes_per_dim: None,
         spatial_decomposition: None,
+        iso_surface_extraction: IsoSurfaceExtraction::MarchingCubes,
+        adaptive_resolution: None,
+        kernel: KernelType::CubicSpline,
+        anisotropy: None,
+        boundary: None,
+        boundary_particles: None,
+        cap_domain_boundary: false,
     };
 
     let mut group = c.benchmark_group("full surface reconstruction");
@@ -196,6 +210,13 @@ pub fn surface_reconstruction_double_dam_break(c: &mut Criterion) {
         enable_multi_threading: true,
         subdomain_num_cubes_per_dim: None,
         spatial_decomposition: None,
+        iso_surface_extraction: IsoSurfaceExtraction::MarchingCubes,
+        adaptive_resolution: None,
+        kernel: KernelType::CubicSpline,
+        anisotropy: None,
+        boundary: None,
+        boundary_particles: None,
+        cap_domain_boundary: false,
     };
 
     let mut group = c.benchmark_group("full surface reconstruction");
@@ -287,6 +308,13 @@ pub fn surface_reconstruction_double_dam_break_inplace(c: &mut Criterion) {
         enable_multi_threading: true,
         subdomain_num_cubes_per_dim: None,
         spatial_decomposition: None,
+        iso_surface_extraction: IsoSurfaceExtraction::MarchingCubes,
+        adaptive_resolution: None,
+        kernel: KernelType::CubicSpline,
+        anisotropy: None,
+        boundary: None,
+        boundary_particles: None,
+        cap_domain_boundary: false,
     };
 
     let mut group = c.benchmark_group("full surface reconstruction");
@@ -2,8 +2,9 @@ use criterion::{criterion_group, Criterion};
 use splashsurf_lib::io::particles_from_file;
 use splashsurf_lib::nalgebra::Vector3;
 use splashsurf_lib::{
-    reconstruct_surface, Parameters, ParticleDensityComputationStrategy,
-    SpatialDecompositionParameters, SubdivisionCriterion, SurfaceReconstruction,
+    reconstruct_surface, IsoSurfaceExtraction, KernelType, Parameters,
+    ParticleDensityComputationStrategy, SpatialDecompositionParameters, SubdivisionCriterion,
+    SurfaceReconstruction,
 };
 use std::path::Path;
 use std::time::Duration;
@@ -30,6 +31,13 @@ fn reconstruct_particles<P: AsRef<Path>>(particle_file: P) -> SurfaceReconstruct
             enable_stitching: true,
             particle_density_computation: ParticleDensityComputationStrategy::SynchronizeSubdomains,
         }),
+        iso_surface_extraction: IsoSurfaceExtraction::MarchingCubes,
+        adaptive_resolution: None,
+        kernel: KernelType::CubicSpline,
+        anisotropy: None,
+        boundary: None,
+        boundary_particles: None,
+        cap_domain_boundary: false,
     };
 
     reconstruct_surface::<i64, _>(particle_positions.as_slice(), &parameters).unwrap()
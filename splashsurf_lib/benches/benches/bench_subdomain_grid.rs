@@ -1,7 +1,9 @@
 use criterion::{criterion_group, Criterion, SamplingMode};
 use nalgebra::Vector3;
 use splashsurf_lib::io::particles_from_file;
-use splashsurf_lib::{reconstruct_surface, Parameters, SurfaceReconstruction};
+use splashsurf_lib::{
+    reconstruct_surface, IsoSurfaceExtraction, KernelType, Parameters, SurfaceReconstruction,
+};
 use std::time::Duration;
 
 fn parameters_canyon() -> Parameters<f32> {
@@ -19,6 +21,13 @@ fn parameters_canyon() -> Parameters<f32> {
         enable_multi_threading: true,
         subdomain_num_cubes_per_dim: Some(32),
         spatial_decomposition: None,
+        iso_surface_extraction: IsoSurfaceExtraction::MarchingCubes,
+        adaptive_resolution: None,
+        kernel: KernelType::CubicSpline,
+        anisotropy: None,
+        boundary: None,
+        boundary_particles: None,
+        cap_domain_boundary: false,
     };
 
     parameters
@@ -0,0 +1,781 @@
+//! Adaptive resolution triangulation with finer cells close to regions of high curvature
+//!
+//! With a uniform background grid, a single cube size has to be chosen for the entire surface. This either wastes
+//! resolution on flat parts of the surface (e.g. the surface of a pool) or under-resolves small features like thin
+//! sheets and droplets of splashes. In adaptive mode, every cell of the background grid is the root of an octree
+//! of cells which are only subdivided where the iso-surface is poorly represented by the values at their corners:
+//!  1. A cell is subdivided if the density values at its corners and at its center are not all on the same side of
+//!     the iso-surface and if the density at its center deviates by more than [`AdaptiveResolutionParameters::refinement_tolerance`]
+//!     (relative to the iso-surface threshold) from the trilinear interpolation of its corners. This deviation is
+//!     large in regions where the density field is strongly curved, e.g. at thin features and small droplets.
+//!  2. Cells are subdivided recursively up to [`AdaptiveResolutionParameters::max_refinement_level`] times, i.e. the
+//!     smallest cells have an edge length of `cube_size / 2^max_refinement_level`. The density values at the
+//!     additional points of subdivided cells are evaluated directly from the particles with the same discretized
+//!     kernel as the density map (see [`evaluate_density_at_points`](crate::density_map::evaluate_density_at_points)).
+//!  3. Cells with ambiguous faces (i.e. the iso-surface crosses the boundary of a face more than twice, also
+//!     considering the values of smaller neighbors) are subdivided as well, because surface nets would connect
+//!     the vertices of the two cells sharing such a face by more than two triangles (non-manifold edges).
+//!  4. The leaf cells of the octree are triangulated by surface nets on the octree (also known as dual contouring):
+//!     every leaf cell intersected by the iso-surface gets one vertex (see [`surface_nets`](crate::surface_nets)) and
+//!     for every minimal edge crossing the iso-surface (i.e. an edge of a leaf cell that is not subdivided by a smaller
+//!     neighbor) the vertices of the three or four leaf cells around the edge are connected.
+//!
+//! Marching cubes with different cube sizes per cell would require special transition cells between cells of different
+//! sizes (e.g. as in the Transvoxel algorithm) to avoid cracks in the surface. In contrast, the dual triangulation directly
+//! connects the vertices of neighboring cells of different sizes. Therefore, the resulting mesh is free of cracks for
+//! arbitrary level differences between neighboring cells.
+//!
+//! As for the uniform surface nets, the iso-surface should not intersect the outermost layer of cells of the
+//! background grid to obtain a closed mesh. Faces that are still ambiguous on the finest level (e.g. at saddle points
+//! of the density field close to the iso-surface threshold) can result in a few non-manifold edges.
+//!
+//! Adaptive resolution is only supported by the global reconstruction with surface nets. The octree of cells is
+//! independent of the spatial decomposition [`Octree`](crate::octree::Octree) of the particles and the stitching
+//! of the marching cubes patches of neighboring subdomains does not support cells of different sizes. Therefore, the reconstruction returns an error if adaptive resolution is
+//! combined with the octree decomposition, the subdomain grid or marching cubes.
+
+use crate::mesh::TriMesh3d;
+use crate::surface_nets::{
+    cell_vertex_position, quad_to_triangles, CELL_CORNER_OFFSETS, EDGE_ADJACENT_CELL_OFFSETS,
+};
+use crate::topology::Axis;
+use crate::{
+    new_map, profile, DensityMap, HashState, Index, MapType, Real, ReconstructionError, UniformGrid,
+};
+use anyhow::anyhow;
+use log::info;
+use nalgebra::Vector3;
+use std::collections::hash_map::Entry;
+use std::collections::HashSet;
+
+/// Parameters for the adaptive refinement of the cells of the background grid
+#[derive(Clone, Debug)]
+pub struct AdaptiveResolutionParameters<R: Real> {
+    /// Maximum number of subdivisions of a cell of the background grid, the smallest cells have an edge length of `cube_size / 2^max_refinement_level`
+    pub max_refinement_level: usize,
+    /// Cells are subdivided if the density at their center deviates by more than this value (relative to the iso-surface threshold) from the trilinear interpolation of their corners
+    pub refinement_tolerance: R,
+}
+
+impl<R: Real> Default for AdaptiveResolutionParameters<R> {
+    /// Returns parameters with a maximum refinement level of 2 and a refinement tolerance of 0.1
+    fn default() -> Self {
+        Self {
+            max_refinement_level: 2,
+            refinement_tolerance: R::from_f64(0.1).unwrap(),
+        }
+    }
+}
+
+impl<R: Real> AdaptiveResolutionParameters<R> {
+    /// Largest supported value of the maximum refinement level
+    pub const MAX_REFINEMENT_LEVEL: usize = 8;
+
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns None if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<AdaptiveResolutionParameters<T>> {
+        Some(AdaptiveResolutionParameters {
+            max_refinement_level: self.max_refinement_level,
+            refinement_tolerance: self.refinement_tolerance.try_convert()?,
+        })
+    }
+
+    /// Checks that the maximum refinement level is supported and that the refinement tolerance is not negative
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.max_refinement_level > Self::MAX_REFINEMENT_LEVEL {
+            return Err(anyhow::anyhow!(
+                "the maximum refinement level of the adaptive resolution ({}) must not exceed {}",
+                self.max_refinement_level,
+                Self::MAX_REFINEMENT_LEVEL
+            ));
+        }
+        if self.refinement_tolerance < R::zero() {
+            return Err(anyhow::anyhow!(
+                "the refinement tolerance of the adaptive resolution has to be non-negative, found {}",
+                self.refinement_tolerance
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Performs an adaptive resolution triangulation of a density map on the given background grid
+///
+/// The density map contains the values at the points of the background grid, points without a value are considered
+/// to be outside of the fluid. The closure `evaluate_density` is used to compute the density values at the additional
+/// points of subdivided cells. It receives a batch of points and has to return their values in the same order.
+pub fn triangulate_density_map<
+    I: Index,
+    R: Real,
+    F: Fn(&[Vector3<R>]) -> Result<Vec<R>, ReconstructionError<I, R>>,
+>(
+    grid: &UniformGrid<I, R>,
+    density_map: &DensityMap<I, R>,
+    iso_surface_threshold: R,
+    parameters: &AdaptiveResolutionParameters<R>,
+    evaluate_density: F,
+) -> Result<TriMesh3d<R>, ReconstructionError<I, R>> {
+    profile!("adaptive::triangulate_density_map");
+
+    let mut mesh = TriMesh3d::default();
+    triangulate_density_map_append(
+        grid,
+        density_map,
+        iso_surface_threshold,
+        parameters,
+        evaluate_density,
+        &mut mesh,
+    )?;
+    Ok(mesh)
+}
+
+/// Performs an adaptive resolution triangulation of a density map on the given background grid, appends triangles to the given mesh
+///
+/// See [`triangulate_density_map`] for details.
+pub fn triangulate_density_map_append<
+    I: Index,
+    R: Real,
+    F: Fn(&[Vector3<R>]) -> Result<Vec<R>, ReconstructionError<I, R>>,
+>(
+    grid: &UniformGrid<I, R>,
+    density_map: &DensityMap<I, R>,
+    iso_surface_threshold: R,
+    parameters: &AdaptiveResolutionParameters<R>,
+    evaluate_density: F,
+    mesh: &mut TriMesh3d<R>,
+) -> Result<(), ReconstructionError<I, R>> {
+    profile!("adaptive::triangulate_density_map_append");
+
+    let mut octree = CellOctree::new(grid, density_map, parameters.max_refinement_level);
+    octree.refine(
+        iso_surface_threshold,
+        parameters.refinement_tolerance,
+        &evaluate_density,
+    )?;
+    octree.triangulate_append(iso_surface_threshold, mesh)
+}
+
+/// Returns an error for inconsistent internal data of the octree
+fn octree_error<I: Index, R: Real>(message: &str) -> ReconstructionError<I, R> {
+    anyhow!("inconsistent adaptive resolution octree: {}", message).into()
+}
+
+/// Integer `[i, j, k]` index of a point or cell, either on the finest refinement level or on a given level
+type Ijk = [i64; 3];
+
+/// Octree of cells with the cells of the background grid as roots
+struct CellOctree<'a, I: Index, R: Real> {
+    grid: &'a UniformGrid<I, R>,
+    density_map: &'a DensityMap<I, R>,
+    max_level: usize,
+    /// Edge length of the cells of the background grid in units of the cells on the finest level
+    root_size: i64,
+    /// Density values at all points of subdivided cells that are not points of the background grid (indexed on the finest level)
+    fine_values: MapType<Ijk, R>,
+    /// Level and index (on this level) of all cells that were subdivided
+    subdivided_cells: HashSet<(usize, Ijk), HashState>,
+}
+
+impl<'a, I: Index, R: Real> CellOctree<'a, I, R> {
+    /// Creates an octree without any subdivided cells
+    fn new(
+        grid: &'a UniformGrid<I, R>,
+        density_map: &'a DensityMap<I, R>,
+        max_level: usize,
+    ) -> Self {
+        Self {
+            grid,
+            density_map,
+            max_level,
+            root_size: 1 << max_level,
+            fine_values: new_map(),
+            subdivided_cells: HashSet::with_hasher(HashState::default()),
+        }
+    }
+
+    /// Returns the edge length of the cells on the given level in units of the cells on the finest level
+    fn cell_size(&self, level: usize) -> i64 {
+        self.root_size >> level
+    }
+
+    /// Converts an index on the finest level to an index of the background grid, if it is part of the grid
+    fn to_grid_index(&self, ijk: &Ijk) -> Option<[I; 3]> {
+        Some([
+            I::from_i64(ijk[0])?,
+            I::from_i64(ijk[1])?,
+            I::from_i64(ijk[2])?,
+        ])
+    }
+
+    /// Returns the density value at the given point (indexed on the finest level) if it is known
+    fn point_value(&self, point: &Ijk) -> Option<R> {
+        if point.iter().all(|&p| p.rem_euclid(self.root_size) == 0) {
+            // Point of the background grid
+            let grid_point = self
+                .grid
+                .get_point(self.to_grid_index(&point.map(|p| p / self.root_size))?)?;
+            Some(
+                self.density_map
+                    .get(self.grid.flatten_point_index(&grid_point))
+                    .unwrap_or_else(R::zero),
+            )
+        } else {
+            self.fine_values.get(point).copied()
+        }
+    }
+
+    /// Returns the coordinates of the given point (indexed on the finest level)
+    fn point_coordinates(&self, point: &Ijk) -> Vector3<R> {
+        let fine_cell_size = self.grid.cell_size() / R::from_i64(self.root_size).unwrap();
+        self.grid.aabb().min()
+            + Vector3::new(
+                R::from_i64(point[0]).unwrap(),
+                R::from_i64(point[1]).unwrap(),
+                R::from_i64(point[2]).unwrap(),
+            ) * fine_cell_size
+    }
+
+    /// Returns the index of the given corner (see [`CELL_CORNER_OFFSETS`]) of a cell on the given level (indexed on the finest level)
+    fn cell_corner(&self, level: usize, cell: &Ijk, corner_offset: &[usize; 3]) -> Ijk {
+        let size = self.cell_size(level);
+        [
+            (cell[0] + corner_offset[0] as i64) * size,
+            (cell[1] + corner_offset[1] as i64) * size,
+            (cell[2] + corner_offset[2] as i64) * size,
+        ]
+    }
+
+    /// Returns the density values at the corners of a cell on the given level if they are known
+    fn cell_corner_values(&self, level: usize, cell: &Ijk) -> Option<[R; 8]> {
+        let mut values = [R::zero(); 8];
+        for (value, offset) in values.iter_mut().zip(CELL_CORNER_OFFSETS.iter()) {
+            *value = self.point_value(&self.cell_corner(level, cell, offset))?;
+        }
+        Some(values)
+    }
+
+    /// Evaluates the density at the given points (indexed on the finest level) and stores the values
+    fn evaluate_points<F: Fn(&[Vector3<R>]) -> Result<Vec<R>, ReconstructionError<I, R>>>(
+        &mut self,
+        points: &[Ijk],
+        evaluate_density: &F,
+    ) -> Result<(), ReconstructionError<I, R>> {
+        let coordinates = points
+            .iter()
+            .map(|point| self.point_coordinates(point))
+            .collect::<Vec<_>>();
+        let values = evaluate_density(&coordinates)?;
+        if values.len() != points.len() {
+            return Err(anyhow!(
+                "the density has to be evaluated at every given point (evaluated {} of {} points)",
+                values.len(),
+                points.len()
+            )
+            .into());
+        }
+        self.fine_values.extend(points.iter().copied().zip(values));
+        Ok(())
+    }
+
+    /// Returns all cells of the background grid that may be subdivided
+    ///
+    /// Cells with all corners inside of the fluid or with all corner values below half of the iso-surface
+    /// threshold (i.e. too far away from the surface) are not considered for subdivision.
+    fn root_cell_candidates(
+        &self,
+        iso_surface_threshold: R,
+    ) -> Result<Vec<Ijk>, ReconstructionError<I, R>> {
+        let min_corner_value = iso_surface_threshold * R::from_f64(0.5).unwrap();
+
+        let mut cells = HashSet::with_hasher(HashState::default());
+        let mut invalid_point = None;
+        self.density_map.for_each(|flat_point_index, value| {
+            if value < min_corner_value {
+                return;
+            }
+
+            let Some(point) = self.grid.try_unflatten_point_index(flat_point_index) else {
+                invalid_point.get_or_insert(flat_point_index);
+                return;
+            };
+            let ijk = point.index().map(|i| i.to_i64().unwrap());
+            for offset in CELL_CORNER_OFFSETS {
+                cells.insert([
+                    ijk[0] - offset[0] as i64,
+                    ijk[1] - offset[1] as i64,
+                    ijk[2] - offset[2] as i64,
+                ]);
+            }
+        });
+        if invalid_point.is_some() {
+            return Err(octree_error(
+                "point of the density map is not part of the grid",
+            ));
+        }
+
+        Ok(cells
+            .into_iter()
+            .filter(|cell| {
+                self.to_grid_index(cell)
+                    .and_then(|ijk| self.grid.get_cell(ijk))
+                    .is_some()
+            })
+            .filter(|cell| {
+                self.cell_corner_values(0, cell)
+                    .is_some_and(|values| values.iter().any(|&v| v <= iso_surface_threshold))
+            })
+            .collect())
+    }
+
+    /// Returns whether the given cell should be subdivided, the value at its center has to be known
+    fn needs_subdivision(
+        &self,
+        level: usize,
+        cell: &Ijk,
+        iso_surface_threshold: R,
+        refinement_tolerance: R,
+    ) -> Result<bool, ReconstructionError<I, R>> {
+        let size = self.cell_size(level);
+        let center = cell.map(|c| c * size + size / 2);
+
+        let corner_values = self.cell_corner_values(level, cell).ok_or_else(|| {
+            octree_error("corner values of a cell have to be known before its subdivision")
+        })?;
+        let center_value = self.point_value(&center).ok_or_else(|| {
+            octree_error("center value of a cell has to be evaluated before its subdivision")
+        })?;
+
+        let samples = || corner_values.iter().chain(std::iter::once(&center_value));
+        let any_inside = samples().any(|&v| v > iso_surface_threshold);
+        let any_outside = samples().any(|&v| v <= iso_surface_threshold);
+
+        // The center value of the trilinear interpolation is the average of the corner values
+        let interpolated_value =
+            corner_values.iter().copied().fold(R::zero(), |a, b| a + b) / R::from_f64(8.0).unwrap();
+
+        Ok(any_inside
+            && any_outside
+            && ((center_value - interpolated_value).abs()
+                > refinement_tolerance * iso_surface_threshold
+                || self.has_ambiguous_face(level, cell, iso_surface_threshold)))
+    }
+
+    /// Returns whether the iso-surface crosses the boundary of a face of the given cell more than twice or a side of a face more than once
+    ///
+    /// All known values on the boundary of the faces are considered, i.e. also the values at points of smaller
+    /// neighboring cells. The surface nets triangulation connects the vertices of two cells by a triangle for every
+    /// minimal edge crossing the iso-surface on the boundary of their shared face (or along their shared edge).
+    /// Therefore, only cells without such ambiguous faces result in a manifold mesh.
+    fn has_ambiguous_face(&self, level: usize, cell: &Ijk, iso_surface_threshold: R) -> bool {
+        let size = self.cell_size(level);
+        let origin = cell.map(|c| c * size);
+
+        Axis::all_possible().iter().any(|axis| {
+            let [u_axis, v_axis] = axis.orthogonal_axes().map(|a| a.dim());
+            [0, size].iter().any(|&offset| {
+                let point_on_face = |u: i64, v: i64| {
+                    let mut point = origin;
+                    point[axis.dim()] += offset;
+                    point[u_axis] += u;
+                    point[v_axis] += v;
+                    point
+                };
+
+                // Walk along the four sides of the face on the finest level and count the sign changes of all known values
+                let side_sign_changes = [
+                    [(0, 0), (1, 0)],
+                    [(size, 0), (0, 1)],
+                    [(size, size), (-1, 0)],
+                    [(0, size), (0, -1)],
+                ]
+                .map(|[(u, v), (du, dv)]| {
+                    let is_inside = (0..=size)
+                        .filter_map(|i| self.point_value(&point_on_face(u + i * du, v + i * dv)))
+                        .map(|value| value > iso_surface_threshold)
+                        .collect::<Vec<_>>();
+                    is_inside.windows(2).filter(|w| w[0] != w[1]).count()
+                });
+
+                // Sides crossing the iso-surface more than once result in multiple triangles between the same vertices
+                side_sign_changes.iter().sum::<usize>() > 2
+                    || side_sign_changes.iter().any(|&n| n > 1)
+            })
+        })
+    }
+
+    /// Recursively subdivides all cells that poorly represent the iso-surface up to the maximum refinement level
+    fn refine<F: Fn(&[Vector3<R>]) -> Result<Vec<R>, ReconstructionError<I, R>>>(
+        &mut self,
+        iso_surface_threshold: R,
+        refinement_tolerance: R,
+        evaluate_density: &F,
+    ) -> Result<(), ReconstructionError<I, R>> {
+        profile!("adaptive refinement");
+
+        let mut cells = self.root_cell_candidates(iso_surface_threshold)?;
+        for level in 0..self.max_level {
+            if cells.is_empty() {
+                break;
+            }
+
+            let size = self.cell_size(level);
+            let half_size = size / 2;
+
+            // Evaluate the density at the centers of all cells of this level
+            let centers = cells
+                .iter()
+                .map(|cell| cell.map(|c| c * size + half_size))
+                .collect::<Vec<_>>();
+            self.evaluate_points(&centers, evaluate_density)?;
+
+            let mut subdivided = Vec::new();
+            for cell in cells {
+                if self.needs_subdivision(
+                    level,
+                    &cell,
+                    iso_surface_threshold,
+                    refinement_tolerance,
+                )? {
+                    subdivided.push(cell);
+                }
+            }
+
+            info!(
+                "Adaptive resolution: subdivided {} cells on level {}.",
+                subdivided.len(),
+                level
+            );
+
+            // The children of the subdivided cells are the candidates for the next level
+            cells = self.subdivide(level, subdivided, evaluate_density)?;
+        }
+
+        // Subdividing cells adds values on the faces of their neighbors which may make these faces ambiguous.
+        // Therefore, neighbors of subdivided cells with ambiguous faces are subdivided as well until there are
+        // none left (or the maximum level is reached).
+        let mut num_subdivided = 0;
+        let mut subdivided = self.subdivided_cells.iter().copied().collect::<Vec<_>>();
+        loop {
+            let mut ambiguous = subdivided
+                .iter()
+                .flat_map(|(level, cell)| self.neighbor_leaves(*level, cell))
+                .filter(|(level, cell)| {
+                    *level < self.max_level
+                        && self.has_ambiguous_face(*level, cell, iso_surface_threshold)
+                })
+                .collect::<Vec<_>>();
+            ambiguous.sort_unstable();
+            ambiguous.dedup();
+            if ambiguous.is_empty() {
+                break;
+            }
+            num_subdivided += ambiguous.len();
+
+            for level in 0..self.max_level {
+                let cells = ambiguous
+                    .iter()
+                    .filter(|(l, _)| *l == level)
+                    .map(|(_, cell)| *cell)
+                    .collect::<Vec<_>>();
+                if !cells.is_empty() {
+                    self.subdivide(level, cells, evaluate_density)?;
+                }
+            }
+            subdivided = ambiguous;
+        }
+
+        info!(
+            "Adaptive resolution: subdivided {} additional cells with ambiguous faces.",
+            num_subdivided
+        );
+        Ok(())
+    }
+
+    /// Subdivides the given cells of the given level, evaluates the density at the corners of their children and returns the children
+    fn subdivide<F: Fn(&[Vector3<R>]) -> Result<Vec<R>, ReconstructionError<I, R>>>(
+        &mut self,
+        level: usize,
+        cells: Vec<Ijk>,
+        evaluate_density: &F,
+    ) -> Result<Vec<Ijk>, ReconstructionError<I, R>> {
+        let size = self.cell_size(level);
+        let half_size = size / 2;
+
+        // Evaluate the density at all remaining corners of the children of the subdivided cells
+        let mut new_points = HashSet::with_hasher(HashState::default());
+        for cell in cells.iter() {
+            for i in 0..3 {
+                for j in 0..3 {
+                    for k in 0..3 {
+                        let point = [
+                            cell[0] * size + i * half_size,
+                            cell[1] * size + j * half_size,
+                            cell[2] * size + k * half_size,
+                        ];
+                        if self.point_value(&point).is_none() {
+                            new_points.insert(point);
+                        }
+                    }
+                }
+            }
+        }
+        self.evaluate_points(
+            &new_points.into_iter().collect::<Vec<_>>(),
+            evaluate_density,
+        )?;
+
+        let children = cells
+            .iter()
+            .flat_map(|cell| {
+                CELL_CORNER_OFFSETS.map(|offset| {
+                    [
+                        2 * cell[0] + offset[0] as i64,
+                        2 * cell[1] + offset[1] as i64,
+                        2 * cell[2] + offset[2] as i64,
+                    ]
+                })
+            })
+            .collect();
+        self.subdivided_cells
+            .extend(cells.into_iter().map(|cell| (level, cell)));
+        Ok(children)
+    }
+
+    /// Returns the leaf cells of the same or a larger size that share a face, an edge or a corner with the given cell
+    ///
+    /// Only the values on the boundary of these leaves change if the given cell is subdivided.
+    fn neighbor_leaves<'b>(
+        &'b self,
+        level: usize,
+        cell: &'b Ijk,
+    ) -> impl Iterator<Item = (usize, Ijk)> + 'b {
+        let size = self.cell_size(level);
+        let offsets =
+            (-1..=1).flat_map(|i| (-1..=1).flat_map(move |j| (-1..=1).map(move |k| [i, j, k])));
+        offsets
+            .filter(|offset| *offset != [0, 0, 0])
+            .filter_map(move |offset: [i64; 3]| {
+                // Cell of the finest level in the neighbor of the given cell, adjacent to the given cell
+                let finest_cell = [0, 1, 2].map(|dim| {
+                    cell[dim] * size
+                        + match offset[dim] {
+                            -1 => -1,
+                            0 => 0,
+                            _ => size,
+                        }
+                });
+                self.leaf_containing(&finest_cell)
+                    .filter(|(leaf_level, _)| *leaf_level <= level)
+            })
+    }
+
+    /// Returns the level and index of the leaf cell that contains the given cell of the finest level, if it is part of the background grid
+    fn leaf_containing(&self, finest_cell: &Ijk) -> Option<(usize, Ijk)> {
+        for level in 0..=self.max_level {
+            let cell = finest_cell.map(|c| c >> (self.max_level - level));
+            if level == 0 {
+                self.grid.get_cell(self.to_grid_index(&cell)?)?;
+            }
+            if level == self.max_level || !self.subdivided_cells.contains(&(level, cell)) {
+                return Some((level, cell));
+            }
+        }
+        unreachable!("every cell of the finest level has to be contained in a leaf")
+    }
+
+    /// Triangulates the leaf cells of the octree using surface nets, appends triangles to the given mesh
+    fn triangulate_append(
+        &self,
+        iso_surface_threshold: R,
+        mesh: &mut TriMesh3d<R>,
+    ) -> Result<(), ReconstructionError<I, R>> {
+        profile!("adaptive triangulation");
+
+        // Collect all edges of any size crossing the iso-surface: (start point, axis, level of the edge, whether the start point is inside)
+        let mut crossing_edges = Vec::new();
+        let mut collect_crossing_edges = |point: Ijk, value: R| {
+            if value <= iso_surface_threshold {
+                return;
+            }
+
+            for level in 0..=self.max_level {
+                let size = self.cell_size(level);
+                // Only points on the corners of the cells of this level can be endpoints of an edge of this level
+                if point.iter().any(|&p| p.rem_euclid(size) != 0) {
+                    continue;
+                }
+
+                for &axis in Axis::all_possible() {
+                    let dim = axis.dim();
+
+                    // Neighbor in positive direction: edge starts at this point
+                    let mut upper = point;
+                    upper[dim] += size;
+                    if let Some(upper_value) = self.point_value(&upper) {
+                        if upper_value <= iso_surface_threshold {
+                            crossing_edges.push((point, axis, level, true));
+                        }
+                    }
+
+                    // Neighbor in negative direction: edge starts at the neighbor
+                    let mut lower = point;
+                    lower[dim] -= size;
+                    if let Some(lower_value) = self.point_value(&lower) {
+                        if lower_value <= iso_surface_threshold {
+                            crossing_edges.push((lower, axis, level, false));
+                        }
+                    }
+                }
+            }
+        };
+
+        let mut invalid_point = None;
+        self.density_map.for_each(|flat_point_index, value| {
+            let Some(point) = self.grid.try_unflatten_point_index(flat_point_index) else {
+                invalid_point.get_or_insert(flat_point_index);
+                return;
+            };
+            collect_crossing_edges(
+                point.index().map(|i| i.to_i64().unwrap() * self.root_size),
+                value,
+            );
+        });
+        if invalid_point.is_some() {
+            return Err(octree_error(
+                "point of the density map is not part of the grid",
+            ));
+        }
+        for (&point, &value) in self.fine_values.iter() {
+            collect_crossing_edges(point, value);
+        }
+
+        let mut leaf_to_vertex = new_map();
+        let mut num_minimal_edges = 0;
+
+        for (origin, axis, level, origin_inside) in crossing_edges {
+            // Collect the leaf cells adjacent to the edge, skip edges at the boundary of the grid
+            let adjacent_leaves = EDGE_ADJACENT_CELL_OFFSETS.map(|offsets| {
+                // Cell of the finest level adjacent to the origin of the edge
+                let mut finest_cell = origin;
+                for (orth_axis, offset) in axis.orthogonal_axes().iter().zip(offsets) {
+                    finest_cell[orth_axis.dim()] -= offset as i64;
+                }
+                self.leaf_containing(&finest_cell)
+            });
+
+            if adjacent_leaves.iter().any(Option::is_none) {
+                continue;
+            }
+            let adjacent_leaves = adjacent_leaves.map(Option::unwrap);
+
+            // Only minimal edges are triangulated: the edge has to be an edge of an adjacent leaf and must not be subdivided by a smaller leaf
+            let leaf_levels = adjacent_leaves.map(|(leaf_level, _)| leaf_level);
+            if !leaf_levels.contains(&level) || leaf_levels.iter().any(|&l| l > level) {
+                continue;
+            }
+            num_minimal_edges += 1;
+
+            let mut quad = [0; 4];
+            for (vertex, (leaf_level, leaf_cell)) in quad.iter_mut().zip(adjacent_leaves) {
+                *vertex = match leaf_to_vertex.entry((leaf_level, leaf_cell)) {
+                    Entry::Occupied(entry) => *entry.get(),
+                    Entry::Vacant(entry) => {
+                        let corner_values = self
+                            .cell_corner_values(leaf_level, &leaf_cell)
+                            .ok_or_else(|| {
+                                octree_error("corner values of a leaf cell have to be known")
+                            })?;
+                        let fine_cell_size =
+                            self.grid.cell_size() / R::from_i64(self.root_size).unwrap();
+
+                        mesh.vertices.push(cell_vertex_position(
+                            &self.point_coordinates(&self.cell_corner(
+                                leaf_level,
+                                &leaf_cell,
+                                &[0, 0, 0],
+                            )),
+                            fine_cell_size * R::from_i64(self.cell_size(leaf_level)).unwrap(),
+                            &corner_values,
+                            iso_surface_threshold,
+                        ));
+                        *entry.insert(mesh.vertices.len() - 1)
+                    }
+                };
+            }
+
+            // Edges adjacent to only three distinct leaves result in a single triangle
+            let triangles = quad_to_triangles(quad, origin_inside, &mesh.vertices);
+            mesh.triangles.extend(
+                triangles
+                    .into_iter()
+                    .filter(|tri| tri[0] != tri[1] && tri[1] != tri[2] && tri[2] != tri[0]),
+            );
+        }
+
+        info!(
+            "Adaptive resolution: triangulated {} minimal edges crossing the iso-surface.",
+            num_minimal_edges
+        );
+        Ok(())
+    }
+}
+
+#[test]
+fn test_adaptive_resolution_closed_surface() {
+    use crate::mesh_quality::compute_mesh_quality;
+
+    let grid = UniformGrid::<i64, f64>::new(&Vector3::repeat(-2.0), &[16, 16, 16], 0.25).unwrap();
+
+    // A large and a small blob, the small blob is poorly resolved by the background grid
+    let field = |x: &Vector3<f64>| {
+        let large = (-(x - Vector3::new(-0.3, 0.0, 0.0)).norm_squared()).exp();
+        let small = (-(x - Vector3::new(1.2, 0.2, 0.1)).norm_squared() / 0.05).exp();
+        large + small
+    };
+
+    let mut sparse_data = new_map();
+    for i in 0..=16 {
+        for j in 0..=16 {
+            for k in 0..=16 {
+                let value = field(&grid.point_coordinates_indices(i, j, k));
+                if value > 1e-6 {
+                    sparse_data.insert(grid.flatten_point_indices(i, j, k), value);
+                }
+            }
+        }
+    }
+    let density_map: DensityMap<i64, f64> = sparse_data.into();
+    let evaluate_density = |points: &[Vector3<f64>]| Ok(points.iter().map(field).collect());
+
+    let uniform_mesh = triangulate_density_map(
+        &grid,
+        &density_map,
+        0.5,
+        &AdaptiveResolutionParameters {
+            max_refinement_level: 0,
+            refinement_tolerance: 0.0,
+        },
+        evaluate_density,
+    )
+    .unwrap();
+
+    for max_refinement_level in [1, 3] {
+        let mesh = triangulate_density_map(
+            &grid,
+            &density_map,
+            0.5,
+            &AdaptiveResolutionParameters {
+                max_refinement_level,
+                refinement_tolerance: 0.02,
+            },
+            evaluate_density,
+        )
+        .unwrap();
+
+        // Refinement adds vertices, the mesh has to stay closed and oriented outwards
+        assert!(mesh.vertices.len() > uniform_mesh.vertices.len());
+        let quality = compute_mesh_quality(&mesh);
+        assert_eq!(quality.num_boundary_edges, 0);
+        assert_eq!(quality.num_components, 2);
+        assert!(mesh.signed_volume() > 0.0);
+    }
+}
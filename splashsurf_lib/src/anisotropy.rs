@@ -0,0 +1,254 @@
+//! Anisotropic kernels for the reconstruction of flat fluid sheets and thin features
+//!
+//! Implements the anisotropic kernels proposed by Yu and Turk ("Reconstructing Surfaces of Particle-Based
+//! Fluids Using Anisotropic Kernels", 2013). With isotropic kernels, flat fluid sheets and thin streams
+//! are reconstructed as "blobby" surfaces. Instead, the kernel of every particle is stretched into an
+//! ellipsoid aligned with the distribution of its neighbors:
+//!  1. A weighted mean position `x̄_i` and covariance matrix `C_i` of the neighborhood of every particle
+//!     are computed using the weight function `w_ij = 1 - (|x_i - x_j| / h)³` where `h` is the compact support radius.
+//!  2. The eigenvalues `σ_k` of the covariance matrix are clamped such that `σ_max / σ_k` does not exceed
+//!     [`AnisotropyParameters::max_eigenvalue_ratio`] and normalized such that their product is one (i.e. the volume
+//!     of the kernel support is preserved). The normalized eigenvalues are the stretch factors of the kernel along the eigenvectors.
+//!  3. The kernel of a particle is evaluated as `W(|G_i (x - x̃_i)|)` where `G_i = R diag(1/σ_k) Rᵀ` is the
+//!     inverse of the stretch transform and `x̃_i = (1 - λ) x_i + λ x̄_i` is the position smoothed by the factor `λ`.
+//!
+//! Particles with fewer neighbors than [`AnisotropyParameters::min_neighbors`] keep an isotropic kernel
+//! (but their position is still smoothed). Because the determinant of every transform is one, the
+//! kernels stay normalized and the same iso-surface threshold can be used as for isotropic kernels.
+
+use crate::neighborhood_search::NeighborhoodList;
+use crate::{profile, ParticleProperties, Real};
+use log::info;
+use nalgebra::{Matrix3, Vector3};
+use rayon::prelude::*;
+
+/// Parameters for the computation of anisotropic kernels
+#[derive(Clone, Debug)]
+pub struct AnisotropyParameters<R: Real> {
+    /// Factor `λ` in `[0, 1]` for the smoothing of the kernel centers towards the weighted mean position of their neighborhood (zero disables the smoothing)
+    pub position_smoothing_factor: R,
+    /// Maximum ratio between the largest and the smallest eigenvalue of the neighborhood covariance matrix, limits the stretching of the kernels
+    pub max_eigenvalue_ratio: R,
+    /// Minimum number of neighbors of a particle to use an anisotropic kernel, particles with fewer neighbors use an isotropic kernel
+    pub min_neighbors: usize,
+}
+
+impl<R: Real> Default for AnisotropyParameters<R> {
+    /// Returns parameters with a position smoothing factor of 0.5, a maximum eigenvalue ratio of 4 and a minimum of 10 neighbors
+    fn default() -> Self {
+        Self {
+            position_smoothing_factor: R::from_f64(0.5).unwrap(),
+            max_eigenvalue_ratio: R::from_f64(4.0).unwrap(),
+            min_neighbors: 10,
+        }
+    }
+}
+
+impl<R: Real> AnisotropyParameters<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns None if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<AnisotropyParameters<T>> {
+        Some(AnisotropyParameters {
+            position_smoothing_factor: self.position_smoothing_factor.try_convert()?,
+            max_eigenvalue_ratio: self.max_eigenvalue_ratio.try_convert()?,
+            min_neighbors: self.min_neighbors,
+        })
+    }
+
+    /// Returns an upper bound for the stretch factor of any kernel computed with these parameters (relative to the isotropic compact support radius)
+    pub fn max_stretch(&self) -> R {
+        // The largest normalized eigenvalue is obtained if the two smaller eigenvalues are clamped
+        self.max_eigenvalue_ratio
+            .max(R::one())
+            .powf(R::from_f64(2.0 / 3.0).unwrap())
+    }
+
+    /// Returns an upper bound for the distance between a particle and its smoothed kernel center (relative to the compact support radius)
+    pub fn max_center_offset(&self) -> R {
+        self.position_smoothing_factor
+    }
+}
+
+/// Smoothed kernel centers and kernel transforms of all particles
+#[derive(Clone, Debug, Default)]
+pub struct AnisotropicKernels<R: Real> {
+    /// Smoothed positions of the particles that are used as the centers of the kernels
+    pub smoothed_positions: Vec<Vector3<R>>,
+    /// Per particle transform `G_i` applied to the distance vector before evaluating the isotropic kernel
+    pub transforms: Vec<Matrix3<R>>,
+    /// The largest stretch factor of all kernels (relative to the isotropic compact support radius)
+    pub max_stretch: R,
+}
+
+impl<R: Real> AnisotropicKernels<R> {
+    /// Returns the number of particles
+    pub fn len(&self) -> usize {
+        self.transforms.len()
+    }
+
+    /// Returns whether there are no particles
+    pub fn is_empty(&self) -> bool {
+        self.transforms.is_empty()
+    }
+}
+
+/// Computes the smoothed kernel centers and the anisotropic kernel transforms of all particles
+///
+/// The neighborhood lists have to contain all neighbors within the compact support radius (without the particle itself).
+/// If per particle compact support radii are provided by `particle_properties`, the neighborhood of every particle
+/// is weighted using its own radius and the neighborhood lists have to be computed using the largest radius.
+pub fn compute_anisotropic_kernels<R: Real, Nl: NeighborhoodList + Sync + ?Sized>(
+    particle_positions: &[Vector3<R>],
+    particle_neighbor_lists: &Nl,
+    compact_support_radius: R,
+    particle_properties: ParticleProperties<R>,
+    parameters: &AnisotropyParameters<R>,
+    enable_multi_threading: bool,
+) -> AnisotropicKernels<R> {
+    profile!("compute_anisotropic_kernels");
+
+    let compute_kernel = |i: usize| {
+        compute_particle_kernel(
+            i,
+            particle_positions,
+            particle_neighbor_lists.neighbors(i),
+            particle_properties.compact_support_radius(i, compact_support_radius),
+            parameters,
+        )
+    };
+
+    let kernels: Vec<_> = if enable_multi_threading {
+        (0..particle_positions.len())
+            .into_par_iter()
+            .map(compute_kernel)
+            .collect()
+    } else {
+        (0..particle_positions.len()).map(compute_kernel).collect()
+    };
+
+    let mut anisotropic_kernels = AnisotropicKernels {
+        smoothed_positions: Vec::with_capacity(kernels.len()),
+        transforms: Vec::with_capacity(kernels.len()),
+        max_stretch: R::one(),
+    };
+
+    let mut num_anisotropic = 0;
+    for (position, transform, stretch) in kernels {
+        anisotropic_kernels.smoothed_positions.push(position);
+        anisotropic_kernels.transforms.push(transform);
+        anisotropic_kernels.max_stretch = anisotropic_kernels.max_stretch.max(stretch);
+        num_anisotropic += (stretch > R::one()) as usize;
+    }
+
+    info!(
+        "Computed anisotropic kernels: {} of {} particles have anisotropic kernels, max. stretch factor: {}",
+        num_anisotropic,
+        anisotropic_kernels.len(),
+        anisotropic_kernels.max_stretch
+    );
+
+    anisotropic_kernels
+}
+
+/// Computes the smoothed kernel center, the kernel transform and the maximum stretch factor of a single particle
+pub(crate) fn compute_particle_kernel<R: Real>(
+    particle_i: usize,
+    particle_positions: &[Vector3<R>],
+    neighbors: &[usize],
+    compact_support_radius: R,
+    parameters: &AnisotropyParameters<R>,
+) -> (Vector3<R>, Matrix3<R>, R) {
+    let x_i = particle_positions[particle_i];
+    let weight = |x_j: &Vector3<R>| {
+        let q = ((x_j - x_i).norm() / compact_support_radius).min(R::one());
+        R::one() - q * q * q
+    };
+
+    // Weighted mean position of the neighborhood including the particle itself
+    let mut weight_sum = R::one();
+    let mut mean = x_i;
+    for x_j in neighbors.iter().map(|&j| &particle_positions[j]) {
+        let w_ij = weight(x_j);
+        weight_sum += w_ij;
+        mean += x_j * w_ij;
+    }
+    mean /= weight_sum;
+
+    let smoothed_position = x_i * (R::one() - parameters.position_smoothing_factor)
+        + mean * parameters.position_smoothing_factor;
+
+    if neighbors.len() < parameters.min_neighbors {
+        return (smoothed_position, Matrix3::identity(), R::one());
+    }
+
+    // Weighted covariance matrix of the neighborhood
+    let mut covariance = {
+        let d = x_i - mean;
+        d * d.transpose()
+    };
+    for x_j in neighbors.iter().map(|&j| &particle_positions[j]) {
+        let d = x_j - mean;
+        covariance += d * d.transpose() * weight(x_j);
+    }
+    covariance /= weight_sum;
+
+    let eigen = covariance.symmetric_eigen();
+    let max_eigenvalue = eigen.eigenvalues.max();
+    if max_eigenvalue <= R::default_epsilon() {
+        return (smoothed_position, Matrix3::identity(), R::one());
+    }
+
+    // Clamp the eigenvalues to limit the stretching and normalize them to preserve the kernel volume
+    let min_eigenvalue = max_eigenvalue / parameters.max_eigenvalue_ratio.max(R::one());
+    let clamped = eigen.eigenvalues.map(|sigma| sigma.max(min_eigenvalue));
+    let normalization = clamped.product().cbrt();
+    let stretch = clamped / normalization;
+
+    let transform = eigen.eigenvectors
+        * Matrix3::from_diagonal(&stretch.map(|s| R::one() / s))
+        * eigen.eigenvectors.transpose();
+
+    (smoothed_position, transform, stretch.max())
+}
+
+#[test]
+fn test_anisotropic_kernel_of_flat_sheet() {
+    // A single layer of particles in the xy-plane
+    let spacing = 0.1;
+    let mut particle_positions = Vec::new();
+    for i in -5..=5 {
+        for j in -5..=5 {
+            particle_positions.push(Vector3::new(i as f64 * spacing, j as f64 * spacing, 0.0));
+        }
+    }
+
+    let compact_support_radius = 3.0 * spacing;
+    let mut neighbor_lists = Vec::new();
+    crate::neighborhood_search::neighborhood_search_naive(
+        &particle_positions,
+        compact_support_radius,
+        &mut neighbor_lists,
+    );
+
+    let parameters = AnisotropyParameters::default();
+    let kernels = compute_anisotropic_kernels(
+        &particle_positions,
+        &neighbor_lists,
+        compact_support_radius,
+        ParticleProperties::uniform(),
+        &parameters,
+        false,
+    );
+
+    // Particle at the center of the sheet
+    let center = 60;
+    assert_eq!(particle_positions[center], Vector3::zeros());
+    assert!(kernels.smoothed_positions[center].norm() < 1e-12);
+
+    // The kernel is compressed along the normal of the sheet and stretched in the plane of the sheet
+    let transform = &kernels.transforms[center];
+    assert!((transform.determinant() - 1.0).abs() < 1e-10);
+    assert!((transform * Vector3::z()).norm() > 1.0);
+    assert!((transform * Vector3::x()).norm() < 1.0);
+    assert!((transform * Vector3::y()).norm() < 1.0);
+    assert!(kernels.max_stretch <= parameters.max_stretch() + 1e-10);
+}
@@ -0,0 +1,883 @@
+//! Handling of solid boundaries (e.g. tank walls) in the surface reconstruction
+//!
+//! Without any knowledge about solid boundaries, the reconstructed fluid surface bulges through walls
+//! (because the kernels of particles close to a wall reach into the solid) or peels away from walls
+//! leaving gaps (because of the missing kernel support on the other side of the wall).
+//! If [`Parameters::boundary`](crate::Parameters::boundary) is set, the reconstruction takes the solid
+//! into account that is described by a [`BoundaryGeometry`]:
+//!  - an analytic signed distance function ([`SignedDistanceFunction`]),
+//!  - a set of boundary particles (the solid is the union of their spheres) or
+//!  - a triangle mesh of the solid surface (e.g. loaded from a PLY or VTK file).
+//!
+//! All geometries are evaluated as signed distance `φ(x)` to the solid surface, negative inside the solid.
+//! The [`BoundaryHandling`] determines how the density field is modified:
+//!  - **Extend**: the fluid particles closer than the compact support radius to the boundary are mirrored
+//!    at the boundary surface, i.e. the density field is extended into the solid. This corresponds to
+//!    "ghost particles" and removes the density deficiency close to the wall, so the surface meets the wall
+//!    without gaps and continues into the solid.
+//!  - **Cut**: the density field is cut at the boundary by replacing every density value `ρ(x)` with
+//!    `min(ρ(x), t (1 + φ(x) / Δ))` where `t` is the iso-surface threshold and `Δ` the cube size.
+//!    This corresponds to the intersection of the fluid with the space outside of the solid, i.e. the
+//!    surface ends exactly at the boundary and is closed by caps on the solid surface.
+//!
+//! For boundary particles and boundary meshes, the signed distance is only evaluated in a narrow band around
+//! the boundary surface. Fluid particles that penetrate the solid deeper than the compact support radius
+//! are therefore not completely removed by the cut.
+//!
+//! Independently of the boundary geometry, [`Parameters::boundary_particles`](crate::Parameters::boundary_particles)
+//! can provide boundary particles with volumes (e.g. the wall samples of an SPH simulation, see [`BoundaryParticles`]).
+//! Like in the density computation of SPH solvers with boundary particles (Akinci et al. 2012), every boundary particle
+//! `b` contributes `ρ_0 V_b W(x_i - x_b)` to the density of a fluid particle `i` and `V_b W(x - x_b)` to the density
+//! field, so the density of the fluid is not underestimated close to walls. To not generate any surface around the
+//! boundary particles on their own, the contribution of the boundary to the density field at a point is limited to
+//! the contribution of the fluid at the same point. As the mirrored particles of [`BoundaryHandling::Extend`] compensate
+//! the density deficiency at the boundary as well, boundary particles are best combined with [`BoundaryHandling::Cut`].
+//!
+//! Boundary handling is supported by the global reconstruction and the reconstruction with a subdomain grid,
+//! but not by the octree-based spatial decomposition.
+
+use crate::kernel::{KernelType, SphKernel, SymmetricKernel3d};
+use crate::mesh::{Mesh3d, TriMesh3d};
+use crate::uniform_grid::UniformGrid;
+use crate::{
+    new_map, profile, Aabb3d, DensityMap, Index, MapType, Parameters, ParticleProperties, Real,
+};
+use log::info;
+use nalgebra::Vector3;
+use num_traits::Bounded;
+use rayon::prelude::*;
+use std::fmt;
+use std::sync::Arc;
+
+/// Parameters for the handling of solid boundaries in the surface reconstruction
+#[derive(Clone, Debug)]
+pub struct BoundaryParameters<R: Real> {
+    /// Geometry of the solid
+    pub geometry: BoundaryGeometry<R>,
+    /// How the density field is modified at the boundary
+    pub handling: BoundaryHandling,
+}
+
+impl<R: Real> BoundaryParameters<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns None if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<BoundaryParameters<T>> {
+        Some(BoundaryParameters {
+            geometry: self.geometry.try_convert()?,
+            handling: self.handling,
+        })
+    }
+}
+
+/// Determines how the density field is modified at solid boundaries
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum BoundaryHandling {
+    /// The density field is cut at the boundary, the surface is closed by caps on the solid surface
+    Cut,
+    /// The density field is extended into the solid by mirroring the particles close to the boundary, the surface continues into the solid
+    Extend,
+    /// The density field is extended into the solid and then cut at the boundary, the surface meets the solid without gaps and is closed by caps on the solid surface
+    #[default]
+    ExtendAndCut,
+}
+
+impl BoundaryHandling {
+    /// Returns whether particles close to the boundary are mirrored into the solid
+    pub fn extends(&self) -> bool {
+        matches!(
+            self,
+            BoundaryHandling::Extend | BoundaryHandling::ExtendAndCut
+        )
+    }
+
+    /// Returns whether the density field is cut at the boundary
+    pub fn cuts(&self) -> bool {
+        matches!(self, BoundaryHandling::Cut | BoundaryHandling::ExtendAndCut)
+    }
+}
+
+/// Description of the geometry of a solid boundary
+#[derive(Clone, Debug)]
+pub enum BoundaryGeometry<R: Real> {
+    /// Analytic signed distance function of the solid
+    SignedDistance(SignedDistanceFunction<R>),
+    /// Boundary particles, the solid is the union of the spheres with the given radius around the particles
+    Particles {
+        /// Positions of the boundary particles
+        positions: Vec<Vector3<R>>,
+        /// Radius of the boundary particles
+        radius: R,
+    },
+    /// Triangle mesh of the solid surface, the triangle normals (counter-clockwise orientation) have to point away from the solid (i.e. into the fluid)
+    Mesh(TriMesh3d<R>),
+}
+
+impl<R: Real> BoundaryGeometry<R> {
+    /// Tries to convert the geometry from one [Real] type to another [Real] type, returns None if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<BoundaryGeometry<T>> {
+        Some(match self {
+            BoundaryGeometry::SignedDistance(sdf) => {
+                BoundaryGeometry::SignedDistance(sdf.convert())
+            }
+            BoundaryGeometry::Particles { positions, radius } => BoundaryGeometry::Particles {
+                positions: positions
+                    .iter()
+                    .map(T::try_convert_vec_from)
+                    .collect::<Option<Vec<_>>>()?,
+                radius: radius.try_convert()?,
+            },
+            BoundaryGeometry::Mesh(mesh) => BoundaryGeometry::Mesh(TriMesh3d {
+                vertices: mesh
+                    .vertices
+                    .iter()
+                    .map(T::try_convert_vec_from)
+                    .collect::<Option<Vec<_>>>()?,
+                triangles: mesh.triangles.clone(),
+            }),
+        })
+    }
+}
+
+/// Type of the closures wrapped by [`SignedDistanceFunction`]
+type DistanceFn<R> = dyn Fn(&Vector3<R>) -> R + Send + Sync;
+
+/// Analytic signed distance function of a solid (negative inside of the solid, positive outside)
+#[derive(Clone)]
+pub struct SignedDistanceFunction<R: Real> {
+    function: Arc<DistanceFn<R>>,
+}
+
+impl<R: Real> fmt::Debug for SignedDistanceFunction<R> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignedDistanceFunction")
+            .finish_non_exhaustive()
+    }
+}
+
+impl<R: Real> SignedDistanceFunction<R> {
+    /// Creates a signed distance function from the given closure (negative inside of the solid, positive outside)
+    pub fn new<F: Fn(&Vector3<R>) -> R + Send + Sync + 'static>(function: F) -> Self {
+        Self {
+            function: Arc::new(function),
+        }
+    }
+
+    /// Solid half-space behind the plane through `point` with the given `normal` (pointing away from the solid), e.g. a floor
+    pub fn half_space(point: Vector3<R>, normal: Vector3<R>) -> Self {
+        let normal = normal.normalize();
+        Self::new(move |x| (x - point).dot(&normal))
+    }
+
+    /// Solid outside of the given box, i.e. a closed container with the fluid inside of the box
+    pub fn box_container(aabb: Aabb3d<R>) -> Self {
+        Self::new(move |x| {
+            let to_min = x - aabb.min();
+            let to_max = aabb.max() - x;
+            if aabb.contains_point(x) {
+                // Distance to the closest face
+                to_min.min().min(to_max.min())
+            } else {
+                // Distance to the closest point of the box
+                -(to_min.map(|d| (-d).max(R::zero())) + to_max.map(|d| (-d).max(R::zero()))).norm()
+            }
+        })
+    }
+
+    /// Evaluates the signed distance function at the given point
+    pub fn evaluate(&self, x: &Vector3<R>) -> R {
+        (self.function)(x)
+    }
+
+    /// Converts the function to another [Real] type by converting the arguments and the return value
+    fn convert<T: Real>(&self) -> SignedDistanceFunction<T> {
+        let function = self.function.clone();
+        SignedDistanceFunction::new(move |x: &Vector3<T>| {
+            R::try_convert_vec_from(x)
+                .and_then(|x| function(&x).try_convert())
+                .unwrap_or_else(<T as Bounded>::max_value)
+        })
+    }
+}
+
+/// Boundary particles with volumes that contribute to the densities of the fluid close to walls
+#[derive(Clone, Debug)]
+pub struct BoundaryParticles<R: Real> {
+    /// Positions of the boundary particles
+    pub positions: Vec<Vector3<R>>,
+    /// Volumes of the boundary particles
+    pub volumes: Vec<R>,
+}
+
+impl<R: Real> BoundaryParticles<R> {
+    /// Creates a set of boundary particles with the given volumes
+    pub fn new(positions: Vec<Vector3<R>>, volumes: Vec<R>) -> Self {
+        Self { positions, volumes }
+    }
+
+    /// Creates a set of boundary particles with volumes estimated from the sampling density of the boundary particles
+    ///
+    /// The volume of every boundary particle is estimated by `V_b = 1 / Σ_k W(x_b - x_k)` where the sum runs over all
+    /// boundary particles (Akinci et al. 2012), this accounts for irregular samplings of the boundary surface.
+    pub fn with_estimated_volumes(
+        positions: Vec<Vector3<R>>,
+        compact_support_radius: R,
+        kernel: KernelType,
+    ) -> Self {
+        profile!("BoundaryParticles::with_estimated_volumes");
+
+        let kernel = SphKernel::new(kernel, compact_support_radius);
+        let cells = CellLists::from_points(&positions, compact_support_radius);
+        let volumes = positions
+            .par_iter()
+            .map(|x_b| {
+                // The sum is positive as it contains the contribution of the particle itself
+                let sum = cells
+                    .candidates(x_b)
+                    .map(|k| kernel.evaluate((x_b - positions[k]).norm()))
+                    .fold(R::zero(), |sum, w| sum + w);
+                sum.recip()
+            })
+            .collect();
+
+        Self { positions, volumes }
+    }
+
+    /// Checks that there is exactly one positive volume per boundary particle
+    pub fn validate(&self) -> Result<(), anyhow::Error> {
+        if self.volumes.len() != self.positions.len() {
+            return Err(anyhow::anyhow!(
+                "number of boundary particle volumes ({}) does not match the number of boundary particles ({})",
+                self.volumes.len(),
+                self.positions.len()
+            ));
+        }
+        if let Some(i) = self.volumes.iter().position(|&v| v <= R::zero()) {
+            return Err(anyhow::anyhow!(
+                "boundary particle volumes have to be positive, found {} for boundary particle {}",
+                self.volumes[i],
+                i
+            ));
+        }
+        Ok(())
+    }
+
+    /// Tries to convert the boundary particles from one [Real] type to another [Real] type, returns None if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<BoundaryParticles<T>> {
+        Some(BoundaryParticles {
+            positions: self
+                .positions
+                .iter()
+                .map(T::try_convert_vec_from)
+                .collect::<Option<Vec<_>>>()?,
+            volumes: self
+                .volumes
+                .iter()
+                .map(|v| v.try_convert())
+                .collect::<Option<Vec<_>>>()?,
+        })
+    }
+}
+
+/// Modifications of the density field at solid boundaries prepared for the evaluation during a reconstruction
+pub(crate) struct BoundaryConditions<R: Real> {
+    /// Signed distance field of the solid, only if the density field is cut at the boundary
+    cut: Option<BoundaryDistanceField<R>>,
+    /// Density contribution of the boundary particles
+    particles: Option<BoundaryParticleDensity<R>>,
+}
+
+impl<R: Real> BoundaryConditions<R> {
+    /// Combines the boundary particles of the parameters with the given distance field, returns `None` if the density field is not modified at all
+    pub(crate) fn new(
+        parameters: &Parameters<R>,
+        distance_field: Option<BoundaryDistanceField<R>>,
+    ) -> Option<Self> {
+        let cut = distance_field.filter(|distance_field| distance_field.cuts());
+        let particles = parameters
+            .boundary_particles
+            .as_ref()
+            .map(|particles| BoundaryParticleDensity::new(particles, parameters));
+        (cut.is_some() || particles.is_some()).then_some(Self { cut, particles })
+    }
+
+    /// Adds the contributions of the boundary particles to the given fluid particle densities
+    pub(crate) fn add_particle_densities(
+        &self,
+        particle_positions: &[Vector3<R>],
+        particle_densities: &mut [R],
+        enable_multi_threading: bool,
+    ) {
+        if let Some(particles) = &self.particles {
+            particles.add_particle_densities(
+                particle_positions,
+                particle_densities,
+                enable_multi_threading,
+            );
+        }
+    }
+
+    /// Returns the density value at the given point after applying all boundary conditions
+    #[inline(always)]
+    pub(crate) fn density(&self, x: &Vector3<R>, density: R) -> R {
+        let density = match &self.particles {
+            Some(particles) => particles.correct_density(x, density),
+            None => density,
+        };
+        match &self.cut {
+            Some(distance_field) => distance_field.cut_density(x, density),
+            None => density,
+        }
+    }
+
+    /// Applies the boundary conditions to all values of a sparse density map of the given grid
+    pub(crate) fn apply_to_density_map<I: Index>(
+        &self,
+        grid: &UniformGrid<I, R>,
+        density_map: &mut DensityMap<I, R>,
+        enable_multi_threading: bool,
+    ) {
+        profile!("apply boundary conditions to density map");
+
+        let apply = |(flat_point_index, density): (I, R)| {
+            let density = grid
+                .try_unflatten_point_index(flat_point_index)
+                .map(|point| self.density(&grid.point_coordinates(&point), density))
+                .unwrap_or(density);
+            (flat_point_index, density)
+        };
+
+        let values = density_map.to_vec();
+        let mut updated_map = new_map();
+        if enable_multi_threading {
+            updated_map.extend(values.into_par_iter().map(apply).collect::<Vec<_>>());
+        } else {
+            updated_map.extend(values.into_iter().map(apply));
+        }
+        *density_map = updated_map.into();
+    }
+}
+
+/// Boundary particles prepared for the evaluation of their density contribution during a reconstruction
+struct BoundaryParticleDensity<R: Real> {
+    positions: Vec<Vector3<R>>,
+    volumes: Vec<R>,
+    cells: CellLists<R>,
+    kernel: SphKernel<R>,
+    rest_density: R,
+}
+
+impl<R: Real> BoundaryParticleDensity<R> {
+    /// Prepares the boundary particles for the kernel and rest density of the given parameters
+    fn new(particles: &BoundaryParticles<R>, parameters: &Parameters<R>) -> Self {
+        profile!("BoundaryParticleDensity::new");
+        Self {
+            positions: particles.positions.clone(),
+            volumes: particles.volumes.clone(),
+            cells: CellLists::from_points(&particles.positions, parameters.compact_support_radius),
+            kernel: SphKernel::new(parameters.kernel, parameters.compact_support_radius),
+            rest_density: parameters.rest_density,
+        }
+    }
+
+    /// Returns the volume fraction `Σ_b V_b W(x - x_b)` of the boundary particles at the given point
+    #[inline(always)]
+    fn volume_fraction(&self, x: &Vector3<R>) -> R {
+        self.cells
+            .candidates(x)
+            .map(|b| self.volumes[b] * self.kernel.evaluate((x - self.positions[b]).norm()))
+            .fold(R::zero(), |sum, v| sum + v)
+    }
+
+    fn add_particle_densities(
+        &self,
+        particle_positions: &[Vector3<R>],
+        particle_densities: &mut [R],
+        enable_multi_threading: bool,
+    ) {
+        profile!("add boundary particle densities");
+        assert_eq!(particle_positions.len(), particle_densities.len());
+
+        let add = |(x, density): (&Vector3<R>, &mut R)| {
+            *density += self.rest_density * self.volume_fraction(x);
+        };
+        if enable_multi_threading {
+            particle_positions
+                .par_iter()
+                .zip(particle_densities.par_iter_mut())
+                .for_each(add);
+        } else {
+            particle_positions
+                .iter()
+                .zip(particle_densities.iter_mut())
+                .for_each(add);
+        }
+    }
+
+    /// Adds the contribution of the boundary particles to the given density value, limited by the density value itself
+    #[inline(always)]
+    fn correct_density(&self, x: &Vector3<R>, density: R) -> R {
+        if density <= R::zero() {
+            return density;
+        }
+        density + self.volume_fraction(x).min(density)
+    }
+}
+
+/// Signed distance field of a boundary geometry prepared for the evaluation during a reconstruction
+pub(crate) struct BoundaryDistanceField<R: Real> {
+    geometry: PreparedGeometry<R>,
+    handling: BoundaryHandling,
+    /// Maximum distance to the boundary where the signed distance has to be known
+    max_distance: R,
+    /// Distance of the particles that are mirrored at the boundary
+    mirror_distance: R,
+    iso_surface_threshold: R,
+    cube_size: R,
+}
+
+enum PreparedGeometry<R: Real> {
+    SignedDistance(SignedDistanceFunction<R>),
+    Particles {
+        positions: Vec<Vector3<R>>,
+        radius: R,
+        cells: CellLists<R>,
+    },
+    Mesh {
+        mesh: TriMesh3d<R>,
+        pseudo_normals: MeshPseudoNormals<R>,
+        cells: CellLists<R>,
+    },
+}
+
+impl<R: Real> BoundaryDistanceField<R> {
+    /// Prepares the signed distance field for a reconstruction with the given kernel radius, cube size and iso-surface threshold
+    pub(crate) fn new(
+        boundary: &BoundaryParameters<R>,
+        compact_support_radius: R,
+        cube_size: R,
+        iso_surface_threshold: R,
+    ) -> Self {
+        profile!("BoundaryDistanceField::new");
+
+        // Outside of the solid, the cut density exceeds the fluid densities within a few cubes from the boundary.
+        // Inside of the solid, the kernels of the mirrored particles reach up to twice the compact support radius.
+        let max_distance = compact_support_radius.times(2) + cube_size.times(4);
+        let geometry = match &boundary.geometry {
+            BoundaryGeometry::SignedDistance(sdf) => PreparedGeometry::SignedDistance(sdf.clone()),
+            BoundaryGeometry::Particles { positions, radius } => PreparedGeometry::Particles {
+                positions: positions.clone(),
+                radius: *radius,
+                cells: CellLists::from_points(positions, max_distance + *radius),
+            },
+            BoundaryGeometry::Mesh(mesh) => {
+                let mut cells = CellLists::new(max_distance);
+                for (i, tri) in mesh.triangles.iter().enumerate() {
+                    let aabb = Aabb3d::from_points(&tri.map(|v| mesh.vertices[v]));
+                    cells.insert(aabb.min(), aabb.max(), i);
+                }
+                PreparedGeometry::Mesh {
+                    pseudo_normals: MeshPseudoNormals::new(mesh),
+                    mesh: mesh.clone(),
+                    cells,
+                }
+            }
+        };
+
+        Self {
+            geometry,
+            handling: boundary.handling,
+            max_distance,
+            mirror_distance: compact_support_radius,
+            iso_surface_threshold,
+            cube_size,
+        }
+    }
+
+    /// Returns the signed distance of the point to the boundary, `None` if the point is farther away from the boundary than the maximum distance
+    pub(crate) fn signed_distance(&self, x: &Vector3<R>) -> Option<R> {
+        match &self.geometry {
+            PreparedGeometry::SignedDistance(sdf) => Some(sdf.evaluate(x)),
+            PreparedGeometry::Particles {
+                positions,
+                radius,
+                cells,
+            } => cells
+                .candidates(x)
+                .map(|i| (positions[i] - x).norm())
+                .reduce(R::min)
+                .map(|distance| distance - *radius)
+                .filter(|&distance| distance <= self.max_distance),
+            PreparedGeometry::Mesh {
+                mesh,
+                pseudo_normals,
+                cells,
+            } => {
+                let (distance_sq, closest, tri, feature) = cells
+                    .candidates(x)
+                    .map(|t| {
+                        let [a, b, c] = mesh.triangles[t].map(|v| mesh.vertices[v]);
+                        let (closest, feature) = closest_point_on_triangle(x, &a, &b, &c);
+                        ((x - closest).norm_squared(), closest, t, feature)
+                    })
+                    .reduce(|a, b| if b.0 < a.0 { b } else { a })?;
+
+                let distance = distance_sq.sqrt();
+                if distance > self.max_distance {
+                    return None;
+                }
+
+                // The sign is determined by the angle weighted pseudo-normal of the closest feature
+                let normal = pseudo_normals.normal(&mesh.triangles[tri], tri, feature);
+                if (x - closest).dot(&normal) < R::zero() {
+                    Some(-distance)
+                } else {
+                    Some(distance)
+                }
+            }
+        }
+    }
+
+    /// Returns the normalized gradient of the signed distance (pointing away from the solid) computed by central differences
+    fn normal(&self, x: &Vector3<R>) -> Option<Vector3<R>> {
+        let eps = self.cube_size.times_f64(1e-2);
+        let mut gradient = Vector3::zeros();
+        for dim in 0..3 {
+            let mut offset = Vector3::zeros();
+            offset[dim] = eps;
+            gradient[dim] =
+                self.signed_distance(&(x + offset))? - self.signed_distance(&(x - offset))?;
+        }
+        gradient.try_normalize(R::default_epsilon())
+    }
+
+    /// Returns the indices of the particles that are mirrored at the boundary and the positions of their mirror images
+    ///
+    /// Only particles outside of the solid that are closer to the boundary than the compact support radius are mirrored.
+    pub(crate) fn mirrored_particles(
+        &self,
+        particle_positions: &[Vector3<R>],
+        enable_multi_threading: bool,
+    ) -> Vec<(usize, Vector3<R>)> {
+        profile!("mirrored_particles");
+
+        let mirror = |(i, x): (usize, &Vector3<R>)| {
+            let distance = self.signed_distance(x)?;
+            if distance < R::zero() || distance >= self.mirror_distance {
+                return None;
+            }
+            let normal = self.normal(x)?;
+            Some((i, x - normal * distance.times(2)))
+        };
+
+        if enable_multi_threading {
+            particle_positions
+                .par_iter()
+                .enumerate()
+                .filter_map(mirror)
+                .collect()
+        } else {
+            particle_positions
+                .iter()
+                .enumerate()
+                .filter_map(mirror)
+                .collect()
+        }
+    }
+
+    /// Returns whether the density field has to be cut at the boundary
+    pub(crate) fn cuts(&self) -> bool {
+        self.handling.cuts()
+    }
+
+    /// Returns the density value at the given point after cutting it at the boundary
+    #[inline(always)]
+    pub(crate) fn cut_density(&self, x: &Vector3<R>, density: R) -> R {
+        match self.signed_distance(x) {
+            Some(distance) => {
+                let boundary_density =
+                    self.iso_surface_threshold * (R::one() + distance / self.cube_size);
+                density.min(boundary_density)
+            }
+            None => density,
+        }
+    }
+}
+
+/// Fluid particles extended by their mirror images at a solid boundary
+pub(crate) struct MirroredParticles<R: Real> {
+    /// Positions of all original particles followed by the mirrored particles
+    pub(crate) positions: Vec<Vector3<R>>,
+    /// Indices of the original particles of the mirrored particles
+    pub(crate) source_indices: Vec<usize>,
+    masses: Option<Vec<R>>,
+    compact_support_radii: Option<Vec<R>>,
+}
+
+impl<R: Real> MirroredParticles<R> {
+    /// Mirrors the particles close to the boundary, returns `None` if no particle has to be mirrored
+    pub(crate) fn new(
+        boundary: &BoundaryDistanceField<R>,
+        particle_positions: &[Vector3<R>],
+        particle_properties: ParticleProperties<R>,
+        enable_multi_threading: bool,
+    ) -> Option<Self> {
+        let mirrored = boundary.mirrored_particles(particle_positions, enable_multi_threading);
+        if mirrored.is_empty() {
+            return None;
+        }
+        info!("Mirrored {} particles at the boundary.", mirrored.len());
+
+        // The mirror images have the same properties as the original particles
+        let extend = |values: &[R]| {
+            let mut extended = values.to_vec();
+            extended.extend(mirrored.iter().map(|&(i, _)| values[i]));
+            extended
+        };
+
+        let mut positions = particle_positions.to_vec();
+        positions.extend(mirrored.iter().map(|(_, x)| *x));
+
+        Some(Self {
+            positions,
+            source_indices: mirrored.iter().map(|&(i, _)| i).collect(),
+            masses: particle_properties.masses().map(extend),
+            compact_support_radii: particle_properties.compact_support_radii().map(extend),
+        })
+    }
+
+    /// Returns the per particle properties of the original and the mirrored particles
+    pub(crate) fn properties(&self) -> ParticleProperties<'_, R> {
+        let mut properties = ParticleProperties::uniform();
+        if let Some(masses) = &self.masses {
+            properties = properties.with_masses(masses);
+        }
+        if let Some(radii) = &self.compact_support_radii {
+            properties = properties.with_compact_support_radii(radii);
+        }
+        properties
+    }
+}
+
+/// Uniform grid of cells storing the indices of the primitives overlapping each cell
+struct CellLists<R: Real> {
+    cell_size: R,
+    cells: MapType<[i64; 3], Vec<usize>>,
+}
+
+impl<R: Real> CellLists<R> {
+    fn new(cell_size: R) -> Self {
+        Self {
+            cell_size,
+            cells: new_map(),
+        }
+    }
+
+    /// Creates cell lists storing the indices of the given points
+    fn from_points(points: &[Vector3<R>], cell_size: R) -> Self {
+        let mut cells = Self::new(cell_size);
+        for (i, x) in points.iter().enumerate() {
+            cells.insert(x, x, i);
+        }
+        cells
+    }
+
+    fn cell(&self, x: &Vector3<R>) -> [i64; 3] {
+        [0, 1, 2].map(|dim| (x[dim] / self.cell_size).floor().to_i64().unwrap_or(0))
+    }
+
+    /// Inserts the primitive with the given index into all cells overlapping the box between `min` and `max`
+    fn insert(&mut self, min: &Vector3<R>, max: &Vector3<R>, index: usize) {
+        let lower = self.cell(min);
+        let upper = self.cell(max);
+        for i in lower[0]..=upper[0] {
+            for j in lower[1]..=upper[1] {
+                for k in lower[2]..=upper[2] {
+                    self.cells.entry([i, j, k]).or_default().push(index);
+                }
+            }
+        }
+    }
+
+    /// Returns the indices of all primitives that overlap the cells closer than the cell size to the point (may contain duplicates)
+    fn candidates<'a>(&'a self, x: &Vector3<R>) -> impl Iterator<Item = usize> + 'a {
+        let [i, j, k] = self.cell(x);
+        (-1..=1)
+            .flat_map(move |di| (-1..=1).flat_map(move |dj| (-1..=1).map(move |dk| [di, dj, dk])))
+            .filter_map(move |[di, dj, dk]| self.cells.get(&[i + di, j + dj, k + dk]))
+            .flatten()
+            .copied()
+    }
+}
+
+/// Feature of a triangle that is closest to a point
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+enum TriangleFeature {
+    /// Vertex with the given local index
+    Vertex(usize),
+    /// Edge between the vertices with the given local indices
+    Edge(usize, usize),
+    /// Interior of the triangle
+    Face,
+}
+
+/// Computes the closest point to `p` on the triangle `abc` (see Ericson, "Real-Time Collision Detection", 2004)
+fn closest_point_on_triangle<R: Real>(
+    p: &Vector3<R>,
+    a: &Vector3<R>,
+    b: &Vector3<R>,
+    c: &Vector3<R>,
+) -> (Vector3<R>, TriangleFeature) {
+    let ab = b - a;
+    let ac = c - a;
+
+    let ap = p - a;
+    let d1 = ab.dot(&ap);
+    let d2 = ac.dot(&ap);
+    if d1 <= R::zero() && d2 <= R::zero() {
+        return (*a, TriangleFeature::Vertex(0));
+    }
+
+    let bp = p - b;
+    let d3 = ab.dot(&bp);
+    let d4 = ac.dot(&bp);
+    if d3 >= R::zero() && d4 <= d3 {
+        return (*b, TriangleFeature::Vertex(1));
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= R::zero() && d1 >= R::zero() && d3 <= R::zero() {
+        let v = d1 / (d1 - d3);
+        return (a + ab * v, TriangleFeature::Edge(0, 1));
+    }
+
+    let cp = p - c;
+    let d5 = ab.dot(&cp);
+    let d6 = ac.dot(&cp);
+    if d6 >= R::zero() && d5 <= d6 {
+        return (*c, TriangleFeature::Vertex(2));
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= R::zero() && d2 >= R::zero() && d6 <= R::zero() {
+        let w = d2 / (d2 - d6);
+        return (a + ac * w, TriangleFeature::Edge(0, 2));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= R::zero() && (d4 - d3) >= R::zero() && (d5 - d6) >= R::zero() {
+        let w = (d4 - d3) / ((d4 - d3) + (d5 - d6));
+        return (b + (c - b) * w, TriangleFeature::Edge(1, 2));
+    }
+
+    let denom = R::one() / (va + vb + vc);
+    let v = vb * denom;
+    let w = vc * denom;
+    (a + ab * v + ac * w, TriangleFeature::Face)
+}
+
+/// Angle weighted pseudo-normals of a triangle mesh for the computation of the sign of the distance to the mesh
+/// (see Bærentzen and Aanæs, "Signed distance computation using the angle weighted pseudonormal", 2005)
+struct MeshPseudoNormals<R: Real> {
+    face_normals: Vec<Vector3<R>>,
+    vertex_normals: Vec<Vector3<R>>,
+    edge_normals: MapType<[usize; 2], Vector3<R>>,
+}
+
+impl<R: Real> MeshPseudoNormals<R> {
+    fn new(mesh: &TriMesh3d<R>) -> Self {
+        let face_normals = mesh
+            .triangles
+            .iter()
+            .map(|tri| {
+                let [a, b, c] = tri.map(|v| mesh.vertices[v]);
+                (b - a).cross(&(c - a)).normalize()
+            })
+            .collect::<Vec<_>>();
+
+        let mut vertex_normals = vec![Vector3::zeros(); mesh.vertices().len()];
+        let mut edge_normals = new_map();
+        for (tri, normal) in mesh.triangles.iter().zip(face_normals.iter()) {
+            for corner in 0..3 {
+                let v = tri[corner];
+                let e1 = mesh.vertices[tri[(corner + 1) % 3]] - mesh.vertices[v];
+                let e2 = mesh.vertices[tri[(corner + 2) % 3]] - mesh.vertices[v];
+                let angle = e1.angle(&e2);
+                vertex_normals[v] += normal * angle;
+
+                let edge = Self::edge_key(v, tri[(corner + 1) % 3]);
+                *edge_normals.entry(edge).or_insert_with(Vector3::zeros) += normal;
+            }
+        }
+
+        Self {
+            face_normals,
+            vertex_normals,
+            edge_normals,
+        }
+    }
+
+    fn edge_key(a: usize, b: usize) -> [usize; 2] {
+        [a.min(b), a.max(b)]
+    }
+
+    /// Returns the pseudo-normal of the given feature of the triangle
+    fn normal(&self, tri: &[usize; 3], tri_index: usize, feature: TriangleFeature) -> Vector3<R> {
+        match feature {
+            TriangleFeature::Face => self.face_normals[tri_index],
+            TriangleFeature::Vertex(v) => self.vertex_normals[tri[v]],
+            TriangleFeature::Edge(a, b) => self.edge_normals[&Self::edge_key(tri[a], tri[b])],
+        }
+    }
+}
+
+#[test]
+fn test_mesh_signed_distance() {
+    let aabb = Aabb3d::new(Vector3::new(-1.0, -0.5, -1.0), Vector3::new(1.0, 1.5, 2.0));
+    let sdf = SignedDistanceFunction::box_container(aabb.clone());
+
+    // Box with all triangles oriented towards the inside of the box
+    let [min, max] = [aabb.min(), aabb.max()];
+    let vertices = (0..8)
+        .map(|c| {
+            Vector3::new(
+                if c & 1 == 0 { min.x } else { max.x },
+                if c & 2 == 0 { min.y } else { max.y },
+                if c & 4 == 0 { min.z } else { max.z },
+            )
+        })
+        .collect();
+    let triangles = vec![
+        [0, 1, 3],
+        [0, 3, 2],
+        [4, 6, 7],
+        [4, 7, 5],
+        [0, 4, 5],
+        [0, 5, 1],
+        [2, 3, 7],
+        [2, 7, 6],
+        [0, 2, 6],
+        [0, 6, 4],
+        [1, 5, 7],
+        [1, 7, 3],
+    ];
+    let boundary = BoundaryParameters {
+        geometry: BoundaryGeometry::Mesh(TriMesh3d {
+            vertices,
+            triangles,
+        }),
+        handling: BoundaryHandling::Cut,
+    };
+    let field = BoundaryDistanceField::new(&boundary, 0.3, 0.1, 0.6);
+
+    let mut x = Vector3::new(0.0, 0.0, 0.0);
+    for i in 0..2000 {
+        // Deterministic pseudo random points around the box
+        x = x.map(|v: f64| (v * 7.31 + 0.37 * (i as f64 + 1.0)).sin());
+        let p = Vector3::new(x.x * 1.3, 0.5 + x.y * 1.3, 0.5 + x.z * 1.8);
+        let expected = sdf.evaluate(&p);
+        match field.signed_distance(&p) {
+            Some(distance) => assert!((distance - expected).abs() < 1e-12),
+            // Only points outside of the narrow band around the boundary have no signed distance
+            None => assert!(expected.abs() > 0.6),
+        }
+    }
+}
@@ -2,7 +2,7 @@ use anyhow::Context;
 use arrayvec::ArrayVec;
 use itertools::Itertools;
 use log::{info, trace};
-use nalgebra::Vector3;
+use nalgebra::{Matrix3, Vector3};
 use num_integer::Integer;
 use num_traits::{FromPrimitive, NumCast};
 use parking_lot::Mutex;
@@ -11,17 +11,24 @@ use std::cell::RefCell;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use thread_local::ThreadLocal;
 
+use crate::anisotropy::{compute_particle_kernel, AnisotropicKernels, AnisotropyParameters};
+use crate::boundary::BoundaryConditions;
 use crate::density_map::sequential_compute_particle_densities_filtered;
-use crate::kernel::{CubicSplineKernel, SymmetricKernel3d};
+use crate::kernel::{KernelType, SphKernel, SymmetricKernel3d};
 use crate::marching_cubes::marching_cubes_lut::marching_cubes_triangulation_iter;
 use crate::mesh::{HexMesh3d, TriMesh3d};
 use crate::neighborhood_search::{
     neighborhood_search_spatial_hashing_flat_filtered,
-    neighborhood_search_spatial_hashing_parallel, FlatNeighborhoodList,
+    neighborhood_search_spatial_hashing_parallel, FlatNeighborhoodList, NeighborhoodList,
 };
+use crate::surface_nets::{
+    cell_vertex_position, quad_to_triangles, CELL_CORNER_OFFSETS, EDGE_ADJACENT_CELL_OFFSETS,
+};
+use crate::topology::Axis;
 use crate::uniform_grid::{EdgeIndex, UniformCartesianCubeGrid3d};
 use crate::{
-    new_map, new_parallel_map, profile, Aabb3d, MapType, Parameters, SurfaceReconstruction,
+    new_map, new_parallel_map, profile, Aabb3d, MapType, Parameters, ParticleProperties,
+    SurfaceReconstruction,
 };
 use crate::{Index, Real};
 
@@ -53,8 +60,10 @@ pub(crate) struct ParametersSubdomainGrid<I: Index, R: Real> {
     particle_radius: R,
     /// Rest mass of each particle
     particle_rest_mass: R,
-    /// SPH kernel compact support radius (in simulation units)
+    /// SPH kernel compact support radius (in simulation units), the largest radius if radii are provided per particle
     compact_support_radius: R,
+    /// SPH kernel function
+    kernel_type: KernelType,
     /// Density value for the iso-surface
     surface_threshold: R,
     /// MC cube size (in simulation units)
@@ -63,6 +72,10 @@ pub(crate) struct ParametersSubdomainGrid<I: Index, R: Real> {
     subdomain_cubes: I,
     /// Margin for ghost particles around each subdomain
     ghost_particle_margin: R,
+    /// Parameters for anisotropic kernels, isotropic kernels are used if `None`
+    anisotropy: Option<AnisotropyParameters<R>>,
+    /// Modifications of the density field at solid boundaries (cut and boundary particle contributions)
+    boundary: Option<BoundaryConditions<R>>,
     /// Implicit global MC background grid (required to compute consistent float coordinates at domain boundaries)
     global_marching_cubes_grid: UniformCartesianCubeGrid3d<GlobalIndex, R>,
     /// Implicit subdomain grid
@@ -82,6 +95,8 @@ pub(crate) struct Subdomains<I: Index> {
 pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
     parameters: &Parameters<R>,
     _particles: &[Vector3<R>],
+    particle_properties: ParticleProperties<R>,
+    boundary: Option<BoundaryConditions<R>>,
     output_surface: &'a SurfaceReconstruction<I, R>,
 ) -> Result<ParametersSubdomainGrid<I, R>, anyhow::Error> {
     let chunk_size = 500;
@@ -96,15 +111,23 @@ pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
     // Physical particle properties
     let particle_radius = parameters.particle_radius;
     let particle_rest_density = parameters.rest_density;
-    let compact_support_radius = parameters.compact_support_radius;
+    // With per particle radii, the neighborhood search and the ghost margin are based on the largest radius
+    let compact_support_radius =
+        particle_properties.max_compact_support_radius(parameters.compact_support_radius);
     let cube_size = parameters.cube_size;
     let surface_threshold = parameters.iso_surface_threshold;
 
     let particle_rest_volume = to_real!(4) * R::frac_pi_3() * particle_radius.powi(3);
     let particle_rest_mass = particle_rest_volume * particle_rest_density;
 
-    let ghost_particle_margin =
-        (compact_support_radius / cube_size).ceil() * cube_size * to_real!(1.01);
+    // Anisotropic kernels are shifted and stretched relative to the particle positions
+    let max_kernel_radius = match &parameters.anisotropy {
+        Some(anisotropy) => {
+            compact_support_radius * (anisotropy.max_stretch() + anisotropy.max_center_offset())
+        }
+        None => compact_support_radius,
+    };
+    let ghost_particle_margin = (max_kernel_radius / cube_size).ceil() * cube_size * to_real!(1.01);
 
     // Compute information of ghost margin volume for debugging
     {
@@ -220,10 +243,14 @@ pub(crate) fn initialize_parameters<'a, I: Index, R: Real>(
         particle_radius,
         particle_rest_mass,
         compact_support_radius,
+        kernel_type: parameters.kernel,
         surface_threshold,
         cube_size,
         subdomain_cubes,
         ghost_particle_margin,
+        anisotropy: parameters.anisotropy.clone(),
+        // Mirrored particles are already part of the input, only the density modifications have to be applied per subdomain
+        boundary,
         global_marching_cubes_grid: global_mc_grid,
         subdomain_grid,
         chunk_size,
@@ -485,12 +512,21 @@ pub(crate) fn decomposition<
 pub(crate) fn compute_global_density_vector<I: Index, R: Real>(
     parameters: &ParametersSubdomainGrid<I, R>,
     global_particles: &[Vector3<R>],
+    particle_properties: ParticleProperties<R>,
     subdomains: &Subdomains<I>,
-) -> Vec<R> {
+) -> (Vec<R>, Option<AnisotropicKernels<R>>) {
     profile!(parent, "compute_global_density_vector");
     info!("Starting computation of global density vector.");
 
     let global_particle_densities = Mutex::new(vec![R::zero(); global_particles.len()]);
+    // The anisotropic kernels are computed from the same neighborhood lists as the densities
+    let global_anisotropic_kernels = parameters.anisotropy.as_ref().map(|_| {
+        Mutex::new(AnisotropicKernels {
+            smoothed_positions: global_particles.to_vec(),
+            transforms: vec![Matrix3::identity(); global_particles.len()],
+            max_stretch: R::one(),
+        })
+    });
 
     #[derive(Default)]
     struct SubdomainWorkspace<R: Real> {
@@ -502,6 +538,10 @@ pub(crate) fn compute_global_density_vector<I: Index, R: Real>(
         particle_densities: Vec<R>,
         // Per particle flag whether the particle is in the interior of this subdomain (non-ghost particle)
         is_inside: Vec<bool>,
+        // Per particle masses of this subdomain (only if provided per particle)
+        particle_masses: Vec<R>,
+        // Per particle compact support radii of this subdomain (only if provided per particle)
+        compact_support_radii: Vec<R>,
     }
 
     let workspace_tls = ThreadLocal::<RefCell<SubdomainWorkspace<R>>>::new();
@@ -522,6 +562,8 @@ pub(crate) fn compute_global_density_vector<I: Index, R: Real>(
                 neighborhood_lists,
                 particle_densities,
                 is_inside,
+                particle_masses,
+                compact_support_radii,
             } = &mut *workspace;
 
             let flat_subdomain_idx: I = flat_subdomain_idx;
@@ -537,6 +579,18 @@ pub(crate) fn compute_global_density_vector<I: Index, R: Real>(
                 );
             }
 
+            // Collect the per particle properties of this subdomain
+            let mut subdomain_properties = ParticleProperties::uniform();
+            if let Some(masses) = particle_properties.masses() {
+                gather_subdomain_data(masses, subdomain_particle_indices, particle_masses);
+                subdomain_properties = subdomain_properties.with_masses(particle_masses);
+            }
+            if let Some(radii) = particle_properties.compact_support_radii() {
+                gather_subdomain_data(radii, subdomain_particle_indices, compact_support_radii);
+                subdomain_properties =
+                    subdomain_properties.with_compact_support_radii(compact_support_radii);
+            }
+
             // Get the cell index and AABB of the subdomain
             let subdomain_idx = parameters
                 .subdomain_grid
@@ -574,7 +628,9 @@ pub(crate) fn compute_global_density_vector<I: Index, R: Real>(
                 &subdomain_particles,
                 neighborhood_lists,
                 parameters.compact_support_radius,
+                parameters.kernel_type,
                 parameters.particle_rest_mass,
+                subdomain_properties,
                 particle_densities,
                 is_inside,
             );
@@ -599,9 +655,52 @@ pub(crate) fn compute_global_density_vector<I: Index, R: Real>(
                         global_particle_densities[particle_idx] = density;
                     });
             }
+
+            if let (Some(anisotropy), Some(global_anisotropic_kernels)) =
+                (&parameters.anisotropy, &global_anisotropic_kernels)
+            {
+                profile!("compute anisotropic kernels");
+                let kernels = is_inside
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .filter(|(_, is_inside)| *is_inside)
+                    .map(|(i, _)| {
+                        (
+                            subdomain_particle_indices[i],
+                            compute_particle_kernel(
+                                i,
+                                subdomain_particles,
+                                neighborhood_lists.neighbors(i),
+                                subdomain_properties
+                                    .compact_support_radius(i, parameters.compact_support_radius),
+                                anisotropy,
+                            ),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+
+                let mut global_anisotropic_kernels = global_anisotropic_kernels.lock();
+                for (particle_idx, (position, transform, stretch)) in kernels {
+                    global_anisotropic_kernels.smoothed_positions[particle_idx] = position;
+                    global_anisotropic_kernels.transforms[particle_idx] = transform;
+                    global_anisotropic_kernels.max_stretch =
+                        global_anisotropic_kernels.max_stretch.max(stretch);
+                }
+            }
         });
 
-    let global_particle_densities = global_particle_densities.into_inner();
+    let mut global_particle_densities = global_particle_densities.into_inner();
+    if let Some(boundary) = &parameters.boundary {
+        boundary.add_particle_densities(global_particles, &mut global_particle_densities, true);
+    }
+    let global_anisotropic_kernels = global_anisotropic_kernels.map(Mutex::into_inner);
+    if let Some(kernels) = &global_anisotropic_kernels {
+        info!(
+            "Computed anisotropic kernels, max. stretch factor: {}",
+            kernels.max_stretch
+        );
+    }
 
     /*
     {
@@ -614,7 +713,7 @@ pub(crate) fn compute_global_density_vector<I: Index, R: Real>(
     }
     */
 
-    global_particle_densities
+    (global_particle_densities, global_anisotropic_kernels)
 }
 
 pub(crate) struct SurfacePatch<I: Index, R: Real> {
@@ -632,6 +731,8 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
     parameters: &ParametersSubdomainGrid<I, R>,
     global_particles: &[Vector3<R>],
     global_particle_densities: &[R],
+    anisotropic_kernels: Option<&AnisotropicKernels<R>>,
+    particle_properties: ParticleProperties<R>,
     subdomains: &Subdomains<I>,
 ) -> Vec<SurfacePatch<I, R>> {
     profile!(parent, "reconstruction");
@@ -639,12 +740,14 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
     let squared_support = parameters.compact_support_radius * parameters.compact_support_radius;
     // Add 1% so that we don't exclude grid points that are just on the kernel boundary
     let squared_support_with_margin = squared_support * to_real!(1.01);
+    // Anisotropic kernels may be stretched beyond the isotropic compact support radius
+    let max_kernel_radius = parameters.compact_support_radius
+        * anisotropic_kernels.map_or(R::one(), |kernels| kernels.max_stretch);
     // Compute radial distance in terms of grid points we have to evaluate for each particle
-    let cube_radius = I::from((parameters.compact_support_radius / parameters.cube_size).ceil())
+    let cube_radius = I::from((max_kernel_radius / parameters.cube_size).ceil())
         .expect("kernel radius in cubes has to fit in index type");
     // Kernel
-    let kernel = CubicSplineKernel::new(parameters.compact_support_radius);
-    //let kernel = DiscreteSquaredDistanceCubicKernel::new::<f64>(1000, parameters.compact_support_radius);
+    let kernel = SphKernel::new(parameters.kernel_type, parameters.compact_support_radius);
 
     let mc_total_cells = parameters.subdomain_cubes.cubed();
     let mc_total_points = (parameters.subdomain_cubes + I::one()).cubed();
@@ -753,6 +856,12 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
         subdomain_particles: Vec<Vector3<R>>,
         // Per particle density values of this subdomain
         subdomain_particle_densities: Vec<R>,
+        // Per particle anisotropic kernel transforms of this subdomain
+        subdomain_kernel_transforms: Vec<Matrix3<R>>,
+        // Per particle masses of this subdomain (only if provided per particle)
+        subdomain_particle_masses: Vec<R>,
+        // Per particle compact support radii of this subdomain (only if provided per particle)
+        subdomain_compact_support_radii: Vec<R>,
         // Cache for the level-set values
         levelset_grid: Vec<R>,
         // Cache for indices
@@ -768,6 +877,9 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
         let SubdomainWorkspace {
             subdomain_particles,
             subdomain_particle_densities,
+            subdomain_kernel_transforms,
+            subdomain_particle_masses,
+            subdomain_compact_support_radii,
             levelset_grid,
             index_cache: _index_cache,
         } = &mut *workspace;
@@ -788,6 +900,27 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
                 subdomain_particle_indices,
                 subdomain_particle_densities,
             );
+            if let Some(kernels) = anisotropic_kernels {
+                gather_subdomain_data(
+                    &kernels.transforms,
+                    subdomain_particle_indices,
+                    subdomain_kernel_transforms,
+                );
+            }
+            if let Some(masses) = particle_properties.masses() {
+                gather_subdomain_data(
+                    masses,
+                    subdomain_particle_indices,
+                    subdomain_particle_masses,
+                );
+            }
+            if let Some(radii) = particle_properties.compact_support_radii() {
+                gather_subdomain_data(
+                    radii,
+                    subdomain_particle_indices,
+                    subdomain_compact_support_radii,
+                );
+            }
         }
 
         // Get the cell index and AABB of the subdomain
@@ -812,11 +945,29 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
 
             let extents = mc_grid.points_per_dim();
 
-            for (p_i, rho_i) in subdomain_particles
+            for (particle_idx, (p_i, rho_i)) in subdomain_particles
                 .iter()
                 .copied()
                 .zip(subdomain_particle_densities.iter().copied())
+                .enumerate()
             {
+                let kernel_transform =
+                    anisotropic_kernels.map(|_| &subdomain_kernel_transforms[particle_idx]);
+                let v_i = particle_properties
+                    .masses()
+                    .map_or(parameters.particle_rest_mass, |_| {
+                        subdomain_particle_masses[particle_idx]
+                    })
+                    / rho_i;
+                // Kernels of particles with smaller compact support radii are evaluated at scaled distances
+                let kernel_scale =
+                    particle_properties
+                        .compact_support_radii()
+                        .map_or(R::one(), |_| {
+                            parameters.compact_support_radius
+                                / subdomain_compact_support_radii[particle_idx]
+                        });
+                let kernel_normalization = kernel_scale * kernel_scale * kernel_scale;
                 // Get grid cell containing particle
                 let particle_cell = mc_grid.enclosing_cell(&p_i);
 
@@ -838,6 +989,11 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
                     (particle_cell[2] + cube_radius + I::two()).min(extents[2]),
                 ];
 
+                // Skip ghost particles with kernels that do not reach the grid of this subdomain
+                if (0..3).any(|dim| lower[dim] >= upper[dim]) {
+                    continue;
+                }
+
                 // Loop over all grid points around the enclosing cell
                 for i in I::range(lower[0], upper[0]).iter() {
                     for j in I::range(lower[1], upper[1]).iter() {
@@ -886,12 +1042,14 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
                                 .point_coordinates(&global_point);
 
                             let dx = p_i - point_coordinates;
+                            // Anisotropic kernels are evaluated in the transformed frame of the particle
+                            let dx = kernel_transform.map_or(dx, |transform| transform * dx)
+                                * kernel_scale;
                             let dx_norm_sq = dx.norm_squared();
 
                             if dx_norm_sq < squared_support_with_margin {
-                                let v_i = parameters.particle_rest_mass / rho_i;
                                 let r = dx_norm_sq.sqrt();
-                                let w_ij = kernel.evaluate(r);
+                                let w_ij = kernel.evaluate(r) * kernel_normalization;
                                 //let w_ij = kernel.evaluate(dx_norm_sq);
 
                                 let interpolated_value = v_i * w_ij;
@@ -906,6 +1064,12 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
             }
         }
 
+        // Use global point indices for consistency with neighboring domains
+        let subdomain_offset = subdomain_idx.index().map(|i| {
+            <i64 as NumCast>::from(i * parameters.subdomain_cubes).expect("index has to fit in i64")
+        });
+        apply_boundary_to_levelset(parameters, &mc_grid, subdomain_offset, levelset_grid, None);
+
         let mut vertices = Vec::new();
         let mut triangles = Vec::new();
 
@@ -1019,6 +1183,9 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
         let SubdomainWorkspace {
             subdomain_particles,
             subdomain_particle_densities,
+            subdomain_kernel_transforms,
+            subdomain_particle_masses,
+            subdomain_compact_support_radii,
             levelset_grid,
             index_cache,
         } = &mut *workspace;
@@ -1039,6 +1206,27 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
                 subdomain_particle_indices,
                 subdomain_particle_densities,
             );
+            if let Some(kernels) = anisotropic_kernels {
+                gather_subdomain_data(
+                    &kernels.transforms,
+                    subdomain_particle_indices,
+                    subdomain_kernel_transforms,
+                );
+            }
+            if let Some(masses) = particle_properties.masses() {
+                gather_subdomain_data(
+                    masses,
+                    subdomain_particle_indices,
+                    subdomain_particle_masses,
+                );
+            }
+            if let Some(radii) = particle_properties.compact_support_radii() {
+                gather_subdomain_data(
+                    radii,
+                    subdomain_particle_indices,
+                    subdomain_compact_support_radii,
+                );
+            }
         }
 
         // Get the cell index and AABB of the subdomain
@@ -1065,11 +1253,29 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
 
             let extents = mc_grid.points_per_dim();
 
-            for (p_i, rho_i) in subdomain_particles
+            for (particle_idx, (p_i, rho_i)) in subdomain_particles
                 .iter()
                 .copied()
                 .zip(subdomain_particle_densities.iter().copied())
+                .enumerate()
             {
+                let kernel_transform =
+                    anisotropic_kernels.map(|_| &subdomain_kernel_transforms[particle_idx]);
+                let v_i = particle_properties
+                    .masses()
+                    .map_or(parameters.particle_rest_mass, |_| {
+                        subdomain_particle_masses[particle_idx]
+                    })
+                    / rho_i;
+                // Kernels of particles with smaller compact support radii are evaluated at scaled distances
+                let kernel_scale =
+                    particle_properties
+                        .compact_support_radii()
+                        .map_or(R::one(), |_| {
+                            parameters.compact_support_radius
+                                / subdomain_compact_support_radii[particle_idx]
+                        });
+                let kernel_normalization = kernel_scale * kernel_scale * kernel_scale;
                 // Get grid cell containing particle
                 let particle_cell = mc_grid.enclosing_cell(&p_i);
 
@@ -1091,6 +1297,11 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
                     (particle_cell[2] + cube_radius + I::two()).min(extents[2]),
                 ];
 
+                // Skip ghost particles with kernels that do not reach the grid of this subdomain
+                if (0..3).any(|dim| lower[dim] >= upper[dim]) {
+                    continue;
+                }
+
                 // Loop over all grid points around the enclosing cell
                 for i in I::range(lower[0], upper[0]).iter() {
                     for j in I::range(lower[1], upper[1]).iter() {
@@ -1139,12 +1350,14 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
                                 .point_coordinates(&global_point);
 
                             let dx = p_i - point_coordinates;
+                            // Anisotropic kernels are evaluated in the transformed frame of the particle
+                            let dx = kernel_transform.map_or(dx, |transform| transform * dx)
+                                * kernel_scale;
                             let dx_norm_sq = dx.norm_squared();
 
                             if dx_norm_sq < squared_support_with_margin {
-                                let v_i = parameters.particle_rest_mass / rho_i;
                                 let r = dx_norm_sq.sqrt();
-                                let w_ij = kernel.evaluate(r);
+                                let w_ij = kernel.evaluate(r) * kernel_normalization;
                                 //let w_ij = kernel.evaluate(dx_norm_sq);
 
                                 let interpolated_value = v_i * w_ij;
@@ -1172,6 +1385,18 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
             }
         }
 
+        // Use global point indices for consistency with neighboring domains
+        let subdomain_offset = subdomain_idx.index().map(|i| {
+            <i64 as NumCast>::from(i * parameters.subdomain_cubes).expect("index has to fit in i64")
+        });
+        apply_boundary_to_levelset(
+            parameters,
+            &mc_grid,
+            subdomain_offset,
+            levelset_grid,
+            Some(index_cache),
+        );
+
         let mut vertices = Vec::new();
         let mut triangles = Vec::new();
 
@@ -1299,6 +1524,79 @@ pub(crate) fn reconstruction<I: Index, R: Real>(
     surface_patches
 }
 
+/// Applies the boundary conditions (cut at a solid boundary and boundary particle contributions) to the level-set values of a local grid
+///
+/// The `global_offset` is added to the local point indices to obtain the indices of the points in the global MC grid.
+/// If an `index_cache` of cells to triangulate is provided, the cells adjacent to points that are lifted above the
+/// iso-surface threshold by the boundary particles are added to it.
+fn apply_boundary_to_levelset<I: Index, R: Real>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    local_grid: &UniformCartesianCubeGrid3d<I, R>,
+    global_offset: [i64; 3],
+    levelset_grid: &mut [R],
+    mut index_cache: Option<&mut Vec<I>>,
+) {
+    let boundary = match parameters.boundary.as_ref() {
+        Some(boundary) => boundary,
+        None => return,
+    };
+    profile!("apply boundary conditions to levelset");
+
+    let extents = local_grid.points_per_dim();
+    for i in I::range(I::zero(), extents[0]).iter() {
+        for j in I::range(I::zero(), extents[1]).iter() {
+            for k in I::range(I::zero(), extents[2]).iter() {
+                let flat_point_idx = local_grid
+                    .flatten_point_indices(i, j, k)
+                    .to_usize()
+                    .unwrap();
+                // Points without any particle contribution are outside of the fluid anyway
+                if levelset_grid[flat_point_idx] <= R::zero() {
+                    continue;
+                }
+
+                let global_point = (|| -> Option<_> {
+                    let mut global_ijk = [0; 3];
+                    for (dim, local_index) in [i, j, k].into_iter().enumerate() {
+                        let global_index =
+                            <i64 as NumCast>::from(local_index)? + global_offset[dim];
+                        global_ijk[dim] = <GlobalIndex as NumCast>::from(global_index)?;
+                    }
+                    parameters.global_marching_cubes_grid.get_point(global_ijk)
+                })();
+
+                if let Some(global_point) = global_point {
+                    let point_coordinates = parameters
+                        .global_marching_cubes_grid
+                        .point_coordinates(&global_point);
+                    let previous_value = levelset_grid[flat_point_idx];
+                    let value = boundary.density(&point_coordinates, previous_value);
+                    levelset_grid[flat_point_idx] = value;
+
+                    if let Some(index_cache) = index_cache.as_mut() {
+                        if previous_value <= parameters.surface_threshold
+                            && value > parameters.surface_threshold
+                        {
+                            let local_point = local_grid
+                                .get_point([i, j, k])
+                                .expect("point has to be part of the subdomain grid");
+                            for c in local_grid
+                                .cells_adjacent_to_point(
+                                    &local_grid.get_point_neighborhood(&local_point),
+                                )
+                                .iter()
+                                .flatten()
+                            {
+                                index_cache.push(local_grid.flatten_cell_index(c));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
 pub(crate) fn stitching<I: Index, R: Real>(
     surface_patches: Vec<SurfacePatch<I, R>>,
 ) -> TriMesh3d<R> {
@@ -1612,6 +1910,431 @@ pub(crate) mod subdomain_classification {
     }
 }
 
+/// Surface patch of a single subdomain triangulated using surface nets
+///
+/// The vertices are identified by the flat index of their cell in the global marching cubes grid.
+/// Every subdomain only generates the quads of the edges starting at one of its own grid points, but
+/// these quads may refer to vertices in the cells of neighboring subdomains.
+pub(crate) struct SurfaceNetsPatch<R: Real> {
+    /// Positions of all vertices of the patch
+    pub vertices: Vec<Vector3<R>>,
+    /// Flat index of the cell in the global marching cubes grid of each vertex
+    pub vertex_cells: Vec<GlobalIndex>,
+    /// Flags indicating whether the cell of a vertex is part of the subdomain of the patch (otherwise it is part of the one cell wide margin)
+    pub vertex_owned_flags: Vec<bool>,
+    /// Triangles of the patch referring to the local vertex indices
+    pub triangles: Vec<[usize; 3]>,
+}
+
+/// Performs the level-set evaluation and a surface nets triangulation of all subdomains
+///
+/// In contrast to the marching cubes reconstruction, the level-set of each subdomain is evaluated
+/// with one additional layer of grid cells around the subdomain. This allows to compute the vertices
+/// of all cells adjacent to the edges of the subdomain locally. The resulting patches can be
+/// merged without stitching using [`stitching_surface_nets`].
+pub(crate) fn reconstruction_surface_nets<I: Index, R: Real>(
+    parameters: &ParametersSubdomainGrid<I, R>,
+    global_particles: &[Vector3<R>],
+    global_particle_densities: &[R],
+    anisotropic_kernels: Option<&AnisotropicKernels<R>>,
+    particle_properties: ParticleProperties<R>,
+    subdomains: &Subdomains<I>,
+) -> Vec<SurfaceNetsPatch<R>> {
+    profile!(parent, "reconstruction_surface_nets");
+
+    let squared_support = parameters.compact_support_radius * parameters.compact_support_radius;
+    // Add 1% so that we don't exclude grid points that are just on the kernel boundary
+    let squared_support_with_margin = squared_support * to_real!(1.01);
+    // Anisotropic kernels may be stretched beyond the isotropic compact support radius
+    let max_kernel_radius = parameters.compact_support_radius
+        * anisotropic_kernels.map_or(R::one(), |kernels| kernels.max_stretch);
+    // Compute radial distance in terms of grid points we have to evaluate for each particle
+    let cube_radius = I::from((max_kernel_radius / parameters.cube_size).ceil())
+        .expect("kernel radius in cubes has to fit in index type");
+    let kernel = SphKernel::new(parameters.kernel_type, parameters.compact_support_radius);
+
+    let subdomain_cubes = parameters.subdomain_cubes;
+    // Number of cells of the local grid including one layer of margin cells on each side
+    let margin_cubes = subdomain_cubes + I::two();
+    let margin_total_points = (margin_cubes + I::one()).cubed();
+    let threshold = parameters.surface_threshold;
+
+    info!("Starting reconstruction (level-set evaluation and local surface nets triangulation).");
+
+    #[derive(Default)]
+    struct SubdomainWorkspace<R: Real> {
+        // Particle positions of this subdomain
+        subdomain_particles: Vec<Vector3<R>>,
+        // Per particle density values of this subdomain
+        subdomain_particle_densities: Vec<R>,
+        // Per particle anisotropic kernel transforms of this subdomain
+        subdomain_kernel_transforms: Vec<Matrix3<R>>,
+        // Per particle masses of this subdomain (only if provided per particle)
+        subdomain_particle_masses: Vec<R>,
+        // Per particle compact support radii of this subdomain (only if provided per particle)
+        subdomain_compact_support_radii: Vec<R>,
+        // Cache for the level-set values
+        levelset_grid: Vec<R>,
+    }
+
+    let workspace_tls = ThreadLocal::<RefCell<SubdomainWorkspace<R>>>::new();
+
+    let reconstruct_subdomain = |flat_subdomain_idx: I, subdomain_particle_indices: &Vec<usize>| {
+        let mut workspace = workspace_tls.get_or_default().borrow_mut();
+        let SubdomainWorkspace {
+            subdomain_particles,
+            subdomain_particle_densities,
+            subdomain_kernel_transforms,
+            subdomain_particle_masses,
+            subdomain_compact_support_radii,
+            levelset_grid,
+        } = &mut *workspace;
+
+        gather_subdomain_data(
+            global_particles,
+            subdomain_particle_indices,
+            subdomain_particles,
+        );
+        gather_subdomain_data(
+            global_particle_densities,
+            subdomain_particle_indices,
+            subdomain_particle_densities,
+        );
+        if let Some(kernels) = anisotropic_kernels {
+            gather_subdomain_data(
+                &kernels.transforms,
+                subdomain_particle_indices,
+                subdomain_kernel_transforms,
+            );
+        }
+        if let Some(masses) = particle_properties.masses() {
+            gather_subdomain_data(
+                masses,
+                subdomain_particle_indices,
+                subdomain_particle_masses,
+            );
+        }
+        if let Some(radii) = particle_properties.compact_support_radii() {
+            gather_subdomain_data(
+                radii,
+                subdomain_particle_indices,
+                subdomain_compact_support_radii,
+            );
+        }
+
+        let subdomain_idx = parameters
+            .subdomain_grid
+            .try_unflatten_cell_index(flat_subdomain_idx)
+            .expect("Subdomain cell does not exist");
+        let subdomain_aabb = parameters.subdomain_grid.cell_aabb(&subdomain_idx);
+
+        // Local grid of the subdomain with one additional layer of cells around it
+        let local_grid = UniformCartesianCubeGrid3d::new(
+            &(subdomain_aabb.min() - Vector3::repeat(parameters.cube_size)),
+            &[margin_cubes; 3],
+            parameters.cube_size,
+        )
+        .unwrap();
+
+        // Converts a local point or cell index to the corresponding index of the global grid (if it is not negative)
+        let subdomain_offset = subdomain_idx.index().map(|i| {
+            <i64 as NumCast>::from(i * subdomain_cubes).expect("index has to fit in i64") - 1
+        });
+        let to_global_ijk = |local_ijk: [I; 3]| -> Option<[GlobalIndex; 3]> {
+            let mut global_ijk = [0; 3];
+            for dim in 0..3 {
+                let global_index = <i64 as NumCast>::from(local_ijk[dim])? + subdomain_offset[dim];
+                global_ijk[dim] = <GlobalIndex as NumCast>::from(global_index)?;
+            }
+            Some(global_ijk)
+        };
+
+        levelset_grid.fill(R::zero());
+        levelset_grid.resize(margin_total_points.to_usize().unwrap(), R::zero());
+
+        {
+            profile!("density grid loop");
+
+            let extents = local_grid.points_per_dim();
+
+            for (particle_idx, (p_i, rho_i)) in subdomain_particles
+                .iter()
+                .copied()
+                .zip(subdomain_particle_densities.iter().copied())
+                .enumerate()
+            {
+                let kernel_transform =
+                    anisotropic_kernels.map(|_| &subdomain_kernel_transforms[particle_idx]);
+                let v_i = particle_properties
+                    .masses()
+                    .map_or(parameters.particle_rest_mass, |_| {
+                        subdomain_particle_masses[particle_idx]
+                    })
+                    / rho_i;
+                // Kernels of particles with smaller compact support radii are evaluated at scaled distances
+                let kernel_scale =
+                    particle_properties
+                        .compact_support_radii()
+                        .map_or(R::one(), |_| {
+                            parameters.compact_support_radius
+                                / subdomain_compact_support_radii[particle_idx]
+                        });
+                let kernel_normalization = kernel_scale * kernel_scale * kernel_scale;
+                let particle_cell = local_grid.enclosing_cell(&p_i);
+
+                let lower = [
+                    (particle_cell[0] - cube_radius).max(I::zero()),
+                    (particle_cell[1] - cube_radius).max(I::zero()),
+                    (particle_cell[2] - cube_radius).max(I::zero()),
+                ];
+
+                let upper = [
+                    (particle_cell[0] + cube_radius + I::two()).min(extents[0]),
+                    (particle_cell[1] + cube_radius + I::two()).min(extents[1]),
+                    (particle_cell[2] + cube_radius + I::two()).min(extents[2]),
+                ];
+
+                // Skip ghost particles with kernels that do not reach the grid of this subdomain
+                if (0..3).any(|dim| lower[dim] >= upper[dim]) {
+                    continue;
+                }
+
+                for i in I::range(lower[0], upper[0]).iter() {
+                    for j in I::range(lower[1], upper[1]).iter() {
+                        for k in I::range(lower[2], upper[2]).iter() {
+                            // Use global coordinate calculation for consistency with neighboring domains
+                            let global_point = match to_global_ijk([i, j, k]).and_then(|ijk| {
+                                parameters.global_marching_cubes_grid.get_point(ijk)
+                            }) {
+                                Some(global_point) => global_point,
+                                None => continue,
+                            };
+                            let point_coordinates = parameters
+                                .global_marching_cubes_grid
+                                .point_coordinates(&global_point);
+
+                            let dx = p_i - point_coordinates;
+                            // Anisotropic kernels are evaluated in the transformed frame of the particle
+                            let dx = kernel_transform.map_or(dx, |transform| transform * dx)
+                                * kernel_scale;
+                            let dx_norm_sq = dx.norm_squared();
+
+                            if dx_norm_sq < squared_support_with_margin {
+                                let w_ij =
+                                    kernel.evaluate(dx_norm_sq.sqrt()) * kernel_normalization;
+
+                                let flat_point_idx = local_grid.flatten_point_indices(i, j, k);
+                                levelset_grid[flat_point_idx.to_usize().unwrap()] += v_i * w_ij;
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        apply_boundary_to_levelset(
+            parameters,
+            &local_grid,
+            subdomain_offset,
+            levelset_grid,
+            None,
+        );
+
+        let levelset_grid: &[R] = levelset_grid.as_slice();
+        let point_value = |i: I, j: I, k: I| -> R {
+            levelset_grid[local_grid
+                .flatten_point_indices(i, j, k)
+                .to_usize()
+                .unwrap()]
+        };
+
+        let mut vertices = Vec::new();
+        let mut vertex_cells = Vec::new();
+        let mut vertex_owned_flags = Vec::new();
+        let mut quads = Vec::new();
+
+        let mut cell_to_vertex = new_map();
+
+        // Returns the local index of the vertex of the given local cell, creates the vertex if necessary
+        let mut get_or_insert_vertex = |cell_ijk: [I; 3], global_cell: GlobalIndex| -> usize {
+            *cell_to_vertex.entry(global_cell).or_insert_with(|| {
+                let [i, j, k] = cell_ijk;
+                let corner_values = CELL_CORNER_OFFSETS.map(|offset| {
+                    point_value(
+                        i + to_index!(offset[0]),
+                        j + to_index!(offset[1]),
+                        k + to_index!(offset[2]),
+                    )
+                });
+                let min_corner = to_global_ijk(cell_ijk)
+                    .and_then(|ijk| parameters.global_marching_cubes_grid.get_point(ijk))
+                    .map(|p| parameters.global_marching_cubes_grid.point_coordinates(&p))
+                    .expect("cell has to be part of the global grid");
+
+                vertices.push(cell_vertex_position(
+                    &min_corner,
+                    parameters.cube_size,
+                    &corner_values,
+                    threshold,
+                ));
+                vertex_cells.push(global_cell);
+                // Cells with local index 0 or larger than the number of subdomain cubes are part of the margin
+                vertex_owned_flags.push(
+                    cell_ijk
+                        .iter()
+                        .all(|&c| c > I::zero() && c <= subdomain_cubes),
+                );
+                vertices.len() - 1
+            })
+        };
+
+        // Returns the flat index of the given local cell in the global grid if it exists
+        let global_cell_index = |cell_ijk: [I; 3]| -> Option<GlobalIndex> {
+            let global_cell = parameters
+                .global_marching_cubes_grid
+                .get_cell(to_global_ijk(cell_ijk)?)?;
+            Some(
+                parameters
+                    .global_marching_cubes_grid
+                    .flatten_cell_index(&global_cell),
+            )
+        };
+
+        {
+            profile!("surface nets triangulation loop");
+
+            // Loop over all points owned by the subdomain (all points except of the ones in the margin and on its max boundary)
+            for i in I::range(I::one(), subdomain_cubes + I::one()).iter() {
+                for j in I::range(I::one(), subdomain_cubes + I::one()).iter() {
+                    for k in I::range(I::one(), subdomain_cubes + I::one()).iter() {
+                        let origin_ijk = [i, j, k];
+                        let origin_inside = point_value(i, j, k) > threshold;
+
+                        // Create vertices for all owned cells intersected by the iso-surface, such
+                        // that vertices referenced by neighboring subdomains use the owned position
+                        {
+                            let corners_inside = CELL_CORNER_OFFSETS.map(|offset| {
+                                point_value(
+                                    i + to_index!(offset[0]),
+                                    j + to_index!(offset[1]),
+                                    k + to_index!(offset[2]),
+                                ) > threshold
+                            });
+                            if corners_inside.iter().any(|&c| c != origin_inside) {
+                                if let Some(global_cell) = global_cell_index(origin_ijk) {
+                                    get_or_insert_vertex(origin_ijk, global_cell);
+                                }
+                            }
+                        }
+
+                        // Generate the quads of all edges starting at this point that cross the iso-surface
+                        for &axis in Axis::all_possible() {
+                            let mut target_ijk = origin_ijk;
+                            target_ijk[axis.dim()] += I::one();
+                            let [ti, tj, tk] = target_ijk;
+                            if (point_value(ti, tj, tk) > threshold) == origin_inside {
+                                continue;
+                            }
+
+                            let adjacent_cells = EDGE_ADJACENT_CELL_OFFSETS.map(|offsets| {
+                                let mut cell_ijk = origin_ijk;
+                                for (orth_axis, offset) in
+                                    axis.orthogonal_axes().iter().zip(offsets)
+                                {
+                                    cell_ijk[orth_axis.dim()] -= to_index!(offset);
+                                }
+                                global_cell_index(cell_ijk).map(|c| (cell_ijk, c))
+                            });
+
+                            // Skip edges on the boundary of the global domain
+                            if adjacent_cells.iter().any(Option::is_none) {
+                                continue;
+                            }
+
+                            let quad = adjacent_cells.map(|cell| {
+                                let (cell_ijk, global_cell) = cell.unwrap();
+                                get_or_insert_vertex(cell_ijk, global_cell)
+                            });
+                            quads.push((quad, origin_inside));
+                        }
+                    }
+                }
+            }
+        }
+
+        let triangles = quads
+            .into_iter()
+            .flat_map(|(quad, origin_inside)| quad_to_triangles(quad, origin_inside, &vertices))
+            .collect();
+
+        SurfaceNetsPatch {
+            vertices,
+            vertex_cells,
+            vertex_owned_flags,
+            triangles,
+        }
+    };
+
+    let mut surface_patches = Vec::with_capacity(subdomains.flat_subdomain_indices.len());
+    subdomains
+        .flat_subdomain_indices
+        .par_iter()
+        .copied()
+        .zip(subdomains.per_subdomain_particles.par_iter())
+        .map(|(flat_subdomain_idx, subdomain_particle_indices)| {
+            profile!("subdomain reconstruction (surface nets)", parent = parent);
+            reconstruct_subdomain(flat_subdomain_idx, subdomain_particle_indices)
+        })
+        .collect_into_vec(&mut surface_patches);
+
+    surface_patches
+}
+
+/// Merges the surface nets patches of all subdomains into a global mesh by identifying vertices of the same global cell
+pub(crate) fn stitching_surface_nets<R: Real>(
+    surface_patches: Vec<SurfaceNetsPatch<R>>,
+) -> TriMesh3d<R> {
+    profile!("stitching_surface_nets");
+    info!("Starting merging of surface nets patches to global mesh.");
+
+    // Prefer the vertex positions computed by the subdomain owning the cell, as only the owning
+    // subdomain is guaranteed to have correct level-set values for all corners of the cell
+    let mut owned_positions = new_map();
+    for patch in surface_patches.iter() {
+        for ((cell, vertex), owned) in patch
+            .vertex_cells
+            .iter()
+            .copied()
+            .zip(patch.vertices.iter().copied())
+            .zip(patch.vertex_owned_flags.iter().copied())
+        {
+            if owned {
+                owned_positions.insert(cell, vertex);
+            }
+        }
+    }
+
+    let mut mesh = TriMesh3d::default();
+    let mut cell_to_vertex = new_map();
+    for patch in surface_patches.iter() {
+        for triangle in patch.triangles.iter() {
+            mesh.triangles.push(triangle.map(|local_vertex| {
+                let cell = patch.vertex_cells[local_vertex];
+                *cell_to_vertex.entry(cell).or_insert_with(|| {
+                    let position = owned_positions
+                        .get(&cell)
+                        .copied()
+                        .unwrap_or(patch.vertices[local_vertex]);
+                    mesh.vertices.push(position);
+                    mesh.vertices.len() - 1
+                })
+            }));
+        }
+    }
+
+    mesh
+}
+
 pub(crate) mod debug {
     use super::*;
 
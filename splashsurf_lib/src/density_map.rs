@@ -19,15 +19,18 @@
 //! indices, even if the density map is only generated for a smaller subdomain.
 
 use crate::aabb::Aabb3d;
-use crate::kernel::DiscreteSquaredDistanceCubicKernel;
+use crate::anisotropy::AnisotropicKernels;
+use crate::kernel::{DiscreteSquaredDistanceKernel, KernelType};
 use crate::mesh::{HexMesh3d, MeshAttribute, MeshWithData};
-use crate::neighborhood_search::NeighborhoodList;
-use crate::uniform_grid::{OwningSubdomainGrid, Subdomain, UniformGrid};
+use crate::neighborhood_search::{self, NeighborhoodList};
+use crate::uniform_grid::{GridConstructionError, OwningSubdomainGrid, Subdomain, UniformGrid};
 use crate::utils::{ChunkSize, ParallelPolicy};
-use crate::{new_map, profile, HashState, Index, MapType, ParallelMapType, Real};
+use crate::{
+    new_map, profile, HashState, Index, MapType, ParallelMapType, ParticleProperties, Real,
+};
 use dashmap::ReadOnlyView as ReadDashMap;
 use log::{info, trace, warn};
-use nalgebra::Vector3;
+use nalgebra::{Matrix3, Vector3};
 use rayon::prelude::*;
 use std::cell::RefCell;
 use thiserror::Error as ThisError;
@@ -54,32 +57,31 @@ pub enum DensityMapError<R: Real> {
 }
 
 /// Computes the individual densities of particles using a standard SPH sum
+///
+/// If per particle masses or compact support radii are provided by `particle_properties`, every
+/// neighbor contributes with its own mass and kernel radius. In this case, the neighborhood lists
+/// have to be computed using the largest compact support radius of all particles.
 #[inline(never)]
 pub fn compute_particle_densities<I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
     particle_neighbor_lists: &[Vec<usize>],
     compact_support_radius: R,
+    kernel_type: KernelType,
     particle_rest_mass: R,
+    particle_properties: ParticleProperties<R>,
     enable_multi_threading: bool,
 ) -> Vec<R> {
     let mut densities = Vec::new();
-    if enable_multi_threading {
-        parallel_compute_particle_densities::<I, R>(
-            particle_positions,
-            particle_neighbor_lists,
-            compact_support_radius,
-            particle_rest_mass,
-            &mut densities,
-        )
-    } else {
-        sequential_compute_particle_densities::<I, R>(
-            particle_positions,
-            particle_neighbor_lists,
-            compact_support_radius,
-            particle_rest_mass,
-            &mut densities,
-        )
-    }
+    compute_particle_densities_inplace::<I, R>(
+        particle_positions,
+        particle_neighbor_lists,
+        compact_support_radius,
+        kernel_type,
+        particle_rest_mass,
+        particle_properties,
+        enable_multi_threading,
+        &mut densities,
+    );
     densities
 }
 
@@ -89,7 +91,9 @@ pub fn compute_particle_densities_inplace<I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
     particle_neighbor_lists: &[Vec<usize>],
     compact_support_radius: R,
+    kernel_type: KernelType,
     particle_rest_mass: R,
+    particle_properties: ParticleProperties<R>,
     enable_multi_threading: bool,
     densities: &mut Vec<R>,
 ) {
@@ -98,7 +102,9 @@ pub fn compute_particle_densities_inplace<I: Index, R: Real>(
             particle_positions,
             particle_neighbor_lists,
             compact_support_radius,
+            kernel_type,
             particle_rest_mass,
+            particle_properties,
             densities,
         )
     } else {
@@ -106,7 +112,9 @@ pub fn compute_particle_densities_inplace<I: Index, R: Real>(
             particle_positions,
             particle_neighbor_lists,
             compact_support_radius,
+            kernel_type,
             particle_rest_mass,
+            particle_properties,
             densities,
         )
     }
@@ -118,13 +126,80 @@ fn init_density_storage<R: Real>(densities: &mut Vec<R>, new_len: usize) {
     // Existing values don't have to be set to zero, as they are overwritten later anyway
 }
 
+/// Discretized kernel and per particle data required to evaluate the SPH density sums
+struct ParticleDensityKernel<'a, R: Real> {
+    /// Kernel discretized with the largest compact support radius of all particles
+    kernel: DiscreteSquaredDistanceKernel<R>,
+    /// Compact support radius of the discretized kernel
+    compact_support_radius: R,
+    particle_rest_mass: R,
+    particle_properties: ParticleProperties<'a, R>,
+}
+
+impl<'a, R: Real> ParticleDensityKernel<'a, R> {
+    fn new(
+        compact_support_radius: R,
+        kernel_type: KernelType,
+        particle_rest_mass: R,
+        particle_properties: ParticleProperties<'a, R>,
+    ) -> Self {
+        let compact_support_radius =
+            particle_properties.max_compact_support_radius(compact_support_radius);
+        Self {
+            kernel: DiscreteSquaredDistanceKernel::from_kernel_type::<f64>(
+                kernel_type,
+                1000,
+                compact_support_radius,
+            ),
+            compact_support_radius,
+            particle_rest_mass,
+            particle_properties,
+        }
+    }
+
+    /// Evaluates the density of particle `i` by summing over the particle itself and its neighbors
+    #[inline(always)]
+    fn density(&self, i: usize, neighbors: &[usize], particle_positions: &[Vector3<R>]) -> R {
+        let x_i = &particle_positions[i];
+        if self.particle_properties.is_uniform() {
+            let mut density = self.kernel.evaluate(R::zero());
+            for x_j in neighbors.iter().map(|&j| &particle_positions[j]) {
+                density += self.kernel.evaluate((x_j - x_i).norm_squared());
+            }
+            density * self.particle_rest_mass
+        } else {
+            let mut density = self.contribution(i, R::zero());
+            for &j in neighbors {
+                density += self.contribution(j, (particle_positions[j] - x_i).norm_squared());
+            }
+            density
+        }
+    }
+
+    /// Density contribution of particle `j` in the given squared distance, the kernel is rescaled to the radius of the particle
+    #[inline(always)]
+    fn contribution(&self, j: usize, r_squared: R) -> R {
+        let scale = self.compact_support_radius
+            / self
+                .particle_properties
+                .compact_support_radius(j, self.compact_support_radius);
+        let scale_squared = scale * scale;
+        self.particle_properties.mass(j, self.particle_rest_mass)
+            * scale_squared
+            * scale
+            * self.kernel.evaluate(r_squared * scale_squared)
+    }
+}
+
 /// Computes the individual densities of particles using a standard SPH sum, sequential implementation
 #[inline(never)]
 pub fn sequential_compute_particle_densities<I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
     particle_neighbor_lists: &[Vec<usize>],
     compact_support_radius: R,
+    kernel_type: KernelType,
     particle_rest_mass: R,
+    particle_properties: ParticleProperties<R>,
     particle_densities: &mut Vec<R>,
 ) {
     profile!("sequential_compute_particle_densities");
@@ -132,20 +207,15 @@ pub fn sequential_compute_particle_densities<I: Index, R: Real>(
     init_density_storage(particle_densities, particle_positions.len());
 
     // Pre-compute the kernel which can be queried using squared distances
-    let kernel = DiscreteSquaredDistanceCubicKernel::new::<f64>(1000, compact_support_radius);
+    let kernel = ParticleDensityKernel::new(
+        compact_support_radius,
+        kernel_type,
+        particle_rest_mass,
+        particle_properties,
+    );
 
-    for (i, (particle_i_position, particle_i_neighbors)) in particle_positions
-        .iter()
-        .zip(particle_neighbor_lists.iter())
-        .enumerate()
-    {
-        let mut particle_i_density = kernel.evaluate(R::zero());
-        for particle_j_position in particle_i_neighbors.iter().map(|&j| &particle_positions[j]) {
-            let r_squared = (particle_j_position - particle_i_position).norm_squared();
-            particle_i_density += kernel.evaluate(r_squared);
-        }
-        particle_i_density *= particle_rest_mass;
-        particle_densities[i] = particle_i_density;
+    for (i, particle_i_neighbors) in particle_neighbor_lists.iter().enumerate() {
+        particle_densities[i] = kernel.density(i, particle_i_neighbors, particle_positions);
     }
 }
 
@@ -154,7 +224,9 @@ pub fn sequential_compute_particle_densities_filtered<I: Index, R: Real, Nl: Nei
     particle_positions: &[Vector3<R>],
     particle_neighbor_lists: &Nl,
     compact_support_radius: R,
+    kernel_type: KernelType,
     particle_rest_mass: R,
+    particle_properties: ParticleProperties<R>,
     particle_densities: &mut Vec<R>,
     filter: &[bool],
 ) {
@@ -163,24 +235,16 @@ pub fn sequential_compute_particle_densities_filtered<I: Index, R: Real, Nl: Nei
     init_density_storage(particle_densities, particle_positions.len());
 
     // Pre-compute the kernel which can be queried using squared distances
-    let kernel = DiscreteSquaredDistanceCubicKernel::new::<f64>(1000, compact_support_radius);
+    let kernel = ParticleDensityKernel::new(
+        compact_support_radius,
+        kernel_type,
+        particle_rest_mass,
+        particle_properties,
+    );
 
-    for (i, particle_i_position) in particle_positions
-        .iter()
-        .enumerate()
-        .filter(|(i, _)| filter[*i])
-    {
-        let mut particle_i_density = kernel.evaluate(R::zero());
-        for particle_j_position in particle_neighbor_lists
-            .neighbors(i)
-            .iter()
-            .map(|&j| &particle_positions[j])
-        {
-            let r_squared = (particle_j_position - particle_i_position).norm_squared();
-            particle_i_density += kernel.evaluate(r_squared);
-        }
-        particle_i_density *= particle_rest_mass;
-        particle_densities[i] = particle_i_density;
+    for i in (0..particle_positions.len()).filter(|&i| filter[i]) {
+        particle_densities[i] =
+            kernel.density(i, particle_neighbor_lists.neighbors(i), particle_positions);
     }
 }
 
@@ -190,7 +254,9 @@ pub fn parallel_compute_particle_densities<I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
     particle_neighbor_lists: &[Vec<usize>],
     compact_support_radius: R,
+    kernel_type: KernelType,
     particle_rest_mass: R,
+    particle_properties: ParticleProperties<R>,
     particle_densities: &mut Vec<R>,
 ) {
     profile!("parallel_compute_particle_densities");
@@ -198,26 +264,21 @@ pub fn parallel_compute_particle_densities<I: Index, R: Real>(
     init_density_storage(particle_densities, particle_positions.len());
 
     // Pre-compute the kernel which can be queried using squared distances
-    let kernel = DiscreteSquaredDistanceCubicKernel::new::<f64>(1000, compact_support_radius);
+    let kernel = ParticleDensityKernel::new(
+        compact_support_radius,
+        kernel_type,
+        particle_rest_mass,
+        particle_properties,
+    );
 
-    particle_positions
+    particle_neighbor_lists
         .par_iter()
         .with_min_len(8)
-        .zip_eq(particle_neighbor_lists.par_iter())
         .zip_eq(particle_densities.par_iter_mut())
-        .for_each(
-            |((particle_i_position, particle_i_neighbors), particle_i_density)| {
-                let mut density = kernel.evaluate(R::zero());
-                for particle_j_position in
-                    particle_i_neighbors.iter().map(|&j| &particle_positions[j])
-                {
-                    let r_squared = (particle_j_position - particle_i_position).norm_squared();
-                    density += kernel.evaluate(r_squared);
-                }
-                density *= particle_rest_mass;
-                *particle_i_density = density;
-            },
-        );
+        .enumerate()
+        .for_each(|(i, (particle_i_neighbors, particle_i_density))| {
+            *particle_i_density = kernel.density(i, particle_i_neighbors, particle_positions);
+        });
 }
 
 /// A sparse density map
@@ -289,15 +350,25 @@ impl<I: Index, R: Real> DensityMap<I, R> {
 }
 
 /// Computes a sparse density map for the fluid based on the specified background grid
+///
+/// If `anisotropic_kernels` are provided, the kernel of every particle is transformed by the corresponding
+/// kernel transform. In this case, the `particle_positions` should be the smoothed kernel centers
+/// (see [`AnisotropicKernels::smoothed_positions`]).
+///
+/// If per particle masses or compact support radii are provided by `particle_properties`, the volume
+/// and the kernel of every particle are computed from its own mass and compact support radius.
 #[inline(never)]
 pub fn generate_sparse_density_map<I: Index, R: Real>(
     grid: &UniformGrid<I, R>,
     subdomain: Option<&OwningSubdomainGrid<I, R>>,
     particle_positions: &[Vector3<R>],
     particle_densities: &[R],
+    anisotropic_kernels: Option<&AnisotropicKernels<R>>,
     active_particles: Option<&[usize]>,
     particle_rest_mass: R,
+    particle_properties: ParticleProperties<R>,
     compact_support_radius: R,
+    kernel_type: KernelType,
     cube_size: R,
     allow_threading: bool,
     density_map: &mut DensityMap<I, R>,
@@ -319,9 +390,12 @@ pub fn generate_sparse_density_map<I: Index, R: Real>(
                 subdomain,
                 particle_positions,
                 particle_densities,
+                anisotropic_kernels,
                 active_particles,
                 particle_rest_mass,
+                particle_properties,
                 compact_support_radius,
+                kernel_type,
                 cube_size,
                 density_map,
             )?;
@@ -332,9 +406,12 @@ pub fn generate_sparse_density_map<I: Index, R: Real>(
                 grid,
                 particle_positions,
                 particle_densities,
+                anisotropic_kernels,
                 active_particles,
                 particle_rest_mass,
+                particle_properties,
                 compact_support_radius,
+                kernel_type,
                 cube_size,
             )?
         } else {
@@ -342,9 +419,12 @@ pub fn generate_sparse_density_map<I: Index, R: Real>(
                 grid,
                 particle_positions,
                 particle_densities,
+                anisotropic_kernels,
                 active_particles,
                 particle_rest_mass,
+                particle_properties,
                 compact_support_radius,
+                kernel_type,
                 cube_size,
             )?
         }
@@ -358,15 +438,112 @@ pub fn generate_sparse_density_map<I: Index, R: Real>(
     Ok(())
 }
 
+/// Evaluates the density field of a sparse density map at arbitrary points, e.g. between the points of the background grid
+///
+/// The same discretized kernel and the same particles as in [`generate_sparse_density_map`] (without anisotropic
+/// kernels) are used, therefore the values at points of the background grid match the values of the density map
+/// (up to floating point rounding). Points without any particle in their support get a value of zero.
+pub fn evaluate_density_at_points<I: Index, R: Real>(
+    grid: &UniformGrid<I, R>,
+    particle_positions: &[Vector3<R>],
+    particle_densities: &[R],
+    particle_rest_mass: R,
+    particle_properties: ParticleProperties<R>,
+    compact_support_radius: R,
+    kernel_type: KernelType,
+    cube_size: R,
+    points: &[Vector3<R>],
+    allow_threading: bool,
+) -> Result<Vec<R>, DensityMapError<R>> {
+    profile!("evaluate_density_at_points");
+    assert_eq!(particle_positions.len(), particle_densities.len());
+
+    let density_map_generator = SparseDensityMapGenerator::<I, R>::try_new(
+        grid,
+        compact_support_radius,
+        kernel_type,
+        cube_size,
+        particle_rest_mass,
+        particle_properties,
+        None,
+    )?;
+
+    Ok(
+        neighborhood_search::map_query_point_neighbors::<I, R, _, _>(
+            particle_positions,
+            points,
+            density_map_generator.kernel_evaluation_radius_sq.sqrt(),
+            allow_threading,
+            |point, neighbors| {
+                density_map_generator.density_at_point(
+                    point,
+                    neighbors,
+                    particle_positions,
+                    particle_densities,
+                )
+            },
+        ),
+    )
+}
+
+/// Returns a grid that extends the given grid by a margin of cells such that a density map generated on it contains the contributions of all particles with a kernel overlapping the given grid
+///
+/// The density map generation ignores particles closer to the boundary of the grid than the kernel evaluation
+/// radius, therefore the margin has to be twice as large. Returns the extended grid and the number of margin cells,
+/// the density map can then be restricted to the given grid using [`restrict_density_map`].
+pub(crate) fn grid_with_exterior_margin<I: Index, R: Real>(
+    grid: &UniformGrid<I, R>,
+    max_kernel_radius: R,
+) -> Result<(UniformGrid<I, R>, I), GridConstructionError<I, R>> {
+    let kernel_extents =
+        compute_kernel_evaluation_radius::<I, R>(max_kernel_radius, grid.cell_size());
+    let margin_cells = (kernel_extents.half_supported_cells + I::one()).times(2);
+
+    let margin = margin_cells.to_real_unchecked::<R>() * grid.cell_size();
+    let extended_grid = UniformGrid::new(
+        &(grid.aabb().min() - Vector3::repeat(margin)),
+        &grid.cells_per_dim().map(|n| n + margin_cells.times(2)),
+        grid.cell_size(),
+    )?;
+
+    Ok((extended_grid, margin_cells))
+}
+
+/// Restricts a density map generated on a grid returned by [`grid_with_exterior_margin`] to the points of the original grid
+pub(crate) fn restrict_density_map<I: Index, R: Real>(
+    extended_grid: &UniformGrid<I, R>,
+    margin_cells: I,
+    grid: &UniformGrid<I, R>,
+    density_map: &DensityMap<I, R>,
+) -> DensityMap<I, R> {
+    profile!("restrict_density_map");
+
+    let mut restricted_map = new_map();
+    density_map.for_each(|flat_point_index, density| {
+        let point = extended_grid
+            .try_unflatten_point_index(flat_point_index)
+            .expect("point of the density map has to be part of the grid");
+        let [i, j, k] = point.index().map(|i| i - margin_cells);
+        if let Some(point) = grid.get_point([i, j, k]) {
+            restricted_map.insert(grid.flatten_point_index(&point), density);
+        }
+    });
+
+    restricted_map.into()
+}
+
 /// Computes a sparse density map for the fluid based on the specified background grid, sequential implementation
 #[inline(never)]
 pub fn sequential_generate_sparse_density_map<I: Index, R: Real>(
     grid: &UniformGrid<I, R>,
     particle_positions: &[Vector3<R>],
     particle_densities: &[R],
+    anisotropic_kernels: Option<&AnisotropicKernels<R>>,
     active_particles: Option<&[usize]>,
     particle_rest_mass: R,
+    particle_properties: ParticleProperties<R>,
     compact_support_radius: R,
+    kernel_type: KernelType,
     cube_size: R,
 ) -> Result<DensityMap<I, R>, DensityMapError<R>> {
     profile!("sequential_generate_sparse_density_map");
@@ -376,30 +553,25 @@ pub fn sequential_generate_sparse_density_map<I: Index, R: Real>(
     let density_map_generator = SparseDensityMapGenerator::try_new(
         grid,
         compact_support_radius,
+        kernel_type,
         cube_size,
         particle_rest_mass,
+        particle_properties,
+        anisotropic_kernels,
     )?;
 
-    let process_particle = |particle_data: (&Vector3<R>, R)| {
-        let (particle, particle_density) = particle_data;
+    let process_particle = |i: usize| {
         density_map_generator.compute_particle_density_contribution(
             grid,
             &mut sparse_densities,
-            particle,
-            particle_density,
+            &particle_positions[i],
+            density_map_generator.particle_kernel(i, particle_densities[i]),
         );
     };
 
     match active_particles {
-        None => particle_positions
-            .iter()
-            .zip(particle_densities.iter().copied())
-            .for_each(process_particle),
-        Some(indices) => indices
-            .iter()
-            .map(|&i| &particle_positions[i])
-            .zip(indices.iter().map(|&i| particle_densities[i]))
-            .for_each(process_particle),
+        None => (0..particle_positions.len()).for_each(process_particle),
+        Some(indices) => indices.iter().copied().for_each(process_particle),
     }
 
     Ok(sparse_densities.into())
@@ -411,9 +583,12 @@ pub fn sequential_generate_sparse_density_map_subdomain<I: Index, R: Real>(
     subdomain: &OwningSubdomainGrid<I, R>,
     particle_positions: &[Vector3<R>],
     particle_densities: &[R],
+    anisotropic_kernels: Option<&AnisotropicKernels<R>>,
     active_particles: Option<&[usize]>,
     particle_rest_mass: R,
+    particle_properties: ParticleProperties<R>,
     compact_support_radius: R,
+    kernel_type: KernelType,
     cube_size: R,
     density_map: &mut DensityMap<I, R>,
 ) -> Result<(), DensityMapError<R>> {
@@ -425,30 +600,25 @@ pub fn sequential_generate_sparse_density_map_subdomain<I: Index, R: Real>(
     let density_map_generator = SparseDensityMapGenerator::try_new(
         &subdomain.global_grid(),
         compact_support_radius,
+        kernel_type,
         cube_size,
         particle_rest_mass,
+        particle_properties,
+        anisotropic_kernels,
     )?;
 
-    let process_particle = |particle_data: (&Vector3<R>, R)| {
-        let (particle, particle_density) = particle_data;
+    let process_particle = |i: usize| {
         density_map_generator.compute_particle_density_contribution_subdomain(
             subdomain,
             &mut sparse_densities,
-            particle,
-            particle_density,
+            &particle_positions[i],
+            density_map_generator.particle_kernel(i, particle_densities[i]),
         );
     };
 
     match active_particles {
-        None => particle_positions
-            .iter()
-            .zip(particle_densities.iter().copied())
-            .for_each(process_particle),
-        Some(indices) => indices
-            .iter()
-            .map(|&i| &particle_positions[i])
-            .zip(indices.iter().map(|&i| particle_densities[i]))
-            .for_each(process_particle),
+        None => (0..particle_positions.len()).for_each(process_particle),
+        Some(indices) => indices.iter().copied().for_each(process_particle),
     }
 
     Ok(())
@@ -460,9 +630,12 @@ pub fn parallel_generate_sparse_density_map<I: Index, R: Real>(
     grid: &UniformGrid<I, R>,
     particle_positions: &[Vector3<R>],
     particle_densities: &[R],
+    anisotropic_kernels: Option<&AnisotropicKernels<R>>,
     active_particles: Option<&[usize]>,
     particle_rest_mass: R,
+    particle_properties: ParticleProperties<R>,
     compact_support_radius: R,
+    kernel_type: KernelType,
     cube_size: R,
 ) -> Result<DensityMap<I, R>, DensityMapError<R>> {
     profile!("parallel_generate_sparse_density_map");
@@ -475,8 +648,11 @@ pub fn parallel_generate_sparse_density_map<I: Index, R: Real>(
         let density_map_generator = SparseDensityMapGenerator::try_new(
             grid,
             compact_support_radius,
+            kernel_type,
             cube_size,
             particle_rest_mass,
+            particle_properties,
+            anisotropic_kernels,
         )?;
 
         profile!("generate thread local maps");
@@ -492,26 +668,32 @@ pub fn parallel_generate_sparse_density_map<I: Index, R: Real>(
                 particle_positions
                     .par_chunks(chunk_size)
                     .zip(particle_densities.par_chunks(chunk_size))
-                    .for_each(|(position_chunk, density_chunk)| {
+                    .enumerate()
+                    .for_each(|(chunk_idx, (position_chunk, density_chunk))| {
                         // Obtain mutable reference to thread local density map
                         let map = sparse_densities
                             .get_or(|| RefCell::new(MapType::with_hasher(HashState::default())));
                         let mut mut_map = map.borrow_mut();
 
-                        let process_particle_map = |particle_data: (&Vector3<R>, R)| {
-                            let (particle, particle_density) = particle_data;
-                            density_map_generator.compute_particle_density_contribution(
-                                grid,
-                                &mut mut_map,
-                                particle,
-                                particle_density,
-                            );
-                        };
+                        let process_particle_map =
+                            |(local_idx, particle_data): (usize, (&Vector3<R>, R))| {
+                                let (particle, particle_density) = particle_data;
+                                density_map_generator.compute_particle_density_contribution(
+                                    grid,
+                                    &mut mut_map,
+                                    particle,
+                                    density_map_generator.particle_kernel(
+                                        chunk_idx * chunk_size + local_idx,
+                                        particle_density,
+                                    ),
+                                );
+                            };
 
                         assert_eq!(position_chunk.len(), density_chunk.len());
                         position_chunk
                             .iter()
                             .zip(density_chunk.iter().copied())
+                            .enumerate()
                             .for_each(process_particle_map);
                     })
             }
@@ -527,20 +709,16 @@ pub fn parallel_generate_sparse_density_map<I: Index, R: Real>(
                         .get_or(|| RefCell::new(MapType::with_hasher(HashState::default())));
                     let mut mut_map = map.borrow_mut();
 
-                    let process_particle_map = |particle_data: (&Vector3<R>, R)| {
-                        let (particle, particle_density) = particle_data;
+                    let process_particle_map = |i: usize| {
                         density_map_generator.compute_particle_density_contribution(
                             grid,
                             &mut mut_map,
-                            particle,
-                            particle_density,
+                            &particle_positions[i],
+                            density_map_generator.particle_kernel(i, particle_densities[i]),
                         );
                     };
 
-                    index_chunk
-                        .iter()
-                        .map(|&i| (&particle_positions[i], particle_densities[i]))
-                        .for_each(process_particle_map);
+                    index_chunk.iter().copied().for_each(process_particle_map);
                 });
             }
         }
@@ -574,15 +752,29 @@ pub fn parallel_generate_sparse_density_map<I: Index, R: Real>(
 }
 
 /// Internal helper type used to evaluate the density contribution for a particle
-struct SparseDensityMapGenerator<I: Index, R: Real> {
+struct SparseDensityMapGenerator<'a, I: Index, R: Real> {
     particle_rest_mass: R,
+    particle_properties: ParticleProperties<'a, R>,
+    anisotropic_kernels: Option<&'a AnisotropicKernels<R>>,
     half_supported_cells: I,
     supported_points: I,
     kernel_evaluation_radius_sq: R,
-    kernel: DiscreteSquaredDistanceCubicKernel<R>,
+    /// Compact support radius of the discretized kernel (the largest compact support radius of all particles)
+    compact_support_radius: R,
+    kernel: DiscreteSquaredDistanceKernel<R>,
     allowed_domain: Aabb3d<R>,
 }
 
+/// Per particle data required to evaluate the density contributions of a particle
+struct ParticleKernel<'a, R: Real> {
+    /// Volume of the particle
+    volume: R,
+    /// Ratio between the compact support radius of the discretized kernel and the compact support radius of the particle
+    scale: R,
+    /// Anisotropic kernel transform of the particle
+    transform: Option<&'a Matrix3<R>>,
+}
+
 pub(crate) struct GridKernelExtents<I: Index, R: Real> {
     // The number of cells in each direction from a particle's cell that can be affected by its compact support
     pub half_supported_cells: I,
@@ -618,22 +810,45 @@ pub(crate) fn compute_kernel_evaluation_radius<I: Index, R: Real>(
 }
 
 // TODO: Maybe remove allowed domain check? And require this is done before, using the active_particles array?
-impl<I: Index, R: Real> SparseDensityMapGenerator<I, R> {
+impl<'a, I: Index, R: Real> SparseDensityMapGenerator<'a, I, R> {
     fn try_new(
         grid: &UniformGrid<I, R>,
         compact_support_radius: R,
+        kernel_type: KernelType,
         cube_size: R,
         particle_rest_mass: R,
+        particle_properties: ParticleProperties<'a, R>,
+        anisotropic_kernels: Option<&'a AnisotropicKernels<R>>,
     ) -> Result<Self, DensityMapError<R>> {
+        // Particles with smaller compact support radii evaluate the kernel at scaled distances
+        let compact_support_radius =
+            particle_properties.max_compact_support_radius(compact_support_radius);
+
+        // Anisotropic kernels can be stretched beyond the compact support radius of the isotropic kernel
+        let max_stretch = anisotropic_kernels
+            .map(|kernels| kernels.max_stretch.max(R::one()))
+            .unwrap_or_else(R::one);
+
         let GridKernelExtents {
             half_supported_cells,
             supported_points,
             kernel_evaluation_radius,
-        } = compute_kernel_evaluation_radius(compact_support_radius, cube_size);
+        } = compute_kernel_evaluation_radius(compact_support_radius * max_stretch, cube_size);
+
+        // The kernel itself is always evaluated using the (transformed) isotropic distance
+        let kernel_evaluation_radius_sq = {
+            let isotropic_radius =
+                compute_kernel_evaluation_radius::<I, R>(compact_support_radius, cube_size)
+                    .kernel_evaluation_radius;
+            isotropic_radius * isotropic_radius
+        };
 
         // Pre-compute the kernel which can be queried using squared distances
-        let kernel_evaluation_radius_sq = kernel_evaluation_radius * kernel_evaluation_radius;
-        let kernel = DiscreteSquaredDistanceCubicKernel::new::<f64>(1000, compact_support_radius);
+        let kernel = DiscreteSquaredDistanceKernel::from_kernel_type::<f64>(
+            kernel_type,
+            1000,
+            compact_support_radius,
+        );
 
         // Shrink the allowed domain for particles by the kernel evaluation radius. This ensures that all cells/points
         // that are affected by a particle are actually part of the domain/grid, so it does not have to be checked in the loops below.
@@ -662,20 +877,75 @@ impl<I: Index, R: Real> SparseDensityMapGenerator<I, R> {
                 half_supported_cells,
                 supported_points,
                 kernel_evaluation_radius_sq,
+                compact_support_radius,
                 kernel,
                 allowed_domain,
                 particle_rest_mass,
+                particle_properties,
+                anisotropic_kernels,
             })
         }
     }
 
+    /// Returns the data required to evaluate the density contributions of the given particle
+    #[inline(always)]
+    fn particle_kernel(&self, particle_index: usize, particle_density: R) -> ParticleKernel<'a, R> {
+        let particle_mass = self
+            .particle_properties
+            .mass(particle_index, self.particle_rest_mass);
+        let particle_compact_support_radius = self
+            .particle_properties
+            .compact_support_radius(particle_index, self.compact_support_radius);
+        ParticleKernel {
+            volume: particle_mass / particle_density,
+            scale: self.compact_support_radius / particle_compact_support_radius,
+            transform: self
+                .anisotropic_kernels
+                .map(|kernels| &kernels.transforms[particle_index]),
+        }
+    }
+
+    /// Computes the density at the given point from the contributions of the given particles, i.e. the value of the density map if the point was a point of the background grid
+    fn density_at_point(
+        &self,
+        point: &Vector3<R>,
+        particles: &[usize],
+        particle_positions: &[Vector3<R>],
+        particle_densities: &[R],
+    ) -> R {
+        let mut density = R::zero();
+        for &j in particles {
+            let particle = &particle_positions[j];
+            // Particles outside of the allowed domain are skipped by the density map as well
+            if !self.allowed_domain.contains_point(particle) {
+                continue;
+            }
+
+            let ParticleKernel {
+                volume: particle_volume,
+                scale: kernel_scale,
+                ..
+            } = self.particle_kernel(j, particle_densities[j]);
+            let kernel_scale_sq = kernel_scale * kernel_scale;
+
+            let r_squared = (point - particle).norm_squared() * kernel_scale_sq;
+            if r_squared < self.kernel_evaluation_radius_sq {
+                density += particle_volume
+                    * kernel_scale_sq
+                    * kernel_scale
+                    * self.kernel.evaluate(r_squared);
+            }
+        }
+        density
+    }
+
     /// Computes all density contributions of a particle to the background grid into the given map
     fn compute_particle_density_contribution(
         &self,
         grid: &UniformGrid<I, R>,
         sparse_densities: &mut MapType<I, R>,
         particle: &Vector3<R>,
-        particle_density: R,
+        particle_kernel: ParticleKernel<R>,
     ) {
         // Skip particles outside of allowed domain
         if !self.allowed_domain.contains_point(particle) {
@@ -704,7 +974,7 @@ impl<I: Index, R: Real> SparseDensityMapGenerator<I, R> {
             &min_supported_point_ijk,
             &max_supported_point_ijk,
             particle,
-            particle_density,
+            particle_kernel,
         );
     }
 
@@ -714,7 +984,7 @@ impl<I: Index, R: Real> SparseDensityMapGenerator<I, R> {
         subdomain: &OwningSubdomainGrid<I, R>,
         sparse_densities: &mut MapType<I, R>,
         particle: &Vector3<R>,
-        particle_density: R,
+        particle_kernel: ParticleKernel<R>,
     ) {
         let grid = subdomain.global_grid();
         let subdomain_grid = subdomain.subdomain_grid();
@@ -782,7 +1052,7 @@ impl<I: Index, R: Real> SparseDensityMapGenerator<I, R> {
             &min_supported_point_ijk,
             &max_supported_point_ijk,
             particle,
-            particle_density,
+            particle_kernel,
         );
     }
 
@@ -795,10 +1065,16 @@ impl<I: Index, R: Real> SparseDensityMapGenerator<I, R> {
         min_supported_point_ijk: &[I; 3],
         max_supported_point_ijk: &[I; 3],
         particle: &Vector3<R>,
-        particle_density: R,
+        particle_kernel: ParticleKernel<R>,
     ) {
-        // Compute the volume of this particle
-        let particle_volume = self.particle_rest_mass / particle_density;
+        let ParticleKernel {
+            volume: particle_volume,
+            scale: kernel_scale,
+            transform: kernel_transform,
+        } = particle_kernel;
+        let kernel_scale_sq = kernel_scale * kernel_scale;
+        // Normalization of the kernel rescaled to the compact support radius of the particle
+        let particle_weight = particle_volume * kernel_scale_sq * kernel_scale;
 
         // TODO: Check performance with just using multiplication
         let min_supported_point = grid.point_coordinates_array(&min_supported_point_ijk);
@@ -831,10 +1107,13 @@ impl<I: Index, R: Real> SparseDensityMapGenerator<I, R> {
                     dz += grid.cell_size();
                     let dzdz = dz * dz;
 
-                    let r_squared = dxdx + dydy + dzdz;
+                    let r_squared = match kernel_transform {
+                        Some(transform) => (transform * Vector3::new(dx, dy, dz)).norm_squared(),
+                        None => dxdx + dydy + dzdz,
+                    } * kernel_scale_sq;
                     if r_squared < self.kernel_evaluation_radius_sq {
                         let density_contribution =
-                            particle_volume * self.kernel.evaluate(r_squared);
+                            particle_weight * self.kernel.evaluate(r_squared);
 
                         let flat_point_index = grid.flatten_point_indices(i, j, k);
                         *sparse_densities
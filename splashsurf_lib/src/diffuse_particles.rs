@@ -0,0 +1,505 @@
+//! Generation of diffuse particles (spray, foam and air bubbles) from the fluid particles
+//!
+//! Implements the generation of diffuse particles of "Unified Spray, Foam and Bubbles for Particle-Based Fluids"
+//! (Ihmsen et al. 2012). For every fluid particle `i`, three potentials in `[0, 1]` are evaluated using its neighbors
+//! `j` within the compact support radius `h` and the radially symmetric weighting `w_ij = 1 - |x_ij| / h`:
+//!  - The trapped air potential `I_ta` is large where particles collide, it is based on the scaled velocity difference
+//!    `sum_j |v_ij| (1 - v̂_ij·x̂_ij) w_ij`.
+//!  - The wave crest potential `I_wc` is large at convex parts of the free surface moving in normal direction, it is based
+//!    on the curvature `sum_j (1 - n̂_i·n̂_j) w_ij` (only neighbors `j` behind the particle with `x̂_ji·n̂_i < 0` contribute)
+//!    of surface particles (see [`particle_classification`](crate::particle_classification)) with `v̂_i·n̂_i >= 0.6`.
+//!  - The kinetic energy potential `I_k` is based on the kinetic energy per unit mass `0.5 |v_i|^2` (in contrast to the
+//!    original method, the energy is not scaled by the particle mass such that it does not depend on the particle resolution).
+//!
+//! Every potential is mapped to `[0, 1]` by clamping it to a range `[τ_min, τ_max]`. Each fluid particle generates
+//! `n_d = I_k (k_ta I_ta + k_wc I_wc) Δt` diffuse particles (rounded stochastically) which are uniformly sampled in a
+//! cylinder with the particle radius around the path of the particle during the time step `Δt`.
+//! The diffuse particles are classified by the number of fluid particles within the compact support radius into spray
+//! (few neighbors), air bubbles (many neighbors) and foam (all other particles). Spray particles keep the velocity of their
+//! sampling, foam and bubble particles get the SPH interpolated velocity of the fluid.
+//!
+//! Note that the diffuse particles are generated independently for every frame, i.e. unlike in the original method,
+//! they are not advected over time and have no lifetime. The random numbers of the sampling are derived from
+//! [`DiffuseParticleParameters::seed`] and the particle indices, i.e. the generation is deterministic.
+
+use crate::neighborhood_search::NeighborhoodList;
+use crate::particle_classification::compute_color_field_gradients;
+use crate::sph_interpolation::SphInterpolator;
+use crate::{density_map, neighborhood_search, profile, Aabb3d, Index, Parameters};
+use crate::{ParticleProperties, Real};
+use log::info;
+use nalgebra::Vector3;
+use rayon::prelude::*;
+
+/// Type of a diffuse particle
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum DiffuseParticleType {
+    /// Diffuse particle with (almost) no fluid neighbors
+    Spray,
+    /// Diffuse particle at the free surface of the fluid
+    Foam,
+    /// Diffuse particle inside of the fluid
+    Bubble,
+}
+
+impl DiffuseParticleType {
+    /// Returns the integer label of the type that is used when writing diffuse particles to files (spray: 0, foam: 1, bubble: 2)
+    pub fn label(self) -> u64 {
+        match self {
+            DiffuseParticleType::Spray => 0,
+            DiffuseParticleType::Foam => 1,
+            DiffuseParticleType::Bubble => 2,
+        }
+    }
+}
+
+/// Parameters for the generation of diffuse particles
+#[derive(Clone, Debug)]
+pub struct DiffuseParticleParameters<R: Real> {
+    /// Range `[τ_min, τ_max]` of the scaled velocity difference that is mapped to a trapped air potential in `[0, 1]`
+    pub trapped_air_range: [R; 2],
+    /// Range `[τ_min, τ_max]` of the curvature that is mapped to a wave crest potential in `[0, 1]`
+    pub wave_crest_range: [R; 2],
+    /// Range `[τ_min, τ_max]` of the kinetic energy per unit mass that is mapped to a kinetic energy potential in `[0, 1]`
+    pub kinetic_energy_range: [R; 2],
+    /// Maximum number of diffuse particles generated per fluid particle and time unit due to trapped air (`k_ta`)
+    pub trapped_air_rate: R,
+    /// Maximum number of diffuse particles generated per fluid particle and time unit at wave crests (`k_wc`)
+    pub wave_crest_rate: R,
+    /// Time step `Δt` used to determine the number of generated diffuse particles and their sampling volume
+    pub time_step: R,
+    /// Threshold for the color field gradient norm (normalized by the compact support radius) above which a fluid particle is considered a surface particle for the wave crest potential
+    pub surface_threshold: R,
+    /// Diffuse particles with at most this number of fluid neighbors are spray particles
+    pub max_spray_neighbors: usize,
+    /// Diffuse particles with at least this number of fluid neighbors are air bubbles
+    pub min_bubble_neighbors: usize,
+    /// Seed for the random numbers used to sample the diffuse particles
+    pub seed: u64,
+}
+
+impl<R: Real> Default for DiffuseParticleParameters<R> {
+    /// Returns the parameters proposed by Ihmsen et al. (trapped air: `[5, 20]`, wave crests: `[2, 8]`, kinetic energy: `[5, 50]`, `k_ta = 4000`, `k_wc = 50000`, spray: less than 6, bubbles: more than 20 neighbors) with a time step of 1/60
+    fn default() -> Self {
+        Self {
+            trapped_air_range: [R::from_f64(5.0).unwrap(), R::from_f64(20.0).unwrap()],
+            wave_crest_range: [R::from_f64(2.0).unwrap(), R::from_f64(8.0).unwrap()],
+            kinetic_energy_range: [R::from_f64(5.0).unwrap(), R::from_f64(50.0).unwrap()],
+            trapped_air_rate: R::from_f64(4000.0).unwrap(),
+            wave_crest_rate: R::from_f64(50000.0).unwrap(),
+            time_step: R::from_f64(1.0 / 60.0).unwrap(),
+            surface_threshold: R::from_f64(0.5).unwrap(),
+            max_spray_neighbors: 5,
+            min_bubble_neighbors: 21,
+            seed: 0,
+        }
+    }
+}
+
+impl<R: Real> DiffuseParticleParameters<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns None if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<DiffuseParticleParameters<T>> {
+        let convert_range = |[min, max]: [R; 2]| -> Option<[T; 2]> {
+            Some([min.try_convert()?, max.try_convert()?])
+        };
+        Some(DiffuseParticleParameters {
+            trapped_air_range: convert_range(self.trapped_air_range)?,
+            wave_crest_range: convert_range(self.wave_crest_range)?,
+            kinetic_energy_range: convert_range(self.kinetic_energy_range)?,
+            trapped_air_rate: self.trapped_air_rate.try_convert()?,
+            wave_crest_rate: self.wave_crest_rate.try_convert()?,
+            time_step: self.time_step.try_convert()?,
+            surface_threshold: self.surface_threshold.try_convert()?,
+            max_spray_neighbors: self.max_spray_neighbors,
+            min_bubble_neighbors: self.min_bubble_neighbors,
+            seed: self.seed,
+        })
+    }
+}
+
+/// Diffuse particles generated from the fluid particles
+#[derive(Clone, Debug, Default)]
+pub struct DiffuseParticles<R: Real> {
+    /// Positions of the diffuse particles
+    pub positions: Vec<Vector3<R>>,
+    /// Velocities of the diffuse particles
+    pub velocities: Vec<Vector3<R>>,
+    /// Types of the diffuse particles
+    pub types: Vec<DiffuseParticleType>,
+}
+
+/// Generates diffuse particles from the given fluid particles, see the [module level documentation](self)
+///
+/// The neighborhood lists have to contain all neighbors within the compact support radius (without the particle itself).
+/// The particle radius, rest density, compact support radius, kernel and multi-threading settings are taken from the
+/// reconstruction parameters.
+pub fn generate_diffuse_particles<R: Real, Nl: NeighborhoodList + Sync + ?Sized>(
+    particle_positions: &[Vector3<R>],
+    particle_velocities: &[Vector3<R>],
+    particle_densities: &[R],
+    particle_neighbor_lists: &Nl,
+    reconstruction_parameters: &Parameters<R>,
+    parameters: &DiffuseParticleParameters<R>,
+) -> DiffuseParticles<R> {
+    profile!("generate_diffuse_particles");
+    assert_eq!(particle_positions.len(), particle_velocities.len());
+    assert_eq!(particle_positions.len(), particle_densities.len());
+    assert_eq!(particle_positions.len(), particle_neighbor_lists.len());
+
+    let particle_radius = reconstruction_parameters.particle_radius;
+    let compact_support_radius = reconstruction_parameters.compact_support_radius;
+    let kernel_type = reconstruction_parameters.kernel;
+    let enable_multi_threading = reconstruction_parameters.enable_multi_threading;
+
+    let particle_rest_volume =
+        R::from_f64((4.0 / 3.0) * std::f64::consts::PI).unwrap() * particle_radius.powi(3);
+    let particle_rest_mass = particle_rest_volume * reconstruction_parameters.rest_density;
+
+    // Outward pointing normals of the surface particles (zero for all other particles)
+    let color_field_gradients = compute_color_field_gradients(
+        particle_positions,
+        particle_densities,
+        particle_neighbor_lists,
+        compact_support_radius,
+        kernel_type,
+        particle_rest_mass,
+        ParticleProperties::uniform(),
+        enable_multi_threading,
+    );
+    let particle_normals = color_field_gradients
+        .iter()
+        .map(|gradient| {
+            let gradient_norm = gradient.norm();
+            if gradient_norm * compact_support_radius > parameters.surface_threshold {
+                -gradient.unscale(gradient_norm)
+            } else {
+                Vector3::zeros()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let one = R::one();
+    let half = R::from_f64(0.5).unwrap();
+    let min_normal_velocity_alignment = R::from_f64(0.6).unwrap();
+    let two_pi = R::from_f64(2.0 * std::f64::consts::PI).unwrap();
+
+    let generate = |i: usize| -> Vec<(Vector3<R>, Vector3<R>)> {
+        let x_i = particle_positions[i];
+        let v_i = particle_velocities[i];
+
+        let kinetic_energy = half * v_i.norm_squared();
+        let kinetic_energy_potential =
+            clamped_potential(kinetic_energy, parameters.kinetic_energy_range);
+        if kinetic_energy_potential <= R::zero() {
+            return Vec::new();
+        }
+
+        let n_i = particle_normals[i];
+        let v_i_normalized = v_i.normalize();
+        // Only surface particles moving in normal direction can be at wave crests
+        let is_wave_crest_candidate =
+            n_i != Vector3::zeros() && v_i_normalized.dot(&n_i) >= min_normal_velocity_alignment;
+
+        let mut scaled_velocity_difference = R::zero();
+        let mut curvature = R::zero();
+        for &j in particle_neighbor_lists.neighbors(i) {
+            let x_ij = x_i - particle_positions[j];
+            let r = x_ij.norm();
+            if r <= R::zero() || r > compact_support_radius {
+                continue;
+            }
+            let weight = one - r / compact_support_radius;
+            let x_ij_normalized = x_ij.unscale(r);
+
+            let v_ij = v_i - particle_velocities[j];
+            let v_ij_norm = v_ij.norm();
+            if v_ij_norm > R::zero() {
+                scaled_velocity_difference +=
+                    v_ij_norm * (one - v_ij.unscale(v_ij_norm).dot(&x_ij_normalized)) * weight;
+            }
+
+            // Only neighbors behind the particle (with respect to its normal) contribute to the curvature of a crest
+            if is_wave_crest_candidate && x_ij_normalized.dot(&n_i) > R::zero() {
+                curvature += (one - n_i.dot(&particle_normals[j])) * weight;
+            }
+        }
+
+        let trapped_air_potential =
+            clamped_potential(scaled_velocity_difference, parameters.trapped_air_range);
+        let wave_crest_potential = clamped_potential(curvature, parameters.wave_crest_range);
+
+        let num_diffuse_particles = kinetic_energy_potential
+            * (parameters.trapped_air_rate * trapped_air_potential
+                + parameters.wave_crest_rate * wave_crest_potential)
+            * parameters.time_step;
+        // Stochastic rounding such that the expected number of particles is preserved
+        let num_diffuse_particles = (num_diffuse_particles
+            + random_number::<R>(parameters.seed, i, 0))
+        .floor()
+        .to_usize()
+        .unwrap_or(0);
+        if num_diffuse_particles == 0 {
+            return Vec::new();
+        }
+
+        // Orthonormal basis of the plane orthogonal to the velocity
+        let e1 = if v_i_normalized.x.abs() < R::from_f64(0.9).unwrap() {
+            v_i_normalized.cross(&Vector3::x())
+        } else {
+            v_i_normalized.cross(&Vector3::y())
+        }
+        .normalize();
+        let e2 = v_i_normalized.cross(&e1);
+        let path_length = v_i.norm() * parameters.time_step;
+
+        (0..num_diffuse_particles)
+            .map(|k| {
+                let sample = 3 * k as u64;
+                let radius =
+                    particle_radius * random_number::<R>(parameters.seed, i, sample + 1).sqrt();
+                let angle = two_pi * random_number::<R>(parameters.seed, i, sample + 2);
+                let height = path_length * random_number::<R>(parameters.seed, i, sample + 3);
+
+                let offset = e1 * (radius * angle.cos()) + e2 * (radius * angle.sin());
+                (x_i + offset + v_i_normalized * height, v_i + offset)
+            })
+            .collect()
+    };
+
+    let samples: Vec<_> = if enable_multi_threading {
+        (0..particle_positions.len())
+            .into_par_iter()
+            .flat_map_iter(generate)
+            .collect()
+    } else {
+        (0..particle_positions.len()).flat_map(generate).collect()
+    };
+    let (positions, mut velocities): (Vec<_>, Vec<_>) = samples.into_iter().unzip();
+
+    // Classify the diffuse particles by their number of fluid neighbors
+    let interpolator = SphInterpolator::new(
+        particle_positions,
+        particle_densities,
+        particle_rest_mass,
+        ParticleProperties::uniform(),
+        compact_support_radius,
+        kernel_type,
+    );
+    let types = interpolator
+        .count_neighbors(&positions)
+        .into_iter()
+        .map(|num_neighbors| {
+            if num_neighbors <= parameters.max_spray_neighbors {
+                DiffuseParticleType::Spray
+            } else if num_neighbors >= parameters.min_bubble_neighbors {
+                DiffuseParticleType::Bubble
+            } else {
+                DiffuseParticleType::Foam
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Foam and bubbles move with the fluid
+    let (fluid_indices, fluid_positions): (Vec<_>, Vec<_>) = positions
+        .iter()
+        .zip(types.iter())
+        .enumerate()
+        .filter(|(_, (_, &diffuse_type))| diffuse_type != DiffuseParticleType::Spray)
+        .map(|(k, (x, _))| (k, *x))
+        .unzip();
+    let fluid_velocities =
+        interpolator.interpolate_vector_quantity(particle_velocities, &fluid_positions, true);
+    for (k, v) in fluid_indices.into_iter().zip(fluid_velocities) {
+        velocities[k] = v;
+    }
+
+    let count = |diffuse_type| types.iter().filter(|&&t| t == diffuse_type).count();
+    info!(
+        "Generated {} diffuse particles: {} spray, {} foam, {} bubbles",
+        positions.len(),
+        count(DiffuseParticleType::Spray),
+        count(DiffuseParticleType::Foam),
+        count(DiffuseParticleType::Bubble)
+    );
+
+    DiffuseParticles {
+        positions,
+        velocities,
+        types,
+    }
+}
+
+/// Generates diffuse particles from the given fluid particles using the reconstruction parameters
+///
+/// Performs a neighborhood search and computes the particle densities (if they are not provided, e.g. from a
+/// previous surface reconstruction) before generating the diffuse particles with [`generate_diffuse_particles`].
+pub fn generate_diffuse_particles_with_parameters<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    particle_velocities: &[Vector3<R>],
+    particle_densities: Option<&[R]>,
+    reconstruction_parameters: &Parameters<R>,
+    parameters: &DiffuseParticleParameters<R>,
+) -> DiffuseParticles<R> {
+    profile!("generate_diffuse_particles_with_parameters");
+
+    if particle_positions.is_empty() {
+        return DiffuseParticles::default();
+    }
+
+    let compact_support_radius = reconstruction_parameters.compact_support_radius;
+    let enable_multi_threading = reconstruction_parameters.enable_multi_threading;
+
+    let mut domain = if enable_multi_threading {
+        Aabb3d::par_from_points(particle_positions)
+    } else {
+        Aabb3d::from_points(particle_positions)
+    };
+    domain.grow_uniformly(compact_support_radius);
+
+    let particle_neighbor_lists = neighborhood_search::search::<I, R>(
+        &domain,
+        particle_positions,
+        compact_support_radius,
+        enable_multi_threading,
+    );
+
+    let computed_densities;
+    let particle_densities = match particle_densities {
+        Some(particle_densities) => particle_densities,
+        None => {
+            let particle_rest_volume = R::from_f64((4.0 / 3.0) * std::f64::consts::PI).unwrap()
+                * reconstruction_parameters.particle_radius.powi(3);
+            let particle_rest_mass = particle_rest_volume * reconstruction_parameters.rest_density;
+
+            computed_densities = density_map::compute_particle_densities::<I, R>(
+                particle_positions,
+                &particle_neighbor_lists,
+                compact_support_radius,
+                reconstruction_parameters.kernel,
+                particle_rest_mass,
+                ParticleProperties::uniform(),
+                enable_multi_threading,
+            );
+            computed_densities.as_slice()
+        }
+    };
+
+    generate_diffuse_particles(
+        particle_positions,
+        particle_velocities,
+        particle_densities,
+        &particle_neighbor_lists,
+        reconstruction_parameters,
+        parameters,
+    )
+}
+
+/// Maps the value linearly from the range `[min, max]` to `[0, 1]`, values outside of the range are clamped
+fn clamped_potential<R: Real>(value: R, [min, max]: [R; 2]) -> R {
+    (value.min(max) - value.min(min)) / (max - min)
+}
+
+/// Returns a pseudo-random number in `[0, 1)` that only depends on the seed, the particle index and the sample index (SplitMix64 hash)
+fn random_number<R: Real>(seed: u64, particle_index: usize, sample: u64) -> R {
+    let mut z = seed
+        ^ (particle_index as u64).wrapping_mul(0x9E3779B97F4A7C15)
+        ^ sample.wrapping_mul(0xD1B54A32D192ED03);
+    z = z.wrapping_add(0x9E3779B97F4A7C15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^= z >> 31;
+    R::from_f64((z >> 11) as f64 / (1u64 << 53) as f64).unwrap()
+}
+
+#[test]
+fn test_generate_diffuse_particles_of_colliding_blocks() {
+    use crate::{IsoSurfaceExtraction, KernelType};
+
+    let particle_radius = 0.025;
+    let parameters = Parameters {
+        particle_radius,
+        rest_density: 1000.0,
+        compact_support_radius: 4.0 * particle_radius,
+        cube_size: 0.5 * particle_radius,
+        iso_surface_threshold: 0.6,
+        domain_aabb: None,
+        enable_multi_threading: false,
+        subdomain_num_cubes_per_dim: None,
+        spatial_decomposition: None,
+        iso_surface_extraction: IsoSurfaceExtraction::MarchingCubes,
+        adaptive_resolution: None,
+        kernel: KernelType::CubicSpline,
+        anisotropy: None,
+        boundary: None,
+        boundary_particles: None,
+        cap_domain_boundary: false,
+    };
+
+    // Two blocks of 8x8x8 particles next to each other
+    let spacing = 2.0 * particle_radius;
+    let mut particle_positions = Vec::new();
+    for i in 0..16 {
+        for j in 0..8 {
+            for k in 0..8 {
+                particle_positions.push(Vector3::new(i as f64, j as f64, k as f64) * spacing);
+            }
+        }
+    }
+
+    // No diffuse particles are generated from fluid at rest
+    let velocities_at_rest = vec![Vector3::zeros(); particle_positions.len()];
+    let diffuse_particles = generate_diffuse_particles_with_parameters::<i64, f64>(
+        &particle_positions,
+        &velocities_at_rest,
+        None,
+        &parameters,
+        &DiffuseParticleParameters::default(),
+    );
+    assert!(diffuse_particles.positions.is_empty());
+
+    // Colliding blocks trap air at the contact plane
+    let colliding_velocities = particle_positions
+        .iter()
+        .map(|x| Vector3::new(if x.x < 7.5 * spacing { 10.0 } else { -10.0 }, 0.0, 0.0))
+        .collect::<Vec<_>>();
+    let diffuse_particles = generate_diffuse_particles_with_parameters::<i64, f64>(
+        &particle_positions,
+        &colliding_velocities,
+        None,
+        &parameters,
+        &DiffuseParticleParameters::default(),
+    );
+    assert!(!diffuse_particles.positions.is_empty());
+    assert_eq!(
+        diffuse_particles.positions.len(),
+        diffuse_particles.velocities.len()
+    );
+    assert_eq!(
+        diffuse_particles.positions.len(),
+        diffuse_particles.types.len()
+    );
+    // All diffuse particles are generated close to the contact plane
+    let contact_plane = 7.5 * spacing;
+    assert!(diffuse_particles
+        .positions
+        .iter()
+        .all(|x| (x.x - contact_plane).abs() < parameters.compact_support_radius + spacing));
+    // The contact plane lies inside of the fluid, i.e. air bubbles are generated
+    assert!(diffuse_particles
+        .types
+        .iter()
+        .any(|&t| t == DiffuseParticleType::Bubble));
+
+    // The generation is deterministic
+    let diffuse_particles_again = generate_diffuse_particles_with_parameters::<i64, f64>(
+        &particle_positions,
+        &colliding_velocities,
+        None,
+        &parameters,
+        &DiffuseParticleParameters::default(),
+    );
+    assert_eq!(
+        diffuse_particles.positions,
+        diffuse_particles_again.positions
+    );
+}
@@ -1,5 +1,6 @@
 //! Convenience functions for importing particle data and meshes from various file formats
 
+use crate::mesh::MeshAttribute;
 use crate::Real;
 use anyhow::anyhow;
 use nalgebra::Vector3;
@@ -12,6 +13,9 @@ pub mod ply_format;
 pub mod vtk_format;
 pub mod xyz_format;
 
+/// Particle positions and point attributes loaded from a file
+pub type ParticlesWithAttributes<R> = (Vec<Vector3<R>>, Vec<MeshAttribute<R>>);
+
 /// Tries to load particles from the given file path, automatically detecting supported file extensions
 pub fn particles_from_file<R: Real, P: AsRef<Path>>(
     input_file: P,
@@ -40,3 +44,125 @@ pub fn particles_from_file<R: Real, P: AsRef<Path>>(
         ))
     }
 }
+
+/// Tries to load particles and the point attributes with the given names from the given file path, automatically detecting supported file extensions
+///
+/// Attributes are supported for VTK/VTU, BGEO, PLY and JSON files, see the `particles_with_optional_attributes_from_*`
+/// functions of the format modules for the supported attribute types. XYZ files can only be loaded without attributes.
+/// Returns an error if one of the attributes does not exist in the file.
+pub fn particles_with_attributes_from_file<R: Real, P: AsRef<Path>>(
+    input_file: P,
+    attribute_names: &[String],
+) -> Result<ParticlesWithAttributes<R>, anyhow::Error> {
+    particles_with_optional_attributes_from_file(input_file, attribute_names, &[])
+}
+
+/// Tries to load particles, the point attributes with the given names and all optional point attributes that exist from the given file path, automatically detecting supported file extensions
+///
+/// In contrast to the attributes in `attribute_names`, optional attributes that are missing in the file are skipped.
+/// The returned attributes are in the order of `attribute_names` followed by the optional attributes that were found.
+/// XYZ files do not support attributes, i.e. they can only be loaded without any (non-optional) attributes.
+pub fn particles_with_optional_attributes_from_file<R: Real, P: AsRef<Path>>(
+    input_file: P,
+    attribute_names: &[String],
+    optional_attribute_names: &[String],
+) -> Result<ParticlesWithAttributes<R>, anyhow::Error> {
+    let input_file = input_file.as_ref();
+    if let Some(extension) = input_file.extension() {
+        let extension = extension
+            .to_str()
+            .ok_or(anyhow!("Invalid extension of input file"))?;
+
+        match extension.to_lowercase().as_str() {
+            "vtk" | "vtu" => vtk_format::particles_with_optional_attributes_from_vtk(
+                input_file,
+                attribute_names,
+                optional_attribute_names,
+            ),
+            "ply" => ply_format::particles_with_optional_attributes_from_ply(
+                input_file,
+                attribute_names,
+                optional_attribute_names,
+            ),
+            "bgeo" => bgeo_format::particles_with_optional_attributes_from_bgeo(
+                input_file,
+                attribute_names,
+                optional_attribute_names,
+            ),
+            "json" => json_format::particles_with_optional_attributes_from_json(
+                input_file,
+                attribute_names,
+                optional_attribute_names,
+            ),
+            "xyz" if attribute_names.is_empty() => {
+                xyz_format::particles_from_xyz(input_file).map(|p| (p, Vec::new()))
+            }
+            _ => Err(anyhow!(
+                "Unsupported file format extension \"{}\" for reading particles and attributes",
+                extension
+            )),
+        }
+    } else {
+        Err(anyhow!(
+            "Unable to detect file format of particle input file (file name has to end with supported extension)",
+        ))
+    }
+}
+
+/// Returns the names of all point attributes of the particle file at the given path, automatically detecting supported file extensions
+///
+/// All returned attributes can be loaded using [`particles_with_attributes_from_file`]. XYZ files do not support attributes,
+/// i.e. the list is always empty for them.
+pub fn point_attribute_names_from_file<P: AsRef<Path>>(
+    input_file: P,
+) -> Result<Vec<String>, anyhow::Error> {
+    let input_file = input_file.as_ref();
+    if let Some(extension) = input_file.extension() {
+        let extension = extension
+            .to_str()
+            .ok_or(anyhow!("Invalid extension of input file"))?;
+
+        match extension.to_lowercase().as_str() {
+            "vtk" | "vtu" => vtk_format::point_attribute_names_from_vtk(input_file),
+            "ply" => ply_format::point_attribute_names_from_ply(input_file),
+            "bgeo" => bgeo_format::point_attribute_names_from_bgeo(input_file),
+            "json" => json_format::point_attribute_names_from_json(input_file),
+            "xyz" => Ok(Vec::new()),
+            _ => Err(anyhow!(
+                "Unsupported file format extension \"{}\" for reading particles",
+                extension
+            )),
+        }
+    } else {
+        Err(anyhow!(
+            "Unable to detect file format of particle input file (file name has to end with supported extension)",
+        ))
+    }
+}
+
+/// Returns the names of all attributes that should be loaded from a file, i.e. all `attribute_names` followed by the optional attributes that exist in the file
+///
+/// Returns an error if one of the `attribute_names` does not exist in the file.
+fn attribute_names_to_load<F: Fn(&str) -> bool>(
+    attribute_names: &[String],
+    optional_attribute_names: &[String],
+    exists: F,
+) -> Result<Vec<String>, anyhow::Error> {
+    let missing_attributes = attribute_names
+        .iter()
+        .filter(|name| !exists(name))
+        .cloned()
+        .collect::<Vec<_>>();
+    if !missing_attributes.is_empty() {
+        return Err(anyhow!(
+            "Missing attribute(s) \"{}\" in input file",
+            missing_attributes.join("\", \""),
+        ));
+    }
+
+    Ok(attribute_names
+        .iter()
+        .chain(optional_attribute_names.iter().filter(|name| exists(name)))
+        .cloned()
+        .collect())
+}
@@ -1,5 +1,7 @@
 //! Helper functions for the BGEO file format
 
+use super::{attribute_names_to_load, ParticlesWithAttributes};
+use crate::mesh::{AttributeData, MeshAttribute};
 use crate::utils::IteratorExt;
 use crate::Real;
 use anyhow::{anyhow, Context};
@@ -28,6 +30,44 @@ pub fn particles_from_bgeo<R: Real, P: AsRef<Path>>(
     particles_from_bgeo_impl(bgeo_file)
 }
 
+/// Convenience function for loading particles and the point attributes with the given names from a BGEO file
+pub fn particles_with_attributes_from_bgeo<R: Real, P: AsRef<Path>>(
+    bgeo_file: P,
+    attribute_names: &[String],
+) -> Result<ParticlesWithAttributes<R>, anyhow::Error> {
+    let bgeo_file = load_bgeo_file(bgeo_file).context("Error while loading BGEO file")?;
+    let attributes = bgeo_file.load_point_attributes(attribute_names)?;
+    let positions = particles_from_bgeo_impl(bgeo_file)?;
+    Ok((positions, attributes))
+}
+
+/// Convenience function for loading particles, the point attributes with the given names and all optional point attributes that exist from a BGEO file
+///
+/// Returns an error if one of the attributes in `attribute_names` does not exist, missing optional attributes are skipped.
+/// The returned attributes are in the order of `attribute_names` followed by the optional attributes that were found.
+pub fn particles_with_optional_attributes_from_bgeo<R: Real, P: AsRef<Path>>(
+    bgeo_file: P,
+    attribute_names: &[String],
+    optional_attribute_names: &[String],
+) -> Result<ParticlesWithAttributes<R>, anyhow::Error> {
+    let bgeo_file = load_bgeo_file(bgeo_file).context("Error while loading BGEO file")?;
+    let existing_names = bgeo_file.point_attribute_names();
+    let names = attribute_names_to_load(attribute_names, optional_attribute_names, |name| {
+        existing_names.iter().any(|n| n == name)
+    })?;
+    let attributes = bgeo_file.load_point_attributes(&names)?;
+    let positions = particles_from_bgeo_impl(bgeo_file)?;
+    Ok((positions, attributes))
+}
+
+/// Returns the names of all point attributes defined in the BGEO file at the given path
+pub fn point_attribute_names_from_bgeo<P: AsRef<Path>>(
+    bgeo_file: P,
+) -> Result<Vec<String>, anyhow::Error> {
+    let bgeo_file = load_bgeo_file(bgeo_file).context("Error while loading BGEO file")?;
+    Ok(bgeo_file.point_attribute_names())
+}
+
 fn particles_from_bgeo_impl<R: Real>(
     bgeo_file: BgeoFile,
 ) -> Result<Vec<Vector3<R>>, anyhow::Error> {
@@ -95,6 +135,19 @@ pub fn particles_to_bgeo<R: Real, P: AsRef<Path>>(
     particles: &[Vector3<R>],
     bgeo_file: P,
     enable_compression: bool,
+) -> Result<(), anyhow::Error> {
+    particles_with_attributes_to_bgeo(particles, &[], bgeo_file, enable_compression)
+}
+
+/// Tries to write a set of particles with point attributes to a BGEO file at the given path
+///
+/// Integer attributes are written as int attributes (values have to fit into `i32`), real scalar attributes as float
+/// attributes and all vector and tensor attributes as vector attributes (tensors with their components in row-major order).
+pub fn particles_with_attributes_to_bgeo<R: Real, P: AsRef<Path>>(
+    particles: &[Vector3<R>],
+    attributes: &[MeshAttribute<R>],
+    bgeo_file: P,
+    enable_compression: bool,
 ) -> Result<(), anyhow::Error> {
     let path = bgeo_file.as_ref();
     let file = OpenOptions::new()
@@ -104,11 +157,14 @@ pub fn particles_to_bgeo<R: Real, P: AsRef<Path>>(
         .context("Cannot open file for writing JSON")?;
     let writer = BufWriter::new(file);
 
-    let bgeo = particles_to_bgeo_impl(particles)?;
+    let bgeo = particles_to_bgeo_impl(particles, attributes)?;
     write_bgeo_file(&bgeo, writer, enable_compression)
 }
 
-fn particles_to_bgeo_impl<R: Real>(particles: &[Vector3<R>]) -> Result<BgeoFile, anyhow::Error> {
+fn particles_to_bgeo_impl<R: Real>(
+    particles: &[Vector3<R>],
+    attributes: &[MeshAttribute<R>],
+) -> Result<BgeoFile, anyhow::Error> {
     let particles_f32 = particles.iter().map(|x| x.as_slice()).flatten().copied().map(|x| Some(x.to_f32())?)
         .map(|vec| {
             vec.ok_or_else(|| {
@@ -117,6 +173,31 @@ fn particles_to_bgeo_impl<R: Real>(particles: &[Vector3<R>]) -> Result<BgeoFile,
         })
         .try_collect_with_capacity(particles.len())?;
 
+    let mut attribute_definitions = Vec::with_capacity(attributes.len());
+    let mut attribute_data = Vec::with_capacity(attributes.len());
+    for attribute in attributes {
+        if attribute.data.len() != particles.len() {
+            return Err(anyhow!(
+                "Number of values of attribute \"{}\" does not match the number of particles",
+                attribute.name
+            ));
+        }
+        let storage = attribute_storage_from_data(&attribute.data)
+            .with_context(|| anyhow!("Attribute \"{}\"", attribute.name))?;
+        let (size, attr_type) = match &storage {
+            AttributeStorage::Int(_) => (1, BgeoAttributeType::Int),
+            AttributeStorage::Float(_) => (1, BgeoAttributeType::Float),
+            AttributeStorage::Vector(n, _) => (*n, BgeoAttributeType::Vector),
+        };
+        attribute_definitions.push(AttribDefinition {
+            name: attribute.name.clone(),
+            size,
+            attr_type,
+            default_values: vec![0; size],
+        });
+        attribute_data.push((attribute.name.clone(), storage));
+    }
+
     Ok(BgeoFile {
         header: BgeoHeader {
             magic_bytes: [66, 103, 101, 111],
@@ -132,18 +213,63 @@ fn particles_to_bgeo_impl<R: Real>(particles: &[Vector3<R>]) -> Result<BgeoFile,
             num_prims: 0,
             num_point_groups: 0,
             num_prim_groups: 0,
-            num_point_attrib: 0,
+            num_point_attrib: attributes
+                .len()
+                .to_i32()
+                .ok_or_else(|| anyhow!("number of attributes is too large for bgeo format"))?,
             num_vertex_attrib: 0,
             num_prim_attrib: 0,
             num_attrib: 0,
         },
         positions: AttributeStorage::Vector(3, particles_f32),
         weights: AttributeStorage::Float(vec![1.0; particles.len()]),
-        attribute_definitions: Vec::new(),
-        attribute_data: Vec::new(),
+        attribute_definitions,
+        attribute_data,
     })
 }
 
+/// Converts attribute data to the corresponding BGEO attribute storage
+fn attribute_storage_from_data<R: Real>(
+    data: &AttributeData<R>,
+) -> Result<AttributeStorage, anyhow::Error> {
+    let to_f32 = |v: &R| {
+        v.to_f32()
+            .ok_or_else(|| anyhow!("Cannot convert an attribute value from Real type to f32"))
+    };
+
+    match data {
+        AttributeData::ScalarU64(values) => values
+            .iter()
+            .map(|&v| {
+                i32::try_from(v).map_err(|_| anyhow!("Cannot convert attribute value {} to i32", v))
+            })
+            .try_collect_with_capacity(values.len())
+            .map(AttributeStorage::Int),
+        AttributeData::ScalarReal(values) => values
+            .iter()
+            .map(to_f32)
+            .try_collect_with_capacity(values.len())
+            .map(AttributeStorage::Float),
+        AttributeData::Vector3Real(values) => values
+            .iter()
+            .flatten()
+            .map(to_f32)
+            .try_collect_with_capacity(values.len() * 3)
+            .map(|values| AttributeStorage::Vector(3, values)),
+        AttributeData::VectorReal(n, values) => values
+            .iter()
+            .map(to_f32)
+            .try_collect_with_capacity(values.len())
+            .map(|values| AttributeStorage::Vector(*n, values)),
+        AttributeData::Matrix3Real(values) => values
+            .iter()
+            .flat_map(|m| m.transpose().data.0.into_iter().flatten())
+            .map(|v| to_f32(&v))
+            .try_collect_with_capacity(values.len() * 9)
+            .map(|values| AttributeStorage::Vector(9, values)),
+    }
+}
+
 pub fn write_bgeo_file<W: io::Write>(
     bgeo: &BgeoFile,
     writer: W,
@@ -308,6 +434,79 @@ impl AttributeStorage {
     }
 }
 
+impl BgeoFile {
+    /// Returns the names of all point attributes stored in the file (excluding the positions)
+    pub fn point_attribute_names(&self) -> Vec<String> {
+        self.attribute_data
+            .iter()
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Tries to load point attributes with the given names, returns an error if an attribute does not exist
+    ///
+    /// Int attributes are loaded as [`AttributeData::ScalarU64`] (negative values are not supported),
+    /// float attributes as [`AttributeData::ScalarReal`], vector attributes with three components as [`AttributeData::Vector3Real`]
+    /// and vector attributes with any other number of components as [`AttributeData::VectorReal`].
+    pub fn load_point_attributes<R: Real>(
+        &self,
+        names: &[String],
+    ) -> Result<Vec<MeshAttribute<R>>, anyhow::Error> {
+        names
+            .iter()
+            .map(|name| {
+                let (_, storage) = self
+                    .attribute_data
+                    .iter()
+                    .find(|(attribute_name, _)| attribute_name == name)
+                    .ok_or_else(|| anyhow!("Attribute {} not found in BGEO file", name))?;
+                let attribute_data = try_convert_attribute_storage(storage)
+                    .with_context(|| anyhow!("Attribute \"{}\"", name))?;
+                Ok(MeshAttribute::new(name, attribute_data))
+            })
+            .collect()
+    }
+}
+
+/// Converts a BGEO attribute storage to the corresponding supported AttributeData
+fn try_convert_attribute_storage<R: Real>(
+    storage: &AttributeStorage,
+) -> Result<AttributeData<R>, anyhow::Error> {
+    let to_real = |v: f32| {
+        R::from_f32(v)
+            .ok_or_else(|| anyhow!("Cannot convert an attribute value from f32 to Real type"))
+    };
+
+    match storage {
+        AttributeStorage::Int(values) => values
+            .iter()
+            .map(|&v| {
+                u64::try_from(v)
+                    .map_err(|_| anyhow!("Cannot convert negative attribute value {} to u64", v))
+            })
+            .try_collect_with_capacity(values.len())
+            .map(AttributeData::ScalarU64),
+        AttributeStorage::Float(values) => values
+            .iter()
+            .map(|&v| to_real(v))
+            .try_collect_with_capacity(values.len())
+            .map(AttributeData::ScalarReal),
+        AttributeStorage::Vector(3, values) => values
+            .chunks_exact(3)
+            .map(|v| Ok(Vector3::new(to_real(v[0])?, to_real(v[1])?, to_real(v[2])?)))
+            .try_collect_with_capacity(values.len() / 3)
+            .map(AttributeData::Vector3Real),
+        AttributeStorage::Vector(0, _) => Err(anyhow!(
+            "BGEO vector attribute without components is not supported"
+        )),
+        AttributeStorage::Vector(n, values) => values
+            .iter()
+            .map(|&v| to_real(v))
+            .try_collect_with_capacity(values.len())
+            .map(|values| AttributeData::VectorReal(*n, values)),
+    }
+}
+
 /// Parsers used to parse the BGEO format
 mod parser {
     use nom::branch::alt;
@@ -850,6 +1049,75 @@ fn test_bgeo_read_dam_break() {
     assert!(enclosing.contains_aabb(&aabb));
 }
 
+#[test]
+fn test_bgeo_read_dam_break_attribute_names() {
+    let input_file = Path::new("../data/dam_break_frame_9_6859_particles.bgeo");
+    let names = point_attribute_names_from_bgeo(input_file).unwrap();
+
+    for name in ["velocity", "density", "id"] {
+        assert!(names.iter().any(|n| n == name));
+    }
+}
+
+#[test]
+fn test_bgeo_read_dam_break_attributes() {
+    let input_file = Path::new("../data/dam_break_frame_9_6859_particles.bgeo");
+    let bgeo = load_bgeo_file(input_file).unwrap();
+
+    assert_eq!(
+        bgeo.point_attribute_names(),
+        vec![
+            "id".to_string(),
+            "density".to_string(),
+            "velocity".to_string()
+        ]
+    );
+
+    let attributes = bgeo
+        .load_point_attributes::<f32>(&bgeo.point_attribute_names())
+        .unwrap();
+    assert_eq!(attributes.len(), 3);
+
+    match &attributes[0].data {
+        AttributeData::ScalarU64(ids) => {
+            assert_eq!(ids.len(), 6859);
+            assert!(ids.iter().all(|&id| id < 6859));
+        }
+        _ => panic!("Int attribute was not loaded as u64 scalars"),
+    }
+    assert!(matches!(&attributes[1].data, AttributeData::ScalarReal(v) if v.len() == 6859));
+    assert!(matches!(&attributes[2].data, AttributeData::Vector3Real(v) if v.len() == 6859));
+
+    assert!(bgeo
+        .load_point_attributes::<f32>(&["pressure".to_string()])
+        .is_err());
+}
+
+#[test]
+fn test_bgeo_read_dam_break_optional_attributes() {
+    let input_file = Path::new("../data/dam_break_frame_9_6859_particles.bgeo");
+    let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+    let (particles, attributes) = particles_with_optional_attributes_from_bgeo::<f32, _>(
+        input_file,
+        &names(&["density"]),
+        &names(&["v", "velocity", "pressure"]),
+    )
+    .unwrap();
+    assert_eq!(particles.len(), 6859);
+    assert_eq!(attributes.len(), 2);
+    assert_eq!(attributes[0].name, "density");
+    assert_eq!(attributes[1].name, "velocity");
+
+    // Missing attributes that are not optional are still an error
+    assert!(particles_with_optional_attributes_from_bgeo::<f32, _>(
+        input_file,
+        &names(&["pressure"]),
+        &[]
+    )
+    .is_err());
+}
+
 #[test]
 fn test_bgeo_write_dam_break() {
     let input_file = Path::new("../data/dam_break_frame_9_6859_particles.bgeo");
@@ -857,7 +1125,7 @@ fn test_bgeo_write_dam_break() {
 
     assert_eq!(particles.len(), 6859);
 
-    let bgeo_to_write = particles_to_bgeo_impl(&particles).unwrap();
+    let bgeo_to_write = particles_to_bgeo_impl(&particles, &[]).unwrap();
 
     let mut buffer: Vec<u8> = Vec::new();
     write_bgeo_file(&bgeo_to_write, &mut buffer, false).unwrap();
@@ -875,6 +1143,50 @@ fn test_bgeo_write_dam_break() {
     assert_eq!(particles, particles_read);
 }
 
+#[test]
+fn test_bgeo_write_attributes() {
+    let particles = vec![Vector3::new(1.0, 2.0, 3.0), Vector3::new(4.0, 5.0, 6.0)];
+    let attributes = vec![
+        MeshAttribute::new("class", vec![2u64, 0]),
+        MeshAttribute::new_real_scalar("density", vec![1000.0f32, 1001.0]),
+        MeshAttribute::new_real_vector3("v", vec![Vector3::new(0.5, 0.0, -0.5); 2]),
+        MeshAttribute::new(
+            "color",
+            AttributeData::VectorReal(4, (0..8).map(|i| i as f32).collect()),
+        ),
+    ];
+
+    let bgeo_to_write = particles_to_bgeo_impl(&particles, &attributes).unwrap();
+    let mut buffer: Vec<u8> = Vec::new();
+    write_bgeo_file(&bgeo_to_write, &mut buffer, false).unwrap();
+
+    let (_, bgeo_read) = bgeo_parser()
+        .parse(buffer.as_slice())
+        .finish()
+        .map_err(|err| err.into_anyhow())
+        .unwrap();
+    assert_eq!(
+        bgeo_read.point_attribute_names(),
+        vec!["class", "density", "v", "color"]
+    );
+
+    let attributes_read = bgeo_read
+        .load_point_attributes::<f32>(&bgeo_read.point_attribute_names())
+        .unwrap();
+    assert!(matches!(&attributes_read[0].data, AttributeData::ScalarU64(v) if v == &vec![2, 0]));
+    assert!(matches!(&attributes_read[1].data, AttributeData::ScalarReal(v) if v[1] == 1001.0));
+    assert!(
+        matches!(&attributes_read[2].data, AttributeData::Vector3Real(v) if v[1] == Vector3::new(0.5, 0.0, -0.5))
+    );
+    assert!(
+        matches!(&attributes_read[3].data, AttributeData::VectorReal(4, v) if v[5] == 5.0 && v.len() == 8)
+    );
+    assert_eq!(
+        particles_from_bgeo_impl::<f32>(bgeo_read).unwrap(),
+        particles
+    );
+}
+
 #[test]
 fn test_bgeo_roundtrip_uncompressed() {
     let input_file = Path::new("../data/dam_break_frame_9_6859_particles.bgeo");
@@ -1,9 +1,12 @@
 //! Helper functions for the JSON file format
 
+use super::{attribute_names_to_load, ParticlesWithAttributes};
+use crate::mesh::{AttributeData, MeshAttribute};
 use crate::utils::IteratorExt;
 use crate::Real;
 use anyhow::{anyhow, Context};
 use nalgebra::Vector3;
+use serde_json::Value;
 use std::fs::{File, OpenOptions};
 use std::io::{BufReader, BufWriter};
 use std::path::Path;
@@ -17,18 +20,55 @@ type ParticleVecF64 = Vec<ParticleF64>;
 /// ```json
 /// [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]
 /// ```
+/// Alternatively, the file may contain an object with the coordinates in its `"positions"` field (see [`particles_with_optional_attributes_from_json`]).
 /// Note that the values are casted to `f64` during deserialization.
 pub fn particles_from_json<R: Real, P: AsRef<Path>>(
     json_file: P,
 ) -> Result<Vec<Vector3<R>>, anyhow::Error> {
+    particles_with_optional_attributes_from_json(json_file, &[], &[])
+        .map(|(particles, _)| particles)
+}
+
+/// Convenience function for loading particles, the point attributes with the given names and all optional point attributes that exist from a JSON file
+///
+/// Attributes require a JSON object that stores the particle coordinates in its `"positions"` field and every point attribute
+/// in a field with the name of the attribute and one value per particle, for example:
+/// ```json
+/// {
+///     "positions": [[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]],
+///     "density": [1000.0, 1002.5],
+///     "velocity": [[0.0, -1.0, 0.0], [0.5, 0.0, 0.0]],
+///     "id": [0, 1]
+/// }
+/// ```
+/// Arrays of non-negative integer literals are loaded as [`AttributeData::ScalarU64`], other arrays of numbers as
+/// [`AttributeData::ScalarReal`], arrays of arrays with three numbers as [`AttributeData::Vector3Real`] and arrays of arrays
+/// with any other number of numbers as [`AttributeData::VectorReal`].
+/// Returns an error if one of the attributes in `attribute_names` does not exist, missing optional attributes are skipped.
+/// The returned attributes are in the order of `attribute_names` followed by the optional attributes that were found.
+pub fn particles_with_optional_attributes_from_json<R: Real, P: AsRef<Path>>(
+    json_file: P,
+    attribute_names: &[String],
+    optional_attribute_names: &[String],
+) -> Result<ParticlesWithAttributes<R>, anyhow::Error> {
     let path = json_file.as_ref();
     let file = File::open(path).context("Cannot open file for JSON parsing")?;
     let reader = BufReader::new(file);
 
-    // Read the JSON contents of the file as an instance of `ParticleVecF64`.
+    // Read the JSON contents of the file as either an array of positions or an object with positions and attributes
     let json = serde_json::from_reader(reader)
         .context("Reading of file to JSON structure failed. Not a valid JSON file.")?;
-    let particles = serde_json::from_value::<ParticleVecF64>(json)
+    let (positions, mut fields) = match json {
+        Value::Object(mut fields) => {
+            let positions = fields.remove("positions").ok_or(anyhow!(
+                "JSON object is missing the \"positions\" field with the particle positions"
+            ))?;
+            (positions, fields)
+        }
+        positions => (positions, Default::default()),
+    };
+
+    let particles = serde_json::from_value::<ParticleVecF64>(positions)
         .context("Parsing of JSON structure as particle positions failed. Expected JSON file containing particle positions like e.g. '[[1.0, 2.0, 3.0], [4.0, 5.0, 6.0]]'.")?;
 
     let len = particles.len();
@@ -47,7 +87,124 @@ pub fn particles_from_json<R: Real, P: AsRef<Path>>(
                 })
         .try_collect_with_capacity(len)?;
 
-    Ok(particles)
+    let names = attribute_names_to_load(attribute_names, optional_attribute_names, |name| {
+        fields.contains_key(name)
+    })?;
+    let attributes = names
+        .iter()
+        .map(|name| {
+            let values = fields.remove(name.as_str()).unwrap();
+            parse_attribute_from_json(values, len)
+                .map(|data| MeshAttribute::new(name, data))
+                .with_context(|| anyhow!("Attribute \"{}\"", name))
+        })
+        .try_collect_with_capacity(names.len())?;
+
+    Ok((particles, attributes))
+}
+
+/// Tries to convert the JSON array of an attribute with one value per particle to the corresponding supported AttributeData
+/// Returns the names of all point attributes of the JSON file at the given path, i.e. all fields of the JSON object except for the `"positions"`
+///
+/// Files that only contain an array of particle positions have no attributes.
+pub fn point_attribute_names_from_json<P: AsRef<Path>>(
+    json_file: P,
+) -> Result<Vec<String>, anyhow::Error> {
+    let path = json_file.as_ref();
+    let file = File::open(path).context("Cannot open file for JSON parsing")?;
+    let reader = BufReader::new(file);
+
+    let json = serde_json::from_reader(reader)
+        .context("Reading of file to JSON structure failed. Not a valid JSON file.")?;
+    Ok(match json {
+        Value::Object(fields) => fields
+            .keys()
+            .filter(|&name| name != "positions")
+            .cloned()
+            .collect(),
+        _ => Vec::new(),
+    })
+}
+
+fn parse_attribute_from_json<R: Real>(
+    values: Value,
+    num_particles: usize,
+) -> Result<AttributeData<R>, anyhow::Error> {
+    let values = match values {
+        Value::Array(values) => values,
+        _ => {
+            return Err(anyhow!(
+                "Attribute values have to be stored in a JSON array"
+            ))
+        }
+    };
+    if values.len() != num_particles {
+        return Err(anyhow!(
+            "Number of attribute values ({}) does not match the number of particles ({})",
+            values.len(),
+            num_particles
+        ));
+    }
+
+    let to_real = |value: &Value| {
+        value.as_f64().and_then(R::from_f64).ok_or_else(|| {
+            anyhow!(
+                "Cannot convert attribute value {} to Real type (expected a number)",
+                value
+            )
+        })
+    };
+
+    if values.iter().all(Value::is_u64) {
+        Ok(AttributeData::ScalarU64(
+            values.iter().map(|v| v.as_u64().unwrap()).collect(),
+        ))
+    } else if values.iter().all(Value::is_array) {
+        let num_components = values
+            .first()
+            .and_then(Value::as_array)
+            .map(Vec::len)
+            .unwrap_or(3);
+        if num_components == 3 {
+            values
+                .iter()
+                .map(|value| match value.as_array().map(Vec::as_slice) {
+                    Some([x, y, z]) => Ok(Vector3::new(to_real(x)?, to_real(y)?, to_real(z)?)),
+                    _ => Err(anyhow!(
+                        "Vector attribute values have to consist of three components (got {})",
+                        value
+                    )),
+                })
+                .try_collect_with_capacity(values.len())
+                .map(AttributeData::Vector3Real)
+        } else if num_components > 0 {
+            let mut components = Vec::with_capacity(values.len() * num_components);
+            for value in &values {
+                let value_components = value.as_array().unwrap();
+                if value_components.len() != num_components {
+                    return Err(anyhow!(
+                        "Vector attribute values have to consist of {} components (got {})",
+                        num_components,
+                        value
+                    ));
+                }
+                for component in value_components {
+                    components.push(to_real(component)?);
+                }
+            }
+            Ok(AttributeData::VectorReal(num_components, components))
+        } else {
+            Err(anyhow!(
+                "Vector attribute values without components are not supported"
+            ))
+        }
+    } else {
+        values
+            .iter()
+            .map(to_real)
+            .try_collect_with_capacity(values.len())
+            .map(AttributeData::ScalarReal)
+    }
 }
 
 /// Tries to write a set of particles to a JSON file at the given path
@@ -86,3 +243,151 @@ pub fn particles_to_json<R: Real, P: AsRef<Path>>(
 
     Ok(())
 }
+
+/// Tries to write a set of particles with point attributes to a JSON file at the given path
+///
+/// The particle coordinates and the attributes are stored in an object with a `"positions"` field and one field per
+/// attribute (see [`particles_with_optional_attributes_from_json`] for an example). Vectors are written as arrays of
+/// their components and 3x3 tensors as arrays of their nine components in row-major order.
+/// Without attributes, the particles are written as a plain array of arrays (see [`particles_to_json`]).
+pub fn particles_with_attributes_to_json<R: Real, P: AsRef<Path>>(
+    particles: &[Vector3<R>],
+    attributes: &[MeshAttribute<R>],
+    json_file: P,
+) -> Result<(), anyhow::Error> {
+    if attributes.is_empty() {
+        return particles_to_json(particles, json_file);
+    }
+
+    let to_json = |v: &R| {
+        v.to_f64().map(Value::from).ok_or_else(|| {
+            anyhow!("Failed to convert value from input float type to f64, value out of range?")
+        })
+    };
+    let to_json_array = |values: &[R]| -> Result<Value, anyhow::Error> {
+        values
+            .iter()
+            .map(to_json)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array)
+    };
+
+    let mut fields = serde_json::Map::new();
+    fields.insert(
+        "positions".to_string(),
+        particles
+            .iter()
+            .map(|p| to_json_array(p.as_slice()))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Value::Array)?,
+    );
+
+    for attribute in attributes {
+        if attribute.data.len() != particles.len() {
+            return Err(anyhow!(
+                "Number of values of attribute \"{}\" does not match the number of particles",
+                attribute.name
+            ));
+        }
+
+        let values = match &attribute.data {
+            AttributeData::ScalarU64(values) => {
+                Ok(values.iter().copied().map(Value::from).collect())
+            }
+            AttributeData::ScalarReal(values) => values.iter().map(to_json).collect(),
+            AttributeData::Vector3Real(values) => {
+                values.iter().map(|v| to_json_array(v.as_slice())).collect()
+            }
+            AttributeData::VectorReal(n, values) => {
+                values.chunks_exact(*n).map(to_json_array).collect()
+            }
+            AttributeData::Matrix3Real(values) => values
+                .iter()
+                .map(|m| to_json_array(m.transpose().as_slice()))
+                .collect(),
+        };
+        fields.insert(attribute.name.clone(), Value::Array(values?));
+    }
+
+    let path = json_file.as_ref();
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)
+        .context("Cannot open file for writing JSON")?;
+    let writer = BufWriter::new(file);
+
+    serde_json::to_writer(writer, &Value::Object(fields))
+        .context("Failed to serialize particles with attributes to JSON file")?;
+
+    Ok(())
+}
+
+#[test]
+fn test_json_read_cube_attributes() {
+    let input_file = Path::new("../data/cube_8_particles_attributes.json");
+    let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+    assert_eq!(particles_from_json::<f32, _>(input_file).unwrap().len(), 8);
+    let mut attribute_names = point_attribute_names_from_json(input_file).unwrap();
+    attribute_names.sort();
+    assert_eq!(attribute_names, names(&["density", "id", "velocity"]));
+
+    let (particles, attributes) = particles_with_optional_attributes_from_json::<f64, _>(
+        input_file,
+        &names(&["id", "density"]),
+        &names(&["v", "velocity"]),
+    )
+    .unwrap();
+    assert_eq!(particles.len(), 8);
+    assert_eq!(particles[7], Vector3::new(1.0, 1.0, 1.0));
+
+    assert_eq!(attributes.len(), 3);
+    assert!(matches!(&attributes[0].data, AttributeData::ScalarU64(ids) if ids[5] == 5));
+    assert!(matches!(&attributes[1].data, AttributeData::ScalarReal(d) if d[1] == 1001.0));
+    assert_eq!(attributes[2].name, "velocity");
+    assert!(
+        matches!(&attributes[2].data, AttributeData::Vector3Real(v) if v[4] == Vector3::new(0.5, 0.0, 0.0))
+    );
+
+    assert!(particles_with_optional_attributes_from_json::<f64, _>(
+        input_file,
+        &names(&["pressure"]),
+        &[]
+    )
+    .is_err());
+}
+
+#[test]
+fn test_json_write_attributes() {
+    let output_dir = Path::new("../out/");
+    std::fs::create_dir_all(output_dir).unwrap();
+    let output_file = output_dir.join("test_json_write_attributes.json");
+
+    let particles = vec![Vector3::new(1.0, 2.0, 3.0), Vector3::new(4.0, 5.0, 6.0)];
+    let attributes = vec![
+        MeshAttribute::new("class", vec![2u64, 0]),
+        MeshAttribute::new_real_scalar("density", vec![1000.0, 1001.5]),
+        MeshAttribute::new_real_vector3("v", vec![Vector3::new(0.5, 0.0, -0.5); 2]),
+        MeshAttribute::new(
+            "color",
+            AttributeData::VectorReal(4, (0..8).map(|i| i as f64).collect()),
+        ),
+    ];
+    particles_with_attributes_to_json(&particles, &attributes, &output_file).unwrap();
+
+    let names = ["class", "density", "v", "color"].map(String::from);
+    let (particles_read, attributes_read) =
+        particles_with_optional_attributes_from_json::<f64, _>(&output_file, &names, &[]).unwrap();
+
+    assert_eq!(particles_read, particles);
+    assert!(matches!(&attributes_read[0].data, AttributeData::ScalarU64(v) if v == &vec![2, 0]));
+    assert!(matches!(&attributes_read[1].data, AttributeData::ScalarReal(v) if v[1] == 1001.5));
+    assert!(
+        matches!(&attributes_read[2].data, AttributeData::Vector3Real(v) if v[1] == Vector3::new(0.5, 0.0, -0.5))
+    );
+    assert!(
+        matches!(&attributes_read[3].data, AttributeData::VectorReal(4, v) if v[5] == 5.0 && v.len() == 8)
+    );
+}
@@ -1,5 +1,6 @@
 //! Helper functions for the PLY file format
 
+use super::{attribute_names_to_load, ParticlesWithAttributes};
 use crate::mesh::{
     AttributeData, CellConnectivity, Mesh3d, MeshAttribute, MeshWithData, TriMesh3d,
 };
@@ -9,7 +10,7 @@ use anyhow::{anyhow, Context};
 use nalgebra::Vector3;
 use num_traits::ToPrimitive;
 use ply_rs::parser::Parser as PlyParser;
-use ply_rs::ply::{DefaultElement, Ply, Property};
+use ply_rs::ply::{DefaultElement, ElementDef, Ply, Property};
 use std::fs;
 use std::io::{BufWriter, Write};
 use std::path::Path;
@@ -24,6 +25,205 @@ pub fn particles_from_ply<R: Real, P: AsRef<Path>>(
     parse_particles_from_ply(&ply)
 }
 
+/// Tries to load the file at the given path as a PLY file and read particle positions, the vertex properties with the given names and all optional vertex properties that exist
+///
+/// Integer vertex properties are loaded as [`AttributeData::ScalarU64`] (negative values are not supported) and floating
+/// point properties as [`AttributeData::ScalarReal`]. A vector attribute `name` is loaded as [`AttributeData::Vector3Real`]
+/// from the three properties `name_x`, `name_y` and `name_z` (or `nx`, `ny` and `nz` for `normals`), i.e. the layout written by [`mesh_to_ply`].
+/// Returns an error if one of the attributes in `attribute_names` does not exist, missing optional attributes are skipped.
+/// The returned attributes are in the order of `attribute_names` followed by the optional attributes that were found.
+pub fn particles_with_optional_attributes_from_ply<R: Real, P: AsRef<Path>>(
+    ply_path: P,
+    attribute_names: &[String],
+    optional_attribute_names: &[String],
+) -> Result<ParticlesWithAttributes<R>, anyhow::Error> {
+    let ply = PlyParser::new()
+        .read_ply(&mut fs::File::open(ply_path).context("Failed to open file for reading")?)
+        .context("Failed to parse PLY file")?;
+    let particles = parse_particles_from_ply(&ply)?;
+
+    let vertex_definition = ply
+        .header
+        .elements
+        .get("vertex")
+        .ok_or(anyhow!("PLY file is missing a 'vertex' element"))?;
+    let names = attribute_names_to_load(attribute_names, optional_attribute_names, |name| {
+        vertex_definition.properties.contains_key(name)
+            || vector_property_names(name).iter().all(|component| {
+                vertex_definition
+                    .properties
+                    .contains_key(component.as_str())
+            })
+    })?;
+
+    let vertices = ply
+        .payload
+        .get("vertex")
+        .ok_or(anyhow!("PLY file is missing a 'vertex' element"))?;
+    let attributes = names
+        .iter()
+        .map(|name| {
+            parse_vertex_attribute_from_ply(vertices, vertex_definition, name)
+                .with_context(|| anyhow!("Attribute \"{}\"", name))
+        })
+        .try_collect_with_capacity(names.len())?;
+
+    Ok((particles, attributes))
+}
+
+/// Returns the names of all attributes of the vertices of the PLY file at the given path that can be loaded with [`particles_with_optional_attributes_from_ply`]
+///
+/// Vertex properties that store the components of a vector attribute (e.g. `velocity_x`, `velocity_y` and `velocity_z`) are
+/// combined to the name of the vector attribute, the vertex positions are not included.
+pub fn point_attribute_names_from_ply<P: AsRef<Path>>(
+    ply_path: P,
+) -> Result<Vec<String>, anyhow::Error> {
+    let ply = PlyParser::<DefaultElement>::new()
+        .read_ply(&mut fs::File::open(ply_path).context("Failed to open file for reading")?)
+        .context("Failed to parse PLY file")?;
+    let vertex_definition = ply
+        .header
+        .elements
+        .get("vertex")
+        .ok_or(anyhow!("PLY file is missing a 'vertex' element"))?;
+
+    let mut names = Vec::new();
+    let mut vector_components = Vec::new();
+    for name in vertex_definition.properties.keys() {
+        if ["x", "y", "z"].contains(&name.as_str()) || vector_components.contains(name) {
+            continue;
+        }
+
+        // Check if the property is the first component of a vector attribute
+        let vector_name = match name.as_str() {
+            "nx" => Some("normals"),
+            _ => name.strip_suffix("_x"),
+        };
+        if let Some(vector_name) = vector_name {
+            let components = vector_property_names(vector_name);
+            if components
+                .iter()
+                .all(|component| vertex_definition.properties.contains_key(component))
+            {
+                names.push(vector_name.to_string());
+                vector_components.extend(components);
+                continue;
+            }
+        }
+
+        names.push(name.clone());
+    }
+    // Remove the remaining components of vector attributes that were listed before their first component
+    names.retain(|name| !vector_components.contains(name));
+
+    Ok(names)
+}
+
+/// Returns the names of the three PLY properties that store the components of the vector attribute with the given name
+fn vector_property_names(name: &str) -> [String; 3] {
+    if name == "normals" {
+        ["nx".to_string(), "ny".to_string(), "nz".to_string()]
+    } else {
+        [
+            format!("{}_x", name),
+            format!("{}_y", name),
+            format!("{}_z", name),
+        ]
+    }
+}
+
+/// Tries to extract the vertex attribute with the given name from the vertices of a PLY file
+fn parse_vertex_attribute_from_ply<R: Real>(
+    vertices: &[DefaultElement],
+    vertex_definition: &ElementDef,
+    name: &str,
+) -> Result<MeshAttribute<R>, anyhow::Error> {
+    let to_real = |property: &Property| {
+        property_to_f64(property)
+            .and_then(R::from_f64)
+            .ok_or_else(|| anyhow!("Cannot convert PLY property {:?} to Real type", property))
+    };
+
+    let data = if vertex_definition.properties.contains_key(name) {
+        let values = vertices
+            .iter()
+            .map(|vertex| vertex_property(vertex, name))
+            .try_collect_with_capacity(vertices.len())?;
+        if values
+            .iter()
+            .all(|&property| property_to_u64(property).is_some())
+        {
+            AttributeData::ScalarU64(
+                values
+                    .into_iter()
+                    .map(|property| property_to_u64(property).unwrap())
+                    .collect(),
+            )
+        } else {
+            AttributeData::ScalarReal(
+                values
+                    .into_iter()
+                    .map(to_real)
+                    .try_collect_with_capacity(vertices.len())?,
+            )
+        }
+    } else {
+        let [x, y, z] = vector_property_names(name);
+        AttributeData::Vector3Real(
+            vertices
+                .iter()
+                .map(|vertex| -> Result<_, anyhow::Error> {
+                    Ok(Vector3::new(
+                        to_real(vertex_property(vertex, &x)?)?,
+                        to_real(vertex_property(vertex, &y)?)?,
+                        to_real(vertex_property(vertex, &z)?)?,
+                    ))
+                })
+                .try_collect_with_capacity(vertices.len())?,
+        )
+    };
+
+    Ok(MeshAttribute::new(name, data))
+}
+
+/// Returns the property with the given name of a PLY vertex
+fn vertex_property<'a>(
+    vertex: &'a DefaultElement,
+    name: &str,
+) -> Result<&'a Property, anyhow::Error> {
+    vertex
+        .get(name)
+        .ok_or_else(|| anyhow!("A vertex is missing the '{}' property", name))
+}
+
+/// Converts a non-negative integer PLY property to `u64`, returns `None` for all other properties
+fn property_to_u64(property: &Property) -> Option<u64> {
+    match *property {
+        Property::Char(v) => v.to_u64(),
+        Property::UChar(v) => v.to_u64(),
+        Property::Short(v) => v.to_u64(),
+        Property::UShort(v) => v.to_u64(),
+        Property::Int(v) => v.to_u64(),
+        Property::UInt(v) => v.to_u64(),
+        _ => None,
+    }
+}
+
+/// Converts a scalar PLY property to `f64`, returns `None` for list properties
+fn property_to_f64(property: &Property) -> Option<f64> {
+    match *property {
+        Property::Char(v) => v.to_f64(),
+        Property::UChar(v) => v.to_f64(),
+        Property::Short(v) => v.to_f64(),
+        Property::UShort(v) => v.to_f64(),
+        Property::Int(v) => v.to_f64(),
+        Property::UInt(v) => v.to_f64(),
+        Property::Float(v) => v.to_f64(),
+        Property::Double(v) => Some(v),
+        _ => None,
+    }
+}
+
 /// Tries to load the file at the given path as a PLY file and read a surface mesh from it
 pub fn surface_mesh_from_ply<R: Real, P: AsRef<Path>>(
     ply_path: P,
@@ -187,14 +387,19 @@ fn parse_mesh_from_ply<R: Real>(
     Ok(mesh)
 }
 
+/// Suffixes of the vertex properties of the components of a 3x3 tensor attribute in row-major order
+const TENSOR_COMPONENT_SUFFIXES: [&str; 9] = ["xx", "xy", "xz", "yx", "yy", "yz", "zx", "zy", "zz"];
+
 /// Tries to write a mesh with attributes into a little endian PLY file
+///
+/// Vector attributes are written as one float property per component with the suffixes `_x`, `_y`, `_z` (or `_0`,
+/// `_1`, ... for vectors with an arbitrary number of components) and 3x3 tensor attributes with the suffixes `_xx`,
+/// `_xy`, ..., `_zz` (row-major order). Cell attributes are written as properties of the faces.
 #[rustfmt::skip]
 pub fn mesh_to_ply<R: Real, M: Mesh3d<R>, P: AsRef<Path>>(
     mesh: &MeshWithData<R, M>,
     filename: P,
 ) -> Result<(), anyhow::Error> {
-    // TODO: Support attributes
-
     let file = fs::OpenOptions::new()
         .read(true)
         .write(true)
@@ -216,19 +421,14 @@ pub fn mesh_to_ply<R: Real, M: Mesh3d<R>, P: AsRef<Path>>(
             write!(&mut writer, "property float ny\n")?;
             write!(&mut writer, "property float nz\n")?;
         } else {
-            match p_attr.data {
-                AttributeData::ScalarU64(_) => write!(&mut writer, "property uint {}\n", p_attr.name)?,
-                AttributeData::ScalarReal(_) => write!(&mut writer, "property float {}\n", p_attr.name)?,
-                AttributeData::Vector3Real(_) => {
-                    write!(&mut writer, "property float {}_x\n", p_attr.name)?;
-                    write!(&mut writer, "property float {}_y\n", p_attr.name)?;
-                    write!(&mut writer, "property float {}_z\n", p_attr.name)?;
-                },
-            }
+            write_property_header(&mut writer, p_attr)?;
         }
     }
     write!(&mut writer, "element face {}\n", mesh.cells().len())?;
     write!(&mut writer, "property list uchar uint vertex_indices\n")?;
+    for c_attr in &mesh.cell_attributes {
+        write_property_header(&mut writer, c_attr)?;
+    }
     write!(&mut writer, "end_header\n")?;
 
     for (i, v) in mesh.vertices().iter().enumerate() {
@@ -237,37 +437,91 @@ pub fn mesh_to_ply<R: Real, M: Mesh3d<R>, P: AsRef<Path>>(
         writer.write_all(&v.z.to_f32().expect("failed to convert coordinate to f32").to_le_bytes())?;
 
         for p_attr in &mesh.point_attributes {
-            match &p_attr.data {
-                AttributeData::ScalarU64(data) => {
-                    let val = data[i].to_u32().expect("failed to convert attribute to u32");
-                    writer.write_all(&val.to_le_bytes())?;
-                },
-                AttributeData::ScalarReal(data) => {
-                    let val = data[i].to_f32().expect("failed to convert attribute to f32");
-                    writer.write_all(&val.to_le_bytes())?;
-                },
-                AttributeData::Vector3Real(data) => {
-                    let val = &data[i];
-                    writer.write_all(&val.x.to_f32().expect("failed to convert attribute to f32").to_le_bytes())?;
-                    writer.write_all(&val.y.to_f32().expect("failed to convert attribute to f32").to_le_bytes())?;
-                    writer.write_all(&val.z.to_f32().expect("failed to convert attribute to f32").to_le_bytes())?;
-                },
-            }
+            write_property_values(&mut writer, p_attr, i)?;
         }
     }
 
-    for c in mesh.cells() {
-        let num_verts = M::Cell::num_vertices().to_u8().expect("failed to convert cell vertex count to u8");
+    for (i, c) in mesh.cells().iter().enumerate() {
+        let num_verts = c.num_vertices_of_cell().to_u8().expect("failed to convert cell vertex count to u8");
         writer.write_all(&num_verts.to_le_bytes())?;
         c.try_for_each_vertex(|v| {
             let idx = v.to_u32().expect("failed to convert vertex index to u32");
             writer.write_all(&idx.to_le_bytes())
         })?;
+
+        for c_attr in &mesh.cell_attributes {
+            write_property_values(&mut writer, c_attr, i)?;
+        }
     }
 
     Ok(())
 }
 
+/// Writes the PLY header lines of the properties of an attribute
+#[rustfmt::skip]
+fn write_property_header<R: Real, W: Write>(
+    writer: &mut W,
+    attribute: &MeshAttribute<R>,
+) -> Result<(), std::io::Error> {
+    match &attribute.data {
+        AttributeData::ScalarU64(_) => writeln!(writer, "property uint {}", attribute.name)?,
+        AttributeData::ScalarReal(_) => writeln!(writer, "property float {}", attribute.name)?,
+        AttributeData::Vector3Real(_) => {
+            writeln!(writer, "property float {}_x", attribute.name)?;
+            writeln!(writer, "property float {}_y", attribute.name)?;
+            writeln!(writer, "property float {}_z", attribute.name)?;
+        },
+        AttributeData::VectorReal(num_components, _) => {
+            for k in 0..*num_components {
+                writeln!(writer, "property float {}_{}", attribute.name, k)?;
+            }
+        },
+        AttributeData::Matrix3Real(_) => {
+            for component in TENSOR_COMPONENT_SUFFIXES {
+                writeln!(writer, "property float {}_{}", attribute.name, component)?;
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Writes the binary values of the properties of an attribute for the vertex or face with the given index
+#[rustfmt::skip]
+fn write_property_values<R: Real, W: Write>(
+    writer: &mut W,
+    attribute: &MeshAttribute<R>,
+    i: usize,
+) -> Result<(), std::io::Error> {
+    match &attribute.data {
+        AttributeData::ScalarU64(data) => {
+            let val = data[i].to_u32().expect("failed to convert attribute to u32");
+            writer.write_all(&val.to_le_bytes())?;
+        },
+        AttributeData::ScalarReal(data) => {
+            let val = data[i].to_f32().expect("failed to convert attribute to f32");
+            writer.write_all(&val.to_le_bytes())?;
+        },
+        AttributeData::Vector3Real(data) => {
+            let val = &data[i];
+            writer.write_all(&val.x.to_f32().expect("failed to convert attribute to f32").to_le_bytes())?;
+            writer.write_all(&val.y.to_f32().expect("failed to convert attribute to f32").to_le_bytes())?;
+            writer.write_all(&val.z.to_f32().expect("failed to convert attribute to f32").to_le_bytes())?;
+        },
+        AttributeData::VectorReal(num_components, data) => {
+            for val in &data[i * num_components..(i + 1) * num_components] {
+                writer.write_all(&val.to_f32().expect("failed to convert attribute to f32").to_le_bytes())?;
+            }
+        },
+        AttributeData::Matrix3Real(data) => {
+            // Row-major order of the components, see `TENSOR_COMPONENT_SUFFIXES`
+            for val in data[i].transpose().iter() {
+                writer.write_all(&val.to_f32().expect("failed to convert attribute to f32").to_le_bytes())?;
+            }
+        },
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 pub mod test {
     use super::*;
@@ -295,6 +549,33 @@ pub mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_ply_read_cube_particle_attributes() -> Result<(), anyhow::Error> {
+        let input_file = Path::new("../data/cube_8_particles_attributes.ply");
+        let names = |names: &[&str]| names.iter().map(|n| n.to_string()).collect::<Vec<_>>();
+
+        let (particles, attributes) = particles_with_optional_attributes_from_ply::<f32, _>(
+            input_file,
+            &names(&["id", "density"]),
+            &names(&["v", "velocity"]),
+        )?;
+
+        assert_eq!(particles.len(), 8);
+        assert_eq!(attributes.len(), 3);
+        assert!(matches!(&attributes[0].data, AttributeData::ScalarU64(ids) if ids[5] == 5));
+        assert_eq!(
+            point_attribute_names_from_ply(input_file)?,
+            names(&["density", "velocity", "id"])
+        );
+        assert!(matches!(&attributes[1].data, AttributeData::ScalarReal(d) if d[1] == 1001.0));
+        assert_eq!(attributes[2].name, "velocity");
+        assert!(
+            matches!(&attributes[2].data, AttributeData::Vector3Real(v) if v[4] == Vector3::new(0.5, 0.0, 0.0))
+        );
+
+        Ok(())
+    }
+
     #[test]
     fn test_ply_read_cube() -> Result<(), anyhow::Error> {
         let input_file = Path::new("../data/cube.ply");
@@ -1,15 +1,18 @@
 //! Helper functions for the VTK file format
 
+use super::{attribute_names_to_load, ParticlesWithAttributes};
 use crate::mesh::{AttributeData, MeshAttribute, MeshWithData, TriMesh3d};
 use crate::utils::IteratorExt;
 use crate::Real;
 use anyhow::{anyhow, Context};
-use nalgebra::Vector3;
+use log::warn;
+use nalgebra::{Matrix3, Vector3};
 use std::borrow::Cow;
 use std::fs::create_dir_all;
 use std::path::{Path, PathBuf};
 use vtkio::model::{
-    Attribute, Attributes, CellType, Cells, PolyDataPiece, UnstructuredGridPiece, VertexNumbers,
+    Attribute, Attributes, CellType, Cells, ElementType, PolyDataPiece, UnstructuredGridPiece,
+    VertexNumbers,
 };
 use vtkio::model::{ByteOrder, DataSet, Version, Vtk};
 use vtkio::IOBuffer;
@@ -104,6 +107,7 @@ impl DataPiece {
                         let attribute_data = try_convert_io_buffer_to_attribute(
                             &data_array.data,
                             data_array.num_comp(),
+                            data_array.elem == ElementType::Tensors,
                         )
                         .with_context(|| anyhow!("Attribute \"{}\"", field_name))?;
                         let mesh_attribute = MeshAttribute::new(field_name, attribute_data);
@@ -116,6 +120,7 @@ impl DataPiece {
                                 let attribute_data = try_convert_io_buffer_to_attribute(
                                     &field_array.data,
                                     field_array.num_comp(),
+                                    false,
                                 )
                                 .with_context(|| anyhow!("Attribute \"{}\"", field_name))?;
                                 let mesh_attribute = MeshAttribute::new(field_name, attribute_data);
@@ -152,16 +157,80 @@ pub fn particles_from_vtk<R: Real, P: AsRef<Path>>(
         .load_as_particles()
 }
 
+/// Returns the names of all point attributes of the first piece of the VTK file at the given path
+pub fn point_attribute_names_from_vtk<P: AsRef<Path>>(
+    file_path: P,
+) -> Result<Vec<String>, anyhow::Error> {
+    let file_path = file_path.as_ref();
+    Ok(VtkFile::load_file(file_path)?
+        .into_pieces()
+        .first()
+        .ok_or_else(|| {
+            anyhow!(
+                "No supported pieces in VTK file \"{}\"",
+                file_path.display()
+            )
+        })?
+        .point_attribute_names())
+}
+
+/// Tries to read a set of particles, the point attributes with the given names and all optional point attributes that exist from the VTK file at the given path
+///
+/// Only the first piece of the file is loaded. Returns an error if one of the attributes in `attribute_names` does not exist,
+/// missing optional attributes are skipped. The returned attributes are in the order of `attribute_names` followed by the
+/// optional attributes that were found.
+pub fn particles_with_optional_attributes_from_vtk<R: Real, P: AsRef<Path>>(
+    file_path: P,
+    attribute_names: &[String],
+    optional_attribute_names: &[String],
+) -> Result<ParticlesWithAttributes<R>, anyhow::Error> {
+    let file_path = file_path.as_ref();
+    let pieces = VtkFile::load_file(file_path)?.into_pieces();
+    if pieces.len() > 1 {
+        warn!("VTK file contains more than one \"piece\". Only the first one will be loaded.");
+    }
+
+    let first_piece = pieces.into_iter().next().ok_or_else(|| {
+        anyhow!(
+            "No supported pieces in VTK file \"{}\"",
+            file_path.display()
+        )
+    })?;
+
+    let existing_names = first_piece.point_attribute_names();
+    let names = attribute_names_to_load(attribute_names, optional_attribute_names, |name| {
+        existing_names.iter().any(|n| n == name)
+    })?;
+    let attributes = first_piece.load_point_attributes(&names)?;
+    let particles = first_piece.load_as_particles()?;
+    Ok((particles, attributes))
+}
+
 /// Tries to write a set of particles to a VTK file at the given path
 pub fn particles_to_vtk<R: Real, P: AsRef<Path>>(
     particles: &[Vector3<R>],
     vtk_file: P,
 ) -> Result<(), anyhow::Error> {
-    write_vtk(
-        UnstructuredGridPiece::from(Particles(particles)),
-        vtk_file,
-        "particles",
-    )
+    particles_with_attributes_to_vtk(particles, &[], vtk_file)
+}
+
+/// Tries to write a set of particles with point attributes to a VTK file at the given path
+pub fn particles_with_attributes_to_vtk<R: Real, P: AsRef<Path>>(
+    particles: &[Vector3<R>],
+    attributes: &[MeshAttribute<R>],
+    vtk_file: P,
+) -> Result<(), anyhow::Error> {
+    let mut piece = UnstructuredGridPiece::from(Particles(particles));
+    for attribute in attributes {
+        if attribute.data.len() != particles.len() {
+            return Err(anyhow!(
+                "Number of values of attribute \"{}\" does not match the number of particles",
+                attribute.name
+            ));
+        }
+        piece.data.point.push(attribute.to_vtk_attribute());
+    }
+    write_vtk(piece, vtk_file, "particles")
 }
 
 /// Tries to read a surface mesh from the VTK file at the given path
@@ -303,31 +372,19 @@ fn surface_mesh_from_unstructured_grid<R: Real>(
 }
 
 /// Converts a VTK IOBuffer to the corresponding supported AttributeData
+///
+/// Scalar attributes with integer values (e.g. phase or object ids) are loaded as [`AttributeData::ScalarU64`]
+/// (negative values are not supported). Attributes with nine components are only loaded as 3x3 tensors if `is_tensor` is set (i.e. for VTK `TENSORS`),
+/// otherwise they are loaded as vectors with an arbitrary number of components.
 fn try_convert_io_buffer_to_attribute<R: Real>(
     io_buffer: &vtkio::model::IOBuffer,
     num_comp: usize,
+    is_tensor: bool,
 ) -> Result<AttributeData<R>, anyhow::Error> {
     match num_comp {
-        1 => match &io_buffer {
-            IOBuffer::U32(vec) => try_map_scalars_to_real(&vec, |val| {
-                R::from_u32(val).ok_or_else(|| {
-                    anyhow!("Cannot convert an attribute value from u32 to Real type")
-                })
-            })
-            .map(|v| AttributeData::ScalarReal(v)),
-            IOBuffer::F32(vec) => try_map_scalars_to_real(&vec, |val| {
-                R::from_f32(val).ok_or_else(|| {
-                    anyhow!("Cannot convert an attribute value from f32 to Real type")
-                })
-            })
-            .map(|v| AttributeData::ScalarReal(v)),
-            IOBuffer::F64(vec) => try_map_scalars_to_real(&vec, |val| {
-                R::from_f64(val).ok_or_else(|| {
-                    anyhow!("Cannot convert an attribute value from f64 to Real type")
-                })
-            })
-            .map(|v| AttributeData::ScalarReal(v)),
-            _ => Err(anyhow!("Unsupported IOBuffer scalar data type")),
+        1 => match try_convert_io_buffer_to_u64(io_buffer) {
+            Some(values) => values.map(AttributeData::ScalarU64),
+            None => try_convert_io_buffer_to_real(io_buffer).map(AttributeData::ScalarReal),
         },
         3 => match &io_buffer {
             IOBuffer::F32(coords) => {
@@ -338,10 +395,73 @@ fn try_convert_io_buffer_to_attribute<R: Real>(
             }
             _ => Err(anyhow!("Unsupported IOBuffer vector data type")),
         },
-        _ => Err(anyhow!(
-            "Unsupported number of components ({}) in VTK IO buffer",
-            num_comp
-        )),
+        // VTK stores the tensor components in row-major order
+        9 if is_tensor => try_convert_io_buffer_to_real(io_buffer).map(|values| {
+            AttributeData::Matrix3Real(
+                values
+                    .chunks_exact(9)
+                    .map(Matrix3::from_row_slice)
+                    .collect(),
+            )
+        }),
+        0 => Err(anyhow!("VTK IO buffer without components is not supported")),
+        _ => try_convert_io_buffer_to_real(io_buffer)
+            .map(|values| AttributeData::VectorReal(num_comp, values)),
+    }
+}
+
+/// Converts all values of a VTK IOBuffer with integer values to u64, returns `None` if the buffer does not contain integers
+fn try_convert_io_buffer_to_u64(
+    io_buffer: &vtkio::model::IOBuffer,
+) -> Option<Result<Vec<u64>, anyhow::Error>> {
+    fn try_map_integers<T: Copy + TryInto<u64> + std::fmt::Display>(
+        values: &[T],
+    ) -> Result<Vec<u64>, anyhow::Error> {
+        values
+            .iter()
+            .copied()
+            .map(|val| {
+                val.try_into().map_err(|_| {
+                    anyhow!(
+                        "Negative integer attribute values are not supported (got {})",
+                        val
+                    )
+                })
+            })
+            .try_collect_with_capacity(values.len())
+    }
+
+    match &io_buffer {
+        IOBuffer::U8(vec) => Some(try_map_integers(vec)),
+        IOBuffer::I8(vec) => Some(try_map_integers(vec)),
+        IOBuffer::U16(vec) => Some(try_map_integers(vec)),
+        IOBuffer::I16(vec) => Some(try_map_integers(vec)),
+        IOBuffer::U32(vec) => Some(try_map_integers(vec)),
+        IOBuffer::I32(vec) => Some(try_map_integers(vec)),
+        IOBuffer::U64(vec) => Some(try_map_integers(vec)),
+        IOBuffer::I64(vec) => Some(try_map_integers(vec)),
+        _ => None,
+    }
+}
+
+/// Converts all values of a VTK IOBuffer to the Real type
+fn try_convert_io_buffer_to_real<R: Real>(
+    io_buffer: &vtkio::model::IOBuffer,
+) -> Result<Vec<R>, anyhow::Error> {
+    match &io_buffer {
+        IOBuffer::U32(vec) => try_map_scalars_to_real(&vec, |val| {
+            R::from_u32(val)
+                .ok_or_else(|| anyhow!("Cannot convert an attribute value from u32 to Real type"))
+        }),
+        IOBuffer::F32(vec) => try_map_scalars_to_real(&vec, |val| {
+            R::from_f32(val)
+                .ok_or_else(|| anyhow!("Cannot convert an attribute value from f32 to Real type"))
+        }),
+        IOBuffer::F64(vec) => try_map_scalars_to_real(&vec, |val| {
+            R::from_f64(val)
+                .ok_or_else(|| anyhow!("Cannot convert an attribute value from f64 to Real type"))
+        }),
+        _ => Err(anyhow!("Unsupported IOBuffer scalar data type")),
     }
 }
 
@@ -453,6 +573,59 @@ pub mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_integer_attribute_round_trip() -> Result<(), anyhow::Error> {
+        let output_dir = Path::new("../out/");
+        create_dir_all(output_dir)?;
+        let output_file = output_dir.join("test_integer_attribute_round_trip.vtk");
+
+        // A block of particles with object id 3 next to a block with object id 7
+        let particles: Vec<Vector3<f64>> = (0..54)
+            .map(|i| Vector3::new((i / 9) as f64, ((i / 3) % 3) as f64, (i % 3) as f64) * 0.1)
+            .collect();
+        let ids: Vec<u64> = (0..54).map(|i| if i < 27 { 3 } else { 7 }).collect();
+        particles_with_attributes_to_vtk(
+            &particles,
+            &[MeshAttribute::new("id", ids.clone())],
+            &output_file,
+        )?;
+
+        let (particles_read, attributes_read) = particles_with_optional_attributes_from_vtk::<
+            f64,
+            _,
+        >(&output_file, &["id".to_string()], &[])?;
+        assert_eq!(particles_read.len(), particles.len());
+        let ids_read = match &attributes_read[0].data {
+            AttributeData::ScalarU64(ids_read) => ids_read,
+            _ => panic!("Integer attribute was not loaded as integer attribute"),
+        };
+        assert_eq!(ids_read, &ids);
+
+        // Interpolated ids have to be ids of the input particles and not averages of them
+        let densities = vec![1000.0; particles_read.len()];
+        let interpolator = crate::sph_interpolation::SphInterpolator::new(
+            &particles_read,
+            &densities,
+            1.0,
+            crate::ParticleProperties::uniform(),
+            0.3,
+            crate::kernel::KernelType::CubicSpline,
+        );
+        let points: Vec<_> = (0..12)
+            .map(|i| Vector3::new(i as f64 * 0.05, 0.1, 0.1))
+            .collect();
+        for interpolated_ids in [
+            interpolator.interpolate_nearest_particle(ids_read, &points),
+            interpolator.interpolate_majority_vote(ids_read, &points),
+        ] {
+            assert!(interpolated_ids.iter().all(|id| *id == 3 || *id == 7));
+            assert_eq!(interpolated_ids.first(), Some(&3));
+            assert_eq!(interpolated_ids.last(), Some(&7));
+        }
+
+        Ok(())
+    }
+
     #[test]
     fn test_cube_8_particles_from_vtu() -> Result<(), anyhow::Error> {
         test_load_num_particles("../data/cube_8_particles.vtu", 8)
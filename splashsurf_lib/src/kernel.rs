@@ -1,4 +1,15 @@
 //! SPH kernel function implementations
+//!
+//! The following kernels are available, all of them implement [`SymmetricKernel3d`] and are normalized
+//! such that their integral over the compact support is one:
+//!  - [`CubicSplineKernel`]: the commonly used cubic spline kernel (default of the reconstruction)
+//!  - [`WendlandC2Kernel`], [`WendlandC4Kernel`], [`WendlandC6Kernel`]: the Wendland kernels (Wendland 1995, Dehnen and Aly 2012)
+//!  - [`Poly6Kernel`] and [`SpikyKernel`]: the kernels proposed by Müller et al. 2003
+//!  - [`GaussianKernel`]: a Gaussian kernel truncated at the compact support radius (standard deviation of a third of the radius)
+//!
+//! The kernel used by the surface reconstruction is selected at runtime using a [`KernelType`], which
+//! can be turned into a [`SphKernel`] that dispatches to the concrete kernel implementations. For performance
+//! critical evaluations, any kernel can be pre-computed into a [`DiscreteSquaredDistanceKernel`] lookup table.
 
 use crate::Real;
 use nalgebra::Vector3;
@@ -8,6 +19,8 @@ use numeric_literals::replace_float_literals;
 
 /// Trait for symmetric kernel functions in three dimensions
 pub trait SymmetricKernel3d<R: Real> {
+    /// Returns the compact support radius of the kernel, i.e. the radius beyond which the kernel is zero
+    fn compact_support_radius(&self) -> R;
     /// Evaluates the kernel at the radial distance `r` relative to the origin
     fn evaluate(&self, r: R) -> R;
     /// Evaluates the kernel gradient at the position `x` relative to the origin
@@ -17,6 +30,7 @@ pub trait SymmetricKernel3d<R: Real> {
 }
 
 /// The commonly used cubic spline kernel
+#[derive(Clone, Debug)]
 pub struct CubicSplineKernel<R: Real> {
     /// Compact support radius of the kernel
     compact_support_radius: R,
@@ -65,6 +79,10 @@ impl<R: Real> CubicSplineKernel<R> {
 }
 
 impl<R: Real> SymmetricKernel3d<R> for CubicSplineKernel<R> {
+    fn compact_support_radius(&self) -> R {
+        self.compact_support_radius
+    }
+
     /// Evaluates the cubic spline kernel at the radial distance `r`
     fn evaluate(&self, r: R) -> R {
         let q = (r + r) / self.compact_support_radius;
@@ -144,9 +162,459 @@ fn test_cubic_kernel_r_integral() {
     }
 }
 
-/// Accelerator for efficient evaluation of a precomputed cubic kernel
+/// Implements [`SymmetricKernel3d`] for a kernel of the form `W(r) = σ/h³ f(r/h)` with compact support `h`
 ///
-/// This structure is used to pre-compute a discrete representation of the cubic kernel function.
+/// The kernel type has to provide the fields `compact_support_radius` and `normalization` (`σ/h³`) as well
+/// as the functions `kernel_function(q)` and `kernel_function_dq(q)` that are only evaluated for `q < 1`.
+macro_rules! impl_radial_kernel {
+    ($kernel:ident) => {
+        impl<R: Real> SymmetricKernel3d<R> for $kernel<R> {
+            fn compact_support_radius(&self) -> R {
+                self.compact_support_radius
+            }
+
+            fn evaluate(&self, r: R) -> R {
+                let q = r / self.compact_support_radius;
+                if q < R::one() {
+                    self.normalization * Self::kernel_function(q)
+                } else {
+                    R::zero()
+                }
+            }
+
+            fn evaluate_gradient(&self, x: Vector3<R>) -> Vector3<R> {
+                let r = x.norm();
+                // The gradient vanishes at the origin due to symmetry
+                if r <= R::default_epsilon() {
+                    return Vector3::zeros();
+                }
+                x.scale(self.evaluate_gradient_norm(r) / r)
+            }
+
+            fn evaluate_gradient_norm(&self, r: R) -> R {
+                let q = r / self.compact_support_radius;
+                if q < R::one() {
+                    self.normalization * Self::kernel_function_dq(q) / self.compact_support_radius
+                } else {
+                    R::zero()
+                }
+            }
+        }
+    };
+}
+
+/// Wendland C2 kernel `W(q) = 21/(2π h³) (1 - q)⁴ (1 + 4q)`
+#[derive(Clone, Debug)]
+pub struct WendlandC2Kernel<R: Real> {
+    /// Compact support radius of the kernel
+    compact_support_radius: R,
+    /// Kernel normalization factor (sigma)
+    normalization: R,
+}
+
+impl<R: Real> WendlandC2Kernel<R> {
+    /// Initializes a Wendland C2 kernel with the given compact support radius
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    pub fn new(compact_support_radius: R) -> Self {
+        let h = compact_support_radius;
+        Self {
+            compact_support_radius,
+            normalization: 21.0 / (2.0 * R::pi() * h * h * h),
+        }
+    }
+
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn kernel_function(q: R) -> R {
+        let x = 1.0 - q;
+        x.powi(4) * (1.0 + 4.0 * q)
+    }
+
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn kernel_function_dq(q: R) -> R {
+        let x = 1.0 - q;
+        -20.0 * q * x.powi(3)
+    }
+}
+
+impl_radial_kernel!(WendlandC2Kernel);
+
+/// Wendland C4 kernel `W(q) = 495/(32π h³) (1 - q)⁶ (1 + 6q + 35/3 q²)`
+#[derive(Clone, Debug)]
+pub struct WendlandC4Kernel<R: Real> {
+    /// Compact support radius of the kernel
+    compact_support_radius: R,
+    /// Kernel normalization factor (sigma)
+    normalization: R,
+}
+
+impl<R: Real> WendlandC4Kernel<R> {
+    /// Initializes a Wendland C4 kernel with the given compact support radius
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    pub fn new(compact_support_radius: R) -> Self {
+        let h = compact_support_radius;
+        Self {
+            compact_support_radius,
+            normalization: 495.0 / (32.0 * R::pi() * h * h * h),
+        }
+    }
+
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn kernel_function(q: R) -> R {
+        let x = 1.0 - q;
+        x.powi(6) * (1.0 + 6.0 * q + (35.0 / 3.0) * q * q)
+    }
+
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn kernel_function_dq(q: R) -> R {
+        let x = 1.0 - q;
+        -(56.0 / 3.0) * q * (1.0 + 5.0 * q) * x.powi(5)
+    }
+}
+
+impl_radial_kernel!(WendlandC4Kernel);
+
+/// Wendland C6 kernel `W(q) = 1365/(64π h³) (1 - q)⁸ (1 + 8q + 25q² + 32q³)`
+#[derive(Clone, Debug)]
+pub struct WendlandC6Kernel<R: Real> {
+    /// Compact support radius of the kernel
+    compact_support_radius: R,
+    /// Kernel normalization factor (sigma)
+    normalization: R,
+}
+
+impl<R: Real> WendlandC6Kernel<R> {
+    /// Initializes a Wendland C6 kernel with the given compact support radius
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    pub fn new(compact_support_radius: R) -> Self {
+        let h = compact_support_radius;
+        Self {
+            compact_support_radius,
+            normalization: 1365.0 / (64.0 * R::pi() * h * h * h),
+        }
+    }
+
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn kernel_function(q: R) -> R {
+        let x = 1.0 - q;
+        x.powi(8) * (1.0 + 8.0 * q + 25.0 * q * q + 32.0 * q * q * q)
+    }
+
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn kernel_function_dq(q: R) -> R {
+        let x = 1.0 - q;
+        -22.0 * q * (1.0 + 7.0 * q + 16.0 * q * q) * x.powi(7)
+    }
+}
+
+impl_radial_kernel!(WendlandC6Kernel);
+
+/// Poly6 kernel `W(q) = 315/(64π h³) (1 - q²)³` (Müller et al. 2003)
+#[derive(Clone, Debug)]
+pub struct Poly6Kernel<R: Real> {
+    /// Compact support radius of the kernel
+    compact_support_radius: R,
+    /// Kernel normalization factor (sigma)
+    normalization: R,
+}
+
+impl<R: Real> Poly6Kernel<R> {
+    /// Initializes a Poly6 kernel with the given compact support radius
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    pub fn new(compact_support_radius: R) -> Self {
+        let h = compact_support_radius;
+        Self {
+            compact_support_radius,
+            normalization: 315.0 / (64.0 * R::pi() * h * h * h),
+        }
+    }
+
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn kernel_function(q: R) -> R {
+        let x = 1.0 - q * q;
+        x * x * x
+    }
+
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn kernel_function_dq(q: R) -> R {
+        let x = 1.0 - q * q;
+        -6.0 * q * x * x
+    }
+}
+
+impl_radial_kernel!(Poly6Kernel);
+
+/// Spiky kernel `W(q) = 15/(π h³) (1 - q)³` (Müller et al. 2003)
+#[derive(Clone, Debug)]
+pub struct SpikyKernel<R: Real> {
+    /// Compact support radius of the kernel
+    compact_support_radius: R,
+    /// Kernel normalization factor (sigma)
+    normalization: R,
+}
+
+impl<R: Real> SpikyKernel<R> {
+    /// Initializes a spiky kernel with the given compact support radius
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    pub fn new(compact_support_radius: R) -> Self {
+        let h = compact_support_radius;
+        Self {
+            compact_support_radius,
+            normalization: 15.0 / (R::pi() * h * h * h),
+        }
+    }
+
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn kernel_function(q: R) -> R {
+        let x = 1.0 - q;
+        x * x * x
+    }
+
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn kernel_function_dq(q: R) -> R {
+        let x = 1.0 - q;
+        -3.0 * x * x
+    }
+}
+
+impl_radial_kernel!(SpikyKernel);
+
+/// Gaussian kernel `W(q) = σ/h³ (exp(-9q²) - exp(-9))` truncated at the compact support radius
+///
+/// The standard deviation of the Gaussian is a third of the compact support radius. The Gaussian is
+/// shifted such that it is continuous at the compact support radius and `σ ≈ 4.8632` normalizes the truncated kernel.
+#[derive(Clone, Debug)]
+pub struct GaussianKernel<R: Real> {
+    /// Compact support radius of the kernel
+    compact_support_radius: R,
+    /// Kernel normalization factor (sigma)
+    normalization: R,
+}
+
+impl<R: Real> GaussianKernel<R> {
+    /// Initializes a truncated Gaussian kernel with the given compact support radius
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    pub fn new(compact_support_radius: R) -> Self {
+        let h = compact_support_radius;
+        Self {
+            compact_support_radius,
+            // Normalization of the shifted and truncated Gaussian: 1 / (4π ∫_0^1 q² (exp(-9q²) - exp(-9)) dq)
+            normalization: 4.863181196769355 / (h * h * h),
+        }
+    }
+
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn kernel_function(q: R) -> R {
+        (-9.0 * q * q).exp() - (-9.0).exp()
+    }
+
+    #[replace_float_literals(R::from_f64(literal).expect("Literal must fit in R"))]
+    fn kernel_function_dq(q: R) -> R {
+        -18.0 * q * (-9.0 * q * q).exp()
+    }
+}
+
+impl_radial_kernel!(GaussianKernel);
+
+/// Selects one of the available SPH kernel functions
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum KernelType {
+    /// The cubic spline kernel, see [`CubicSplineKernel`]
+    #[default]
+    CubicSpline,
+    /// The Wendland C2 kernel, see [`WendlandC2Kernel`]
+    WendlandC2,
+    /// The Wendland C4 kernel, see [`WendlandC4Kernel`]
+    WendlandC4,
+    /// The Wendland C6 kernel, see [`WendlandC6Kernel`]
+    WendlandC6,
+    /// The Poly6 kernel, see [`Poly6Kernel`]
+    Poly6,
+    /// The spiky kernel, see [`SpikyKernel`]
+    Spiky,
+    /// The truncated Gaussian kernel, see [`GaussianKernel`]
+    Gaussian,
+}
+
+impl KernelType {
+    /// Returns all available kernel types
+    pub const fn all() -> &'static [KernelType] {
+        &[
+            KernelType::CubicSpline,
+            KernelType::WendlandC2,
+            KernelType::WendlandC4,
+            KernelType::WendlandC6,
+            KernelType::Poly6,
+            KernelType::Spiky,
+            KernelType::Gaussian,
+        ]
+    }
+}
+
+/// SPH kernel with a type selected at runtime that dispatches to the corresponding kernel implementation
+#[derive(Clone, Debug)]
+pub enum SphKernel<R: Real> {
+    CubicSpline(CubicSplineKernel<R>),
+    WendlandC2(WendlandC2Kernel<R>),
+    WendlandC4(WendlandC4Kernel<R>),
+    WendlandC6(WendlandC6Kernel<R>),
+    Poly6(Poly6Kernel<R>),
+    Spiky(SpikyKernel<R>),
+    Gaussian(GaussianKernel<R>),
+}
+
+impl<R: Real> SphKernel<R> {
+    /// Initializes a kernel of the given type with the given compact support radius
+    pub fn new(kernel_type: KernelType, compact_support_radius: R) -> Self {
+        match kernel_type {
+            KernelType::CubicSpline => {
+                Self::CubicSpline(CubicSplineKernel::new(compact_support_radius))
+            }
+            KernelType::WendlandC2 => {
+                Self::WendlandC2(WendlandC2Kernel::new(compact_support_radius))
+            }
+            KernelType::WendlandC4 => {
+                Self::WendlandC4(WendlandC4Kernel::new(compact_support_radius))
+            }
+            KernelType::WendlandC6 => {
+                Self::WendlandC6(WendlandC6Kernel::new(compact_support_radius))
+            }
+            KernelType::Poly6 => Self::Poly6(Poly6Kernel::new(compact_support_radius)),
+            KernelType::Spiky => Self::Spiky(SpikyKernel::new(compact_support_radius)),
+            KernelType::Gaussian => Self::Gaussian(GaussianKernel::new(compact_support_radius)),
+        }
+    }
+
+    /// Returns the type of this kernel
+    pub fn kernel_type(&self) -> KernelType {
+        match self {
+            Self::CubicSpline(_) => KernelType::CubicSpline,
+            Self::WendlandC2(_) => KernelType::WendlandC2,
+            Self::WendlandC4(_) => KernelType::WendlandC4,
+            Self::WendlandC6(_) => KernelType::WendlandC6,
+            Self::Poly6(_) => KernelType::Poly6,
+            Self::Spiky(_) => KernelType::Spiky,
+            Self::Gaussian(_) => KernelType::Gaussian,
+        }
+    }
+}
+
+/// Forwards a call to the kernel wrapped by a [`SphKernel`]
+macro_rules! dispatch_kernel {
+    ($kernel:expr, $k:ident => $call:expr) => {
+        match $kernel {
+            SphKernel::CubicSpline($k) => $call,
+            SphKernel::WendlandC2($k) => $call,
+            SphKernel::WendlandC4($k) => $call,
+            SphKernel::WendlandC6($k) => $call,
+            SphKernel::Poly6($k) => $call,
+            SphKernel::Spiky($k) => $call,
+            SphKernel::Gaussian($k) => $call,
+        }
+    };
+}
+
+impl<R: Real> SymmetricKernel3d<R> for SphKernel<R> {
+    fn compact_support_radius(&self) -> R {
+        dispatch_kernel!(self, k => k.compact_support_radius())
+    }
+
+    #[inline(always)]
+    fn evaluate(&self, r: R) -> R {
+        dispatch_kernel!(self, k => k.evaluate(r))
+    }
+
+    #[inline(always)]
+    fn evaluate_gradient(&self, x: Vector3<R>) -> Vector3<R> {
+        dispatch_kernel!(self, k => k.evaluate_gradient(x))
+    }
+
+    #[inline(always)]
+    fn evaluate_gradient_norm(&self, r: R) -> R {
+        dispatch_kernel!(self, k => k.evaluate_gradient_norm(r))
+    }
+}
+
+#[test]
+fn test_kernels_r_compact_support() {
+    let hs = [0.025, 0.1, 2.0];
+    for &kernel_type in KernelType::all() {
+        for &h in hs.iter() {
+            let kernel = SphKernel::new(kernel_type, h);
+            assert_eq!(kernel.compact_support_radius(), h);
+            assert!(kernel.evaluate(0.0) > 0.0, "{:?}", kernel_type);
+            assert!(kernel.evaluate(0.5 * h) > 0.0, "{:?}", kernel_type);
+            assert_eq!(kernel.evaluate(h), 0.0, "{:?}", kernel_type);
+            assert_eq!(kernel.evaluate(10.0 * h), 0.0, "{:?}", kernel_type);
+            assert_eq!(kernel.evaluate_gradient_norm(h), 0.0, "{:?}", kernel_type);
+        }
+    }
+}
+
+#[test]
+fn test_kernels_r_integral() {
+    let h = 0.1;
+    let n = 40;
+
+    for &kernel_type in KernelType::all() {
+        let kernel = SphKernel::new(kernel_type, h);
+
+        let dr = h / (n as f64);
+        let dvol = dr * dr * dr;
+
+        let mut integral = 0.0;
+        for i in -n..n {
+            for j in -n..n {
+                for k in -n..n {
+                    let r =
+                        (Vector3::new(i as f64, j as f64, k as f64).add_scalar(0.5) * dr).norm();
+                    integral += dvol * kernel.evaluate(r);
+                }
+            }
+        }
+
+        assert!(
+            (integral - 1.0).abs() <= 5e-3,
+            "integral of {:?} kernel is {}",
+            kernel_type,
+            integral
+        );
+    }
+}
+
+#[test]
+fn test_kernels_gradient() {
+    let h = 0.1;
+    let eps = 1e-7;
+
+    for &kernel_type in KernelType::all() {
+        let kernel = SphKernel::new(kernel_type, h);
+        for i in 1..20 {
+            let r = (i as f64) * 0.05 * h;
+            // Compare to central finite differences
+            let fd = (kernel.evaluate(r + eps) - kernel.evaluate(r - eps)) / (2.0 * eps);
+            let gradient_norm = kernel.evaluate_gradient_norm(r);
+            assert!(
+                (fd - gradient_norm).abs() <= 1e-4 * fd.abs().max(1.0),
+                "{:?} at r={}: finite differences {}, gradient {}",
+                kernel_type,
+                r,
+                fd,
+                gradient_norm
+            );
+
+            let x = Vector3::new(1.0, -2.0, 0.5).normalize() * r;
+            let gradient = kernel.evaluate_gradient(x);
+            assert!(
+                (gradient - x.normalize() * gradient_norm).norm()
+                    <= 1e-8 * gradient_norm.abs().max(1.0)
+            );
+        }
+    }
+}
+
+/// Accelerator for efficient evaluation of a precomputed kernel
+///
+/// This structure is used to pre-compute a discrete representation of a kernel function.
 /// In some computations that require many evaluations of the kernel, it is more efficient to evaluate
 /// the kernel using a squared distance to avoid taking the square root.
 /// To produce an appropriate quantization of the kernel for this use case, the compact support
@@ -158,48 +626,72 @@ fn test_cubic_kernel_r_integral() {
 /// `s` by just mapping this radius back to the corresponding segment index `i` followed by a lookup
 /// in the value array, i.e. `k(sqrt(s)) ≈ K[s/dr]` (while taking care of rounding and clamping to the
 /// allowed index range).
-pub struct DiscreteSquaredDistanceCubicKernel<R: Real> {
+#[derive(Clone, Debug)]
+pub struct DiscreteSquaredDistanceKernel<R: Real> {
     /// Precomputed values of the kernel function
     values: Vec<R>,
     /// The radial resolution of the discretization on a quadratic scale
     dr: R,
 }
 
-impl<R: Real> DiscreteSquaredDistanceCubicKernel<R> {
-    /// Precomputes the discrete cubic kernel with compact support radius `h`, the squared radius `h * h` is divided into `n` segments for the quantization
+/// Discrete representation of the cubic spline kernel, see [`DiscreteSquaredDistanceKernel`]
+pub type DiscreteSquaredDistanceCubicKernel<R> = DiscreteSquaredDistanceKernel<R>;
+
+impl<R: Real> DiscreteSquaredDistanceKernel<R> {
+    /// Precomputes the discrete cubic spline kernel with compact support radius `h`, the squared radius `h * h` is divided into `n` segments for the quantization
+    ///
+    /// The kernel values are computed using the floating point type `PR`.
     pub fn new<PR: Real>(n: usize, h: R) -> Self {
-        let mut values = Vec::with_capacity(n);
+        Self::from_kernel_type::<PR>(KernelType::CubicSpline, n, h)
+    }
 
+    /// Precomputes the discrete kernel of the given type with compact support radius `h`, the squared radius `h * h` is divided into `n` segments for the quantization
+    ///
+    /// The kernel values are computed using the floating point type `PR`.
+    pub fn from_kernel_type<PR: Real>(kernel_type: KernelType, n: usize, h: R) -> Self {
         let compact_support: PR = h
             .try_convert()
             .expect("Compact support radius `h` has to fit into kernel pre-computation type `PR`");
-        let compact_support_squared = compact_support * compact_support;
+        let kernel = SphKernel::new(kernel_type, compact_support);
+
+        let discrete_kernel = DiscreteSquaredDistanceKernel::<PR>::from_kernel(n, &kernel);
+        Self {
+            values: discrete_kernel
+                .values
+                .into_iter()
+                .map(|v| {
+                    v.try_convert()
+                        .expect("Kernel value has to fit into target type `R`")
+                })
+                .collect(),
+            dr: discrete_kernel.dr.try_convert().unwrap(),
+        }
+    }
+
+    /// Precomputes the discrete representation of the given kernel, the squared compact support radius is divided into `n` segments for the quantization
+    pub fn from_kernel<K: SymmetricKernel3d<R>>(n: usize, kernel: &K) -> Self {
+        let mut values = Vec::with_capacity(n);
 
-        let kernel = CubicSplineKernel::new(compact_support);
+        let compact_support = kernel.compact_support_radius();
+        let compact_support_squared = compact_support * compact_support;
 
         // Radial width of one discrete kernel value
         let dr = compact_support_squared
-            / PR::from_usize(n)
-                .expect("Number of discrete kernel steps `n` has to fit into kernel pre-computation type `PR`");
+            / R::from_usize(n)
+                .expect("Number of discrete kernel steps `n` has to fit into kernel type `R`");
         // Evaluate the kernel per discrete segment
         for i in 0..n {
-            let i_and_half = PR::from_usize(i).unwrap() + PR::from_f64(0.5).unwrap();
+            let i_and_half = R::from_usize(i).unwrap() + R::from_f64(0.5).unwrap();
             let r_squared = dr * i_and_half;
             let r = r_squared.sqrt();
 
-            let kernel_value = kernel.evaluate(r);
-            values.push(
-                kernel_value
-                    .try_convert()
-                    .expect("Kernel value has to fit into target type `R`"),
-            );
+            values.push(kernel.evaluate(r));
         }
 
-        let dr = dr.try_convert().unwrap();
         Self { values, dr }
     }
 
-    /// Evaluates the precomputed kernel function at the specified squared radius, i.e. returns an approximate cubic kernel value at the radius `sqrt(r_squared)`
+    /// Evaluates the precomputed kernel function at the specified squared radius, i.e. returns an approximate kernel value at the radius `sqrt(r_squared)`
     #[inline(always)]
     pub fn evaluate(&self, r_squared: R) -> R {
         let normalized = (r_squared / self.dr).round();
@@ -241,3 +733,38 @@ fn test_discrete_kernel() {
         }
     }
 }
+
+#[test]
+fn test_discrete_kernels() {
+    let n = 10000;
+    let h = 0.025;
+
+    for &kernel_type in KernelType::all() {
+        let discrete_kernel =
+            DiscreteSquaredDistanceKernel::<f32>::from_kernel_type::<f64>(kernel_type, n, h as f32);
+        let kernel = SphKernel::new(kernel_type, h);
+
+        // Test the pre-computed values using a linear stepping
+        let dr = h / (n as f64);
+        for i in 0..n {
+            let r = (i as f64) * dr;
+
+            let discrete = discrete_kernel.evaluate((r * r) as f32) as f64;
+            let continuous = kernel.evaluate(r);
+
+            let diff = (discrete - continuous).abs();
+            let rel_diff = diff / continuous;
+            assert!(
+                rel_diff <= 5e-2 || diff <= 1e-1,
+                "{:?} at r={}, r/h={}, discrete: {}, continuous: {}, diff: {}, rel_diff: {}",
+                kernel_type,
+                r,
+                r / h,
+                discrete,
+                continuous,
+                diff,
+                rel_diff
+            );
+        }
+    }
+}
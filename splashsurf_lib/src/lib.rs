@@ -4,6 +4,9 @@
 //! Library for surface reconstruction of SPH particle data using marching cubes.
 //!
 //! Entry points are the [`reconstruct_surface`] or [`reconstruct_surface_inplace`] functions.
+//! Particles with varying masses or kernel radii can be reconstructed using [`reconstruct_surface_with_properties`].
+//! Multiphase particle data with one surface per phase can be reconstructed using [`multiphase::reconstruct_multiphase_surfaces`].
+//! Solid boundaries (e.g. tank walls) can be taken into account by the reconstruction, see the [`boundary`] module.
 //!
 //! ## Feature flags
 //! The following features are all non-default features to reduce the amount of additional dependencies.
@@ -34,11 +37,16 @@ use thiserror::Error as ThisError;
 pub use vtkio;
 
 pub use crate::aabb::{Aabb2d, Aabb3d, AxisAlignedBoundingBox};
+pub use crate::adaptive::AdaptiveResolutionParameters;
+pub use crate::anisotropy::AnisotropyParameters;
+pub use crate::boundary::{BoundaryParameters, BoundaryParticles};
 pub use crate::density_map::DensityMap;
+pub use crate::kernel::KernelType;
 pub use crate::octree::SubdivisionCriterion;
 pub use crate::traits::{Index, Real, ThreadSafe};
 pub use crate::uniform_grid::UniformGrid;
 
+use crate::boundary::{BoundaryConditions, BoundaryDistanceField, MirroredParticles};
 use crate::density_map::DensityMapError;
 use crate::marching_cubes::MarchingCubesError;
 use crate::mesh::TriMesh3d;
@@ -53,8 +61,12 @@ pub mod profiling;
 pub mod profiling_macro;
 
 mod aabb;
+pub mod adaptive;
+pub mod anisotropy;
+pub mod boundary;
 pub(crate) mod dense_subdomains;
 pub mod density_map;
+pub mod diffuse_particles;
 pub mod generic_tree;
 #[cfg(feature = "io")]
 #[cfg_attr(doc_cfg, doc(cfg(feature = "io")))]
@@ -62,11 +74,18 @@ pub mod io;
 pub mod kernel;
 pub mod marching_cubes;
 pub mod mesh;
+pub mod mesh_quality;
+pub mod multiphase;
 pub mod neighborhood_search;
 pub mod octree;
+pub mod particle_classification;
+pub mod postprocessing;
 pub mod reconstruction;
 mod reconstruction_octree;
 pub mod sph_interpolation;
+pub mod surface_nets;
+pub mod surface_tracking;
+pub mod temporal;
 pub mod topology;
 mod traits;
 pub mod uniform_grid;
@@ -169,6 +188,22 @@ pub enum ParticleDensityComputationStrategy {
     IndependentSubdomains,
 }
 
+/// Available methods for the extraction of the iso-surface mesh from the density values on the background grid
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum IsoSurfaceExtraction {
+    /// Marching cubes: places vertices on the edges of the background grid that cross the iso-surface.
+    ///
+    /// Supported by all reconstruction approaches, but generates many small and degenerate ("sliver") triangles.
+    #[default]
+    MarchingCubes,
+    /// Surface nets: places one vertex per cell intersected by the iso-surface and connects the vertices of adjacent cells.
+    ///
+    /// Results in considerably better triangle quality than marching cubes (see [`surface_nets`]).
+    /// Supported by the global reconstruction and the reconstruction with a subdomain grid
+    /// (see [`Parameters::subdomain_num_cubes_per_dim`]), but not by the octree-based spatial decomposition.
+    SurfaceNets,
+}
+
 impl<R: Real> SpatialDecompositionParameters<R> {
     /// Tries to convert the parameters from one [`Real`] type to another [`Real`] type, returns `None` if conversion fails
     pub fn try_convert<T: Real>(&self) -> Option<SpatialDecompositionParameters<T>> {
@@ -207,6 +242,26 @@ pub struct Parameters<R: Real> {
     /// Parameters for the spatial decomposition (octree subdivision) of the particles.
     /// If not provided, no octree is generated and a global approach is used instead.
     pub spatial_decomposition: Option<SpatialDecompositionParameters<R>>,
+    /// Method used to extract the iso-surface mesh from the density values on the background grid
+    pub iso_surface_extraction: IsoSurfaceExtraction,
+    /// Parameters for the adaptive refinement of the background grid (see [`adaptive`]), if not provided the background grid is not refined.
+    /// Only supported by the global reconstruction with surface nets and isotropic kernels.
+    pub adaptive_resolution: Option<AdaptiveResolutionParameters<R>>,
+    /// SPH kernel function used for the computation of the particle densities and the density field
+    pub kernel: KernelType,
+    /// Parameters for anisotropic kernels (see [`anisotropy`]), if not provided isotropic kernels are used.
+    /// Supported by the global reconstruction and the reconstruction with a subdomain grid, but not by the octree-based spatial decomposition.
+    pub anisotropy: Option<AnisotropyParameters<R>>,
+    /// Parameters for the handling of a solid boundary (see [`boundary`]), if not provided the fluid is reconstructed without boundary.
+    /// Supported by the global reconstruction and the reconstruction with a subdomain grid, but not by the octree-based spatial decomposition.
+    pub boundary: Option<BoundaryParameters<R>>,
+    /// Boundary particles with volumes that contribute to the densities of the fluid close to walls (see [`boundary`]).
+    /// No surface is generated around the boundary particles on their own. Not supported by the octree-based spatial decomposition.
+    pub boundary_particles: Option<BoundaryParticles<R>>,
+    /// Whether to close the surface with caps on the outer faces of the reconstruction domain where it is cut by the
+    /// domain boundary (e.g. if the `domain_aabb` is smaller than the extent of the fluid), resulting in a closed mesh.
+    /// Only supported by the global reconstruction with marching cubes, i.e. no subdomain grid or octree-based spatial decomposition.
+    pub cap_domain_boundary: bool,
 }
 
 impl<R: Real> Parameters<R> {
@@ -222,10 +277,121 @@ impl<R: Real> Parameters<R> {
             enable_multi_threading: self.enable_multi_threading,
             subdomain_num_cubes_per_dim: self.subdomain_num_cubes_per_dim,
             spatial_decomposition: map_option!(&self.spatial_decomposition, sd => sd.try_convert()?),
+            iso_surface_extraction: self.iso_surface_extraction,
+            adaptive_resolution: map_option!(&self.adaptive_resolution, a => a.try_convert()?),
+            kernel: self.kernel,
+            anisotropy: map_option!(&self.anisotropy, a => a.try_convert()?),
+            boundary: map_option!(&self.boundary, b => b.try_convert()?),
+            boundary_particles: map_option!(&self.boundary_particles, b => b.try_convert()?),
+            cap_domain_boundary: self.cap_domain_boundary,
         })
     }
 }
 
+/// Optional per particle properties that override the uniform values derived from the [`Parameters`]
+///
+/// By default, all particles have the same mass (derived from [`Parameters::particle_radius`] and
+/// [`Parameters::rest_density`]) and the same [`Parameters::compact_support_radius`]. For simulations
+/// with varying particle sizes (e.g. multi-resolution or multiphase simulations) the masses and/or
+/// the compact support radii can be provided per particle instead. Per particle volumes can be
+/// converted to masses by multiplying them with the rest density.
+///
+/// The density of a particle and the density field are evaluated in "scatter" formulation, i.e.
+/// every particle `j` contributes with its own mass `m_j` and kernel radius `h_j`.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct ParticleProperties<'a, R: Real> {
+    masses: Option<&'a [R]>,
+    compact_support_radii: Option<&'a [R]>,
+    max_compact_support_radius: Option<R>,
+}
+
+impl<'a, R: Real> ParticleProperties<'a, R> {
+    /// Returns properties without any per particle values, i.e. all particles use the uniform values from the [`Parameters`]
+    pub fn uniform() -> Self {
+        Self::default()
+    }
+
+    /// Sets the mass per particle
+    pub fn with_masses(mut self, masses: &'a [R]) -> Self {
+        self.masses = Some(masses);
+        self
+    }
+
+    /// Sets the kernel compact support radius per particle
+    pub fn with_compact_support_radii(mut self, compact_support_radii: &'a [R]) -> Self {
+        self.compact_support_radii = Some(compact_support_radii);
+        self.max_compact_support_radius = compact_support_radii.iter().copied().reduce(R::max);
+        self
+    }
+
+    /// Returns the per particle masses if they were provided
+    pub fn masses(&self) -> Option<&'a [R]> {
+        self.masses
+    }
+
+    /// Returns the per particle compact support radii if they were provided
+    pub fn compact_support_radii(&self) -> Option<&'a [R]> {
+        self.compact_support_radii
+    }
+
+    /// Returns whether no per particle values were provided
+    pub fn is_uniform(&self) -> bool {
+        self.masses.is_none() && self.compact_support_radii.is_none()
+    }
+
+    /// Returns the mass of the given particle or the uniform mass if no per particle masses were provided
+    #[inline(always)]
+    pub fn mass(&self, particle_index: usize, uniform_mass: R) -> R {
+        self.masses.map_or(uniform_mass, |m| m[particle_index])
+    }
+
+    /// Returns the compact support radius of the given particle or the uniform radius if no per particle radii were provided
+    #[inline(always)]
+    pub fn compact_support_radius(
+        &self,
+        particle_index: usize,
+        uniform_compact_support_radius: R,
+    ) -> R {
+        self.compact_support_radii
+            .map_or(uniform_compact_support_radius, |h| h[particle_index])
+    }
+
+    /// Returns the largest compact support radius of all particles or the uniform radius if no per particle radii were provided
+    pub fn max_compact_support_radius(&self, uniform_compact_support_radius: R) -> R {
+        self.max_compact_support_radius
+            .unwrap_or(uniform_compact_support_radius)
+    }
+
+    /// Checks that the number of values matches the number of particles and that all values are positive
+    pub fn validate(&self, num_particles: usize) -> Result<(), anyhow::Error> {
+        let check = |values: Option<&[R]>, name: &str| -> Result<(), anyhow::Error> {
+            if let Some(values) = values {
+                if values.len() != num_particles {
+                    return Err(anyhow::anyhow!(
+                        "number of per particle {} ({}) does not match the number of particles ({})",
+                        name,
+                        values.len(),
+                        num_particles
+                    ));
+                }
+                if let Some(i) = values.iter().position(|&v| v <= R::zero()) {
+                    return Err(anyhow::anyhow!(
+                        "per particle {} have to be positive, found {} for particle {}",
+                        name,
+                        values[i],
+                        i
+                    ));
+                }
+            }
+            Ok(())
+        };
+
+        check(self.masses, "masses")?;
+        check(self.compact_support_radii, "compact support radii")?;
+        Ok(())
+    }
+}
+
 /// Result data returned when the surface reconstruction was successful
 #[derive(Clone, Debug)]
 pub struct SurfaceReconstruction<I: Index, R: Real> {
@@ -349,15 +515,137 @@ pub fn reconstruct_surface_inplace<'a, I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
     parameters: &Parameters<R>,
     output_surface: &'a mut SurfaceReconstruction<I, R>,
+) -> Result<(), ReconstructionError<I, R>> {
+    reconstruct_surface_with_properties_inplace(
+        particle_positions,
+        ParticleProperties::uniform(),
+        parameters,
+        output_surface,
+    )
+}
+
+/// Performs a surface reconstruction of particles with per particle masses and/or compact support radii
+///
+/// See [`ParticleProperties`] for details. Per particle properties are supported by the global reconstruction
+/// and the reconstruction with a subdomain grid, but not by the octree-based spatial decomposition.
+#[inline(never)]
+pub fn reconstruct_surface_with_properties<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    particle_properties: ParticleProperties<R>,
+    parameters: &Parameters<R>,
+) -> Result<SurfaceReconstruction<I, R>, ReconstructionError<I, R>> {
+    let mut surface = SurfaceReconstruction::default();
+    reconstruct_surface_with_properties_inplace(
+        particle_positions,
+        particle_properties,
+        parameters,
+        &mut surface,
+    )?;
+    Ok(surface)
+}
+
+/// Performs a surface reconstruction of particles with per particle masses and/or compact support radii, inplace
+pub fn reconstruct_surface_with_properties_inplace<'a, I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    particle_properties: ParticleProperties<R>,
+    parameters: &Parameters<R>,
+    output_surface: &'a mut SurfaceReconstruction<I, R>,
 ) -> Result<(), ReconstructionError<I, R>> {
     // Clear the existing mesh
     output_surface.mesh.clear();
 
+    particle_properties.validate(particle_positions.len())?;
+
+    if parameters.boundary.is_some()
+        && parameters.subdomain_num_cubes_per_dim.is_none()
+        && parameters.spatial_decomposition.is_some()
+    {
+        return Err(anyhow::anyhow!(
+            "solid boundaries are not supported with octree-based spatial decomposition"
+        )
+        .into());
+    }
+    if let Some(boundary_particles) = parameters.boundary_particles.as_ref() {
+        if parameters.subdomain_num_cubes_per_dim.is_none()
+            && parameters.spatial_decomposition.is_some()
+        {
+            return Err(anyhow::anyhow!(
+                "boundary particles are not supported with octree-based spatial decomposition"
+            )
+            .into());
+        }
+        boundary_particles.validate()?;
+    }
+    if parameters.cap_domain_boundary
+        && (parameters.subdomain_num_cubes_per_dim.is_some()
+            || parameters.spatial_decomposition.is_some()
+            || parameters.iso_surface_extraction != IsoSurfaceExtraction::MarchingCubes)
+    {
+        return Err(anyhow::anyhow!(
+            "caps at the domain boundary are only supported by the global reconstruction with marching cubes"
+        )
+        .into());
+    }
+
+    if let Some(adaptive_resolution) = parameters.adaptive_resolution.as_ref() {
+        if parameters.subdomain_num_cubes_per_dim.is_some()
+            || parameters.spatial_decomposition.is_some()
+            || parameters.iso_surface_extraction != IsoSurfaceExtraction::SurfaceNets
+        {
+            return Err(anyhow::anyhow!(
+                "adaptive resolution is only supported by the global reconstruction with surface nets"
+            )
+            .into());
+        }
+        if parameters.anisotropy.is_some()
+            || parameters.boundary.is_some()
+            || parameters.boundary_particles.is_some()
+        {
+            return Err(anyhow::anyhow!(
+                "adaptive resolution is not supported with anisotropic kernels or solid boundaries"
+            )
+            .into());
+        }
+        adaptive_resolution.validate()?;
+    }
+
+    // Prepare the signed distance field of the solid boundary and mirror the particles close to it
+    let boundary = parameters.boundary.as_ref().map(|boundary| {
+        BoundaryDistanceField::new(
+            boundary,
+            particle_properties.max_compact_support_radius(parameters.compact_support_radius),
+            parameters.cube_size,
+            parameters.iso_surface_threshold,
+        )
+    });
+    let mirrored_particles = parameters
+        .boundary
+        .as_ref()
+        .zip(boundary.as_ref())
+        .filter(|(parameters, _)| parameters.handling.extends())
+        .and_then(|(_, boundary)| {
+            MirroredParticles::new(
+                boundary,
+                particle_positions,
+                particle_properties,
+                parameters.enable_multi_threading,
+            )
+        });
+    // The mirrored particles are part of the input from now on, only the density modifications remain
+    let boundary = BoundaryConditions::new(parameters, boundary);
+    let num_particles = particle_positions.len();
+    let (particle_positions, particle_properties) = match &mirrored_particles {
+        Some(mirrored) => (mirrored.positions.as_slice(), mirrored.properties()),
+        None => (particle_positions, particle_properties),
+    };
+
+    let max_kernel_radius = max_kernel_radius(parameters, particle_properties);
+
     // Initialize grid for the reconstruction
     output_surface.grid = grid_for_reconstruction(
         particle_positions,
         parameters.particle_radius,
-        parameters.compact_support_radius,
+        max_kernel_radius,
         parameters.cube_size,
         parameters.domain_aabb.as_ref(),
         parameters.enable_multi_threading,
@@ -368,10 +656,32 @@ pub fn reconstruct_surface_inplace<'a, I: Index, R: Real>(
     if parameters.subdomain_num_cubes_per_dim.is_some() {
         reconstruction::reconstruct_surface_subdomain_grid::<I, R>(
             particle_positions,
+            particle_properties,
+            boundary,
             parameters,
             output_surface,
         )?;
     } else if parameters.spatial_decomposition.is_some() {
+        if parameters.iso_surface_extraction != IsoSurfaceExtraction::MarchingCubes {
+            return Err(anyhow::anyhow!(
+                "the iso-surface extraction method {:?} is not supported with octree-based spatial decomposition",
+                parameters.iso_surface_extraction
+            )
+            .into());
+        }
+        if parameters.anisotropy.is_some() {
+            return Err(anyhow::anyhow!(
+                "anisotropic kernels are not supported with octree-based spatial decomposition"
+            )
+            .into());
+        }
+        if !particle_properties.is_uniform() {
+            return Err(anyhow::anyhow!(
+                "per particle masses and compact support radii are not supported with octree-based spatial decomposition"
+            )
+            .into());
+        }
+
         reconstruction_octree::reconstruct_surface_domain_decomposition(
             particle_positions,
             parameters,
@@ -380,14 +690,53 @@ pub fn reconstruct_surface_inplace<'a, I: Index, R: Real>(
     } else {
         reconstruction_octree::reconstruct_surface_global(
             particle_positions,
+            particle_properties,
+            boundary.as_ref(),
             parameters,
             output_surface,
         )?;
     }
 
+    // Only return the densities of the original particles
+    if let Some(particle_densities) = output_surface.particle_densities.as_mut() {
+        particle_densities.truncate(num_particles);
+    }
+
     Ok(())
 }
 
+/// Returns the maximum distance from a particle that can be affected by its kernel
+pub(crate) fn max_kernel_radius<R: Real>(
+    parameters: &Parameters<R>,
+    particle_properties: ParticleProperties<R>,
+) -> R {
+    // Anisotropic kernels are shifted and may be stretched beyond the compact support radius
+    particle_properties.max_compact_support_radius(parameters.compact_support_radius)
+        * parameters
+            .anisotropy
+            .as_ref()
+            .map(|a| a.max_stretch() + a.max_center_offset())
+            .unwrap_or_else(R::one)
+}
+
+/// Returns the domain for the neighborhood search of the particles, i.e. the AABB of the grid extended to all particles outside of a user specified domain
+pub(crate) fn neighborhood_search_domain<I: Index, R: Real>(
+    grid: &UniformGrid<I, R>,
+    particle_positions: &[Vector3<R>],
+    parameters: &Parameters<R>,
+) -> Aabb3d<R> {
+    let mut domain = grid.aabb().clone();
+    if parameters.domain_aabb.is_some() && !particle_positions.is_empty() {
+        let particles_aabb = if parameters.enable_multi_threading {
+            Aabb3d::par_from_points(particle_positions)
+        } else {
+            Aabb3d::from_points(particle_positions)
+        };
+        domain.join(&particles_aabb);
+    }
+    domain
+}
+
 /// Constructs the background grid for marching cubes based on the parameters supplied to the surface reconstruction
 pub fn grid_for_reconstruction<I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
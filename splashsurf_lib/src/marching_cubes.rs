@@ -1,5 +1,6 @@
 //! Triangulation of [`DensityMap`](crate::density_map::DensityMap)s using marching cubes
 
+use crate::marching_cubes::boundary_caps::triangulate_grid_boundary_caps;
 use crate::marching_cubes::narrow_band_extraction::{
     construct_mc_input, construct_mc_input_with_stitching_data,
 };
@@ -12,6 +13,7 @@ use crate::{new_map, profile, DensityMap, Index, MapType, Real, UniformGrid};
 use nalgebra::Vector3;
 use thiserror::Error as ThisError;
 
+mod boundary_caps;
 pub mod marching_cubes_lut;
 mod narrow_band_extraction;
 mod stitching;
@@ -156,6 +158,38 @@ pub fn triangulate_density_map_append<I: Index, R: Real>(
     Ok(())
 }
 
+/// Performs a marching cubes triangulation of a density map on the given background grid and closes the surface with caps on the outer faces of the grid, appends triangles to the given mesh
+///
+/// Without the caps, the surface is open where the fluid is cut by the boundary of the grid, e.g. if the grid
+/// is smaller than the extent of the particles. The caps are placed on the parts of the outer faces of the grid
+/// that are above the iso-surface threshold and share their vertices with the adjacent marching cubes triangles.
+pub fn triangulate_density_map_capped_append<I: Index, R: Real>(
+    grid: &UniformGrid<I, R>,
+    density_map: &DensityMap<I, R>,
+    iso_surface_threshold: R,
+    mesh: &mut TriMesh3d<R>,
+) -> Result<(), MarchingCubesError> {
+    profile!("triangulate_density_map_capped_append");
+
+    let subdomain = DummySubdomain::new(grid);
+    let marching_cubes_data = construct_mc_input(
+        &subdomain,
+        density_map,
+        iso_surface_threshold,
+        &mut mesh.vertices,
+    );
+
+    triangulate_grid_boundary_caps(
+        grid,
+        density_map,
+        iso_surface_threshold,
+        &marching_cubes_data,
+        mesh,
+    )?;
+    triangulate(marching_cubes_data, mesh)?;
+    Ok(())
+}
+
 /// Performs triangulation of the given density map to a surface patch
 pub(crate) fn triangulate_density_map_to_surface_patch<I: Index, R: Real>(
     subdomain: &OwningSubdomainGrid<I, R>,
@@ -0,0 +1,297 @@
+//! Cap polygons that close a marching cubes surface where it is cut by the outer faces of the grid
+
+use crate::marching_cubes::marching_cubes_lut::marching_cubes_triangulation_iter;
+use crate::marching_cubes::triangulation::TriangulationError;
+use crate::marching_cubes::MarchingCubesInput;
+use crate::mesh::TriMesh3d;
+use crate::topology::DirectedAxis;
+use crate::uniform_grid::{CellIndex, GridBoundaryFaceFlags, UniformGrid};
+use crate::{new_map, profile, DensityMap, Index, MapType, Real};
+use anyhow::anyhow;
+
+/// The local points of each face of a cell in CCW order when looking at the face from outside of the cell, indexed by [`DirectedAxis::to_usize`]
+#[rustfmt::skip]
+const FACE_LOCAL_POINTS: [[usize; 4]; 6] = [
+    [0, 4, 7, 3], // x-
+    [0, 1, 5, 4], // y-
+    [0, 3, 2, 1], // z-
+    [1, 2, 6, 5], // x+
+    [3, 7, 6, 2], // y+
+    [4, 5, 6, 7], // z+
+];
+
+/// The local edges of each face of a cell, the `i`-th edge connects the `i`-th and `(i+1)`-th point of the face in [`FACE_LOCAL_POINTS`]
+#[rustfmt::skip]
+const FACE_LOCAL_EDGES: [[usize; 4]; 6] = [
+    [8,  7, 11, 3], // x-
+    [0,  9,  4, 8], // y-
+    [3,  2,  1, 0], // z-
+    [1, 10,  5, 9], // x+
+    [11, 6, 10, 2], // y+
+    [4,  5,  6, 7], // z+
+];
+
+/// Appends cap polygons on the outer faces of the grid to the mesh that close the marching cubes surface of the density map
+///
+/// The cap of a boundary face of a cell covers the part of the face that is above the iso-surface threshold. It
+/// reuses the iso-surface vertices of the cell data on the edges of the face and adds vertices at the grid points
+/// above the threshold. The outline of the caps on the faces follows the marching cubes triangulation of the
+/// respective cells, so the resulting mesh is closed if the marching cubes input covers the entire grid.
+pub(crate) fn triangulate_grid_boundary_caps<I: Index, R: Real>(
+    grid: &UniformGrid<I, R>,
+    density_map: &DensityMap<I, R>,
+    iso_surface_threshold: R,
+    input: &MarchingCubesInput<I>,
+    mesh: &mut TriMesh3d<R>,
+) -> Result<(), TriangulationError> {
+    profile!("triangulate_grid_boundary_caps");
+
+    let cells_per_dim = grid.cells_per_dim();
+    // Vertices of the caps located at grid points, indexed by the flat point index
+    let mut point_vertices: MapType<I, usize> = new_map();
+    let mut polygon = Vec::with_capacity(8);
+
+    for face in DirectedAxis::all_possible() {
+        let dim = face.axis.dim();
+        let [axis_u, axis_v] = face.axis.orthogonal_axes();
+        let layer = if face.direction.is_positive() {
+            cells_per_dim[dim] - I::one()
+        } else {
+            I::zero()
+        };
+
+        // Loop over the layer of cells touching this face of the grid
+        for u in I::range(I::zero(), cells_per_dim[axis_u.dim()]).iter() {
+            for v in I::range(I::zero(), cells_per_dim[axis_v.dim()]).iter() {
+                let mut cell_ijk = [I::zero(); 3];
+                cell_ijk[dim] = layer;
+                cell_ijk[axis_u.dim()] = u;
+                cell_ijk[axis_v.dim()] = v;
+                let cell = grid
+                    .get_cell(cell_ijk)
+                    .expect("boundary cell has to be part of the grid");
+
+                // Cells at edges and corners of the grid touch several faces, process them only once with all of their faces
+                let boundary_faces = GridBoundaryFaceFlags::classify_cell(grid, &cell);
+                if boundary_faces.iter_individual().next() != Some(*face) {
+                    continue;
+                }
+
+                let vertices_inside = [0, 1, 2, 3, 4, 5, 6, 7].map(|local_point| {
+                    let point = cell
+                        .global_point_index_of(local_point)
+                        .expect("cell corner has to be part of the grid");
+                    density_map
+                        .get(grid.flatten_point_index(&point))
+                        .is_some_and(|value| value > iso_surface_threshold)
+                });
+
+                for cell_face in boundary_faces.iter_individual() {
+                    let face_points = &FACE_LOCAL_POINTS[cell_face.to_usize()];
+                    let face_edges = &FACE_LOCAL_EDGES[cell_face.to_usize()];
+                    let inside = face_points.map(|local_point| vertices_inside[local_point]);
+
+                    if inside.iter().all(|&is_inside| !is_inside) {
+                        continue;
+                    }
+
+                    if inside.iter().all(|&is_inside| is_inside) {
+                        polygon.clear();
+                        for &local_point in face_points {
+                            polygon.push(cap_point_vertex(
+                                grid,
+                                &cell,
+                                local_point,
+                                &mut point_vertices,
+                                &mut mesh.vertices,
+                            ));
+                        }
+                        triangulate_polygon(&polygon, &mut mesh.triangles);
+                        continue;
+                    }
+
+                    let flat_cell_index = grid.flatten_cell_index(&cell);
+                    let cell_data = input.cell_data.get(&flat_cell_index).ok_or_else(|| {
+                        anyhow!("Missing cell data for boundary cell {:?} that is intersected by the iso-surface. This is a bug.", cell.index())
+                    })?;
+                    let edge_vertex = |face_edge: usize| {
+                        let local_edge = face_edges[face_edge];
+                        cell_data.iso_surface_vertices[local_edge].ok_or_else(|| {
+                            anyhow!(
+                                "Missing iso surface vertex at edge {} of boundary cell {:?}. This is a bug.",
+                                local_edge,
+                                cell.index()
+                            )
+                        })
+                    };
+
+                    // Edges of the face where the boundary of the face enters (outside to inside) and exits (inside to outside) the iso-surface
+                    let is_entry = |i: usize| !inside[i] && inside[(i + 1) % 4];
+                    let is_exit = |i: usize| inside[i] && !inside[(i + 1) % 4];
+                    let num_crossings = (0..4).filter(|&i| is_exit(i)).count() * 2;
+
+                    // For ambiguous faces, the connection of exits to entries is taken from the triangulation of the cell
+                    let segments = if num_crossings == 4 {
+                        face_segments(&vertices_inside, face_edges)
+                    } else {
+                        Vec::new()
+                    };
+                    let next_entry = |exit: usize| -> Result<usize, anyhow::Error> {
+                        if num_crossings == 2 {
+                            return Ok((0..4).find(|&i| is_entry(i)).unwrap());
+                        }
+                        segments
+                            .iter()
+                            .find_map(|&(a, b)| {
+                                if a == exit {
+                                    Some(b)
+                                } else if b == exit {
+                                    Some(a)
+                                } else {
+                                    None
+                                }
+                            })
+                            .filter(|&entry| is_entry(entry))
+                            .ok_or_else(|| {
+                                anyhow!(
+                                    "The triangulation of boundary cell {:?} is inconsistent with its face. This is a bug.",
+                                    cell.index()
+                                )
+                            })
+                    };
+
+                    // Walk along the boundary of the face in CCW order and follow the iso-surface from exits to entries
+                    let mut visited = [false; 4];
+                    for start in (0..4).filter(|&i| is_entry(i)) {
+                        if visited[start] {
+                            continue;
+                        }
+
+                        polygon.clear();
+                        let mut entry = start;
+                        loop {
+                            visited[entry] = true;
+                            polygon.push(edge_vertex(entry)?);
+
+                            let mut i = (entry + 1) % 4;
+                            while inside[i] {
+                                polygon.push(cap_point_vertex(
+                                    grid,
+                                    &cell,
+                                    face_points[i],
+                                    &mut point_vertices,
+                                    &mut mesh.vertices,
+                                ));
+                                i = (i + 1) % 4;
+                            }
+
+                            let exit = (i + 3) % 4;
+                            polygon.push(edge_vertex(exit)?);
+
+                            entry = next_entry(exit)?;
+                            if entry == start {
+                                break;
+                            }
+                        }
+
+                        triangulate_polygon(&polygon, &mut mesh.triangles);
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the vertex of a cap at a local point of the cell, the vertex is added to the mesh if it does not exist yet
+fn cap_point_vertex<I: Index, R: Real>(
+    grid: &UniformGrid<I, R>,
+    cell: &CellIndex<I>,
+    local_point: usize,
+    point_vertices: &mut MapType<I, usize>,
+    vertices: &mut Vec<nalgebra::Vector3<R>>,
+) -> usize {
+    let point = cell
+        .global_point_index_of(local_point)
+        .expect("cell corner has to be part of the grid");
+    *point_vertices
+        .entry(grid.flatten_point_index(&point))
+        .or_insert_with(|| {
+            vertices.push(grid.point_coordinates(&point));
+            vertices.len() - 1
+        })
+}
+
+/// Returns the segments of the outline of the marching cubes triangulation of a cell on one of its faces, given by pairs of indices into the edges of the face
+fn face_segments(vertices_inside: &[bool; 8], face_edges: &[usize; 4]) -> Vec<(usize, usize)> {
+    let face_edge = |local_edge: i32| {
+        face_edges
+            .iter()
+            .position(|&face_edge| face_edge as i32 == local_edge)
+    };
+
+    // The outline of the triangulation consists of the triangle edges that are not shared with another triangle of the cell
+    let mut edges: Vec<((i32, i32), usize)> = Vec::with_capacity(15);
+    for triangle in marching_cubes_triangulation_iter(vertices_inside) {
+        for k in 0..3 {
+            let (a, b) = (triangle[k], triangle[(k + 1) % 3]);
+            let key = (a.min(b), a.max(b));
+            match edges.iter_mut().find(|(edge, _)| *edge == key) {
+                Some((_, count)) => *count += 1,
+                None => edges.push((key, 1)),
+            }
+        }
+    }
+
+    edges
+        .into_iter()
+        .filter(|&(_, count)| count == 1)
+        .filter_map(|((a, b), _)| Some((face_edge(a)?, face_edge(b)?)))
+        .collect()
+}
+
+/// Appends a fan triangulation of the convex polygon to the triangles
+fn triangulate_polygon(polygon: &[usize], triangles: &mut Vec<[usize; 3]>) {
+    for k in 1..polygon.len().saturating_sub(1) {
+        triangles.push([polygon[0], polygon[k], polygon[k + 1]]);
+    }
+}
+
+#[test]
+fn test_face_local_points_and_edges_consistency() {
+    let grid = UniformGrid::<i32, f64>::new(&nalgebra::Vector3::zeros(), &[1, 1, 1], 1.0).unwrap();
+    let cell = grid.get_cell([0, 0, 0]).unwrap();
+
+    for face in DirectedAxis::all_possible() {
+        let points = FACE_LOCAL_POINTS[face.to_usize()];
+        let edges = FACE_LOCAL_EDGES[face.to_usize()];
+        let coords =
+            points.map(|p| grid.point_coordinates(&cell.global_point_index_of(p).unwrap()));
+
+        // All points lie on the face
+        let face_coord = if face.direction.is_positive() {
+            1.0
+        } else {
+            0.0
+        };
+        assert!(coords.iter().all(|c| c[face.axis.dim()] == face_coord));
+
+        // The points are in CCW order seen from outside of the cell
+        let normal = (coords[1] - coords[0]).cross(&(coords[2] - coords[1]));
+        assert_eq!(normal[face.axis.dim()], face.direction.apply_step(0.0, 1.0));
+
+        // The edges connect successive points
+        for i in 0..4 {
+            let edge = cell.global_edge_index_of(edges[i]).unwrap();
+            let mut edge_points = [*edge.origin(), edge.target()];
+            let mut face_points = [
+                cell.global_point_index_of(points[i]).unwrap(),
+                cell.global_point_index_of(points[(i + 1) % 4]).unwrap(),
+            ];
+            edge_points.sort_by_key(|p| *p.index());
+            face_points.sort_by_key(|p| *p.index());
+            assert_eq!(edge_points, face_points);
+        }
+    }
+}
@@ -1,8 +1,10 @@
 //! Basic mesh types used by the library and implementation of VTK export
 //!
-//! This modules provides three basic types of meshes embedded in three dimensional spaces used
+//! This modules provides the basic types of meshes embedded in three dimensional spaces used
 //! by the library:
 //!  - [`TriMesh3d`]
+//!  - [`QuadMesh3d`]
+//!  - [`MixedTriQuadMesh3d`]
 //!  - [`HexMesh3d`]
 //!  - [`PointCloud3d`]
 //!
@@ -18,7 +20,7 @@
 
 use crate::{new_map, Real};
 use bytemuck_derive::{Pod, Zeroable};
-use nalgebra::{Unit, Vector3};
+use nalgebra::{Matrix3, Unit, Vector3};
 use rayon::prelude::*;
 use std::cell::RefCell;
 use std::fmt::Debug;
@@ -42,9 +44,16 @@ pub struct MeshAttribute<R: Real> {
 /// One value in the data-set corresponds is associated to a point or cell of the mesh.
 #[derive(Clone, Debug)]
 pub enum AttributeData<R: Real> {
+    /// Unsigned integer scalars (e.g. particle ids, phase ids or object ids)
     ScalarU64(Vec<u64>),
+    /// Real valued scalars
     ScalarReal(Vec<R>),
+    /// Real valued 3D vectors
     Vector3Real(Vec<Vector3<R>>),
+    /// Real valued vectors with the given number of components, the components of all vectors are stored contiguously
+    VectorReal(usize, Vec<R>),
+    /// Real valued 3x3 tensors (e.g. stress or deformation gradient tensors)
+    Matrix3Real(Vec<Matrix3<R>>),
 }
 
 /// A triangle (surface) mesh in 3D
@@ -56,6 +65,24 @@ pub struct TriMesh3d<R: Real> {
     pub triangles: Vec<[usize; 3]>,
 }
 
+/// A quad (surface) mesh in 3D
+#[derive(Clone, Debug, Default)]
+pub struct QuadMesh3d<R: Real> {
+    /// Coordinates of all vertices of the mesh
+    pub vertices: Vec<Vector3<R>>,
+    /// The quads of the mesh identified by their vertex indices (in counter-clockwise order)
+    pub quads: Vec<[usize; 4]>,
+}
+
+/// A surface mesh in 3D consisting of triangles and quads
+#[derive(Clone, Debug, Default)]
+pub struct MixedTriQuadMesh3d<R: Real> {
+    /// Coordinates of all vertices of the mesh
+    pub vertices: Vec<Vector3<R>>,
+    /// The triangle and quad cells of the mesh identified by their vertex indices
+    pub cells: Vec<TriangleOrQuadCell>,
+}
+
 /// A hexahedral (volumetric) mesh in 3D
 #[derive(Clone, Debug, Default)]
 pub struct HexMesh3d<R: Real> {
@@ -98,8 +125,12 @@ pub trait Mesh3d<R: Real> {
 
 /// Basic interface for mesh cells consisting of a collection of vertex indices
 pub trait CellConnectivity {
-    /// Returns the number of vertices per cell
+    /// Returns the number of vertices per cell (the maximum number of vertices for cell types with a varying number of vertices)
     fn num_vertices() -> usize;
+    /// Returns the number of vertices of this cell
+    fn num_vertices_of_cell(&self) -> usize {
+        Self::num_vertices()
+    }
     /// Calls the given closure with each vertex index that is part of this cell, stopping at the first error and returning that error
     fn try_for_each_vertex<E, F: FnMut(usize) -> Result<(), E>>(&self, f: F) -> Result<(), E>;
     /// Calls the given closure with each vertex index that is part of this cell
@@ -116,6 +147,18 @@ pub trait CellConnectivity {
 #[derive(Copy, Clone, Pod, Zeroable)]
 #[repr(transparent)]
 pub struct TriangleCell(pub [usize; 3]);
+/// Cell type for the [`QuadMesh3d`]
+#[derive(Copy, Clone, Pod, Zeroable)]
+#[repr(transparent)]
+pub struct QuadCell(pub [usize; 4]);
+/// Cell type for the [`MixedTriQuadMesh3d`]
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum TriangleOrQuadCell {
+    /// A triangle identified by its vertex indices
+    Tri([usize; 3]),
+    /// A quad identified by its vertex indices (in counter-clockwise order)
+    Quad([usize; 4]),
+}
 /// Cell type for the [`HexMesh3d`]
 #[derive(Copy, Clone, Pod, Zeroable)]
 #[repr(transparent)]
@@ -135,6 +178,36 @@ impl CellConnectivity for TriangleCell {
     }
 }
 
+impl CellConnectivity for QuadCell {
+    fn num_vertices() -> usize {
+        4
+    }
+
+    fn try_for_each_vertex<E, F: FnMut(usize) -> Result<(), E>>(&self, f: F) -> Result<(), E> {
+        self.0.iter().copied().try_for_each(f)
+    }
+}
+
+impl CellConnectivity for TriangleOrQuadCell {
+    fn num_vertices() -> usize {
+        4
+    }
+
+    fn num_vertices_of_cell(&self) -> usize {
+        match self {
+            TriangleOrQuadCell::Tri(_) => 3,
+            TriangleOrQuadCell::Quad(_) => 4,
+        }
+    }
+
+    fn try_for_each_vertex<E, F: FnMut(usize) -> Result<(), E>>(&self, f: F) -> Result<(), E> {
+        match self {
+            TriangleOrQuadCell::Tri(tri) => tri.iter().copied().try_for_each(f),
+            TriangleOrQuadCell::Quad(quad) => quad.iter().copied().try_for_each(f),
+        }
+    }
+}
+
 impl CellConnectivity for HexCell {
     fn num_vertices() -> usize {
         8
@@ -167,6 +240,30 @@ impl<R: Real> Mesh3d<R> for TriMesh3d<R> {
     }
 }
 
+impl<R: Real> Mesh3d<R> for QuadMesh3d<R> {
+    type Cell = QuadCell;
+
+    fn vertices(&self) -> &[Vector3<R>] {
+        self.vertices.as_slice()
+    }
+
+    fn cells(&self) -> &[QuadCell] {
+        bytemuck::cast_slice::<[usize; 4], QuadCell>(self.quads.as_slice())
+    }
+}
+
+impl<R: Real> Mesh3d<R> for MixedTriQuadMesh3d<R> {
+    type Cell = TriangleOrQuadCell;
+
+    fn vertices(&self) -> &[Vector3<R>] {
+        self.vertices.as_slice()
+    }
+
+    fn cells(&self) -> &[TriangleOrQuadCell] {
+        self.cells.as_slice()
+    }
+}
+
 impl<R: Real> Mesh3d<R> for HexMesh3d<R> {
     type Cell = HexCell;
 
@@ -395,6 +492,63 @@ impl<R: Real> TriMesh3d<R> {
         normals
     }
 
+    /// Computes the total surface area of all triangles of the mesh
+    pub fn area(&self) -> R {
+        let half = R::from_f64(0.5).unwrap();
+        self.triangles
+            .iter()
+            .map(|tri| {
+                let v0 = &self.vertices[tri[0]];
+                let v1 = &self.vertices[tri[1]];
+                let v2 = &self.vertices[tri[2]];
+                (v1 - v0).cross(&(v2 - v0)).norm() * half
+            })
+            .fold(R::zero(), |a, b| a + b)
+    }
+
+    /// Computes the signed volume enclosed by the mesh (positive if the triangles are oriented counter-clockwise when viewed from the outside), only meaningful for closed meshes
+    pub fn signed_volume(&self) -> R {
+        let sixth = R::from_f64(1.0 / 6.0).unwrap();
+        self.triangles
+            .iter()
+            .map(|tri| {
+                let v0 = &self.vertices[tri[0]];
+                let v1 = &self.vertices[tri[1]];
+                let v2 = &self.vertices[tri[2]];
+                v0.dot(&v1.cross(v2)) * sixth
+            })
+            .fold(R::zero(), |a, b| a + b)
+    }
+
+    /// Computes the vertex-vertex connectivity of the mesh, i.e. for every vertex the indices of all vertices that share an edge with it
+    pub fn vertex_vertex_connectivity(&self) -> Vec<Vec<usize>> {
+        let mut connectivity = vec![Vec::new(); self.vertices.len()];
+
+        for tri in self.triangles.iter() {
+            for (i0, i1) in [(0, 1), (1, 2), (2, 0)] {
+                let (v0, v1) = (tri[i0], tri[i1]);
+                if !connectivity[v0].contains(&v1) {
+                    connectivity[v0].push(v1);
+                }
+                if !connectivity[v1].contains(&v0) {
+                    connectivity[v1].push(v0);
+                }
+            }
+        }
+
+        connectivity
+    }
+
+    /// Returns a flag per vertex that is `true` if the vertex is part of a boundary edge (see [`Self::find_boundary_edges`])
+    pub fn boundary_vertex_flags(&self) -> Vec<bool> {
+        let mut is_boundary = vec![false; self.vertices.len()];
+        for (edge, _, _) in self.find_boundary_edges() {
+            is_boundary[edge[0]] = true;
+            is_boundary[edge[1]] = true;
+        }
+        is_boundary
+    }
+
     /// Returns all boundary edges of the mesh
     ///
     /// Returns edges which are only connected to exactly one triangle, along with the connected triangle
@@ -474,6 +628,185 @@ fn test_find_boundary() {
     );
 }
 
+/// Meshes that are used by the tests of several modules
+#[cfg(test)]
+pub(crate) mod test_meshes {
+    use super::TriMesh3d;
+    use nalgebra::Vector3;
+
+    /// Returns a closed triangulated UV sphere with unit radius centered at the origin
+    pub(crate) fn uv_sphere() -> TriMesh3d<f64> {
+        let n_lat = 16;
+        let n_lon = 32;
+        let mut vertices = vec![Vector3::new(0.0, 0.0, 1.0), Vector3::new(0.0, 0.0, -1.0)];
+        for i in 1..n_lat {
+            let theta = std::f64::consts::PI * (i as f64) / (n_lat as f64);
+            for j in 0..n_lon {
+                let phi = 2.0 * std::f64::consts::PI * (j as f64) / (n_lon as f64);
+                vertices.push(Vector3::new(
+                    theta.sin() * phi.cos(),
+                    theta.sin() * phi.sin(),
+                    theta.cos(),
+                ));
+            }
+        }
+
+        let ring = |i: usize, j: usize| 2 + (i - 1) * n_lon + (j % n_lon);
+        let mut triangles = Vec::new();
+        for j in 0..n_lon {
+            triangles.push([0, ring(1, j), ring(1, j + 1)]);
+            triangles.push([1, ring(n_lat - 1, j + 1), ring(n_lat - 1, j)]);
+        }
+        for i in 1..(n_lat - 1) {
+            for j in 0..n_lon {
+                triangles.push([ring(i, j), ring(i + 1, j), ring(i + 1, j + 1)]);
+                triangles.push([ring(i, j), ring(i + 1, j + 1), ring(i, j + 1)]);
+            }
+        }
+
+        TriMesh3d {
+            vertices,
+            triangles,
+        }
+    }
+}
+
+#[test]
+fn test_vertex_vertex_connectivity() {
+    let mesh = TriMesh3d::<f64> {
+        vertices: vec![
+            Vector3::new_random(),
+            Vector3::new_random(),
+            Vector3::new_random(),
+            Vector3::new_random(),
+        ],
+        triangles: vec![[0, 1, 2], [2, 1, 3]],
+    };
+
+    let mut connectivity = mesh.vertex_vertex_connectivity();
+    for neighbors in connectivity.iter_mut() {
+        neighbors.sort_unstable();
+    }
+
+    assert_eq!(
+        connectivity,
+        vec![vec![1, 2], vec![0, 2, 3], vec![0, 1, 3], vec![1, 2]]
+    );
+    assert_eq!(mesh.boundary_vertex_flags(), vec![true; 4]);
+}
+
+#[test]
+fn test_area_and_volume() {
+    // Tetrahedron with outward oriented triangles
+    let mesh = TriMesh3d::<f64> {
+        vertices: vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+        ],
+        triangles: vec![[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]],
+    };
+
+    assert!((mesh.area() - (1.5 + 0.5 * 3.0_f64.sqrt())).abs() < 1e-12);
+    assert!((mesh.signed_volume() - 1.0 / 6.0).abs() < 1e-12);
+}
+
+impl<R: Real> MixedTriQuadMesh3d<R> {
+    /// Returns the number of triangles and the number of quads of the mesh
+    pub fn count_cell_types(&self) -> (usize, usize) {
+        let num_quads = self
+            .cells
+            .iter()
+            .filter(|c| matches!(c, TriangleOrQuadCell::Quad(_)))
+            .count();
+        (self.cells.len() - num_quads, num_quads)
+    }
+
+    /// Returns a triangle mesh with the same vertices where every quad is split along its diagonal from the first to the third vertex
+    pub fn to_tri_mesh(&self) -> TriMesh3d<R> {
+        let mut triangles = Vec::with_capacity(self.cells.len() * 2);
+        for cell in self.cells.iter() {
+            match *cell {
+                TriangleOrQuadCell::Tri(tri) => triangles.push(tri),
+                TriangleOrQuadCell::Quad([q0, q1, q2, q3]) => {
+                    triangles.push([q0, q1, q2]);
+                    triangles.push([q0, q2, q3]);
+                }
+            }
+        }
+
+        TriMesh3d {
+            vertices: self.vertices.clone(),
+            triangles,
+        }
+    }
+}
+
+/// Converts a triangle mesh into a mixed mesh consisting only of triangles
+impl<R: Real> From<TriMesh3d<R>> for MixedTriQuadMesh3d<R> {
+    fn from(mesh: TriMesh3d<R>) -> Self {
+        Self {
+            vertices: mesh.vertices,
+            cells: mesh
+                .triangles
+                .into_iter()
+                .map(TriangleOrQuadCell::Tri)
+                .collect(),
+        }
+    }
+}
+
+/// Converts a quad mesh into a mixed mesh consisting only of quads
+impl<R: Real> From<QuadMesh3d<R>> for MixedTriQuadMesh3d<R> {
+    fn from(mesh: QuadMesh3d<R>) -> Self {
+        Self {
+            vertices: mesh.vertices,
+            cells: mesh
+                .quads
+                .into_iter()
+                .map(TriangleOrQuadCell::Quad)
+                .collect(),
+        }
+    }
+}
+
+#[test]
+fn test_mixed_mesh_to_tri_mesh() {
+    // Unit square split into a quad and two triangles
+    let mesh = MixedTriQuadMesh3d::<f64> {
+        vertices: vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.5, 0.0, 0.0),
+            Vector3::new(0.5, 1.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+        ],
+        cells: vec![
+            TriangleOrQuadCell::Quad([0, 1, 2, 3]),
+            TriangleOrQuadCell::Tri([1, 4, 5]),
+            TriangleOrQuadCell::Tri([1, 5, 2]),
+        ],
+    };
+
+    assert_eq!(mesh.count_cell_types(), (2, 1));
+    assert_eq!(TriangleCell::num_vertices(), 3);
+    assert_eq!(TriangleOrQuadCell::num_vertices(), 4);
+    assert_eq!(
+        mesh.cells
+            .iter()
+            .map(|c| c.num_vertices_of_cell())
+            .sum::<usize>(),
+        10
+    );
+
+    let tri_mesh = mesh.to_tri_mesh();
+    assert_eq!(tri_mesh.triangles.len(), 4);
+    assert!((tri_mesh.area() - 1.0).abs() < 1e-12);
+    assert_eq!(tri_mesh.find_boundary_edges().len(), 6);
+}
+
 /// Wrapper type for meshes with attached point or cell data
 #[derive(Clone, Debug)]
 pub struct MeshWithData<R: Real, MeshT: Mesh3d<R>> {
@@ -554,10 +887,18 @@ impl<R: Real> MeshAttribute<R> {
         }
     }
 
+    /// Creates a new named mesh attribute with 3x3 tensor values implementing the [`Real`](crate::Real) trait
+    pub fn new_real_matrix3<S: Into<String>>(name: S, data: impl Into<Vec<Matrix3<R>>>) -> Self {
+        Self {
+            name: name.into(),
+            data: AttributeData::Matrix3Real(data.into()),
+        }
+    }
+
     /// Converts the mesh attribute to a [`vtkio::model::Attribute`](https://docs.rs/vtkio/0.6.*/vtkio/model/enum.Attribute.html)
     #[cfg(feature = "vtk_extras")]
     #[cfg_attr(doc_cfg, doc(cfg(feature = "vtk_extras")))]
-    fn to_vtk_attribute(&self) -> Attribute {
+    pub(crate) fn to_vtk_attribute(&self) -> Attribute {
         match &self.data {
             AttributeData::ScalarU64(u64_vec) => {
                 Attribute::scalars(&self.name, 1).with_data(u64_vec.clone())
@@ -567,17 +908,31 @@ impl<R: Real> MeshAttribute<R> {
             }
             AttributeData::Vector3Real(vec3r_vec) => Attribute::scalars(&self.name, 3)
                 .with_data(vec3r_vec.iter().flatten().copied().collect::<Vec<R>>()),
+            AttributeData::VectorReal(num_components, real_vec) => {
+                Attribute::generic(&self.name, *num_components as u32).with_data(real_vec.clone())
+            }
+            // VTK expects the tensor components in row-major order
+            AttributeData::Matrix3Real(mat3r_vec) => Attribute::tensors(&self.name).with_data(
+                mat3r_vec
+                    .iter()
+                    .flat_map(|m| m.transpose().data.0.into_iter().flatten())
+                    .collect::<Vec<R>>(),
+            ),
         }
     }
 }
 
 impl<R: Real> AttributeData<R> {
     /// Returns the number of entries in the data set
-    fn len(&self) -> usize {
+    pub(crate) fn len(&self) -> usize {
         match self {
             AttributeData::ScalarU64(v) => v.len(),
             AttributeData::ScalarReal(v) => v.len(),
             AttributeData::Vector3Real(v) => v.len(),
+            AttributeData::VectorReal(num_components, v) => {
+                v.len().checked_div(*num_components).unwrap_or(0)
+            }
+            AttributeData::Matrix3Real(v) => v.len(),
         }
     }
 }
@@ -636,15 +991,19 @@ pub mod vtk_helper {
     use vtkio::IOBuffer;
 
     use super::{
-        CellConnectivity, HexCell, HexMesh3d, Mesh3d, PointCell, PointCloud3d, Real, TriMesh3d,
-        TriangleCell,
+        CellConnectivity, HexCell, HexMesh3d, Mesh3d, MixedTriQuadMesh3d, PointCell, PointCloud3d,
+        QuadCell, QuadMesh3d, Real, TriMesh3d, TriangleCell, TriangleOrQuadCell,
     };
 
     /// Trait that can be implemented by mesh cells to return the corresponding [`vtkio::model::CellType`](https://docs.rs/vtkio/0.6.*/vtkio/model/enum.CellType.html)
     #[cfg_attr(doc_cfg, doc(cfg(feature = "vtk_extras")))]
     pub trait HasVtkCellType {
-        /// Returns the corresponding [`vtkio::model::CellType`](https://docs.rs/vtkio/0.6.*/vtkio/model/enum.CellType.html) of the cell
+        /// Returns the corresponding [`vtkio::model::CellType`](https://docs.rs/vtkio/0.6.*/vtkio/model/enum.CellType.html) of the cell type
         fn vtk_cell_type() -> CellType;
+        /// Returns the corresponding [`vtkio::model::CellType`](https://docs.rs/vtkio/0.6.*/vtkio/model/enum.CellType.html) of this cell
+        fn vtk_cell_type_of_cell(&self) -> CellType {
+            Self::vtk_cell_type()
+        }
     }
 
     #[cfg_attr(doc_cfg, doc(cfg(feature = "vtk_extras")))]
@@ -654,6 +1013,27 @@ pub mod vtk_helper {
         }
     }
 
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "vtk_extras")))]
+    impl HasVtkCellType for QuadCell {
+        fn vtk_cell_type() -> CellType {
+            CellType::Quad
+        }
+    }
+
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "vtk_extras")))]
+    impl HasVtkCellType for TriangleOrQuadCell {
+        fn vtk_cell_type() -> CellType {
+            CellType::Polygon
+        }
+
+        fn vtk_cell_type_of_cell(&self) -> CellType {
+            match self {
+                TriangleOrQuadCell::Tri(_) => CellType::Triangle,
+                TriangleOrQuadCell::Quad(_) => CellType::Quad,
+            }
+        }
+    }
+
     #[cfg_attr(doc_cfg, doc(cfg(feature = "vtk_extras")))]
     impl HasVtkCellType for HexCell {
         fn vtk_cell_type() -> CellType {
@@ -682,17 +1062,22 @@ pub mod vtk_helper {
             points
         };
 
-        let vertices_per_cell = MeshT::Cell::num_vertices();
         let vertices = {
-            let mut vertices = Vec::with_capacity(mesh.cells().len() * (vertices_per_cell + 1));
+            let num_cell_vertices: usize =
+                mesh.cells().iter().map(|c| c.num_vertices_of_cell()).sum();
+            let mut vertices = Vec::with_capacity(mesh.cells().len() + num_cell_vertices);
             for cell in mesh.cells().iter() {
-                vertices.push(vertices_per_cell as u32);
+                vertices.push(cell.num_vertices_of_cell() as u32);
                 cell.for_each_vertex(|v| vertices.push(v as u32));
             }
             vertices
         };
 
-        let cell_types = vec![<MeshT::Cell as HasVtkCellType>::vtk_cell_type(); mesh.cells().len()];
+        let cell_types = mesh
+            .cells()
+            .iter()
+            .map(|c| c.vtk_cell_type_of_cell())
+            .collect::<Vec<_>>();
 
         new_unstructured_grid_piece(points, vertices, cell_types)
     }
@@ -708,6 +1093,28 @@ pub mod vtk_helper {
         }
     }
 
+    /// Creates a [`vtkio::model::UnstructuredGridPiece`](https://docs.rs/vtkio/0.6.*/vtkio/model/struct.UnstructuredGridPiece.html) representing this mesh
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "vtk_extras")))]
+    impl<'a, R> From<&'a QuadMesh3d<R>> for UnstructuredGridPiece
+    where
+        R: Real,
+    {
+        fn from(mesh: &'a QuadMesh3d<R>) -> Self {
+            mesh_to_unstructured_grid(mesh)
+        }
+    }
+
+    /// Creates a [`vtkio::model::UnstructuredGridPiece`](https://docs.rs/vtkio/0.6.*/vtkio/model/struct.UnstructuredGridPiece.html) representing this mesh
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "vtk_extras")))]
+    impl<'a, R> From<&'a MixedTriQuadMesh3d<R>> for UnstructuredGridPiece
+    where
+        R: Real,
+    {
+        fn from(mesh: &'a MixedTriQuadMesh3d<R>) -> Self {
+            mesh_to_unstructured_grid(mesh)
+        }
+    }
+
     /// Creates a [`vtkio::model::UnstructuredGridPiece`](https://docs.rs/vtkio/0.6.*/vtkio/model/struct.UnstructuredGridPiece.html) representing this mesh
     #[cfg_attr(doc_cfg, doc(cfg(feature = "vtk_extras")))]
     impl<'a, R> From<&'a HexMesh3d<R>> for UnstructuredGridPiece
@@ -738,6 +1145,22 @@ pub mod vtk_helper {
         }
     }
 
+    /// Creates a [`vtkio::model::UnstructuredGridPiece`](https://docs.rs/vtkio/0.6.*/vtkio/model/struct.UnstructuredGridPiece.html) representing this mesh and wraps it into a [`vtkio::model::DataSet`](https://docs.rs/vtkio/0.6.*/vtkio/model/enum.DataSet.html)
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "vtk_extras")))]
+    impl<R: Real> From<&QuadMesh3d<R>> for DataSet {
+        fn from(mesh: &QuadMesh3d<R>) -> Self {
+            DataSet::inline(UnstructuredGridPiece::from(mesh))
+        }
+    }
+
+    /// Creates a [`vtkio::model::UnstructuredGridPiece`](https://docs.rs/vtkio/0.6.*/vtkio/model/struct.UnstructuredGridPiece.html) representing this mesh and wraps it into a [`vtkio::model::DataSet`](https://docs.rs/vtkio/0.6.*/vtkio/model/enum.DataSet.html)
+    #[cfg_attr(doc_cfg, doc(cfg(feature = "vtk_extras")))]
+    impl<R: Real> From<&MixedTriQuadMesh3d<R>> for DataSet {
+        fn from(mesh: &MixedTriQuadMesh3d<R>) -> Self {
+            DataSet::inline(UnstructuredGridPiece::from(mesh))
+        }
+    }
+
     /// Creates a [`vtkio::model::UnstructuredGridPiece`](https://docs.rs/vtkio/0.6.*/vtkio/model/struct.UnstructuredGridPiece.html) representing this mesh and wraps it into a [`vtkio::model::DataSet`](https://docs.rs/vtkio/0.6.*/vtkio/model/enum.DataSet.html)
     #[cfg_attr(doc_cfg, doc(cfg(feature = "vtk_extras")))]
     impl<R: Real> Into<DataSet> for &HexMesh3d<R> {
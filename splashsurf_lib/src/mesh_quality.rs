@@ -0,0 +1,359 @@
+//! Quality metrics and topological properties of triangle meshes
+//!
+//! The function [`compute_mesh_quality`] evaluates objective numbers that can be used to compare reconstructions
+//! with different parameters (e.g. cube size, smoothing length or surface threshold):
+//!  - the shape of the triangles (aspect ratios, smallest and largest angles, edge lengths, degenerate triangles),
+//!  - geometric properties (surface area, enclosed volume, bounding box),
+//!  - topological properties (connected components, Euler characteristic, genus, boundary loops, non-manifold edges).
+
+use crate::mesh::TriMesh3d;
+use crate::postprocessing::compute_mesh_components;
+use crate::{new_map, profile, Aabb3d, Real};
+use num_traits::Bounded;
+
+/// Minimum, maximum and mean of a quantity over all elements of a mesh
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ValueStatistics<R: Real> {
+    /// Smallest value
+    pub min: R,
+    /// Largest value
+    pub max: R,
+    /// Arithmetic mean of all values
+    pub mean: R,
+}
+
+impl<R: Real> ValueStatistics<R> {
+    /// Computes the statistics of the given values, returns `None` if there are no values
+    fn from_values<It: IntoIterator<Item = R>>(values: It) -> Option<Self> {
+        let mut count = 0;
+        let mut min = <R as Bounded>::max_value();
+        let mut max = <R as Bounded>::min_value();
+        let mut sum = R::zero();
+        for value in values {
+            count += 1;
+            min = min.min(value);
+            max = max.max(value);
+            sum += value;
+        }
+
+        (count > 0).then(|| Self {
+            min,
+            max,
+            mean: sum / R::from_usize(count).unwrap(),
+        })
+    }
+}
+
+/// Quality metrics and topological properties of a triangle mesh, see [`compute_mesh_quality`]
+#[derive(Clone, Debug)]
+pub struct MeshQualityStatistics<R: Real> {
+    /// Number of vertices of the mesh
+    pub num_vertices: usize,
+    /// Number of vertices that are not referenced by any triangle
+    pub num_unreferenced_vertices: usize,
+    /// Number of triangles of the mesh
+    pub num_triangles: usize,
+    /// Number of unique edges of the mesh
+    pub num_edges: usize,
+    /// Number of triangles with repeated vertices or a vanishing area (these are excluded from the angle and aspect ratio statistics)
+    pub num_degenerate_triangles: usize,
+    /// Statistics of the lengths of all unique edges
+    pub edge_length: Option<ValueStatistics<R>>,
+    /// Statistics of the aspect ratios of the triangles (ratio of the longest edge to the shortest altitude, normalized to 1 for equilateral triangles)
+    pub aspect_ratio: Option<ValueStatistics<R>>,
+    /// Statistics of the smallest angle of every triangle (in degrees)
+    pub min_angle: Option<ValueStatistics<R>>,
+    /// Statistics of the largest angle of every triangle (in degrees)
+    pub max_angle: Option<ValueStatistics<R>>,
+    /// Total surface area of the mesh
+    pub area: R,
+    /// Signed volume enclosed by the mesh, only meaningful for closed meshes (see [`TriMesh3d::signed_volume`])
+    pub volume: R,
+    /// Axis aligned bounding box of all vertices, `None` for meshes without vertices
+    pub aabb: Option<Aabb3d<R>>,
+    /// Number of connected components of the triangles
+    pub num_components: usize,
+    /// Euler characteristic `V - E + F` of the mesh (only vertices referenced by triangles are counted)
+    pub euler_characteristic: i64,
+    /// Total genus of all components, `None` if the mesh has non-manifold edges
+    pub genus: Option<u64>,
+    /// Number of edges that are connected to exactly one triangle
+    pub num_boundary_edges: usize,
+    /// Number of closed loops formed by the boundary edges (i.e. holes of the mesh)
+    pub num_boundary_loops: usize,
+    /// Number of edges that are connected to more than two triangles
+    pub num_non_manifold_edges: usize,
+}
+
+impl<R: Real> MeshQualityStatistics<R> {
+    /// Returns whether the mesh is closed (no boundary edges) and edge-manifold
+    pub fn is_closed_manifold(&self) -> bool {
+        self.num_boundary_edges == 0 && self.num_non_manifold_edges == 0
+    }
+}
+
+/// Computes quality metrics and topological properties of the given mesh, see the [module level documentation](self)
+pub fn compute_mesh_quality<R: Real>(mesh: &TriMesh3d<R>) -> MeshQualityStatistics<R> {
+    profile!("compute_mesh_quality");
+
+    // Count the number of triangles connected to every unique edge
+    let mut edge_counts = new_map();
+    for tri in mesh.triangles.iter() {
+        for (i0, i1) in [(0, 1), (1, 2), (2, 0)] {
+            let (v0, v1) = (tri[i0], tri[i1]);
+            let edge = if v0 < v1 { [v0, v1] } else { [v1, v0] };
+            *edge_counts.entry(edge).or_insert(0usize) += 1;
+        }
+    }
+
+    let edge_length = ValueStatistics::from_values(
+        edge_counts
+            .keys()
+            .map(|&[v0, v1]| (mesh.vertices[v1] - mesh.vertices[v0]).norm()),
+    );
+
+    let num_boundary_edges = edge_counts.values().filter(|&&count| count == 1).count();
+    let num_non_manifold_edges = edge_counts.values().filter(|&&count| count > 2).count();
+
+    // Count the closed loops of boundary edges by walking along the (oriented) boundary edges. At a vertex, the next boundary
+    // edge is found by rotating around the vertex through the adjacent triangles, so that loops touching at a vertex are not merged.
+    let num_boundary_loops = {
+        let boundary_edges = mesh.find_boundary_edges();
+        let mut boundary_edge_indices = new_map();
+        for (i, (edge, _, _)) in boundary_edges.iter().enumerate() {
+            boundary_edge_indices.insert(*edge, i);
+        }
+
+        // Triangle of every oriented edge
+        let mut edge_triangles = new_map();
+        for (tri_idx, tri) in mesh.triangles.iter().enumerate() {
+            for (i0, i1) in [(0, 1), (1, 2), (2, 0)] {
+                edge_triangles.insert([tri[i0], tri[i1]], tri_idx);
+            }
+        }
+
+        // Returns the boundary edge following the given boundary edge in its loop
+        let next_boundary_edge = |i: usize| {
+            let ([_, v], mut tri_idx, _) = boundary_edges[i];
+            for _ in 0..mesh.triangles.len() {
+                // The edge leaving the vertex in the current triangle
+                let tri = &mesh.triangles[tri_idx];
+                let local_idx = tri.iter().position(|&w| w == v)?;
+                let w = tri[(local_idx + 1) % 3];
+                match edge_triangles.get(&[w, v]) {
+                    Some(&neighbor_idx) => tri_idx = neighbor_idx,
+                    None => return boundary_edge_indices.get(&[v, w]).copied(),
+                }
+            }
+            None
+        };
+
+        let mut visited = vec![false; boundary_edges.len()];
+        let mut num_loops = 0;
+        for start in 0..boundary_edges.len() {
+            if visited[start] {
+                continue;
+            }
+            num_loops += 1;
+            let mut edge = Some(start);
+            while let Some(i) = edge.filter(|&i| !visited[i]) {
+                visited[i] = true;
+                edge = next_boundary_edge(i);
+            }
+        }
+        num_loops
+    };
+
+    // Shape of the triangles
+    let mut num_degenerate_triangles = 0;
+    let mut aspect_ratios = Vec::with_capacity(mesh.triangles.len());
+    let mut min_angles = Vec::with_capacity(mesh.triangles.len());
+    let mut max_angles = Vec::with_capacity(mesh.triangles.len());
+    let half = R::from_f64(0.5).unwrap();
+    let sqrt_3_over_4 = R::from_f64(3.0f64.sqrt() / 4.0).unwrap();
+    let to_degrees = R::from_f64(180.0 / std::f64::consts::PI).unwrap();
+    for tri in mesh.triangles.iter() {
+        let [v0, v1, v2] = tri.map(|v| mesh.vertices[v]);
+        let edges = [v1 - v0, v2 - v1, v0 - v2];
+        let lengths_squared = edges.map(|e| e.norm_squared());
+        let longest_squared = lengths_squared[0]
+            .max(lengths_squared[1])
+            .max(lengths_squared[2]);
+        let area = edges[0].cross(&edges[1]).norm() * half;
+
+        if tri[0] == tri[1]
+            || tri[1] == tri[2]
+            || tri[2] == tri[0]
+            || area <= R::default_epsilon() * longest_squared
+        {
+            num_degenerate_triangles += 1;
+            continue;
+        }
+
+        // Longest edge divided by the shortest altitude (2A/L), normalized by the value of an equilateral triangle
+        aspect_ratios.push(longest_squared * sqrt_3_over_4 / area);
+
+        // The angle at every vertex is enclosed by its outgoing and (reversed) incoming edge
+        let angles = [0, 1, 2].map(|i| {
+            let outgoing = &edges[i];
+            let incoming = -&edges[(i + 2) % 3];
+            outgoing.angle(&incoming) * to_degrees
+        });
+        min_angles.push(angles[0].min(angles[1]).min(angles[2]));
+        max_angles.push(angles[0].max(angles[1]).max(angles[2]));
+    }
+
+    // Topology
+    let mut is_referenced = vec![false; mesh.vertices.len()];
+    for tri in mesh.triangles.iter() {
+        for &v in tri {
+            is_referenced[v] = true;
+        }
+    }
+    let num_referenced_vertices = is_referenced.iter().filter(|&&r| r).count();
+
+    let num_components = compute_mesh_components(mesh).components.len();
+    let euler_characteristic =
+        num_referenced_vertices as i64 - edge_counts.len() as i64 + mesh.triangles.len() as i64;
+    // For every component: χ = 2 - 2g - b
+    let genus = {
+        let twice_genus =
+            2 * num_components as i64 - num_boundary_loops as i64 - euler_characteristic;
+        (num_non_manifold_edges == 0 && twice_genus >= 0 && twice_genus % 2 == 0)
+            .then_some(twice_genus as u64 / 2)
+    };
+
+    MeshQualityStatistics {
+        num_vertices: mesh.vertices.len(),
+        num_unreferenced_vertices: mesh.vertices.len() - num_referenced_vertices,
+        num_triangles: mesh.triangles.len(),
+        num_edges: edge_counts.len(),
+        num_degenerate_triangles,
+        edge_length,
+        aspect_ratio: ValueStatistics::from_values(aspect_ratios),
+        min_angle: ValueStatistics::from_values(min_angles),
+        max_angle: ValueStatistics::from_values(max_angles),
+        area: mesh.area(),
+        volume: mesh.signed_volume(),
+        aabb: (!mesh.vertices.is_empty()).then(|| Aabb3d::from_points(&mesh.vertices)),
+        num_components,
+        euler_characteristic,
+        genus,
+        num_boundary_edges,
+        num_boundary_loops,
+        num_non_manifold_edges,
+    }
+}
+
+#[test]
+fn test_mesh_quality_of_tetrahedron_and_torus() {
+    use nalgebra::Vector3;
+
+    // Regular tetrahedron with outward oriented triangles
+    let mut mesh = TriMesh3d::<f64> {
+        vertices: vec![
+            Vector3::new(1.0, 1.0, 1.0),
+            Vector3::new(1.0, -1.0, -1.0),
+            Vector3::new(-1.0, 1.0, -1.0),
+            Vector3::new(-1.0, -1.0, 1.0),
+        ],
+        triangles: vec![[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]],
+    };
+
+    let stats = compute_mesh_quality(&mesh);
+    assert_eq!(stats.num_edges, 6);
+    assert_eq!(stats.num_degenerate_triangles, 0);
+    assert_eq!(stats.euler_characteristic, 2);
+    assert_eq!(stats.genus, Some(0));
+    assert_eq!(stats.num_boundary_loops, 0);
+    assert!(stats.is_closed_manifold());
+    let aspect_ratio = stats.aspect_ratio.unwrap();
+    assert!((aspect_ratio.max - 1.0).abs() < 1e-12);
+    let min_angle = stats.min_angle.unwrap();
+    assert!((min_angle.min - 60.0).abs() < 1e-10);
+    assert!((stats.edge_length.unwrap().mean - 8.0f64.sqrt()).abs() < 1e-12);
+    assert!((stats.volume - 8.0 / 3.0).abs() < 1e-12);
+
+    // Removing a triangle opens a hole
+    mesh.triangles.pop();
+    let stats = compute_mesh_quality(&mesh);
+    assert_eq!(stats.num_boundary_edges, 3);
+    assert_eq!(stats.num_boundary_loops, 1);
+    assert_eq!(stats.euler_characteristic, 1);
+    assert_eq!(stats.genus, Some(0));
+
+    // Two triangles that only share a vertex have two separate boundary loops
+    let bowtie = TriMesh3d::<f64> {
+        vertices: vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+            Vector3::new(-1.0, -1.0, 0.0),
+        ],
+        triangles: vec![[0, 1, 2], [0, 3, 4]],
+    };
+    let stats = compute_mesh_quality(&bowtie);
+    assert_eq!(stats.num_boundary_edges, 6);
+    assert_eq!(stats.num_boundary_loops, 2);
+
+    // A square of two triangles has a single boundary loop
+    let square = TriMesh3d::<f64> {
+        vertices: vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+        ],
+        triangles: vec![[0, 1, 2], [0, 2, 3]],
+    };
+    let stats = compute_mesh_quality(&square);
+    assert_eq!(stats.num_boundary_edges, 4);
+    assert_eq!(stats.num_boundary_loops, 1);
+
+    // Triangle with collinear vertices
+    let degenerate = TriMesh3d::<f64> {
+        vertices: vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+        ],
+        triangles: vec![[0, 1, 2]],
+    };
+    let stats = compute_mesh_quality(&degenerate);
+    assert_eq!(stats.num_degenerate_triangles, 1);
+    assert!(stats.aspect_ratio.is_none());
+
+    // Torus made of a 4x4 grid with periodic connectivity
+    let n = 4;
+    let mut torus = TriMesh3d::<f64>::default();
+    for i in 0..n {
+        for j in 0..n {
+            let (u, v) = (
+                i as f64 / n as f64 * std::f64::consts::TAU,
+                j as f64 / n as f64 * std::f64::consts::TAU,
+            );
+            let r = 2.0 + v.cos();
+            torus
+                .vertices
+                .push(Vector3::new(r * u.cos(), r * u.sin(), v.sin()));
+        }
+    }
+    let index = |i: usize, j: usize| (i % n) * n + (j % n);
+    for i in 0..n {
+        for j in 0..n {
+            torus
+                .triangles
+                .push([index(i, j), index(i + 1, j), index(i + 1, j + 1)]);
+            torus
+                .triangles
+                .push([index(i, j), index(i + 1, j + 1), index(i, j + 1)]);
+        }
+    }
+
+    let stats = compute_mesh_quality(&torus);
+    assert_eq!(stats.num_components, 1);
+    assert_eq!(stats.euler_characteristic, 0);
+    assert_eq!(stats.genus, Some(1));
+    assert!(stats.is_closed_manifold());
+}
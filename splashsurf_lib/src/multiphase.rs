@@ -0,0 +1,423 @@
+//! Surface reconstruction of multiphase particle data with one surface per phase
+//!
+//! Simulations of multiple immiscible fluids assign a phase id to every particle. The function
+//! [`reconstruct_multiphase_surfaces`] reconstructs one closed surface per phase in a single pass:
+//! the background grid, the neighborhood search, the particle densities and (optionally) the anisotropic kernels
+//! are computed once for all particles and shared by the reconstructions of the individual phases.
+//! Only the density map and the iso-surface extraction are performed per phase.
+//!
+//! Two modes are supported (see [`PhaseReconstructionMode`]):
+//!  - [`Separate`](PhaseReconstructionMode::Separate): every phase is reconstructed as if the particles
+//!    of all other phases did not exist, i.e. the particle densities only take same-phase neighbors into account.
+//!    The surfaces of adjacent phases overlap at their interface.
+//!  - [`AgainstOthers`](PhaseReconstructionMode::AgainstOthers): the particle densities take all neighbors into account
+//!    and the density map of a phase is only evaluated from its own particles. The surface of a phase then
+//!    follows the interface to the adjacent phases, where the density map drops to roughly half of its interior value.
+//!    With an iso-surface threshold of `0.5`, the surfaces of two adjacent phases approximately coincide at their shared interface.
+//!
+//! Solid boundaries and boundary particles (see [`boundary`](crate::boundary)) are applied to the surfaces of all phases, mirrored particles keep the phase of their original particle.
+//! With [`Parameters::cap_domain_boundary`], the surfaces of all phases are closed at the boundary of the domain.
+//!
+//! Currently, only the global reconstruction approach is supported, i.e. neither the octree-based spatial decomposition
+//! nor the subdomain grid may be enabled in the [`Parameters`].
+
+use crate::boundary::{BoundaryConditions, BoundaryDistanceField, MirroredParticles};
+use crate::mesh::{AttributeData, TriMesh3d};
+use crate::uniform_grid::UniformGrid;
+use crate::{
+    anisotropy, density_map, grid_for_reconstruction, marching_cubes, max_kernel_radius,
+    neighborhood_search, neighborhood_search_domain, new_map, profile, surface_nets, Index,
+    IsoSurfaceExtraction, Parameters, ParticleProperties, Real, ReconstructionError,
+};
+use anyhow::anyhow;
+use log::info;
+use nalgebra::Vector3;
+use rayon::prelude::*;
+use std::collections::BTreeMap;
+
+/// Determines how the particles of the other phases are taken into account when reconstructing the surface of a phase
+#[derive(Copy, Clone, Debug, Default, Eq, PartialEq)]
+pub enum PhaseReconstructionMode {
+    /// Every phase is reconstructed independently, particles of other phases are ignored
+    #[default]
+    Separate,
+    /// Every phase is reconstructed against all other phases, the surfaces of adjacent phases share their interface
+    AgainstOthers,
+}
+
+/// Surface of a single phase resulting from a multiphase reconstruction
+#[derive(Clone, Debug)]
+pub struct PhaseSurface<R: Real> {
+    /// Id of the phase
+    phase_id: u64,
+    /// Number of particles of the phase
+    num_particles: usize,
+    /// Surface mesh of the phase
+    mesh: TriMesh3d<R>,
+}
+
+impl<R: Real> PhaseSurface<R> {
+    /// Returns the id of the phase of this surface
+    pub fn phase_id(&self) -> u64 {
+        self.phase_id
+    }
+
+    /// Returns the number of particles of the phase of this surface
+    pub fn num_particles(&self) -> usize {
+        self.num_particles
+    }
+
+    /// Returns a reference to the surface mesh of the phase
+    pub fn mesh(&self) -> &TriMesh3d<R> {
+        &self.mesh
+    }
+
+    /// Consumes the phase surface and returns its mesh
+    pub fn into_mesh(self) -> TriMesh3d<R> {
+        self.mesh
+    }
+}
+
+/// Result data returned when the multiphase surface reconstruction was successful
+#[derive(Clone, Debug)]
+pub struct MultiphaseSurfaceReconstruction<I: Index, R: Real> {
+    /// Background grid that was shared by the reconstructions of all phases
+    grid: UniformGrid<I, R>,
+    /// Per particle densities (computed according to the [`PhaseReconstructionMode`])
+    particle_densities: Vec<R>,
+    /// Surfaces of all phases, sorted by their phase id
+    phases: Vec<PhaseSurface<R>>,
+}
+
+impl<I: Index, R: Real> MultiphaseSurfaceReconstruction<I, R> {
+    /// Returns a reference to the background grid that was used for the reconstruction of all phases
+    pub fn grid(&self) -> &UniformGrid<I, R> {
+        &self.grid
+    }
+
+    /// Returns a reference to the per particle densities
+    pub fn particle_densities(&self) -> &[R] {
+        self.particle_densities.as_slice()
+    }
+
+    /// Returns the surfaces of all phases sorted by their phase id
+    pub fn phases(&self) -> &[PhaseSurface<R>] {
+        self.phases.as_slice()
+    }
+
+    /// Returns the surface of the phase with the given id, if there are particles with this phase id
+    pub fn phase(&self, phase_id: u64) -> Option<&PhaseSurface<R>> {
+        self.phases
+            .binary_search_by_key(&phase_id, PhaseSurface::phase_id)
+            .ok()
+            .map(|i| &self.phases[i])
+    }
+}
+
+/// Tries to convert attribute data (e.g. loaded from a VTK or BGEO file) to per particle phase ids
+///
+/// Supports integer attributes as well as real valued attributes if all values are non-negative integers.
+pub fn phase_ids_from_attribute<R: Real>(
+    attribute: &AttributeData<R>,
+) -> Result<Vec<u64>, anyhow::Error> {
+    match attribute {
+        AttributeData::ScalarU64(values) => Ok(values.clone()),
+        AttributeData::ScalarReal(values) => values
+            .iter()
+            .map(|&v| {
+                v.to_u64()
+                    .filter(|&id| R::from_u64(id) == Some(v))
+                    .ok_or_else(|| anyhow!("phase id {} is not a non-negative integer", v))
+            })
+            .collect(),
+        AttributeData::Vector3Real(_)
+        | AttributeData::VectorReal(..)
+        | AttributeData::Matrix3Real(_) => Err(anyhow!(
+            "vector attributes cannot be used as phase ids, a scalar attribute is required"
+        )),
+    }
+}
+
+/// Performs a surface reconstruction of every phase of the given particles, see the [module level documentation](self)
+///
+/// Returns one surface per distinct phase id in `particle_phases`, sorted by the phase id. The reconstructions of
+/// all phases share the background grid, the neighborhood search and the particle densities.
+pub fn reconstruct_multiphase_surfaces<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    particle_phases: &[u64],
+    particle_properties: ParticleProperties<R>,
+    mode: PhaseReconstructionMode,
+    parameters: &Parameters<R>,
+) -> Result<MultiphaseSurfaceReconstruction<I, R>, ReconstructionError<I, R>> {
+    profile!("reconstruct_multiphase_surfaces");
+
+    if particle_phases.len() != particle_positions.len() {
+        return Err(anyhow!(
+            "the number of phase ids ({}) does not match the number of particles ({})",
+            particle_phases.len(),
+            particle_positions.len()
+        )
+        .into());
+    }
+    if parameters.subdomain_num_cubes_per_dim.is_some()
+        || parameters.spatial_decomposition.is_some()
+    {
+        return Err(anyhow!(
+            "multiphase reconstruction is only supported without spatial decomposition and without subdomain grid"
+        )
+        .into());
+    }
+    if parameters.adaptive_resolution.is_some() {
+        return Err(anyhow!(
+            "adaptive resolution is not supported by the multiphase reconstruction"
+        )
+        .into());
+    }
+    if parameters.cap_domain_boundary
+        && parameters.iso_surface_extraction != IsoSurfaceExtraction::MarchingCubes
+    {
+        return Err(
+            anyhow!("caps at the domain boundary are only supported with marching cubes").into(),
+        );
+    }
+    particle_properties.validate(particle_positions.len())?;
+
+    // Mirror the particles close to a solid boundary, the mirrored particles belong to the phase of their original particle
+    let boundary = parameters.boundary.as_ref().map(|boundary| {
+        BoundaryDistanceField::new(
+            boundary,
+            particle_properties.max_compact_support_radius(parameters.compact_support_radius),
+            parameters.cube_size,
+            parameters.iso_surface_threshold,
+        )
+    });
+    let mirrored_particles = parameters
+        .boundary
+        .as_ref()
+        .zip(boundary.as_ref())
+        .filter(|(parameters, _)| parameters.handling.extends())
+        .and_then(|(_, boundary)| {
+            MirroredParticles::new(
+                boundary,
+                particle_positions,
+                particle_properties,
+                parameters.enable_multi_threading,
+            )
+        });
+    let boundary = BoundaryConditions::new(parameters, boundary);
+    let num_particles = particle_positions.len();
+    let mirrored_phases;
+    let (particle_positions, particle_phases, particle_properties) = match &mirrored_particles {
+        Some(mirrored) => {
+            mirrored_phases = particle_phases
+                .iter()
+                .copied()
+                .chain(mirrored.source_indices.iter().map(|&i| particle_phases[i]))
+                .collect::<Vec<_>>();
+            (
+                mirrored.positions.as_slice(),
+                mirrored_phases.as_slice(),
+                mirrored.properties(),
+            )
+        }
+        None => (particle_positions, particle_phases, particle_properties),
+    };
+
+    // Collect the particles of every phase
+    let phase_particles = {
+        let mut phase_particles = BTreeMap::<u64, Vec<usize>>::new();
+        for (i, &phase) in particle_phases.iter().enumerate() {
+            phase_particles.entry(phase).or_default().push(i);
+        }
+        phase_particles
+    };
+    info!(
+        "Reconstructing surfaces of {} phase(s) ({:?} mode)...",
+        phase_particles.len(),
+        mode
+    );
+
+    let compact_support_radius =
+        particle_properties.max_compact_support_radius(parameters.compact_support_radius);
+    let max_kernel_radius = max_kernel_radius(parameters, particle_properties);
+
+    // The background grid is shared by all phases
+    let grid = grid_for_reconstruction::<I, R>(
+        particle_positions,
+        parameters.particle_radius,
+        max_kernel_radius,
+        parameters.cube_size,
+        parameters.domain_aabb.as_ref(),
+        parameters.enable_multi_threading,
+    )?;
+    grid.log_grid_info();
+
+    let particle_rest_volume = R::from_f64((4.0 / 3.0) * std::f64::consts::PI).unwrap()
+        * parameters.particle_radius.powi(3);
+    let particle_rest_mass = particle_rest_volume * parameters.rest_density;
+
+    // A single neighborhood search for the particles of all phases
+    let mut particle_neighbor_lists = Vec::new();
+    neighborhood_search::search_inplace::<I, R>(
+        &neighborhood_search_domain(&grid, particle_positions, parameters),
+        particle_positions,
+        compact_support_radius,
+        parameters.enable_multi_threading,
+        &mut particle_neighbor_lists,
+    );
+
+    // Without interaction between the phases, only neighbors of the same phase are relevant
+    if mode == PhaseReconstructionMode::Separate {
+        profile!("filter neighbor lists by phase");
+        let retain_same_phase = |(i, neighbors): (usize, &mut Vec<usize>)| {
+            neighbors.retain(|&j| particle_phases[j] == particle_phases[i])
+        };
+        if parameters.enable_multi_threading {
+            particle_neighbor_lists
+                .par_iter_mut()
+                .enumerate()
+                .for_each(retain_same_phase);
+        } else {
+            particle_neighbor_lists
+                .iter_mut()
+                .enumerate()
+                .for_each(retain_same_phase);
+        }
+    }
+
+    let mut particle_densities = Vec::new();
+    density_map::compute_particle_densities_inplace::<I, R>(
+        particle_positions,
+        particle_neighbor_lists.as_slice(),
+        parameters.compact_support_radius,
+        parameters.kernel,
+        particle_rest_mass,
+        particle_properties,
+        parameters.enable_multi_threading,
+        &mut particle_densities,
+    );
+    if let Some(boundary) = boundary.as_ref() {
+        boundary.add_particle_densities(
+            particle_positions,
+            &mut particle_densities,
+            parameters.enable_multi_threading,
+        );
+    }
+
+    let anisotropic_kernels = parameters.anisotropy.as_ref().map(|anisotropy| {
+        anisotropy::compute_anisotropic_kernels(
+            particle_positions,
+            &particle_neighbor_lists,
+            parameters.compact_support_radius,
+            particle_properties,
+            anisotropy,
+            parameters.enable_multi_threading,
+        )
+    });
+    let kernel_centers = anisotropic_kernels
+        .as_ref()
+        .map(|kernels| kernels.smoothed_positions.as_slice())
+        .unwrap_or(particle_positions);
+
+    // Caps at the domain boundary require the contributions of the particles outside of the domain
+    let exterior_margin = if parameters.cap_domain_boundary {
+        Some(density_map::grid_with_exterior_margin(
+            &grid,
+            max_kernel_radius,
+        )?)
+    } else {
+        None
+    };
+
+    // Density map and iso-surface extraction for every phase
+    let mut phases = Vec::with_capacity(phase_particles.len());
+    for (phase_id, active_particles) in phase_particles {
+        profile!("reconstruct phase surface");
+        info!(
+            "Reconstructing surface of phase {} ({} particles)...",
+            phase_id,
+            active_particles.len()
+        );
+
+        let mut density_map = new_map().into();
+        density_map::generate_sparse_density_map(
+            exterior_margin
+                .as_ref()
+                .map_or(&grid, |(extended_grid, _)| extended_grid),
+            None,
+            kernel_centers,
+            particle_densities.as_slice(),
+            anisotropic_kernels.as_ref(),
+            Some(active_particles.as_slice()),
+            particle_rest_mass,
+            particle_properties,
+            parameters.compact_support_radius,
+            parameters.kernel,
+            parameters.cube_size,
+            parameters.enable_multi_threading,
+            &mut density_map,
+        )?;
+        if let Some((extended_grid, margin_cells)) = exterior_margin.as_ref() {
+            density_map = density_map::restrict_density_map(
+                extended_grid,
+                *margin_cells,
+                &grid,
+                &density_map,
+            );
+        }
+        if let Some(boundary) = boundary.as_ref() {
+            boundary.apply_to_density_map(
+                &grid,
+                &mut density_map,
+                parameters.enable_multi_threading,
+            );
+        }
+
+        let mut mesh = TriMesh3d::default();
+        match parameters.iso_surface_extraction {
+            IsoSurfaceExtraction::MarchingCubes if parameters.cap_domain_boundary => {
+                marching_cubes::triangulate_density_map_capped_append(
+                    &grid,
+                    &density_map,
+                    parameters.iso_surface_threshold,
+                    &mut mesh,
+                )?;
+            }
+            IsoSurfaceExtraction::MarchingCubes => {
+                marching_cubes::triangulate_density_map_append(
+                    &grid,
+                    None,
+                    &density_map,
+                    parameters.iso_surface_threshold,
+                    &mut mesh,
+                )?;
+            }
+            IsoSurfaceExtraction::SurfaceNets => {
+                surface_nets::triangulate_density_map_append(
+                    &grid,
+                    &density_map,
+                    parameters.iso_surface_threshold,
+                    &mut mesh,
+                );
+            }
+        }
+
+        phases.push(PhaseSurface {
+            phase_id,
+            num_particles: active_particles
+                .iter()
+                .filter(|&&i| i < num_particles)
+                .count(),
+            mesh,
+        });
+    }
+
+    // Only return the densities of the original particles
+    particle_densities.truncate(num_particles);
+
+    Ok(MultiphaseSurfaceReconstruction {
+        grid,
+        particle_densities,
+        phases,
+    })
+}
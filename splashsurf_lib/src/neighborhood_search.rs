@@ -9,6 +9,7 @@ use crate::utils::UnsafeSlice;
 use crate::{new_map, profile, Aabb3d, HashState, Index, MapType, ParallelMapType, Real};
 use nalgebra::Vector3;
 use rayon::prelude::*;
+use thiserror::Error as ThisError;
 
 // TODO: Replace some unwrap() calls with errors, e.g. if domain excludes some particles that are neighbors
 // TODO: Check if input parameters are valid (valid domain, valid search radius)
@@ -755,6 +756,260 @@ pub fn compute_neigborhood_stats(neighborhood_list: &Vec<Vec<usize>>) -> Neighbo
     }
 }
 
+/// Errors that can occur during the estimation of the particle spacing
+#[derive(Debug, ThisError)]
+pub enum SpacingEstimationError<R: Real> {
+    /// Indicates that there are less than two distinct particles
+    #[error("too few distinct particles to estimate the particle spacing")]
+    TooFewParticles,
+    /// Indicates that most particles do not have any distinct neighbor within the largest search radius that was tried
+    #[error("most particles do not have a distinct neighbor within the maximum search radius ({search_radius})")]
+    NoNeighbors {
+        /// The largest search radius that was used for the nearest neighbor search
+        search_radius: R,
+    },
+    /// Indicates that the bounding box of the particles is too large for a neighborhood search grid with the search radius as cell size
+    #[error("the bounding box of the particles is too large for a neighborhood search with the search radius {search_radius}")]
+    GridTooLarge {
+        /// The search radius that could not be used for the neighborhood search
+        search_radius: R,
+    },
+}
+
+/// Maximum number of particles whose nearest neighbor distances are used to estimate the particle spacing
+const SPACING_ESTIMATION_SAMPLES: usize = 1000;
+/// Maximum number of times the search radius is doubled to find the nearest neighbors of the sampled particles
+const SPACING_ESTIMATION_MAX_DOUBLINGS: usize = 8;
+/// Maximum number of refinements of the initial search radius based on the occupied cells
+const SPACING_ESTIMATION_MAX_SEED_ITERATIONS: usize = 16;
+
+/// Estimates the particle spacing as the median distance of a sample of the particles to their nearest neighbor
+///
+/// The median is computed over up to 1000 evenly distributed particles, coinciding particles are ignored. The initial
+/// search radius is the volume per particle of the cells that are occupied by particles, which is robust against outliers
+/// that inflate the bounding box of the particles. The search radius is doubled (at most eight times) until more than
+/// half of the sampled particles have a neighbor, which is sufficient to determine their median exactly.
+pub fn estimate_particle_spacing<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    enable_multi_threading: bool,
+) -> Result<R, SpacingEstimationError<R>> {
+    profile!("estimate_particle_spacing");
+
+    let n = particle_positions.len();
+    if n < 2 {
+        return Err(SpacingEstimationError::TooFewParticles);
+    }
+
+    let aabb = if enable_multi_threading {
+        Aabb3d::par_from_points(particle_positions)
+    } else {
+        Aabb3d::from_points(particle_positions)
+    };
+    let extents = aabb.extents();
+    let max_extent = extents.max();
+    if max_extent <= R::zero() {
+        return Err(SpacingEstimationError::TooFewParticles);
+    }
+
+    // Spacing of the particles if they were uniformly distributed in their bounding box (or bounding square for planar data)
+    let n_real = R::from_usize(n).unwrap();
+    let volume = extents.x * extents.y * extents.z;
+    let uniform_spacing = if volume > R::zero() {
+        (volume / n_real).cbrt()
+    } else {
+        max_extent / n_real.sqrt()
+    };
+
+    // Counts the cells of the given size that contain at least one particle, `None` if the cell indices overflow
+    let count_occupied_cells = |cell_size: R| -> Option<usize> {
+        let mut occupied_cells = std::collections::HashSet::with_hasher(HashState::default());
+        for x in particle_positions {
+            let ijk = (x - aabb.min()).map(|d| (d / cell_size).floor());
+            occupied_cells.insert([ijk.x.to_i64()?, ijk.y.to_i64()?, ijk.z.to_i64()?]);
+        }
+        Some(occupied_cells.len())
+    };
+
+    // Shrink the cells to the volume per particle of the occupied cells until most particles are in their own cell
+    let mut search_radius = uniform_spacing;
+    for _ in 0..SPACING_ESTIMATION_MAX_SEED_ITERATIONS {
+        let Some(occupied_cells) = count_occupied_cells(search_radius) else {
+            break;
+        };
+        let occupied_fraction = R::from_usize(occupied_cells).unwrap() / n_real;
+        search_radius *= occupied_fraction.cbrt();
+        if occupied_fraction.cbrt() > R::from_f64(0.5).unwrap() {
+            break;
+        }
+    }
+
+    let sample_step = (n / SPACING_ESTIMATION_SAMPLES).max(1);
+    let sample: Vec<_> = particle_positions
+        .iter()
+        .step_by(sample_step)
+        .copied()
+        .collect();
+
+    for _ in 0..=SPACING_ESTIMATION_MAX_DOUBLINGS {
+        // The neighborhood search grid may not be representable with the index type for far away outliers
+        let mut domain = aabb.clone();
+        domain.grow_uniformly(search_radius);
+        if UniformGrid::<I, R>::from_aabb(&domain, search_radius).is_err() {
+            return Err(SpacingEstimationError::GridTooLarge { search_radius });
+        }
+
+        let mut nearest_distances: Vec<R> = nearest_particle_distances_filtered::<I, R>(
+            particle_positions,
+            &sample,
+            search_radius,
+            enable_multi_threading,
+        )
+        .into_iter()
+        .flatten()
+        .collect();
+
+        // Particles without a neighbor are further away than all found distances, so the median is among the found distances
+        let median_index = sample.len() / 2;
+        if nearest_distances.len() > median_index {
+            let (_, median, _) = nearest_distances.select_nth_unstable_by(median_index, |a, b| {
+                a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)
+            });
+            return Ok(*median);
+        }
+
+        search_radius = search_radius.times(2);
+    }
+
+    Err(SpacingEstimationError::NoNeighbors {
+        search_radius: search_radius.times_f64(0.5),
+    })
+}
+
+/// Computes the distance of each query point to the closest particle that does not coincide with the query point
+fn nearest_particle_distances_filtered<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    query_points: &[Vector3<R>],
+    search_radius: R,
+    enable_multi_threading: bool,
+) -> Vec<Option<R>> {
+    map_query_point_neighbors::<I, R, _, _>(
+        particle_positions,
+        query_points,
+        search_radius,
+        enable_multi_threading,
+        |x, neighbors| {
+            neighbors
+                .iter()
+                .map(|&j| (particle_positions[j] - x).norm())
+                .filter(|&d| d > R::zero())
+                .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        },
+    )
+}
+
+/// Estimates the particle radius of the simulation as half of the particle spacing, see [`estimate_particle_spacing`]
+///
+/// Note that for the surface reconstruction, a slightly larger radius than the actual particle radius of the simulation
+/// usually gives better results (e.g. 1.4 to 1.6 times larger).
+pub fn estimate_particle_radius<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    enable_multi_threading: bool,
+) -> Result<R, SpacingEstimationError<R>> {
+    estimate_particle_spacing::<I, R>(particle_positions, enable_multi_threading)
+        .map(|spacing| spacing * R::from_f64(0.5).unwrap())
+}
+
+/// Computes the distance of each query point to the closest particle, returns `None` for query points without a particle in the given search radius
+///
+/// See [`map_query_point_neighbors`] for details on the neighborhood search.
+pub fn nearest_particle_distances<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    query_points: &[Vector3<R>],
+    search_radius: R,
+    enable_multi_threading: bool,
+) -> Vec<Option<R>> {
+    profile!("nearest_particle_distances");
+
+    map_query_point_neighbors::<I, R, _, _>(
+        particle_positions,
+        query_points,
+        search_radius,
+        enable_multi_threading,
+        |x, neighbors| {
+            neighbors
+                .iter()
+                .map(|&j| (particle_positions[j] - x).norm())
+                .min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+        },
+    )
+}
+
+/// Maps every query point with the indices of all particles within the given search radius of the point to a value, returns the values in the order of the query points
+///
+/// The particles are spatially hashed into a grid with a cell size of the search radius, such that only the
+/// particles in the cell of a query point and its adjacent cells have to be checked.
+pub fn map_query_point_neighbors<
+    I: Index,
+    R: Real,
+    T: Send,
+    F: Fn(&Vector3<R>, &[usize]) -> T + Sync,
+>(
+    particle_positions: &[Vector3<R>],
+    query_points: &[Vector3<R>],
+    search_radius: R,
+    enable_multi_threading: bool,
+    f: F,
+) -> Vec<T> {
+    profile!("map_query_point_neighbors");
+
+    assert!(
+        search_radius > R::zero(),
+        "Search radius for neighborhood search has to be positive!"
+    );
+
+    if particle_positions.is_empty() {
+        return query_points.iter().map(|x| f(x, &[])).collect();
+    }
+
+    let mut domain = if enable_multi_threading {
+        Aabb3d::par_from_points(particle_positions)
+    } else {
+        Aabb3d::from_points(particle_positions)
+    };
+    domain.grow_uniformly(search_radius);
+
+    let grid = UniformGrid::from_aabb(&domain, search_radius)
+        .expect("Failed to construct grid for neighborhood search!");
+    let particles_per_cell =
+        sequential_generate_cell_to_particle_map::<I, R>(&grid, particle_positions);
+
+    let search_radius_squared = search_radius * search_radius;
+    let map_point = |x: &Vector3<R>| {
+        let mut neighbors = Vec::new();
+        // Points outside of the grown domain are farther away than the search radius from all particles
+        if domain.contains_point(x) {
+            if let Some(cell) = grid.get_cell(grid.enclosing_cell(x)) {
+                neighbors.extend(
+                    grid.cells_adjacent_to_cell(&cell)
+                        .chain(std::iter::once(cell))
+                        .filter_map(|c| particles_per_cell.get(&grid.flatten_cell_index(&c)))
+                        .flat_map(|particles| particles.iter().copied())
+                        .filter(|&j| {
+                            (particle_positions[j] - x).norm_squared() <= search_radius_squared
+                        }),
+                );
+            }
+        }
+        f(x, &neighbors)
+    };
+
+    if enable_multi_threading {
+        query_points.par_iter().map(map_point).collect()
+    } else {
+        query_points.iter().map(map_point).collect()
+    }
+}
+
 // Generates a map for spatially hashed indices of all particles (map from cell -> enclosed particles)
 #[inline(never)]
 fn sequential_generate_cell_to_particle_map<I: Index, R: Real>(
@@ -809,3 +1064,66 @@ fn parallel_generate_cell_to_particle_map<I: Index, R: Real>(
 
     particles_per_cell
 }
+
+#[test]
+fn test_estimate_particle_radius() {
+    // Block of particles with a spacing of 0.1 and a few outliers
+    let mut particle_positions = Vec::new();
+    for i in 0..10 {
+        for j in 0..12 {
+            for k in 0..8 {
+                particle_positions.push(Vector3::new(i as f64, j as f64, k as f64) * 0.1);
+            }
+        }
+    }
+    particle_positions.push(Vector3::new(10.0, 10.0, 10.0));
+    particle_positions.push(Vector3::new(-5.0, 0.0, 3.0));
+
+    for enable_multi_threading in [false, true] {
+        let radius =
+            estimate_particle_radius::<i64, f64>(&particle_positions, enable_multi_threading)
+                .unwrap();
+        assert!((radius - 0.05).abs() < 1e-12);
+    }
+
+    assert!(matches!(
+        estimate_particle_radius::<i64, f64>(&particle_positions[..1], false),
+        Err(SpacingEstimationError::TooFewParticles)
+    ));
+    assert!(matches!(
+        estimate_particle_radius::<i64, f64>(&[Vector3::zeros(); 4], false),
+        Err(SpacingEstimationError::TooFewParticles)
+    ));
+}
+
+#[test]
+fn test_estimate_particle_spacing_duplicates_and_outlier() {
+    // Block of particles with a spacing of 0.1 where every particle is stored twice, and a single far away outlier
+    let mut particle_positions = Vec::new();
+    for i in 0..20 {
+        for j in 0..20 {
+            for k in 0..20 {
+                let x = Vector3::new(i as f64, j as f64, k as f64) * 0.1;
+                particle_positions.push(x);
+                particle_positions.push(x);
+            }
+        }
+    }
+    particle_positions.push(Vector3::new(5000.0, -3000.0, 1000.0));
+
+    for enable_multi_threading in [false, true] {
+        let spacing =
+            estimate_particle_spacing::<i64, f64>(&particle_positions, enable_multi_threading)
+                .unwrap();
+        assert!((spacing - 0.1).abs() < 1e-12);
+    }
+
+    // Two far apart clusters of coinciding particles do not have any distinct neighbors at the particle scale
+    let clusters: Vec<_> = (0..200)
+        .map(|i| Vector3::new(if i < 100 { 0.0 } else { 1000.0 }, 0.0, 0.0))
+        .collect();
+    assert!(matches!(
+        estimate_particle_spacing::<i64, f64>(&clusters, false),
+        Err(SpacingEstimationError::NoNeighbors { .. })
+    ));
+}
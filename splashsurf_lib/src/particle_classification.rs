@@ -0,0 +1,298 @@
+//! Classification of particles into interior, surface and isolated particles (e.g. for foam and spray generation)
+//!
+//! The classification of every particle is based on its neighborhood within the compact support radius `h`:
+//!  1. Particles with at most [`ClassificationParameters::max_isolated_neighbors`] neighbors are classified as
+//!     [`ParticleClass::Isolated`] (e.g. spray particles).
+//!  2. For all other particles, the gradient of the SPH color field `∇c_i = sum_j V_j ∇W(x_i - x_j)` is evaluated.
+//!     It vanishes in the interior of the fluid and its norm is largest at the free surface. Particles with a
+//!     normalized gradient norm `h |∇c_i|` above [`ClassificationParameters::surface_threshold`] are classified as
+//!     [`ParticleClass::Surface`], all remaining particles as [`ParticleClass::Interior`].
+//!
+//! For reference, the normalized gradient norm of a particle exactly on a planar free surface of a fluid at rest
+//! density is about 1.4 for the cubic spline kernel and decreases to about 0.3 for a particle at half of the compact
+//! support radius below the surface.
+
+use crate::neighborhood_search::NeighborhoodList;
+use crate::{density_map, neighborhood_search, profile, Aabb3d, Index, Parameters};
+use crate::{kernel::SphKernel, kernel::SymmetricKernel3d, KernelType, ParticleProperties, Real};
+use log::info;
+use nalgebra::Vector3;
+use rayon::prelude::*;
+
+/// Class of a particle with respect to the free surface of the fluid
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ParticleClass {
+    /// Particle in the interior of the fluid
+    Interior,
+    /// Particle at the free surface of the fluid
+    Surface,
+    /// Particle with (almost) no neighbors, e.g. spray
+    Isolated,
+}
+
+impl ParticleClass {
+    /// Returns the integer label of the class that is used when writing the classification to files (interior: 0, surface: 1, isolated: 2)
+    pub fn label(self) -> u64 {
+        match self {
+            ParticleClass::Interior => 0,
+            ParticleClass::Surface => 1,
+            ParticleClass::Isolated => 2,
+        }
+    }
+}
+
+/// Parameters for the classification of particles
+#[derive(Clone, Debug)]
+pub struct ClassificationParameters<R: Real> {
+    /// Threshold for the color field gradient norm (normalized by the compact support radius) above which a particle is classified as surface particle
+    pub surface_threshold: R,
+    /// Particles with at most this number of neighbors are classified as isolated particles
+    pub max_isolated_neighbors: usize,
+}
+
+impl<R: Real> Default for ClassificationParameters<R> {
+    /// Returns parameters with a surface threshold of 0.5 and at most 5 neighbors for isolated particles
+    fn default() -> Self {
+        Self {
+            surface_threshold: R::from_f64(0.5).unwrap(),
+            max_isolated_neighbors: 5,
+        }
+    }
+}
+
+impl<R: Real> ClassificationParameters<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns None if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<ClassificationParameters<T>> {
+        Some(ClassificationParameters {
+            surface_threshold: self.surface_threshold.try_convert()?,
+            max_isolated_neighbors: self.max_isolated_neighbors,
+        })
+    }
+}
+
+/// Classifies the given particles, see the [module level documentation](self)
+///
+/// The neighborhood lists have to contain all neighbors within the compact support radius (without the particle itself).
+/// If per particle masses or compact support radii are provided by `particle_properties`, every neighbor contributes
+/// with its own volume and kernel radius and the neighborhood lists have to be computed using the largest radius.
+pub fn classify_particles<R: Real, Nl: NeighborhoodList + Sync + ?Sized>(
+    particle_positions: &[Vector3<R>],
+    particle_densities: &[R],
+    particle_neighbor_lists: &Nl,
+    compact_support_radius: R,
+    kernel_type: KernelType,
+    particle_rest_mass: R,
+    particle_properties: ParticleProperties<R>,
+    parameters: &ClassificationParameters<R>,
+    enable_multi_threading: bool,
+) -> Vec<ParticleClass> {
+    profile!("classify_particles");
+    assert_eq!(particle_positions.len(), particle_densities.len());
+    assert_eq!(particle_positions.len(), particle_neighbor_lists.len());
+
+    let color_field_gradients = compute_color_field_gradients(
+        particle_positions,
+        particle_densities,
+        particle_neighbor_lists,
+        compact_support_radius,
+        kernel_type,
+        particle_rest_mass,
+        particle_properties,
+        enable_multi_threading,
+    );
+
+    let classify = |i: usize| {
+        if particle_neighbor_lists.neighbors(i).len() <= parameters.max_isolated_neighbors {
+            return ParticleClass::Isolated;
+        }
+
+        let h_i = particle_properties.compact_support_radius(i, compact_support_radius);
+        if color_field_gradients[i].norm() * h_i > parameters.surface_threshold {
+            ParticleClass::Surface
+        } else {
+            ParticleClass::Interior
+        }
+    };
+
+    let classes: Vec<_> = if enable_multi_threading {
+        (0..particle_positions.len())
+            .into_par_iter()
+            .map(classify)
+            .collect()
+    } else {
+        (0..particle_positions.len()).map(classify).collect()
+    };
+
+    let count = |class| classes.iter().filter(|&&c| c == class).count();
+    info!(
+        "Classified particles: {} interior, {} surface, {} isolated",
+        count(ParticleClass::Interior),
+        count(ParticleClass::Surface),
+        count(ParticleClass::Isolated)
+    );
+
+    classes
+}
+
+/// Computes the gradient of the SPH color field `∇c_i = sum_j V_j ∇W(x_i - x_j)` of all particles (points into the fluid)
+pub(crate) fn compute_color_field_gradients<R: Real, Nl: NeighborhoodList + Sync + ?Sized>(
+    particle_positions: &[Vector3<R>],
+    particle_densities: &[R],
+    particle_neighbor_lists: &Nl,
+    compact_support_radius: R,
+    kernel_type: KernelType,
+    particle_rest_mass: R,
+    particle_properties: ParticleProperties<R>,
+    enable_multi_threading: bool,
+) -> Vec<Vector3<R>> {
+    let max_compact_support_radius =
+        particle_properties.max_compact_support_radius(compact_support_radius);
+    let kernel = SphKernel::new(kernel_type, max_compact_support_radius);
+
+    let color_field_gradient = |i: usize| {
+        let x_i = particle_positions[i];
+        let mut gradient = Vector3::zeros();
+        for &j in particle_neighbor_lists.neighbors(i) {
+            let dx = x_i - particle_positions[j];
+            let r = dx.norm();
+            if r <= R::zero() {
+                continue;
+            }
+
+            let vol_j = particle_properties.mass(j, particle_rest_mass) / particle_densities[j];
+            // Evaluate the kernel of the neighbor rescaled to its own compact support radius
+            let s = max_compact_support_radius
+                / particle_properties.compact_support_radius(j, max_compact_support_radius);
+            let gradient_norm = s * s * s * s * kernel.evaluate_gradient_norm(r * s);
+            gradient += dx.unscale(r) * (vol_j * gradient_norm);
+        }
+        gradient
+    };
+
+    if enable_multi_threading {
+        (0..particle_positions.len())
+            .into_par_iter()
+            .map(color_field_gradient)
+            .collect()
+    } else {
+        (0..particle_positions.len())
+            .map(color_field_gradient)
+            .collect()
+    }
+}
+
+/// Classifies the given particles using the kernel, particle radius and rest density of the reconstruction parameters
+///
+/// Performs a neighborhood search and computes the particle densities before classifying the particles
+/// with [`classify_particles`].
+pub fn classify_particles_with_parameters<I: Index, R: Real>(
+    particle_positions: &[Vector3<R>],
+    reconstruction_parameters: &Parameters<R>,
+    parameters: &ClassificationParameters<R>,
+) -> Vec<ParticleClass> {
+    profile!("classify_particles_with_parameters");
+
+    if particle_positions.is_empty() {
+        return Vec::new();
+    }
+
+    let compact_support_radius = reconstruction_parameters.compact_support_radius;
+    let enable_multi_threading = reconstruction_parameters.enable_multi_threading;
+
+    let mut domain = if enable_multi_threading {
+        Aabb3d::par_from_points(particle_positions)
+    } else {
+        Aabb3d::from_points(particle_positions)
+    };
+    domain.grow_uniformly(compact_support_radius);
+
+    let particle_neighbor_lists = neighborhood_search::search::<I, R>(
+        &domain,
+        particle_positions,
+        compact_support_radius,
+        enable_multi_threading,
+    );
+
+    let particle_rest_volume = R::from_f64((4.0 / 3.0) * std::f64::consts::PI).unwrap()
+        * reconstruction_parameters.particle_radius.powi(3);
+    let particle_rest_mass = particle_rest_volume * reconstruction_parameters.rest_density;
+
+    let particle_densities = density_map::compute_particle_densities::<I, R>(
+        particle_positions,
+        &particle_neighbor_lists,
+        compact_support_radius,
+        reconstruction_parameters.kernel,
+        particle_rest_mass,
+        ParticleProperties::uniform(),
+        enable_multi_threading,
+    );
+
+    classify_particles(
+        particle_positions,
+        &particle_densities,
+        &particle_neighbor_lists,
+        compact_support_radius,
+        reconstruction_parameters.kernel,
+        particle_rest_mass,
+        ParticleProperties::uniform(),
+        parameters,
+        enable_multi_threading,
+    )
+}
+
+#[test]
+fn test_classify_particles_of_block_with_spray() {
+    // A block of 10x10x10 particles and a single spray particle far away from the block
+    let particle_radius = 0.025;
+    let spacing = 2.0 * particle_radius;
+    let mut particle_positions = Vec::new();
+    for i in 0..10 {
+        for j in 0..10 {
+            for k in 0..10 {
+                particle_positions.push(Vector3::new(i as f64, j as f64, k as f64) * spacing);
+            }
+        }
+    }
+    particle_positions.push(Vector3::new(2.0, 2.0, 2.0));
+
+    let compact_support_radius = 4.0 * particle_radius;
+    let mut neighbor_lists = Vec::new();
+    neighborhood_search::neighborhood_search_naive(
+        &particle_positions,
+        compact_support_radius,
+        &mut neighbor_lists,
+    );
+
+    let particle_rest_mass = 1000.0 * spacing.powi(3);
+    let particle_densities = density_map::compute_particle_densities::<i64, f64>(
+        &particle_positions,
+        &neighbor_lists,
+        compact_support_radius,
+        KernelType::CubicSpline,
+        particle_rest_mass,
+        ParticleProperties::uniform(),
+        false,
+    );
+
+    let classes = classify_particles(
+        &particle_positions,
+        &particle_densities,
+        &neighbor_lists,
+        compact_support_radius,
+        KernelType::CubicSpline,
+        particle_rest_mass,
+        ParticleProperties::uniform(),
+        &ClassificationParameters::default(),
+        false,
+    );
+
+    let index = |i: usize, j: usize, k: usize| 100 * i + 10 * j + k;
+    // Particles on the faces, edges and corners of the block are surface particles
+    assert_eq!(classes[index(0, 0, 0)], ParticleClass::Surface);
+    assert_eq!(classes[index(0, 5, 5)], ParticleClass::Surface);
+    assert_eq!(classes[index(9, 4, 9)], ParticleClass::Surface);
+    // Particles deep inside of the block are interior particles
+    assert_eq!(classes[index(5, 5, 5)], ParticleClass::Interior);
+    assert_eq!(classes[index(3, 6, 4)], ParticleClass::Interior);
+    assert_eq!(classes[1000], ParticleClass::Isolated);
+}
@@ -0,0 +1,29 @@
+//! Post-processing of reconstructed surface meshes
+//!
+//! The functions in this module operate on the [`TriMesh3d`](crate::mesh::TriMesh3d) returned by
+//! the surface reconstruction and can be used to improve the quality of the mesh before it is
+//! written to disk:
+//!  - [`smoothing`]: Laplacian and Taubin smoothing to remove grid-aligned artifacts of marching cubes, optionally
+//!    weighted by the distance to the particles and with volume correction
+//!  - [`decimation`]: Quadric error metric edge-collapse simplification to reduce the number of triangles
+//!  - [`vertex_snapping`]: Removal of sliver triangles by snapping marching cubes vertices close to grid points onto the grid
+//!  - [`quad_conversion`]: Conversion into a quad-dominant [`MixedTriQuadMesh3d`](crate::mesh::MixedTriQuadMesh3d) by pairing adjacent triangles
+//!  - [`components`]: Analysis of the connected components of a mesh and removal of small fragments (e.g. droplets)
+
+pub mod components;
+pub mod decimation;
+pub mod quad_conversion;
+pub mod smoothing;
+pub mod vertex_snapping;
+
+pub use components::{
+    compute_mesh_components, filter_mesh_components, ComponentFilterParameters, MeshComponent,
+    MeshComponents,
+};
+pub use decimation::{decimate_mesh, DecimationParameters};
+pub use quad_conversion::{convert_tris_to_quads, QuadConversionParameters};
+pub use smoothing::{
+    smooth_mesh, smooth_mesh_weighted, LaplacianWeights, ParticleDistanceWeighting,
+    SmoothingParameters,
+};
+pub use vertex_snapping::snap_vertices_to_grid;
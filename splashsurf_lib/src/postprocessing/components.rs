@@ -0,0 +1,306 @@
+//! Connected component analysis of triangle meshes and removal of small fragments
+//!
+//! Reconstructions of splashes often contain a large number of tiny disconnected meshes (e.g. single droplets).
+//! The connected components of a mesh (i.e. sets of triangles connected by shared vertices) can be analyzed
+//! using [`compute_mesh_components`], which reports the triangle count, area, enclosed volume and bounding box
+//! of every component. Components can be removed based on their size using [`filter_mesh_components`].
+
+use crate::mesh::TriMesh3d;
+use crate::{profile, Aabb3d, Real};
+use log::info;
+use std::cmp::Ordering;
+
+/// Properties of a connected component of a triangle mesh
+#[derive(Clone, Debug)]
+pub struct MeshComponent<R: Real> {
+    /// Number of triangles of the component
+    pub num_triangles: usize,
+    /// Surface area of the component
+    pub area: R,
+    /// Signed volume enclosed by the component (positive for outward oriented triangles), only meaningful for closed components
+    pub volume: R,
+    /// Axis aligned bounding box of the vertices of the component
+    pub aabb: Aabb3d<R>,
+}
+
+/// Connected components of a triangle mesh
+#[derive(Clone, Debug)]
+pub struct MeshComponents<R: Real> {
+    /// Index of the component of every triangle
+    pub triangle_components: Vec<usize>,
+    /// Properties of every component, components are numbered in the order of their first triangle
+    pub components: Vec<MeshComponent<R>>,
+}
+
+/// Criteria for the removal of small connected components of a mesh
+///
+/// Components are removed if they violate any of the given criteria.
+#[derive(Clone, Debug, Default)]
+pub struct ComponentFilterParameters<R: Real> {
+    /// Remove all components with fewer triangles
+    pub min_triangles: Option<usize>,
+    /// Remove all components with a smaller surface area
+    pub min_area: Option<R>,
+    /// Only keep this number of components with the largest surface area
+    pub keep_largest: Option<usize>,
+}
+
+impl<R: Real> ComponentFilterParameters<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns None if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<ComponentFilterParameters<T>> {
+        Some(ComponentFilterParameters {
+            min_triangles: self.min_triangles,
+            min_area: match self.min_area {
+                Some(min_area) => Some(min_area.try_convert()?),
+                None => None,
+            },
+            keep_largest: self.keep_largest,
+        })
+    }
+}
+
+/// Computes the connected components of the mesh, returns the number of components and the component of every vertex
+///
+/// Vertices that are not referenced by any triangle form a component on their own.
+pub(crate) fn vertex_components<R: Real>(mesh: &TriMesh3d<R>) -> (usize, Vec<usize>) {
+    // Union-find over the vertices connected by triangles
+    let mut parent = (0..mesh.vertices.len()).collect::<Vec<_>>();
+    fn find(parent: &mut [usize], mut v: usize) -> usize {
+        while parent[v] != v {
+            parent[v] = parent[parent[v]];
+            v = parent[v];
+        }
+        v
+    }
+    for tri in mesh.triangles.iter() {
+        let r0 = find(&mut parent, tri[0]);
+        for &v in &tri[1..] {
+            let r = find(&mut parent, v);
+            if r != r0 {
+                parent[r] = r0;
+            }
+        }
+    }
+
+    let mut component_of_root = vec![usize::MAX; mesh.vertices.len()];
+    let mut vertex_component = Vec::with_capacity(mesh.vertices.len());
+    let mut count = 0;
+    for v in 0..mesh.vertices.len() {
+        let root = find(&mut parent, v);
+        if component_of_root[root] == usize::MAX {
+            component_of_root[root] = count;
+            count += 1;
+        }
+        vertex_component.push(component_of_root[root]);
+    }
+
+    (count, vertex_component)
+}
+
+/// Computes the connected components of the triangles of the mesh and their properties
+pub fn compute_mesh_components<R: Real>(mesh: &TriMesh3d<R>) -> MeshComponents<R> {
+    profile!("compute_mesh_components");
+
+    let (num_vertex_components, vertex_component) = vertex_components(mesh);
+
+    // Number the components in the order of their first triangle, ignoring vertices without triangles
+    let mut component_index = vec![usize::MAX; num_vertex_components];
+    let mut components: Vec<MeshComponent<R>> = Vec::new();
+    let mut triangle_components = Vec::with_capacity(mesh.triangles.len());
+
+    let half = R::from_f64(0.5).unwrap();
+    let sixth = R::from_f64(1.0 / 6.0).unwrap();
+    for tri in mesh.triangles.iter() {
+        let vertex_component = vertex_component[tri[0]];
+        if component_index[vertex_component] == usize::MAX {
+            component_index[vertex_component] = components.len();
+            components.push(MeshComponent {
+                num_triangles: 0,
+                area: R::zero(),
+                volume: R::zero(),
+                aabb: Aabb3d::from_point(mesh.vertices[tri[0]]),
+            });
+        }
+
+        let c = component_index[vertex_component];
+        triangle_components.push(c);
+
+        let [v0, v1, v2] = tri.map(|v| mesh.vertices[v]);
+        let component = &mut components[c];
+        component.num_triangles += 1;
+        component.area += (v1 - v0).cross(&(v2 - v0)).norm() * half;
+        component.volume += v0.dot(&v1.cross(&v2)) * sixth;
+        for v in [v0, v1, v2] {
+            component.aabb.join_with_point(&v);
+        }
+    }
+
+    MeshComponents {
+        triangle_components,
+        components,
+    }
+}
+
+/// Removes all connected components of the mesh that violate the given criteria, returns the components of the remaining mesh
+///
+/// Vertices that are no longer referenced by any triangle are removed from the mesh.
+/// The relative order of the remaining vertices and triangles is preserved.
+pub fn filter_mesh_components<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    parameters: &ComponentFilterParameters<R>,
+) -> MeshComponents<R> {
+    profile!("filter_mesh_components");
+
+    let components = compute_mesh_components(mesh);
+
+    let mut keep_component = components
+        .components
+        .iter()
+        .map(|component| {
+            let too_few_triangles = matches!(parameters.min_triangles, Some(min_triangles) if component.num_triangles < min_triangles);
+            let too_small = matches!(parameters.min_area, Some(min_area) if component.area < min_area);
+            !too_few_triangles && !too_small
+        })
+        .collect::<Vec<_>>();
+
+    if let Some(keep_largest) = parameters.keep_largest {
+        let mut by_area = (0..components.components.len())
+            .filter(|&c| keep_component[c])
+            .collect::<Vec<_>>();
+        // Sort by descending area, components with equal area are kept in their original order
+        by_area.sort_by(|&a, &b| {
+            components.components[b]
+                .area
+                .partial_cmp(&components.components[a].area)
+                .unwrap_or(Ordering::Equal)
+        });
+        for &c in by_area.iter().skip(keep_largest) {
+            keep_component[c] = false;
+        }
+    }
+
+    let num_components_before = components.components.len();
+    let num_triangles_before = mesh.triangles.len();
+
+    // Renumber the remaining components
+    let mut new_component_index = vec![usize::MAX; num_components_before];
+    let mut remaining_components = Vec::new();
+    for (c, component) in components.components.into_iter().enumerate() {
+        if keep_component[c] {
+            new_component_index[c] = remaining_components.len();
+            remaining_components.push(component);
+        }
+    }
+
+    // Remove all vertices that are not referenced by the remaining triangles
+    let mut vertex_map = vec![usize::MAX; mesh.vertices.len()];
+    for (tri, &c) in mesh.triangles.iter().zip(&components.triangle_components) {
+        if keep_component[c] {
+            for &v in tri {
+                vertex_map[v] = 0;
+            }
+        }
+    }
+    let mut vertices = Vec::new();
+    for (v, new_v) in vertex_map.iter_mut().enumerate() {
+        if *new_v != usize::MAX {
+            *new_v = vertices.len();
+            vertices.push(mesh.vertices[v]);
+        }
+    }
+
+    // Remove the triangles of the removed components
+    let mut triangles = Vec::new();
+    let mut triangle_components = Vec::new();
+    for (tri, &c) in mesh.triangles.iter().zip(&components.triangle_components) {
+        if keep_component[c] {
+            triangles.push(tri.map(|v| vertex_map[v]));
+            triangle_components.push(new_component_index[c]);
+        }
+    }
+
+    mesh.vertices = vertices;
+    mesh.triangles = triangles;
+
+    info!(
+        "Removed {} of {} mesh components ({} of {} triangles).",
+        num_components_before - remaining_components.len(),
+        num_components_before,
+        num_triangles_before - mesh.triangles.len(),
+        num_triangles_before
+    );
+
+    MeshComponents {
+        triangle_components,
+        components: remaining_components,
+    }
+}
+
+#[test]
+fn test_filter_mesh_components() {
+    use nalgebra::Vector3;
+
+    // Appends an outward oriented tetrahedron with the given origin and edge length
+    let push_tetrahedron = |mesh: &mut TriMesh3d<f64>, origin: Vector3<f64>, size: f64| {
+        let offset = mesh.vertices.len();
+        mesh.vertices.extend([
+            origin,
+            origin + Vector3::x() * size,
+            origin + Vector3::y() * size,
+            origin + Vector3::z() * size,
+        ]);
+        mesh.triangles.extend(
+            [[0, 2, 1], [0, 1, 3], [0, 3, 2], [1, 2, 3]].map(|tri| tri.map(|v| v + offset)),
+        );
+    };
+
+    let mut mesh = TriMesh3d::default();
+    push_tetrahedron(&mut mesh, Vector3::new(5.0, 0.0, 0.0), 0.1);
+    // Vertex that is not referenced by any triangle
+    mesh.vertices.push(Vector3::new(-1.0, -1.0, -1.0));
+    push_tetrahedron(&mut mesh, Vector3::zeros(), 1.0);
+    push_tetrahedron(&mut mesh, Vector3::new(0.0, 5.0, 0.0), 0.5);
+
+    let components = compute_mesh_components(&mesh);
+    assert_eq!(components.components.len(), 3);
+    assert_eq!(
+        components.triangle_components,
+        vec![0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2]
+    );
+    let large = &components.components[1];
+    assert_eq!(large.num_triangles, 4);
+    assert!((large.volume - 1.0 / 6.0).abs() < 1e-12);
+    assert!((large.area - (1.5 + 0.5 * 3.0f64.sqrt())).abs() < 1e-12);
+    assert_eq!(large.aabb.min(), &Vector3::zeros());
+    assert_eq!(large.aabb.max(), &Vector3::repeat(1.0));
+
+    // Remove the smallest tetrahedron by its area
+    let mut filtered_mesh = mesh.clone();
+    let remaining = filter_mesh_components(
+        &mut filtered_mesh,
+        &ComponentFilterParameters {
+            min_area: Some(0.1),
+            ..Default::default()
+        },
+    );
+    assert_eq!(remaining.components.len(), 2);
+    assert_eq!(filtered_mesh.vertices.len(), 8);
+    assert_eq!(filtered_mesh.triangles.len(), 8);
+    assert_eq!(filtered_mesh.vertices[0], Vector3::zeros());
+    assert_eq!(remaining.triangle_components, vec![0, 0, 0, 0, 1, 1, 1, 1]);
+
+    // Only keep the largest tetrahedron
+    let mut filtered_mesh = mesh.clone();
+    let remaining = filter_mesh_components(
+        &mut filtered_mesh,
+        &ComponentFilterParameters {
+            keep_largest: Some(1),
+            ..Default::default()
+        },
+    );
+    assert_eq!(remaining.components.len(), 1);
+    assert_eq!(remaining.components[0].num_triangles, 4);
+    assert_eq!(filtered_mesh.vertices.len(), 4);
+    assert!((filtered_mesh.signed_volume() - 1.0 / 6.0).abs() < 1e-12);
+    assert!(filtered_mesh.find_boundary_edges().is_empty());
+}
@@ -0,0 +1,445 @@
+//! Simplification of triangle meshes using quadric error metric (QEM) edge collapses
+//!
+//! Implements the edge collapse decimation by Garland and Heckbert ("Surface Simplification Using
+//! Quadric Error Metrics", 1997). Every vertex accumulates the quadric of the planes of its adjacent
+//! triangles, the edges with the smallest error of their optimal collapse position are collapsed first.
+//!
+//! To keep the mesh manifold, collapses that violate the link condition, that would flip the
+//! orientation of a triangle or that would reduce the valence of a vertex below three are rejected.
+//! Vertices on the boundary of the mesh are never moved or removed, therefore a closed mesh stays
+//! closed (which can be verified using [`TriMesh3d::find_boundary_edges`]).
+
+use crate::mesh::TriMesh3d;
+use crate::{profile, Real};
+use log::info;
+use nalgebra::{Matrix4, Vector3, Vector4};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Parameters for the decimation of triangle meshes
+///
+/// At least one of the stopping criteria should be specified, otherwise the mesh is simplified as much as possible.
+#[derive(Clone, Debug)]
+pub struct DecimationParameters<R: Real> {
+    /// Stop the decimation as soon as the mesh has at most this number of triangles
+    pub target_triangle_count: Option<usize>,
+    /// Do not perform collapses with an error larger than this value (in distance units, the quadric error is a sum of squared distances to the planes of the original triangles)
+    pub max_error: Option<R>,
+}
+
+impl<R: Real> DecimationParameters<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns None if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<DecimationParameters<T>> {
+        Some(DecimationParameters {
+            target_triangle_count: self.target_triangle_count,
+            max_error: match self.max_error {
+                Some(max_error) => Some(max_error.try_convert()?),
+                None => None,
+            },
+        })
+    }
+}
+
+/// A candidate for an edge collapse stored in the priority queue
+struct CollapseCandidate<R: Real> {
+    /// Quadric error of the collapse
+    cost: R,
+    /// The vertex that is kept by the collapse
+    v0: usize,
+    /// The vertex that is removed by the collapse
+    v1: usize,
+    /// Version of the two vertices when the candidate was created, used to detect outdated candidates
+    versions: [usize; 2],
+    /// Position of the remaining vertex after the collapse
+    position: Vector3<R>,
+}
+
+impl<R: Real> PartialEq for CollapseCandidate<R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<R: Real> Eq for CollapseCandidate<R> {}
+
+impl<R: Real> PartialOrd for CollapseCandidate<R> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<R: Real> Ord for CollapseCandidate<R> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed order to turn the max-heap into a min-heap
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+    }
+}
+
+/// Internal state of the decimation
+struct Decimation<'a, R: Real> {
+    mesh: &'a mut TriMesh3d<R>,
+    quadrics: Vec<Matrix4<R>>,
+    /// Indices of the triangles adjacent to each vertex
+    vertex_triangles: Vec<Vec<usize>>,
+    /// Vertices that are part of a boundary edge of the mesh
+    boundary_vertices: Vec<bool>,
+    vertex_alive: Vec<bool>,
+    vertex_versions: Vec<usize>,
+    triangle_alive: Vec<bool>,
+    num_triangles: usize,
+}
+
+/// Simplifies the given mesh inplace using quadric error metric edge collapses until one of the stopping criteria is reached
+pub fn decimate_mesh<R: Real>(mesh: &mut TriMesh3d<R>, parameters: &DecimationParameters<R>) {
+    profile!("decimate_mesh");
+
+    info!(
+        "Decimating mesh with {} triangles (target triangle count: {:?}, max error: {:?})...",
+        mesh.triangles.len(),
+        parameters.target_triangle_count,
+        parameters.max_error
+    );
+
+    let target_triangle_count = parameters.target_triangle_count.unwrap_or(0);
+    let max_cost = parameters.max_error.map(|e| e * e);
+
+    let mut decimation = Decimation::new(mesh);
+    let mut queue = BinaryHeap::new();
+    for tri in decimation.mesh.triangles.iter() {
+        for (i0, i1) in [(0, 1), (1, 2), (2, 0)] {
+            // In a consistently oriented mesh every interior edge appears once in each direction
+            if tri[i0] < tri[i1] {
+                if let Some(candidate) = decimation.collapse_candidate(tri[i0], tri[i1]) {
+                    queue.push(candidate);
+                }
+            }
+        }
+    }
+
+    while decimation.num_triangles > target_triangle_count {
+        let candidate = match queue.pop() {
+            Some(candidate) => candidate,
+            None => break,
+        };
+
+        if let Some(max_cost) = max_cost {
+            if candidate.cost > max_cost {
+                break;
+            }
+        }
+
+        if !decimation.is_up_to_date(&candidate) || !decimation.is_collapse_valid(&candidate) {
+            continue;
+        }
+
+        decimation.collapse(&candidate);
+
+        // Update the candidates of all edges adjacent to the remaining vertex
+        let v0 = candidate.v0;
+        for v in decimation.vertex_neighbors(v0) {
+            if let Some(candidate) = decimation.collapse_candidate(v0, v) {
+                queue.push(candidate);
+            }
+        }
+    }
+
+    decimation.compact();
+
+    info!(
+        "Decimation finished, mesh has {} vertices and {} triangles.",
+        mesh.vertices.len(),
+        mesh.triangles.len()
+    );
+}
+
+impl<'a, R: Real> Decimation<'a, R> {
+    fn new(mesh: &'a mut TriMesh3d<R>) -> Self {
+        let mut quadrics = vec![Matrix4::zeros(); mesh.vertices.len()];
+        let mut vertex_triangles = vec![Vec::new(); mesh.vertices.len()];
+
+        for (tri_idx, tri) in mesh.triangles.iter().enumerate() {
+            let quadric = triangle_quadric(mesh, tri);
+            for &v in tri {
+                quadrics[v] += quadric;
+                vertex_triangles[v].push(tri_idx);
+            }
+        }
+
+        let boundary_vertices = mesh.boundary_vertex_flags();
+        let num_vertices = mesh.vertices.len();
+        let num_triangles = mesh.triangles.len();
+
+        Self {
+            mesh,
+            quadrics,
+            vertex_triangles,
+            boundary_vertices,
+            vertex_alive: vec![true; num_vertices],
+            vertex_versions: vec![0; num_vertices],
+            triangle_alive: vec![true; num_triangles],
+            num_triangles,
+        }
+    }
+
+    /// Returns all vertices that share an edge with the given vertex
+    fn vertex_neighbors(&self, v: usize) -> Vec<usize> {
+        let mut neighbors = Vec::new();
+        for &tri_idx in &self.vertex_triangles[v] {
+            for &w in &self.mesh.triangles[tri_idx] {
+                if w != v && !neighbors.contains(&w) {
+                    neighbors.push(w);
+                }
+            }
+        }
+        neighbors
+    }
+
+    /// Computes the optimal position and error of collapsing the given edge, returns `None` if the edge cannot be collapsed
+    fn collapse_candidate(&self, v0: usize, v1: usize) -> Option<CollapseCandidate<R>> {
+        if self.boundary_vertices[v0] || self.boundary_vertices[v1] {
+            return None;
+        }
+
+        let quadric = self.quadrics[v0] + self.quadrics[v1];
+        let p0 = self.mesh.vertices[v0];
+        let p1 = self.mesh.vertices[v1];
+        let midpoint = (p0 + p1).scale(R::from_f64(0.5).unwrap());
+
+        let mut candidates = vec![p0, p1, midpoint];
+        if let Some(optimum) = quadric_minimizer(&quadric) {
+            // Reject the optimum of (almost) singular quadrics that is located far away from the edge
+            let max_distance = (p1 - p0).norm() * R::from_f64(2.0).unwrap();
+            if (optimum - midpoint).norm() <= max_distance {
+                candidates.push(optimum);
+            }
+        }
+
+        candidates
+            .into_iter()
+            .map(|p| (quadric_error(&quadric, &p), p))
+            .min_by(|(a, _), (b, _)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+            .map(|(cost, position)| CollapseCandidate {
+                cost,
+                v0,
+                v1,
+                versions: [self.vertex_versions[v0], self.vertex_versions[v1]],
+                position,
+            })
+    }
+
+    /// Returns whether the vertices of the candidate were not modified since its creation
+    fn is_up_to_date(&self, candidate: &CollapseCandidate<R>) -> bool {
+        self.vertex_alive[candidate.v0]
+            && self.vertex_alive[candidate.v1]
+            && self.vertex_versions[candidate.v0] == candidate.versions[0]
+            && self.vertex_versions[candidate.v1] == candidate.versions[1]
+    }
+
+    /// Checks whether the collapse preserves the manifold property and the orientation of all triangles
+    fn is_collapse_valid(&self, candidate: &CollapseCandidate<R>) -> bool {
+        let (v0, v1) = (candidate.v0, candidate.v1);
+
+        let neighbors0 = self.vertex_neighbors(v0);
+        let neighbors1 = self.vertex_neighbors(v1);
+        if !neighbors0.contains(&v1) {
+            return false;
+        }
+
+        // Link condition: the only common neighbors are the opposite vertices of the two triangles adjacent to the edge
+        let common = neighbors0
+            .iter()
+            .copied()
+            .filter(|v| neighbors1.contains(v))
+            .collect::<Vec<_>>();
+        let shared_triangles = self.vertex_triangles[v0]
+            .iter()
+            .filter(|&&tri_idx| self.mesh.triangles[tri_idx].contains(&v1))
+            .count();
+        if common.len() != 2 || shared_triangles != 2 {
+            return false;
+        }
+
+        // The opposite vertices lose one neighbor, the remaining vertex gets the neighbors of both
+        for &v in &common {
+            if self.vertex_neighbors(v).len() <= 3 {
+                return false;
+            }
+        }
+        if neighbors0.len() + neighbors1.len() - 4 < 3 {
+            return false;
+        }
+
+        // Check that no triangle flips its orientation or degenerates
+        for (v_moved, tris) in [
+            (v0, &self.vertex_triangles[v0]),
+            (v1, &self.vertex_triangles[v1]),
+        ] {
+            for &tri_idx in tris {
+                let tri = &self.mesh.triangles[tri_idx];
+                if tri.contains(&v0) && tri.contains(&v1) {
+                    continue;
+                }
+
+                let old_normal = triangle_normal(&self.mesh.vertices, tri, None);
+                let new_normal = triangle_normal(
+                    &self.mesh.vertices,
+                    tri,
+                    Some((v_moved, &candidate.position)),
+                );
+
+                match (
+                    old_normal.try_normalize(R::default_epsilon()),
+                    new_normal.try_normalize(R::default_epsilon()),
+                ) {
+                    (Some(old_normal), Some(new_normal)) => {
+                        if old_normal.dot(&new_normal) <= R::from_f64(0.2).unwrap() {
+                            return false;
+                        }
+                    }
+                    (_, None) => return false,
+                    (None, Some(_)) => {}
+                }
+            }
+        }
+
+        true
+    }
+
+    /// Collapses the edge of the candidate by merging its second vertex into the first vertex
+    fn collapse(&mut self, candidate: &CollapseCandidate<R>) {
+        let (v0, v1) = (candidate.v0, candidate.v1);
+
+        let tris1 = std::mem::take(&mut self.vertex_triangles[v1]);
+        for tri_idx in tris1 {
+            let tri = self.mesh.triangles[tri_idx];
+            if tri.contains(&v0) {
+                // Remove the triangles adjacent to the collapsed edge
+                self.triangle_alive[tri_idx] = false;
+                self.num_triangles -= 1;
+                for v in tri {
+                    if v != v1 {
+                        self.vertex_triangles[v].retain(|&t| t != tri_idx);
+                    }
+                }
+            } else {
+                for v in self.mesh.triangles[tri_idx].iter_mut() {
+                    if *v == v1 {
+                        *v = v0;
+                    }
+                }
+                self.vertex_triangles[v0].push(tri_idx);
+            }
+        }
+
+        self.mesh.vertices[v0] = candidate.position;
+        let quadric_v1 = self.quadrics[v1];
+        self.quadrics[v0] += quadric_v1;
+        self.vertex_alive[v1] = false;
+        self.vertex_versions[v0] += 1;
+        self.vertex_versions[v1] += 1;
+    }
+
+    /// Removes all collapsed vertices and triangles from the mesh
+    fn compact(self) {
+        let mut new_indices = vec![usize::MAX; self.mesh.vertices.len()];
+        let mut num_vertices = 0;
+        for (i, alive) in self.vertex_alive.iter().copied().enumerate() {
+            if alive {
+                new_indices[i] = num_vertices;
+                self.mesh.vertices.swap(num_vertices, i);
+                num_vertices += 1;
+            }
+        }
+        self.mesh.vertices.truncate(num_vertices);
+
+        let triangle_alive = self.triangle_alive;
+        let mut tri_idx = 0;
+        self.mesh.triangles.retain(|_| {
+            tri_idx += 1;
+            triangle_alive[tri_idx - 1]
+        });
+        for tri in self.mesh.triangles.iter_mut() {
+            for v in tri.iter_mut() {
+                *v = new_indices[*v];
+            }
+        }
+    }
+}
+
+/// Computes the (fundamental error) quadric of the plane of the given triangle
+fn triangle_quadric<R: Real>(mesh: &TriMesh3d<R>, tri: &[usize; 3]) -> Matrix4<R> {
+    let normal = triangle_normal(&mesh.vertices, tri, None);
+    match normal.try_normalize(R::default_epsilon()) {
+        Some(n) => {
+            let d = -n.dot(&mesh.vertices[tri[0]]);
+            let plane = Vector4::new(n.x, n.y, n.z, d);
+            plane * plane.transpose()
+        }
+        None => Matrix4::zeros(),
+    }
+}
+
+/// Computes the (unnormalized) normal of a triangle, optionally replacing the position of one of its vertices
+fn triangle_normal<R: Real>(
+    vertices: &[Vector3<R>],
+    tri: &[usize; 3],
+    replace: Option<(usize, &Vector3<R>)>,
+) -> Vector3<R> {
+    let position = |v: usize| match replace {
+        Some((replaced, p)) if replaced == v => *p,
+        _ => vertices[v],
+    };
+
+    let v0 = position(tri[0]);
+    let v1 = position(tri[1]);
+    let v2 = position(tri[2]);
+    (v1 - v0).cross(&(v2 - v0))
+}
+
+/// Evaluates the error of the given quadric at the given position
+fn quadric_error<R: Real>(quadric: &Matrix4<R>, p: &Vector3<R>) -> R {
+    let p = Vector4::new(p.x, p.y, p.z, R::one());
+    p.dot(&(quadric * p))
+}
+
+/// Computes the position with minimal error of the quadric, returns `None` if the quadric is singular
+fn quadric_minimizer<R: Real>(quadric: &Matrix4<R>) -> Option<Vector3<R>> {
+    let a = quadric.fixed_view::<3, 3>(0, 0).into_owned();
+    let b = quadric.fixed_view::<3, 1>(0, 3).into_owned();
+    a.try_inverse().map(|a_inv| -(a_inv * b))
+}
+
+#[test]
+fn test_decimation_keeps_mesh_closed() {
+    let mut mesh = crate::mesh::test_meshes::uv_sphere();
+    assert!(mesh.find_boundary_edges().is_empty());
+    let num_triangles = mesh.triangles.len();
+
+    decimate_mesh(
+        &mut mesh,
+        &DecimationParameters {
+            target_triangle_count: Some(num_triangles / 4),
+            max_error: None,
+        },
+    );
+
+    assert!(mesh.triangles.len() <= num_triangles / 4);
+    assert!(mesh.find_boundary_edges().is_empty());
+
+    // Every edge has to be shared by exactly two triangles
+    let mut edge_counts = crate::new_map();
+    for tri in mesh.triangles.iter() {
+        for (i0, i1) in [(0, 1), (1, 2), (2, 0)] {
+            let edge = [tri[i0].min(tri[i1]), tri[i0].max(tri[i1])];
+            *edge_counts.entry(edge).or_insert(0) += 1;
+        }
+    }
+    assert!(edge_counts.values().all(|&count| count == 2));
+
+    // Euler characteristic of a sphere
+    let euler = mesh.vertices.len() as i64 - edge_counts.len() as i64 + mesh.triangles.len() as i64;
+    assert_eq!(euler, 2);
+}
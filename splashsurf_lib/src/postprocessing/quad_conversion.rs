@@ -0,0 +1,278 @@
+//! Conversion of triangle meshes into quad-dominant meshes by pairing adjacent triangles
+//!
+//! Every interior edge of the mesh whose two adjacent triangles form a reasonably planar, convex
+//! and "square" quad is a candidate for a merge. The candidates are processed greedily, starting
+//! with the quads whose interior angles deviate least from a right angle. Triangles that cannot be
+//! paired are kept. The vertices of the mesh are not modified, therefore point attributes computed
+//! for the triangle mesh (e.g. normals) stay valid for the resulting mesh.
+//!
+//! Meshes generated by surface nets are particularly well suited for this conversion, as every quad
+//! of the extraction is split into two triangles which are merged again by the conversion.
+
+use crate::mesh::{MixedTriQuadMesh3d, TriMesh3d, TriangleOrQuadCell};
+use crate::{new_map, profile, Real};
+use log::info;
+use nalgebra::Vector3;
+use std::cmp::Ordering;
+
+/// Parameters for the conversion of triangle meshes into quad-dominant meshes
+#[derive(Clone, Debug)]
+pub struct QuadConversionParameters<R: Real> {
+    /// Maximum angle between the normals of two triangles that are merged into a quad (in radians)
+    pub max_normal_angle: R,
+    /// Maximum interior angle at the corners of a resulting quad (in radians), has to be smaller than `π` to ensure convex quads
+    pub max_interior_angle: R,
+    /// Maximum ratio of the lengths of the longest and the shortest edge of a resulting quad
+    pub max_edge_ratio: R,
+}
+
+impl<R: Real> Default for QuadConversionParameters<R> {
+    /// Returns parameters with a maximum normal angle of 10°, a maximum interior angle of 135° and a maximum edge ratio of 1.75
+    fn default() -> Self {
+        Self {
+            max_normal_angle: R::from_f64(10.0_f64.to_radians()).unwrap(),
+            max_interior_angle: R::from_f64(135.0_f64.to_radians()).unwrap(),
+            max_edge_ratio: R::from_f64(1.75).unwrap(),
+        }
+    }
+}
+
+impl<R: Real> QuadConversionParameters<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns None if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<QuadConversionParameters<T>> {
+        Some(QuadConversionParameters {
+            max_normal_angle: self.max_normal_angle.try_convert()?,
+            max_interior_angle: self.max_interior_angle.try_convert()?,
+            max_edge_ratio: self.max_edge_ratio.try_convert()?,
+        })
+    }
+}
+
+/// A pair of triangles that can be merged into a quad
+struct QuadCandidate<R: Real> {
+    /// Deviation of the interior angles of the quad from right angles
+    score: R,
+    triangles: [usize; 2],
+    quad: [usize; 4],
+}
+
+/// Converts a triangle mesh into a quad-dominant mesh by merging pairs of adjacent triangles into quads
+///
+/// Only pairs of consistently oriented triangles sharing a manifold edge are merged. The resulting quads
+/// have the same orientation as the triangles. See [`QuadConversionParameters`] for the criteria
+/// that have to be fulfilled by a merged quad.
+pub fn convert_tris_to_quads<R: Real>(
+    mesh: &TriMesh3d<R>,
+    parameters: &QuadConversionParameters<R>,
+) -> MixedTriQuadMesh3d<R> {
+    profile!("convert_tris_to_quads");
+
+    info!(
+        "Converting mesh with {} triangles to quads (max normal angle: {}, max interior angle: {}, max edge ratio: {})...",
+        mesh.triangles.len(),
+        parameters.max_normal_angle,
+        parameters.max_interior_angle,
+        parameters.max_edge_ratio
+    );
+
+    // Map from directed edges to the triangle containing the edge and the vertex opposite to it
+    let mut directed_edges = new_map();
+    let mut non_manifold_edges = new_map();
+    for (tri_idx, tri) in mesh.triangles.iter().enumerate() {
+        for i in 0..3 {
+            let edge = [tri[i], tri[(i + 1) % 3]];
+            if directed_edges
+                .insert(edge, (tri_idx, tri[(i + 2) % 3]))
+                .is_some()
+            {
+                non_manifold_edges.insert(edge, ());
+            }
+        }
+    }
+
+    let mut candidates = Vec::new();
+    for (&[u, v], &(tri_a, a)) in directed_edges.iter() {
+        // Visit every undirected edge only once
+        if u > v || non_manifold_edges.contains_key(&[u, v]) {
+            continue;
+        }
+
+        if let Some(&(tri_b, b)) = directed_edges.get(&[v, u]) {
+            if tri_a == tri_b || a == b || non_manifold_edges.contains_key(&[v, u]) {
+                continue;
+            }
+
+            // Triangle a contains the edge u -> v, triangle b contains the edge v -> u
+            let quad = [u, b, v, a];
+            if let Some(score) = quad_score(&mesh.vertices, &quad, parameters) {
+                candidates.push(QuadCandidate {
+                    score,
+                    triangles: [tri_a, tri_b],
+                    quad,
+                });
+            }
+        }
+    }
+
+    candidates.sort_unstable_by(|a, b| a.score.partial_cmp(&b.score).unwrap_or(Ordering::Equal));
+
+    // Greedily merge the best candidates, the quad is stored with the triangle with the smaller index
+    let mut triangle_quads: Vec<Option<[usize; 4]>> = vec![None; mesh.triangles.len()];
+    let mut triangle_merged = vec![false; mesh.triangles.len()];
+    for candidate in candidates {
+        let [tri_a, tri_b] = candidate.triangles;
+        if triangle_merged[tri_a] || triangle_merged[tri_b] {
+            continue;
+        }
+
+        triangle_merged[tri_a] = true;
+        triangle_merged[tri_b] = true;
+        triangle_quads[tri_a.min(tri_b)] = Some(candidate.quad);
+    }
+
+    let mut cells = Vec::with_capacity(mesh.triangles.len());
+    for (tri_idx, tri) in mesh.triangles.iter().enumerate() {
+        if let Some(quad) = triangle_quads[tri_idx] {
+            cells.push(TriangleOrQuadCell::Quad(quad));
+        } else if !triangle_merged[tri_idx] {
+            cells.push(TriangleOrQuadCell::Tri(*tri));
+        }
+    }
+
+    let quad_mesh = MixedTriQuadMesh3d {
+        vertices: mesh.vertices.clone(),
+        cells,
+    };
+
+    let (num_triangles, num_quads) = quad_mesh.count_cell_types();
+    info!(
+        "Merged {} triangles into {} quads, {} triangles remain.",
+        2 * num_quads,
+        num_quads,
+        num_triangles
+    );
+
+    quad_mesh
+}
+
+/// Returns the deviation of the interior angles of the quad from right angles or `None` if the quad violates any of the criteria
+fn quad_score<R: Real>(
+    vertices: &[Vector3<R>],
+    quad: &[usize; 4],
+    parameters: &QuadConversionParameters<R>,
+) -> Option<R> {
+    let corners = quad.map(|v| vertices[v]);
+    let triangle_normal = |i0: usize, i1: usize, i2: usize| {
+        (corners[i1] - corners[i0]).cross(&(corners[i2] - corners[i0]))
+    };
+
+    // The two triangles of the quad, split along the shared edge from corner 0 to corner 2
+    let n_a = triangle_normal(0, 2, 3);
+    let n_b = triangle_normal(2, 0, 1);
+    let (norm_a, norm_b) = (n_a.norm(), n_b.norm());
+    if norm_a <= R::default_epsilon() || norm_b <= R::default_epsilon() {
+        return None;
+    }
+
+    let normal_angle = clamped_acos(n_a.dot(&n_b) / (norm_a * norm_b));
+    if normal_angle > parameters.max_normal_angle {
+        return None;
+    }
+
+    // The quad is only convex if the split along the other diagonal is oriented consistently as well
+    let n_c = triangle_normal(1, 2, 3);
+    let n_d = triangle_normal(3, 0, 1);
+    let n_sum = n_a + n_b;
+    if n_c.dot(&n_sum) <= R::zero() || n_d.dot(&n_sum) <= R::zero() {
+        return None;
+    }
+
+    let edge_lengths = [0, 1, 2, 3].map(|i| (corners[(i + 1) % 4] - corners[i]).norm());
+    let min_edge = edge_lengths.iter().copied().fold(edge_lengths[0], R::min);
+    let max_edge = edge_lengths.iter().copied().fold(edge_lengths[0], R::max);
+    if min_edge <= R::default_epsilon() || max_edge / min_edge > parameters.max_edge_ratio {
+        return None;
+    }
+
+    let right_angle = R::frac_pi_2();
+    let mut score = R::zero();
+    for i in 0..4 {
+        let prev = corners[(i + 3) % 4] - corners[i];
+        let next = corners[(i + 1) % 4] - corners[i];
+        let interior_angle =
+            clamped_acos(prev.dot(&next) / (edge_lengths[(i + 3) % 4] * edge_lengths[i]));
+        if interior_angle > parameters.max_interior_angle {
+            return None;
+        }
+        score += (interior_angle - right_angle).abs();
+    }
+
+    Some(score)
+}
+
+/// Arc cosine of the value clamped to `[-1, 1]` to guard against rounding errors
+fn clamped_acos<R: Real>(value: R) -> R {
+    value.max(-R::one()).min(R::one()).acos()
+}
+
+#[test]
+fn test_quad_conversion_of_cube() {
+    // Unit cube with two triangles per face, oriented outwards
+    let mesh = TriMesh3d::<f64> {
+        vertices: vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(1.0, 1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 1.0),
+            Vector3::new(0.0, 1.0, 1.0),
+            Vector3::new(1.0, 1.0, 1.0),
+        ],
+        triangles: vec![
+            [0, 2, 3],
+            [0, 3, 1],
+            [4, 5, 7],
+            [4, 7, 6],
+            [0, 1, 5],
+            [0, 5, 4],
+            [2, 6, 7],
+            [2, 7, 3],
+            [0, 4, 6],
+            [0, 6, 2],
+            [1, 3, 7],
+            [1, 7, 5],
+        ],
+    };
+    assert!(mesh.find_boundary_edges().is_empty());
+    assert!((mesh.signed_volume() - 1.0).abs() < 1e-12);
+
+    let quad_mesh = convert_tris_to_quads(&mesh, &QuadConversionParameters::default());
+    assert_eq!(quad_mesh.count_cell_types(), (0, 6));
+
+    // The quads have to keep the orientation of the triangles
+    let tri_mesh = quad_mesh.to_tri_mesh();
+    assert!(tri_mesh.find_boundary_edges().is_empty());
+    assert!((tri_mesh.signed_volume() - 1.0).abs() < 1e-12);
+}
+
+#[test]
+fn test_quad_conversion_rejects_bad_quads() {
+    // Two triangles forming a thin rhombus and two triangles folded by 90°
+    let mesh = TriMesh3d::<f64> {
+        vertices: vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.2, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(1.0, -0.2, 0.0),
+            Vector3::new(5.0, 0.0, 0.0),
+            Vector3::new(6.0, 0.0, 0.0),
+            Vector3::new(5.0, 1.0, 0.0),
+            Vector3::new(5.0, 0.0, 1.0),
+        ],
+        triangles: vec![[0, 3, 2], [0, 2, 1], [4, 5, 6], [5, 4, 7]],
+    };
+
+    let quad_mesh = convert_tris_to_quads(&mesh, &QuadConversionParameters::default());
+    assert_eq!(quad_mesh.count_cell_types(), (4, 0));
+}
@@ -0,0 +1,635 @@
+//! Laplacian and Taubin smoothing of triangle meshes
+//!
+//! The smoothing moves every vertex of the mesh along the discrete Laplacian (i.e. towards a weighted
+//! average of its neighbors). Pure Laplacian smoothing shrinks the mesh, the Taubin variant
+//! counteracts this by alternating a smoothing step with step size `λ > 0` and an "inflation" step
+//! with step size `μ < -λ`.
+//!
+//! Vertices on the boundary of the mesh (i.e. vertices of edges that are only adjacent to a single
+//! triangle) are kept fixed.
+//!
+//! To avoid shrinking of thin sheets and small droplets, [`smooth_mesh_weighted`] scales the step size
+//! of every vertex depending on its distance to the closest fluid particle: vertices close to particles
+//! stay (almost) fixed while vertices "floating" between particles are smoothed with the full step size.
+//! Optionally, the volume enclosed by the mesh can be restored after smoothing by offsetting the
+//! vertices along their normals (see [`SmoothingParameters::preserve_volume`]), the offset of every
+//! vertex is scaled with the same factor as its smoothing step size.
+
+use crate::mesh::TriMesh3d;
+use crate::{neighborhood_search, profile, Index, Real};
+use log::info;
+use nalgebra::Vector3;
+use rayon::prelude::*;
+
+/// Number of iterations of the volume correction after smoothing
+const VOLUME_CORRECTION_ITERATIONS: usize = 3;
+
+/// Weighting scheme for the contribution of the neighbors of a vertex in the discrete Laplacian
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+pub enum LaplacianWeights {
+    /// All neighbors of a vertex contribute with the same weight ("umbrella operator")
+    Uniform,
+    /// Neighbors are weighted by the cotangents of the two angles opposite to the connecting edge
+    Cotangent,
+}
+
+/// Parameters for the smoothing of triangle meshes
+#[derive(Clone, Debug)]
+pub struct SmoothingParameters<R: Real> {
+    /// Weighting scheme used for the discrete Laplacian
+    pub weights: LaplacianWeights,
+    /// Step size `λ` of each smoothing step, typically in the range `(0, 1]`
+    pub lambda: R,
+    /// Optional negative step size `μ` of an inflation step performed after every smoothing step (Taubin smoothing), if `None` plain Laplacian smoothing is performed
+    pub mu: Option<R>,
+    /// Number of smoothing iterations
+    pub iterations: usize,
+    /// Whether to offset the vertices along their normals after smoothing such that the enclosed volume of the mesh matches the volume before smoothing (vertices that are kept fixed by the smoothing are not moved)
+    pub preserve_volume: bool,
+}
+
+/// Per-vertex scaling of the smoothing step size based on the distance of a vertex to the closest particle
+///
+/// The step size is scaled linearly from zero for vertices with a distance of at most `fixed_distance`
+/// to one for vertices with a distance of at least `full_smoothing_distance`. The `fixed_distance` should
+/// not be larger than the `full_smoothing_distance`, otherwise the scaling degenerates to a step function.
+#[derive(Clone, Debug)]
+pub struct ParticleDistanceWeighting<R: Real> {
+    /// Vertices closer than this distance to a particle are not moved
+    pub fixed_distance: R,
+    /// Vertices farther away than this distance from all particles are smoothed with the full step size
+    pub full_smoothing_distance: R,
+}
+
+impl<R: Real> SmoothingParameters<R> {
+    /// Parameters for plain Laplacian smoothing with the given weights, step size and number of iterations
+    pub fn laplacian(weights: LaplacianWeights, lambda: R, iterations: usize) -> Self {
+        Self {
+            weights,
+            lambda,
+            mu: None,
+            iterations,
+            preserve_volume: false,
+        }
+    }
+
+    /// Parameters for Taubin smoothing with the given weights, step sizes and number of iterations
+    pub fn taubin(weights: LaplacianWeights, lambda: R, mu: R, iterations: usize) -> Self {
+        Self {
+            weights,
+            lambda,
+            mu: Some(mu),
+            iterations,
+            preserve_volume: false,
+        }
+    }
+
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns None if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<SmoothingParameters<T>> {
+        Some(SmoothingParameters {
+            weights: self.weights,
+            lambda: self.lambda.try_convert()?,
+            mu: match self.mu {
+                Some(mu) => Some(mu.try_convert()?),
+                None => None,
+            },
+            iterations: self.iterations,
+            preserve_volume: self.preserve_volume,
+        })
+    }
+}
+
+impl<R: Real> ParticleDistanceWeighting<R> {
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns None if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<ParticleDistanceWeighting<T>> {
+        Some(ParticleDistanceWeighting {
+            fixed_distance: self.fixed_distance.try_convert()?,
+            full_smoothing_distance: self.full_smoothing_distance.try_convert()?,
+        })
+    }
+
+    /// Computes the step size factor for each of the given vertices from the distance to the closest of the given particles
+    ///
+    /// The closest particles are found with the spatial hashing of the [`neighborhood_search`](crate::neighborhood_search).
+    pub fn compute_vertex_weights<I: Index>(
+        &self,
+        vertices: &[Vector3<R>],
+        particle_positions: &[Vector3<R>],
+        enable_multi_threading: bool,
+    ) -> Vec<R> {
+        profile!("compute_vertex_weights");
+
+        // Vertices farther away than the full smoothing distance from all particles are not affected by the particles
+        if self.full_smoothing_distance <= R::zero() {
+            return vec![R::one(); vertices.len()];
+        }
+        let distances = neighborhood_search::nearest_particle_distances::<I, R>(
+            particle_positions,
+            vertices,
+            self.full_smoothing_distance,
+            enable_multi_threading,
+        );
+
+        let transition_width = self.full_smoothing_distance - self.fixed_distance;
+        let weight = |distance: Option<R>| match distance {
+            Some(distance) if distance <= self.fixed_distance => R::zero(),
+            Some(distance)
+                if distance < self.full_smoothing_distance && transition_width > R::zero() =>
+            {
+                (distance - self.fixed_distance) / transition_width
+            }
+            _ => R::one(),
+        };
+
+        if enable_multi_threading {
+            distances.into_par_iter().map(weight).collect()
+        } else {
+            distances.into_iter().map(weight).collect()
+        }
+    }
+}
+
+/// Smoothes the vertex positions of the given mesh inplace using Laplacian or Taubin smoothing
+pub fn smooth_mesh<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    parameters: &SmoothingParameters<R>,
+    enable_multi_threading: bool,
+) {
+    profile!("smooth_mesh");
+
+    info!(
+        "Smoothing mesh with {} vertices ({:?} weights, lambda: {}, mu: {:?}, iterations: {})...",
+        mesh.vertices.len(),
+        parameters.weights,
+        parameters.lambda,
+        parameters.mu,
+        parameters.iterations
+    );
+
+    let connectivity = mesh.vertex_vertex_connectivity();
+    let boundary_vertices = mesh.boundary_vertex_flags();
+
+    smooth_mesh_with_connectivity(
+        mesh,
+        &connectivity,
+        &boundary_vertices,
+        None,
+        parameters,
+        enable_multi_threading,
+    );
+}
+
+/// Smoothes the vertex positions of the given mesh inplace with a step size per vertex that depends on its distance to the closest particle
+///
+/// See [`ParticleDistanceWeighting`] for the computation of the per-vertex step size.
+pub fn smooth_mesh_weighted<I: Index, R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    particle_positions: &[Vector3<R>],
+    weighting: &ParticleDistanceWeighting<R>,
+    parameters: &SmoothingParameters<R>,
+    enable_multi_threading: bool,
+) {
+    profile!("smooth_mesh_weighted");
+
+    info!(
+        "Smoothing mesh with {} vertices using particle distance weights ({:?} weights, lambda: {}, mu: {:?}, iterations: {})...",
+        mesh.vertices.len(),
+        parameters.weights,
+        parameters.lambda,
+        parameters.mu,
+        parameters.iterations
+    );
+
+    let connectivity = mesh.vertex_vertex_connectivity();
+    let boundary_vertices = mesh.boundary_vertex_flags();
+    let vertex_weights = weighting.compute_vertex_weights::<I>(
+        &mesh.vertices,
+        particle_positions,
+        enable_multi_threading,
+    );
+
+    smooth_mesh_with_connectivity(
+        mesh,
+        &connectivity,
+        &boundary_vertices,
+        Some(&vertex_weights),
+        parameters,
+        enable_multi_threading,
+    );
+}
+
+/// Performs the smoothing iterations using precomputed connectivity information and optional per-vertex factors for the step size
+pub(crate) fn smooth_mesh_with_connectivity<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    connectivity: &[Vec<usize>],
+    boundary_vertices: &[bool],
+    vertex_weights: Option<&[R]>,
+    parameters: &SmoothingParameters<R>,
+    enable_multi_threading: bool,
+) {
+    assert_eq!(connectivity.len(), mesh.vertices.len());
+    assert_eq!(boundary_vertices.len(), mesh.vertices.len());
+    if let Some(vertex_weights) = vertex_weights {
+        assert_eq!(vertex_weights.len(), mesh.vertices.len());
+    }
+
+    // Boundary vertices are kept fixed, all other vertices are moved with their (optional) weight
+    let step_factors: Vec<R> = (0..mesh.vertices.len())
+        .map(|i| {
+            if boundary_vertices[i] {
+                R::zero()
+            } else {
+                vertex_weights.map(|w| w[i]).unwrap_or(R::one())
+            }
+        })
+        .collect();
+
+    let initial_volume = parameters.preserve_volume.then(|| mesh.signed_volume());
+
+    let mut buffer = Vec::with_capacity(mesh.vertices.len());
+    for _ in 0..parameters.iterations {
+        laplacian_step(
+            mesh,
+            connectivity,
+            &step_factors,
+            parameters.weights,
+            parameters.lambda,
+            &mut buffer,
+            enable_multi_threading,
+        );
+        if let Some(mu) = parameters.mu {
+            laplacian_step(
+                mesh,
+                connectivity,
+                &step_factors,
+                parameters.weights,
+                mu,
+                &mut buffer,
+                enable_multi_threading,
+            );
+        }
+    }
+
+    if let Some(initial_volume) = initial_volume {
+        correct_volume(mesh, initial_volume, &step_factors, enable_multi_threading);
+    }
+}
+
+/// Offsets the vertices along their normals such that the signed volume of the mesh approaches the target volume
+///
+/// The offset of every vertex is scaled by its step size factor, such that vertices with a factor of zero stay fixed.
+fn correct_volume<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    target_volume: R,
+    step_factors: &[R],
+    enable_multi_threading: bool,
+) {
+    profile!("correct_volume");
+
+    let sixth = R::from_f64(1.0 / 6.0).unwrap();
+    let mut vertex_areas = vec![R::zero(); mesh.vertices.len()];
+    for _ in 0..VOLUME_CORRECTION_ITERATIONS {
+        // Every triangle contributes a third of its area to each of its vertices
+        vertex_areas.iter_mut().for_each(|a| *a = R::zero());
+        for tri in mesh.triangles.iter() {
+            let v0 = &mesh.vertices[tri[0]];
+            let v1 = &mesh.vertices[tri[1]];
+            let v2 = &mesh.vertices[tri[2]];
+            let area = (v1 - v0).cross(&(v2 - v0)).norm() * sixth;
+            for &i in tri {
+                vertex_areas[i] += area;
+            }
+        }
+
+        let weighted_area = vertex_areas
+            .iter()
+            .zip(step_factors.iter())
+            .map(|(&a, &w)| w * a)
+            .fold(R::zero(), |a, b| a + b);
+        if weighted_area <= R::zero() {
+            return;
+        }
+
+        // Moving every vertex by `factor * offset` along its normal changes the volume approximately by `offset * weighted_area`
+        let offset = (target_volume - mesh.signed_volume()) / weighted_area;
+        let normal_directions = mesh.vertex_normal_directions();
+
+        let offset_vertex = |(i, v): (usize, &mut Vector3<R>)| {
+            let factor = step_factors[i];
+            if factor > R::zero() {
+                if let Some(n) = normal_directions[i].try_normalize(R::default_epsilon()) {
+                    *v += n * (offset * factor);
+                }
+            }
+        };
+        if enable_multi_threading {
+            mesh.vertices
+                .par_iter_mut()
+                .enumerate()
+                .for_each(offset_vertex);
+        } else {
+            mesh.vertices.iter_mut().enumerate().for_each(offset_vertex);
+        }
+    }
+
+    info!(
+        "Corrected mesh volume after smoothing (target: {}, result: {})",
+        target_volume,
+        mesh.signed_volume()
+    );
+}
+
+/// Moves every vertex by the given step size scaled by its step size factor along its (normalized) discrete Laplacian
+fn laplacian_step<R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    connectivity: &[Vec<usize>],
+    step_factors: &[R],
+    weights: LaplacianWeights,
+    step_size: R,
+    buffer: &mut Vec<Vector3<R>>,
+    enable_multi_threading: bool,
+) {
+    let edge_weights = match weights {
+        LaplacianWeights::Uniform => None,
+        LaplacianWeights::Cotangent => Some(cotangent_edge_weights(mesh, connectivity)),
+    };
+
+    let vertices = &mesh.vertices;
+    let smooth_vertex = |(i, v_i): (usize, &Vector3<R>)| {
+        if step_factors[i] <= R::zero() {
+            return *v_i;
+        }
+
+        let mut laplacian = Vector3::<R>::zeros();
+        let mut weight_sum = R::zero();
+        for (local_j, &j) in connectivity[i].iter().enumerate() {
+            let w_ij = edge_weights
+                .as_ref()
+                .map(|w| w[i][local_j])
+                .unwrap_or(R::one());
+            laplacian += (vertices[j] - v_i) * w_ij;
+            weight_sum += w_ij;
+        }
+
+        if weight_sum <= R::zero() {
+            return *v_i;
+        }
+
+        v_i + laplacian * (step_size * step_factors[i] / weight_sum)
+    };
+
+    if enable_multi_threading {
+        vertices
+            .par_iter()
+            .enumerate()
+            .map(smooth_vertex)
+            .collect_into_vec(buffer);
+    } else {
+        buffer.clear();
+        buffer.extend(vertices.iter().enumerate().map(smooth_vertex));
+    }
+
+    std::mem::swap(&mut mesh.vertices, buffer);
+}
+
+/// Computes the cotangent weight of every edge, stored in the same layout as the given vertex-vertex connectivity
+fn cotangent_edge_weights<R: Real>(
+    mesh: &TriMesh3d<R>,
+    connectivity: &[Vec<usize>],
+) -> Vec<Vec<R>> {
+    let half = R::from_f64(0.5).unwrap();
+    let mut weights = connectivity
+        .iter()
+        .map(|neighbors| vec![R::zero(); neighbors.len()])
+        .collect::<Vec<_>>();
+
+    let mut add_weight = |i: usize, j: usize, w: R| {
+        if let Some(local_j) = connectivity[i].iter().position(|&n| n == j) {
+            weights[i][local_j] += w;
+        }
+    };
+
+    for tri in mesh.triangles.iter() {
+        for k in 0..3 {
+            // Angle at vertex `k` is opposite to the edge `(i, j)`
+            let o = tri[k];
+            let i = tri[(k + 1) % 3];
+            let j = tri[(k + 2) % 3];
+
+            let a = mesh.vertices[i] - mesh.vertices[o];
+            let b = mesh.vertices[j] - mesh.vertices[o];
+            let cross_norm = a.cross(&b).norm();
+            if cross_norm <= R::default_epsilon() {
+                continue;
+            }
+
+            // Negative cotangents (obtuse angles) are clamped to keep the operator a convex combination
+            let cot = (a.dot(&b) / cross_norm).max(R::zero()) * half;
+            add_weight(i, j, cot);
+            add_weight(j, i, cot);
+        }
+    }
+
+    weights
+}
+
+#[cfg(test)]
+fn test_bump_mesh() -> TriMesh3d<f64> {
+    // 3x3 grid of vertices in the xy-plane with the center vertex lifted out of the plane
+    let mut vertices = Vec::new();
+    for j in 0..3 {
+        for i in 0..3 {
+            vertices.push(Vector3::new(i as f64, j as f64, 0.0));
+        }
+    }
+    vertices[4].z = 1.0;
+
+    let mut triangles = Vec::new();
+    for j in 0..2 {
+        for i in 0..2 {
+            let a = i + 3 * j;
+            triangles.push([a, a + 1, a + 4]);
+            triangles.push([a, a + 4, a + 3]);
+        }
+    }
+
+    TriMesh3d {
+        vertices,
+        triangles,
+    }
+}
+
+#[test]
+fn test_smoothing_flattens_bump() {
+    let mesh = test_bump_mesh();
+
+    for weights in [LaplacianWeights::Uniform, LaplacianWeights::Cotangent] {
+        let mut smoothed = mesh.clone();
+        smooth_mesh(
+            &mut smoothed,
+            &SmoothingParameters::laplacian(weights, 1.0, 1),
+            false,
+        );
+
+        // The interior vertex is moved into the plane of its neighbors, the boundary is fixed
+        assert!(smoothed.vertices[4].z.abs() < 1e-12);
+        for (i, (v_smoothed, v)) in smoothed
+            .vertices
+            .iter()
+            .zip(mesh.vertices.iter())
+            .enumerate()
+        {
+            if i != 4 {
+                assert_eq!(v_smoothed, v);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_weighted_smoothing_keeps_vertices_close_to_particles_fixed() {
+    let mesh = test_bump_mesh();
+    let weighting = ParticleDistanceWeighting {
+        fixed_distance: 0.5,
+        full_smoothing_distance: 1.0,
+    };
+    let parameters = SmoothingParameters::laplacian(LaplacianWeights::Uniform, 1.0, 1);
+
+    for enable_multi_threading in [false, true] {
+        // A particle at the lifted vertex keeps it fixed
+        let mut smoothed = mesh.clone();
+        smooth_mesh_weighted::<i64, f64>(
+            &mut smoothed,
+            &[mesh.vertices[4]],
+            &weighting,
+            &parameters,
+            enable_multi_threading,
+        );
+        assert_eq!(smoothed.vertices, mesh.vertices);
+
+        // Without particles in the vicinity, the vertex is smoothed with the full step size
+        let mut smoothed = mesh.clone();
+        smooth_mesh_weighted::<i64, f64>(
+            &mut smoothed,
+            &[Vector3::new(10.0, 10.0, 10.0)],
+            &weighting,
+            &parameters,
+            enable_multi_threading,
+        );
+        assert!(smoothed.vertices[4].z.abs() < 1e-12);
+    }
+}
+
+#[test]
+fn test_particle_distance_weights_linear_ramp() {
+    let weighting = ParticleDistanceWeighting {
+        fixed_distance: 1.0,
+        full_smoothing_distance: 2.0,
+    };
+    let particle_positions = [Vector3::new(0.0, 0.0, 0.0), Vector3::new(3.5, 0.0, 0.0)];
+
+    // Vertices with their expected weights, the weights depend on the distance to the closest particle
+    let vertices_and_weights: [(Vector3<f64>, f64); 10] = [
+        (Vector3::new(0.0, 0.0, 0.0), 0.0),
+        (Vector3::new(0.0, -0.5, 0.0), 0.0),
+        (Vector3::new(0.0, -1.0, 0.0), 0.0),
+        (Vector3::new(0.0, -1.25, 0.0), 0.25),
+        (Vector3::new(0.0, -1.5, 0.0), 0.5),
+        (Vector3::new(0.0, -1.75, 0.0), 0.75),
+        (Vector3::new(0.0, -2.0, 0.0), 1.0),
+        (Vector3::new(0.0, -10.0, 0.0), 1.0),
+        (Vector3::new(3.0, 0.0, 0.0), 0.0),
+        (Vector3::new(3.5, 1.5, 0.0), 0.5),
+    ];
+    let vertices = vertices_and_weights
+        .iter()
+        .map(|(v, _)| *v)
+        .collect::<Vec<_>>();
+
+    for enable_multi_threading in [false, true] {
+        let weights = weighting.compute_vertex_weights::<i64>(
+            &vertices,
+            &particle_positions,
+            enable_multi_threading,
+        );
+        assert_eq!(weights.len(), vertices.len());
+        for (w, (_, w_expected)) in weights.iter().zip(vertices_and_weights.iter()) {
+            assert!((w - w_expected).abs() < 1e-12);
+        }
+    }
+}
+
+#[test]
+fn test_smoothing_preserves_volume() {
+    let mesh = crate::mesh::test_meshes::uv_sphere();
+    let initial_volume = mesh.signed_volume();
+
+    for mut parameters in [
+        SmoothingParameters::laplacian(LaplacianWeights::Uniform, 0.5, 10),
+        SmoothingParameters::taubin(LaplacianWeights::Cotangent, 0.5, -0.53, 10),
+    ] {
+        // Laplacian smoothing shrinks the sphere, Taubin smoothing slightly inflates it
+        let mut smoothed = mesh.clone();
+        smooth_mesh(&mut smoothed, &parameters, false);
+        assert!(((smoothed.signed_volume() - initial_volume) / initial_volume).abs() > 1e-3);
+
+        // The volume correction restores the initial volume
+        parameters.preserve_volume = true;
+        let mut smoothed = mesh.clone();
+        smooth_mesh(&mut smoothed, &parameters, true);
+        assert!(
+            ((smoothed.signed_volume() - initial_volume) / initial_volume).abs() < 1e-4,
+            "volume after correction: {}, initial volume: {}",
+            smoothed.signed_volume(),
+            initial_volume
+        );
+        assert!(smoothed.find_boundary_edges().is_empty());
+    }
+}
+
+#[test]
+fn test_volume_correction_keeps_fixed_vertices() {
+    let mesh = crate::mesh::test_meshes::uv_sphere();
+    let initial_volume = mesh.signed_volume();
+    let connectivity = mesh.vertex_vertex_connectivity();
+    let boundary_vertices = mesh.boundary_vertex_flags();
+
+    // Vertices of the upper hemisphere are fixed (e.g. close to particles), the lower hemisphere is smoothed
+    let vertex_weights: Vec<f64> = mesh
+        .vertices
+        .iter()
+        .map(|v| if v.z > 0.0 { 0.0 } else { 1.0 })
+        .collect();
+
+    let mut parameters = SmoothingParameters::laplacian(LaplacianWeights::Uniform, 0.5, 10);
+    parameters.preserve_volume = true;
+    for enable_multi_threading in [false, true] {
+        let mut smoothed = mesh.clone();
+        smooth_mesh_with_connectivity(
+            &mut smoothed,
+            &connectivity,
+            &boundary_vertices,
+            Some(&vertex_weights),
+            &parameters,
+            enable_multi_threading,
+        );
+
+        // The volume is restored only by the vertices that are not fixed
+        assert!(
+            ((smoothed.signed_volume() - initial_volume) / initial_volume).abs() < 1e-3,
+            "volume after correction: {}, initial volume: {}",
+            smoothed.signed_volume(),
+            initial_volume
+        );
+        for ((v_smoothed, v), w) in smoothed
+            .vertices
+            .iter()
+            .zip(mesh.vertices.iter())
+            .zip(vertex_weights.iter())
+        {
+            if *w == 0.0 {
+                assert_eq!(v_smoothed, v);
+            }
+        }
+    }
+}
@@ -0,0 +1,389 @@
+//! Removal of sliver triangles from marching cubes meshes by snapping vertices to the background grid
+//!
+//! Marching cubes places its vertices on the edges of the background grid. If the density value at a
+//! grid point is very close to the iso-surface threshold, the vertices on all edges adjacent to this point
+//! are placed extremely close to the grid point, resulting in tiny and degenerate ("sliver") triangles.
+//!
+//! The cleanup implemented in this module snaps all vertices that are closer than a given distance
+//! to a grid point onto this point and merges them into a single vertex ("edge clustering"). Triangles
+//! that degenerate due to the merge are removed, as well as pairs of coinciding triangles with opposite
+//! orientation (e.g. the two sides of a collapsed thin feature).
+//!
+//! If vertices of different sheets of the surface are close to the same grid point (e.g. at thin
+//! features), merging them would pinch the sheets together at the merged vertex or connect more than
+//! two triangles to an edge. Such merges are skipped, i.e. the vertices close to these grid points are
+//! left untouched. A merge is only performed if the triangles around the merged vertex form a single
+//! fan. Therefore, closed manifold meshes stay closed and manifold.
+
+use crate::mesh::TriMesh3d;
+use crate::{new_map, profile, Index, MapType, Real, UniformGrid};
+use log::info;
+use nalgebra::Vector3;
+
+/// Snaps vertices of a marching cubes mesh that are close to a point of the background grid onto the point, merges them and removes the resulting degenerate triangles
+///
+/// Vertices that are closer than `snap_distance * cube_size` to a point of the given grid are moved to
+/// this point. All vertices snapped onto the same grid point are merged into a single vertex.
+/// The `snap_distance` is relative to the cell size of the grid and should be smaller than `0.5`,
+/// otherwise vertices are snapped onto the closest grid point in any case.
+/// Vertices close to grid points where the merge would result in a non-manifold mesh are not modified
+/// (see the [module level documentation](self)).
+///
+/// The `grid` should be the background grid of the reconstruction that generated the mesh (see [`SurfaceReconstruction::grid`](crate::SurfaceReconstruction::grid)).
+/// Returns the number of triangles that were removed.
+pub fn snap_vertices_to_grid<I: Index, R: Real>(
+    mesh: &mut TriMesh3d<R>,
+    grid: &UniformGrid<I, R>,
+    snap_distance: R,
+) -> usize {
+    profile!("snap_vertices_to_grid");
+
+    let cube_size = grid.cell_size();
+    let max_distance = snap_distance * cube_size;
+    let grid_min = grid.aabb().min();
+
+    // Group the vertices by the grid point they are snapped onto
+    let mut clusters: Vec<(Vector3<R>, Vec<usize>)> = Vec::new();
+    let mut point_clusters = new_map();
+    for (i, vertex) in mesh.vertices.iter().enumerate() {
+        let relative_pos = (vertex - grid_min) / cube_size;
+        let closest_point = [
+            relative_pos.x.round().to_index::<I>(),
+            relative_pos.y.round().to_index::<I>(),
+            relative_pos.z.round().to_index::<I>(),
+        ];
+
+        let snapped_point = match closest_point {
+            [Some(i), Some(j), Some(k)] if grid.point_exists(&[i, j, k]) => {
+                let point_coords = grid.point_coordinates_array(&[i, j, k]);
+                ((vertex - point_coords).norm() < max_distance).then_some(([i, j, k], point_coords))
+            }
+            _ => None,
+        };
+
+        if let Some((ijk, point_coords)) = snapped_point {
+            let cluster = *point_clusters.entry(ijk).or_insert_with(|| {
+                clusters.push((point_coords, Vec::new()));
+                clusters.len() - 1
+            });
+            clusters[cluster].1.push(i);
+        }
+    }
+
+    // Triangles adjacent to every vertex, kept up to date while merging
+    let mut triangles = mesh.triangles.clone();
+    let mut is_removed = vec![false; triangles.len()];
+    let mut vertex_triangles = vec![Vec::new(); mesh.vertices.len()];
+    for (t, tri) in triangles.iter().enumerate() {
+        for &v in tri {
+            vertex_triangles[v].push(t);
+        }
+    }
+
+    let mut num_snapped = 0;
+    let mut num_skipped = 0;
+    for (point_coords, cluster) in clusters.iter() {
+        let merged_vertex = cluster[0];
+        if cluster.len() > 1 {
+            let merge = match merge_vertices(cluster, &triangles, &is_removed, &vertex_triangles) {
+                Some(merge) => merge,
+                None => {
+                    num_skipped += 1;
+                    continue;
+                }
+            };
+
+            for &t in merge.removed_triangles.iter() {
+                is_removed[t] = true;
+            }
+            for &(t, tri) in merge.remapped_triangles.iter() {
+                triangles[t] = tri;
+            }
+            for &v in cluster.iter() {
+                vertex_triangles[v].clear();
+            }
+            vertex_triangles[merged_vertex] =
+                merge.remapped_triangles.iter().map(|&(t, _)| t).collect();
+        }
+
+        mesh.vertices[merged_vertex] = *point_coords;
+        num_snapped += cluster.len();
+    }
+
+    let num_triangles_before = mesh.triangles.len();
+    let triangles = triangles
+        .into_iter()
+        .zip(is_removed)
+        .filter_map(|(tri, is_removed)| (!is_removed).then_some(tri))
+        .collect::<Vec<_>>();
+
+    // Remove vertices that are no longer referenced by any triangle
+    let mut is_referenced = vec![false; mesh.vertices.len()];
+    for tri in triangles.iter() {
+        for &v in tri {
+            is_referenced[v] = true;
+        }
+    }
+
+    let mut compacted_indices = vec![usize::MAX; mesh.vertices.len()];
+    let mut compacted_vertices = Vec::with_capacity(mesh.vertices.len());
+    for (i, vertex) in mesh.vertices.iter().enumerate() {
+        if is_referenced[i] {
+            compacted_indices[i] = compacted_vertices.len();
+            compacted_vertices.push(*vertex);
+        }
+    }
+
+    let mut triangles = triangles;
+    for tri in triangles.iter_mut() {
+        for v in tri.iter_mut() {
+            *v = compacted_indices[*v];
+        }
+    }
+
+    let num_removed = num_triangles_before - triangles.len();
+    info!(
+        "Snapped {} vertices to grid points, removed {} degenerate triangles and {} vertices (skipped {} grid points where merging would result in a non-manifold mesh).",
+        num_snapped,
+        num_removed,
+        mesh.vertices.len() - compacted_vertices.len(),
+        num_skipped
+    );
+
+    mesh.vertices = compacted_vertices;
+    mesh.triangles = triangles;
+
+    num_removed
+}
+
+/// Triangles that are modified by merging the vertices of a cluster
+struct ClusterMerge {
+    /// Triangles adjacent to the merged vertex with their new vertex indices
+    remapped_triangles: Vec<(usize, [usize; 3])>,
+    /// Triangles that degenerate or coincide with another triangle due to the merge
+    removed_triangles: Vec<usize>,
+}
+
+/// Merges all vertices of the cluster into its first vertex, returns `None` if the merge would result in a non-manifold edge or vertex
+fn merge_vertices(
+    cluster: &[usize],
+    triangles: &[[usize; 3]],
+    is_removed: &[bool],
+    vertex_triangles: &[Vec<usize>],
+) -> Option<ClusterMerge> {
+    let merged_vertex = cluster[0];
+
+    let mut adjacent_triangles = cluster
+        .iter()
+        .flat_map(|&v| vertex_triangles[v].iter().copied())
+        .filter(|&t| !is_removed[t])
+        .collect::<Vec<_>>();
+    adjacent_triangles.sort_unstable();
+    adjacent_triangles.dedup();
+
+    // Remap the triangles and remove triangles that degenerated to an edge or a point
+    let mut removed_triangles = Vec::new();
+    let mut remapped_triangles = Vec::new();
+    for t in adjacent_triangles {
+        let tri = triangles[t].map(|v| {
+            if cluster.contains(&v) {
+                merged_vertex
+            } else {
+                v
+            }
+        });
+        if tri[0] != tri[1] && tri[1] != tri[2] && tri[2] != tri[0] {
+            remapped_triangles.push((t, tri));
+        } else {
+            removed_triangles.push(t);
+        }
+    }
+
+    // Coinciding triangles are removed if they can be paired up with opposite orientations
+    let mut coinciding_triangles: MapType<[usize; 3], [usize; 2]> = new_map();
+    for (_, tri) in remapped_triangles.iter() {
+        let (key, orientation) = sorted_with_orientation(tri);
+        coinciding_triangles.entry(key).or_default()[orientation] += 1;
+    }
+    if coinciding_triangles
+        .values()
+        .any(|counts| counts[0] + counts[1] > 1 && counts[0] != counts[1])
+    {
+        return None;
+    }
+    remapped_triangles.retain(|&(t, tri)| {
+        let counts = coinciding_triangles[&sorted_with_orientation(&tri).0];
+        if counts[0] + counts[1] > 1 {
+            removed_triangles.push(t);
+            false
+        } else {
+            true
+        }
+    });
+
+    // The edges opposite to the merged vertex in the remaining triangles form its link, which has to be
+    // a single cycle (interior vertex) or a single path (boundary vertex) for a manifold mesh
+    let mut next_link_vertex: MapType<usize, usize> = new_map();
+    let mut previous_link_vertex: MapType<usize, usize> = new_map();
+    for (_, tri) in remapped_triangles.iter() {
+        let k = tri.iter().position(|&v| v == merged_vertex)?;
+        let (a, b) = (tri[(k + 1) % 3], tri[(k + 2) % 3]);
+        if next_link_vertex.insert(a, b).is_some() || previous_link_vertex.insert(b, a).is_some() {
+            return None;
+        }
+    }
+
+    let mut path_starts = next_link_vertex
+        .keys()
+        .filter(|a| !previous_link_vertex.contains_key(*a));
+    let start = match (path_starts.next(), path_starts.next()) {
+        (Some(&start), None) => Some(start),
+        (None, _) => next_link_vertex.keys().next().copied(),
+        (Some(_), Some(_)) => return None,
+    };
+    if let Some(start) = start {
+        let mut num_link_edges = 0;
+        let mut current = start;
+        while let Some(&next) = next_link_vertex.get(&current) {
+            num_link_edges += 1;
+            current = next;
+            if current == start {
+                break;
+            }
+        }
+        if num_link_edges != next_link_vertex.len() {
+            return None;
+        }
+    }
+
+    Some(ClusterMerge {
+        remapped_triangles,
+        removed_triangles,
+    })
+}
+
+/// Returns the sorted vertex indices of the triangle and the index of its orientation relative to the sorted indices
+fn sorted_with_orientation(tri: &[usize; 3]) -> ([usize; 3], usize) {
+    let mut sorted = *tri;
+    sorted.sort_unstable();
+    // Rotate the triangle such that its smallest vertex comes first
+    let k = tri.iter().position(|&v| v == sorted[0]).unwrap();
+    let is_sorted_orientation = tri[(k + 1) % 3] == sorted[1];
+    (sorted, if is_sorted_orientation { 0 } else { 1 })
+}
+
+#[test]
+fn test_snapping_keeps_mesh_closed() {
+    use crate::mesh_quality::compute_mesh_quality;
+
+    // Octahedron with vertices slightly offset from the grid points of a unit grid
+    let mut mesh = TriMesh3d {
+        vertices: vec![
+            Vector3::new(1.02, 0.0, 0.0),
+            Vector3::new(-1.0, 0.0, 0.0),
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, -1.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(0.0, 0.0, -1.0),
+            // Additional vertex very close to the first vertex, splitting the triangle [0, 2, 4]
+            Vector3::new(0.99, 0.02, 0.02),
+        ],
+        triangles: vec![
+            [0, 2, 6],
+            [2, 4, 6],
+            [4, 0, 6],
+            [2, 1, 4],
+            [1, 3, 4],
+            [3, 0, 4],
+            [0, 2, 5],
+            [2, 1, 5],
+            [1, 3, 5],
+            [3, 0, 5],
+        ],
+    };
+    // Fix orientation of the lower half
+    for tri in mesh.triangles.iter_mut().skip(6) {
+        tri.swap(0, 1);
+    }
+    assert!(mesh.find_boundary_edges().is_empty());
+
+    let grid = UniformGrid::<i64, f64>::from_aabb(
+        &crate::Aabb3d::new(Vector3::repeat(-2.0), Vector3::repeat(2.0)),
+        1.0,
+    )
+    .unwrap();
+
+    let removed = snap_vertices_to_grid(&mut mesh, &grid, 0.1);
+    assert_eq!(removed, 2);
+    assert_eq!(mesh.vertices.len(), 6);
+    assert_eq!(mesh.triangles.len(), 8);
+    assert_eq!(mesh.vertices[0], Vector3::new(1.0, 0.0, 0.0));
+    assert!(mesh.find_boundary_edges().is_empty());
+    assert_eq!(compute_mesh_quality(&mesh).num_non_manifold_edges, 0);
+
+    // Thin slab where the vertices of the top and bottom sheet are close to the same grid points
+    let coords = [-1.5, 0.0, 1.0];
+    let mut vertices = Vec::new();
+    for z in [0.02, -0.02] {
+        for y in coords {
+            for x in coords {
+                vertices.push(Vector3::new(x, y, z));
+            }
+        }
+    }
+
+    let top = |i: usize, j: usize| i + 3 * j;
+    let bottom = |i: usize, j: usize| 9 + i + 3 * j;
+    // The sheets are triangulated with different diagonals such that their triangles do not coincide when fused
+    let mut triangles = Vec::new();
+    for j in 0..2 {
+        for i in 0..2 {
+            triangles.push([top(i, j), top(i + 1, j), top(i + 1, j + 1)]);
+            triangles.push([top(i, j), top(i + 1, j + 1), top(i, j + 1)]);
+            triangles.push([bottom(i, j), bottom(i, j + 1), bottom(i + 1, j)]);
+            triangles.push([bottom(i + 1, j), bottom(i, j + 1), bottom(i + 1, j + 1)]);
+        }
+    }
+    // Side walls along the boundary of the sheets (counterclockwise when viewed from above)
+    let ring = [
+        (0, 0),
+        (1, 0),
+        (2, 0),
+        (2, 1),
+        (2, 2),
+        (1, 2),
+        (0, 2),
+        (0, 1),
+    ];
+    for k in 0..ring.len() {
+        let (p, q) = (ring[k], ring[(k + 1) % ring.len()]);
+        triangles.push([top(p.0, p.1), bottom(p.0, p.1), bottom(q.0, q.1)]);
+        triangles.push([top(p.0, p.1), bottom(q.0, q.1), top(q.0, q.1)]);
+    }
+
+    let mut mesh = TriMesh3d {
+        vertices,
+        triangles,
+    };
+    let quality = compute_mesh_quality(&mesh);
+    assert!(quality.is_closed_manifold());
+    assert!(quality.volume > 0.0);
+
+    snap_vertices_to_grid(&mut mesh, &grid, 0.1);
+
+    // The sheets may not be fused at the grid points
+    let quality = compute_mesh_quality(&mesh);
+    assert!(quality.is_closed_manifold());
+    assert_eq!(quality.num_non_manifold_edges, 0);
+    assert_eq!(quality.num_boundary_edges, 0);
+    assert_eq!(quality.num_components, 1);
+    assert_eq!(quality.genus, Some(0));
+    assert!(mesh
+        .vertices
+        .iter()
+        .any(|v| v.xy().norm() < 0.1 && v.z > 0.0));
+    assert!(mesh
+        .vertices
+        .iter()
+        .any(|v| v.xy().norm() < 0.1 && v.z < 0.0));
+}
@@ -1,22 +1,36 @@
 use log::info;
 use nalgebra::Vector3;
 
+use crate::boundary::BoundaryConditions;
 use crate::dense_subdomains::{
-    compute_global_density_vector, decomposition, initialize_parameters, reconstruction, stitching,
+    compute_global_density_vector, decomposition, initialize_parameters, reconstruction,
+    reconstruction_surface_nets, stitching, stitching_surface_nets,
     subdomain_classification::GhostMarginClassifier,
 };
-use crate::{profile, Index, Parameters, Real, SurfaceReconstruction};
+use crate::{
+    profile, Index, IsoSurfaceExtraction, Parameters, ParticleProperties, Real,
+    SurfaceReconstruction,
+};
 
 /// Performs a surface reconstruction with a regular grid for domain decomposition
 pub(crate) fn reconstruct_surface_subdomain_grid<'a, I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
+    particle_properties: ParticleProperties<R>,
+    boundary: Option<BoundaryConditions<R>>,
     parameters: &Parameters<R>,
     output_surface: &'a mut SurfaceReconstruction<I, R>,
 ) -> Result<(), anyhow::Error> {
     let mesh = {
         profile!("surface reconstruction subdomain-grid");
 
-        let parameters = initialize_parameters(parameters, &particle_positions, output_surface)?;
+        let iso_surface_extraction = parameters.iso_surface_extraction;
+        let parameters = initialize_parameters(
+            parameters,
+            &particle_positions,
+            particle_properties,
+            boundary,
+            output_surface,
+        )?;
 
         // Filter "narrow band"
         /*
@@ -38,17 +52,43 @@ pub(crate) fn reconstruct_surface_subdomain_grid<'a, I: Index, R: Real>(
         }
          */
 
-        let particle_densities =
-            compute_global_density_vector(&parameters, &particle_positions, &subdomains);
-
-        let surface_patches = reconstruction(
+        let (particle_densities, anisotropic_kernels) = compute_global_density_vector(
             &parameters,
             &particle_positions,
-            &particle_densities,
+            particle_properties,
             &subdomains,
         );
+        // Anisotropic kernels are centered at the smoothed particle positions
+        let kernel_centers = anisotropic_kernels
+            .as_ref()
+            .map_or(particle_positions, |kernels| &kernels.smoothed_positions);
+
+        let global_mesh = match iso_surface_extraction {
+            IsoSurfaceExtraction::MarchingCubes => {
+                let surface_patches = reconstruction(
+                    &parameters,
+                    kernel_centers,
+                    &particle_densities,
+                    anisotropic_kernels.as_ref(),
+                    particle_properties,
+                    &subdomains,
+                );
+
+                stitching(surface_patches)
+            }
+            IsoSurfaceExtraction::SurfaceNets => {
+                let surface_patches = reconstruction_surface_nets(
+                    &parameters,
+                    kernel_centers,
+                    &particle_densities,
+                    anisotropic_kernels.as_ref(),
+                    particle_properties,
+                    &subdomains,
+                );
 
-        let global_mesh = stitching(surface_patches);
+                stitching_surface_nets(surface_patches)
+            }
+        };
         info!(
             "Global mesh has {} vertices and {} triangles.",
             global_mesh.vertices.len(),
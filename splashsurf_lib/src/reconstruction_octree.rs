@@ -1,5 +1,6 @@
 //! Helper functions calling the individual steps of the reconstruction pipeline
 
+use crate::boundary::BoundaryConditions;
 use crate::generic_tree::*;
 use crate::marching_cubes::SurfacePatch;
 use crate::mesh::TriMesh3d;
@@ -7,9 +8,10 @@ use crate::octree::{NodeData, Octree, OctreeNode};
 use crate::uniform_grid::{OwningSubdomainGrid, Subdomain, UniformGrid};
 use crate::workspace::LocalReconstructionWorkspace;
 use crate::{
-    density_map, marching_cubes, neighborhood_search, new_map, profile, utils, Index, Parameters,
-    ParticleDensityComputationStrategy, Real, ReconstructionError, SpatialDecompositionParameters,
-    SurfaceReconstruction,
+    adaptive, anisotropy, density_map, marching_cubes, max_kernel_radius, neighborhood_search,
+    neighborhood_search_domain, new_map, profile, surface_nets, utils, Index, IsoSurfaceExtraction,
+    Parameters, ParticleDensityComputationStrategy, ParticleProperties, Real, ReconstructionError,
+    SpatialDecompositionParameters, SurfaceReconstruction,
 };
 use log::{debug, info, trace};
 use nalgebra::Vector3;
@@ -19,6 +21,8 @@ use parking_lot::Mutex;
 /// Performs a global surface reconstruction without domain decomposition
 pub(crate) fn reconstruct_surface_global<'a, I: Index, R: Real>(
     particle_positions: &[Vector3<R>],
+    particle_properties: ParticleProperties<R>,
+    boundary: Option<&BoundaryConditions<R>>,
     parameters: &Parameters<R>,
     output_surface: &'a mut SurfaceReconstruction<I, R>,
 ) -> Result<(), ReconstructionError<I, R>> {
@@ -51,6 +55,8 @@ pub(crate) fn reconstruct_surface_global<'a, I: Index, R: Real>(
         None,
         particle_positions,
         None,
+        particle_properties,
+        boundary,
         parameters,
         &mut output_surface.mesh,
     )?;
@@ -228,6 +234,7 @@ impl<I: Index, R: Real> OctreeBasedSurfaceReconstruction<I, R> {
             compute_particle_densities_and_neighbors(
                 grid,
                 global_particle_positions,
+                ParticleProperties::uniform(),
                 parameters,
                 &mut workspace.particle_neighbor_lists,
                 &mut densities,
@@ -292,6 +299,7 @@ impl<I: Index, R: Real> OctreeBasedSurfaceReconstruction<I, R> {
                 compute_particle_densities_and_neighbors(
                     grid,
                     tl_workspace.particle_positions.as_slice(),
+                    ParticleProperties::uniform(),
                     parameters,
                     &mut tl_workspace.particle_neighbor_lists,
                     &mut tl_workspace.particle_densities,
@@ -395,6 +403,8 @@ impl<I: Index, R: Real> OctreeBasedSurfaceReconstruction<I, R> {
                             Some(&subdomain_grid),
                             node_particle_positions.as_slice(),
                             node_particle_densities.as_ref().map(|v| v.as_slice()),
+                            ParticleProperties::uniform(),
+                            None,
                             &self.parameters,
                             &mut node_mesh,
                         )?;
@@ -590,6 +600,7 @@ impl<I: Index, R: Real> OctreeBasedSurfaceReconstruction<I, R> {
 pub(crate) fn compute_particle_densities_and_neighbors<I: Index, R: Real>(
     grid: &UniformGrid<I, R>,
     particle_positions: &[Vector3<R>],
+    particle_properties: ParticleProperties<R>,
     parameters: &Parameters<R>,
     particle_neighbor_lists: &mut Vec<Vec<usize>>,
     densities: &mut Vec<R>,
@@ -603,9 +614,9 @@ pub(crate) fn compute_particle_densities_and_neighbors<I: Index, R: Real>(
 
     trace!("Starting neighborhood search...");
     neighborhood_search::search_inplace::<I, R>(
-        &grid.aabb(),
+        &neighborhood_search_domain(grid, particle_positions, parameters),
         particle_positions,
-        parameters.compact_support_radius,
+        particle_properties.max_compact_support_radius(parameters.compact_support_radius),
         parameters.enable_multi_threading,
         particle_neighbor_lists,
     );
@@ -615,7 +626,9 @@ pub(crate) fn compute_particle_densities_and_neighbors<I: Index, R: Real>(
         particle_positions,
         particle_neighbor_lists.as_slice(),
         parameters.compact_support_radius,
+        parameters.kernel,
         particle_rest_mass,
+        particle_properties,
         parameters.enable_multi_threading,
         densities,
     );
@@ -628,6 +641,8 @@ pub(crate) fn reconstruct_single_surface_append<'a, I: Index, R: Real>(
     subdomain_grid: Option<&OwningSubdomainGrid<I, R>>,
     particle_positions: &[Vector3<R>],
     particle_densities: Option<&[R]>,
+    particle_properties: ParticleProperties<R>,
+    boundary: Option<&BoundaryConditions<R>>,
     parameters: &Parameters<R>,
     output_mesh: &'a mut TriMesh3d<R>,
 ) -> Result<(), ReconstructionError<I, R>> {
@@ -643,36 +658,150 @@ pub(crate) fn reconstruct_single_surface_append<'a, I: Index, R: Real>(
         compute_particle_densities_and_neighbors(
             grid,
             particle_positions,
+            particle_properties,
             parameters,
             &mut workspace.particle_neighbor_lists,
             &mut workspace.particle_densities,
         );
+        if let Some(boundary) = boundary {
+            boundary.add_particle_densities(
+                particle_positions,
+                &mut workspace.particle_densities,
+                parameters.enable_multi_threading,
+            );
+        }
         workspace.particle_densities.as_slice()
     };
 
+    // Compute the anisotropic kernels using the neighborhood lists of the density computation
+    let anisotropic_kernels = parameters.anisotropy.as_ref().map(|anisotropy| {
+        assert!(
+            subdomain_grid.is_none()
+                && workspace.particle_neighbor_lists.len() == particle_positions.len(),
+            "anisotropic kernels are not supported for octree subdomains"
+        );
+        anisotropy::compute_anisotropic_kernels(
+            particle_positions,
+            &workspace.particle_neighbor_lists,
+            parameters.compact_support_radius,
+            particle_properties,
+            anisotropy,
+            parameters.enable_multi_threading,
+        )
+    });
+    let kernel_centers = anisotropic_kernels
+        .as_ref()
+        .map(|kernels| kernels.smoothed_positions.as_slice())
+        .unwrap_or(particle_positions);
+
     // Create a new density map, reusing memory with the workspace is bad for cache efficiency
     // Alternatively one could reuse memory with a custom caching allocator
     let mut density_map = new_map().into();
+    // Caps at the domain boundary require the contributions of the particles outside of the domain
+    let exterior_margin = if parameters.cap_domain_boundary {
+        let max_kernel_radius = max_kernel_radius(parameters, particle_properties);
+        Some(density_map::grid_with_exterior_margin(
+            grid,
+            max_kernel_radius,
+        )?)
+    } else {
+        None
+    };
     density_map::generate_sparse_density_map(
-        grid,
+        exterior_margin
+            .as_ref()
+            .map_or(grid, |(extended_grid, _)| extended_grid),
         subdomain_grid,
-        particle_positions,
+        kernel_centers,
         particle_densities,
+        anisotropic_kernels.as_ref(),
         None,
         particle_rest_mass,
+        particle_properties,
         parameters.compact_support_radius,
+        parameters.kernel,
         parameters.cube_size,
         parameters.enable_multi_threading,
         &mut density_map,
     )?;
+    if let Some((extended_grid, margin_cells)) = exterior_margin.as_ref() {
+        density_map =
+            density_map::restrict_density_map(extended_grid, *margin_cells, grid, &density_map);
+    }
 
-    marching_cubes::triangulate_density_map_append(
-        grid,
-        subdomain_grid,
-        &density_map,
-        parameters.iso_surface_threshold,
-        output_mesh,
-    )?;
+    if let Some(boundary) = boundary {
+        assert!(
+            subdomain_grid.is_none(),
+            "solid boundaries are not supported for octree subdomains"
+        );
+        boundary.apply_to_density_map(grid, &mut density_map, parameters.enable_multi_threading);
+    }
+
+    match parameters.iso_surface_extraction {
+        IsoSurfaceExtraction::MarchingCubes if parameters.cap_domain_boundary => {
+            assert!(
+                subdomain_grid.is_none(),
+                "caps at the domain boundary are not supported for octree subdomains"
+            );
+            marching_cubes::triangulate_density_map_capped_append(
+                grid,
+                &density_map,
+                parameters.iso_surface_threshold,
+                output_mesh,
+            )?;
+        }
+        IsoSurfaceExtraction::MarchingCubes => {
+            marching_cubes::triangulate_density_map_append(
+                grid,
+                subdomain_grid,
+                &density_map,
+                parameters.iso_surface_threshold,
+                output_mesh,
+            )?;
+        }
+        IsoSurfaceExtraction::SurfaceNets if parameters.adaptive_resolution.is_some() => {
+            assert!(
+                subdomain_grid.is_none() && boundary.is_none(),
+                "adaptive resolution is not supported for octree subdomains and solid boundaries"
+            );
+            // The densities at the points of subdivided cells are evaluated directly from the particles,
+            // consistent with the values of the density map at the points of the background grid
+            adaptive::triangulate_density_map_append(
+                grid,
+                &density_map,
+                parameters.iso_surface_threshold,
+                parameters.adaptive_resolution.as_ref().unwrap(),
+                |points| {
+                    density_map::evaluate_density_at_points(
+                        grid,
+                        kernel_centers,
+                        particle_densities,
+                        particle_rest_mass,
+                        particle_properties,
+                        parameters.compact_support_radius,
+                        parameters.kernel,
+                        parameters.cube_size,
+                        points,
+                        parameters.enable_multi_threading,
+                    )
+                    .map_err(ReconstructionError::from)
+                },
+                output_mesh,
+            )?;
+        }
+        IsoSurfaceExtraction::SurfaceNets => {
+            assert!(
+                subdomain_grid.is_none(),
+                "surface nets are not supported for octree subdomains"
+            );
+            surface_nets::triangulate_density_map_append(
+                grid,
+                &density_map,
+                parameters.iso_surface_threshold,
+                output_mesh,
+            );
+        }
+    }
 
     Ok(())
 }
@@ -699,6 +828,7 @@ pub(crate) fn reconstruct_surface_patch<I: Index, R: Real>(
         compute_particle_densities_and_neighbors(
             subdomain_grid.global_grid(),
             particle_positions,
+            ParticleProperties::uniform(),
             parameters,
             &mut workspace.particle_neighbor_lists,
             &mut workspace.particle_densities,
@@ -715,8 +845,11 @@ pub(crate) fn reconstruct_surface_patch<I: Index, R: Real>(
         particle_positions,
         particle_densities,
         None,
+        None,
         particle_rest_mass,
+        ParticleProperties::uniform(),
         parameters.compact_support_radius,
+        parameters.kernel,
         parameters.cube_size,
         parameters.enable_multi_threading,
         &mut density_map,
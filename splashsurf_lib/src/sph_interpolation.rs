@@ -1,10 +1,14 @@
 //! Functions for interpolating quantities (e.g. normals, scalar fields) by evaluating SPH sums
+//!
+//! Real valued quantities (scalars, vectors and tensors) are interpolated using SPH sums, quantities that cannot be
+//! averaged (e.g. integer ids) are transferred from the closest particle or by a weighted majority vote.
 
-use crate::kernel::SymmetricKernel3d;
+use crate::kernel::{KernelType, SphKernel, SymmetricKernel3d};
 use crate::profile;
+use crate::ParticleProperties;
 use crate::Real;
-use crate::{kernel, ThreadSafe};
-use nalgebra::{SVector, Unit, Vector3};
+use crate::ThreadSafe;
+use nalgebra::{Matrix3, SMatrix, SVector, Unit, Vector3};
 use rayon::prelude::*;
 use rstar::primitives::GeomWithData;
 use rstar::RTree;
@@ -12,7 +16,8 @@ use std::ops::AddAssign;
 
 /// Acceleration structure for interpolating field quantities of the fluid to arbitrary points using SPH interpolation
 pub struct SphInterpolator<R: Real> {
-    compact_support_radius: R,
+    /// Kernel with the largest compact support radius of all particles
+    kernel: SphKernel<R>,
     tree: RTree<Particle<R>>,
 }
 
@@ -25,6 +30,24 @@ struct ParticleData<R: Real> {
     index: usize,
     /// Volume associated with each particle which is needed to evaluate the SPH density field
     volume: R,
+    /// Ratio between the compact support radius of the interpolator kernel and the compact support radius of the particle
+    kernel_scale: R,
+}
+
+impl<R: Real> ParticleData<R> {
+    /// Evaluates the kernel of the particle, rescaled to its compact support radius
+    #[inline(always)]
+    fn evaluate_kernel(&self, kernel: &SphKernel<R>, r: R) -> R {
+        let s = self.kernel_scale;
+        s * s * s * kernel.evaluate(r * s)
+    }
+
+    /// Evaluates the gradient norm of the kernel of the particle, rescaled to its compact support radius
+    #[inline(always)]
+    fn evaluate_kernel_gradient_norm(&self, kernel: &SphKernel<R>, r: R) -> R {
+        let s = self.kernel_scale;
+        s * s * s * s * kernel.evaluate_gradient_norm(r * s)
+    }
 }
 
 /// Trait for per-particle quantities that can be interpolated using SPH
@@ -45,7 +68,7 @@ impl<R: Real> InterpolationQuantity<R> for R {
     }
 }
 
-impl<R: Real, const D: usize> InterpolationQuantity<R> for SVector<R, D> {
+impl<R: Real, const M: usize, const N: usize> InterpolationQuantity<R> for SMatrix<R, M, N> {
     fn zero() -> Self {
         Self::zeros()
     }
@@ -57,18 +80,31 @@ impl<R: Real, const D: usize> InterpolationQuantity<R> for SVector<R, D> {
 
 impl<R: Real> SphInterpolator<R> {
     /// Initializes the acceleration structure for interpolating values of the given fluid particles, this is a relatively expensive operation (builds an R-tree)
+    ///
+    /// The per particle masses and compact support radii of `particle_properties` are used instead of the
+    /// uniform `particle_rest_mass` and `compact_support_radius` if they are provided.
     pub fn new(
         particle_positions: &[Vector3<R>],
         particle_densities: &[R],
         particle_rest_mass: R,
+        particle_properties: ParticleProperties<R>,
         compact_support_radius: R,
+        kernel_type: KernelType,
     ) -> Self {
         assert_eq!(particle_positions.len(), particle_densities.len());
 
-        let tree = build_rtree(particle_positions, particle_densities, particle_rest_mass);
+        let max_compact_support_radius =
+            particle_properties.max_compact_support_radius(compact_support_radius);
+        let tree = build_rtree(
+            particle_positions,
+            particle_densities,
+            particle_rest_mass,
+            particle_properties,
+            max_compact_support_radius,
+        );
 
         Self {
-            compact_support_radius,
+            kernel: SphKernel::new(kernel_type, max_compact_support_radius),
             tree,
         }
     }
@@ -81,8 +117,8 @@ impl<R: Real> SphInterpolator<R> {
     ) {
         profile!("interpolate_normals_inplace");
 
-        let squared_support = self.compact_support_radius * self.compact_support_radius;
-        let kernel = kernel::CubicSplineKernel::new(self.compact_support_radius);
+        let kernel = &self.kernel;
+        let squared_support = kernel.compact_support_radius() * kernel.compact_support_radius();
 
         interpolation_points
             .par_iter()
@@ -107,7 +143,8 @@ impl<R: Real> SphInterpolator<R> {
 
                     // Compute the contribution of the neighbor to the gradient of the density field
                     // TODO: Replace this by a discrete gradient norm evaluation
-                    let kernel_grad = dx.unscale(r) * kernel.evaluate_gradient_norm(r);
+                    let kernel_grad =
+                        dx.unscale(r) * p_j.data.evaluate_kernel_gradient_norm(kernel, r);
                     density_grad += kernel_grad * vol_j;
                 }
 
@@ -127,6 +164,67 @@ impl<R: Real> SphInterpolator<R> {
         normals
     }
 
+    /// Evaluates the SPH indicator function of the fluid and its gradient at the given points
+    ///
+    /// The indicator function `sum_j V_j W(x - x_j)` is the field that is compared to the iso-surface threshold by the
+    /// surface reconstruction. Its gradient points into the fluid, i.e. opposite to the surface normals.
+    pub fn interpolate_indicator_with_gradient(
+        &self,
+        interpolation_points: &[Vector3<R>],
+    ) -> Vec<(R, Vector3<R>)> {
+        profile!("interpolate_indicator_with_gradient");
+
+        let kernel = &self.kernel;
+        let squared_support = kernel.compact_support_radius() * kernel.compact_support_radius();
+
+        interpolation_points
+            .par_iter()
+            .map(|x_i| {
+                let mut indicator = R::zero();
+                let mut indicator_grad = Vector3::zeros();
+
+                let query_point = bytemuck::cast::<_, [R; 3]>(*x_i);
+                for p_j in self
+                    .tree
+                    .locate_within_distance(query_point, squared_support)
+                {
+                    let vol_j = p_j.data.volume;
+                    let x_j = bytemuck::cast_ref::<_, Vector3<R>>(p_j.geom());
+
+                    let dx = x_j - x_i;
+                    let r = dx.norm();
+
+                    indicator += vol_j * p_j.data.evaluate_kernel(kernel, r);
+                    // The gradient norm of the kernel is negative, i.e. the gradient points towards the particle
+                    if r > R::zero() {
+                        indicator_grad -= dx.unscale(r)
+                            * (vol_j * p_j.data.evaluate_kernel_gradient_norm(kernel, r));
+                    }
+                }
+
+                (indicator, indicator_grad)
+            })
+            .collect()
+    }
+
+    /// Counts the particles within the compact support radius of the kernel around each of the given points
+    pub fn count_neighbors(&self, interpolation_points: &[Vector3<R>]) -> Vec<usize> {
+        profile!("count_neighbors");
+
+        let squared_support =
+            self.kernel.compact_support_radius() * self.kernel.compact_support_radius();
+
+        interpolation_points
+            .par_iter()
+            .map(|x_i| {
+                let query_point = bytemuck::cast::<_, [R; 3]>(*x_i);
+                self.tree
+                    .locate_within_distance(query_point, squared_support)
+                    .count()
+            })
+            .collect()
+    }
+
     /// Interpolates a scalar per particle quantity to the given points, panics if the there are less per-particles values than particles, appends to the given vector
     #[allow(non_snake_case)]
     fn interpolate_scalar_quantity_inplace(
@@ -195,6 +293,155 @@ impl<R: Real> SphInterpolator<R> {
         values
     }
 
+    /// Interpolates a 3x3 tensor per particle quantity to the given points, panics if the there are less per-particles values than particles
+    pub fn interpolate_tensor_quantity(
+        &self,
+        particle_quantity: &[Matrix3<R>],
+        interpolation_points: &[Vector3<R>],
+        first_order_correction: bool,
+    ) -> Vec<Matrix3<R>> {
+        let mut values = Vec::with_capacity(interpolation_points.len());
+        self.interpolate_quantity_inplace(
+            particle_quantity,
+            interpolation_points,
+            &mut values,
+            first_order_correction,
+        );
+        values
+    }
+
+    /// Interpolates a vectorial per particle quantity with a number of components only known at runtime to the given points
+    ///
+    /// The components of all vectors are stored contiguously, i.e. `particle_quantity` has to contain
+    /// `num_components` values per particle and the returned vector contains `num_components` values per interpolation point.
+    #[allow(non_snake_case)]
+    pub fn interpolate_flat_vector_quantity(
+        &self,
+        particle_quantity: &[R],
+        num_components: usize,
+        interpolation_points: &[Vector3<R>],
+        first_order_correction: bool,
+    ) -> Vec<R> {
+        profile!("interpolate_flat_vector_quantity");
+        assert!(num_components > 0);
+        assert_eq!(particle_quantity.len(), self.tree.size() * num_components);
+
+        let kernel = &self.kernel;
+        let squared_support = kernel.compact_support_radius() * kernel.compact_support_radius();
+
+        let mut interpolated_values = vec![R::zero(); interpolation_points.len() * num_components];
+        interpolated_values
+            .par_chunks_exact_mut(num_components)
+            .zip(interpolation_points.par_iter())
+            .for_each(|(interpolated_value, x_i)| {
+                let mut correction = R::zero();
+
+                let query_point = bytemuck::cast::<_, [R; 3]>(*x_i);
+                for p_j in self
+                    .tree
+                    .locate_within_distance(query_point, squared_support)
+                {
+                    let vol_j = p_j.data.volume;
+                    let x_j = bytemuck::cast_ref::<_, Vector3<R>>(p_j.geom());
+                    let r = (x_j - x_i).norm();
+
+                    let offset = p_j.data.index * num_components;
+                    let A_j = &particle_quantity[offset..offset + num_components];
+                    let W_ij = p_j.data.evaluate_kernel(kernel, r);
+
+                    for (a_i, &a_j) in interpolated_value.iter_mut().zip(A_j) {
+                        *a_i += a_j * vol_j * W_ij;
+                    }
+                    correction += vol_j * W_ij;
+                }
+
+                if first_order_correction {
+                    let correction_factor = correction.recip();
+                    for a_i in interpolated_value.iter_mut() {
+                        *a_i *= correction_factor;
+                    }
+                }
+            });
+
+        interpolated_values
+    }
+
+    /// Interpolates a per particle quantity to the given points by copying the value of the closest particle, panics if the there are less per-particles values than particles
+    ///
+    /// This is useful for quantities that cannot be averaged, e.g. integer phase or object ids.
+    pub fn interpolate_nearest_particle<T: Clone + ThreadSafe>(
+        &self,
+        particle_quantity: &[T],
+        interpolation_points: &[Vector3<R>],
+    ) -> Vec<T> {
+        profile!("interpolate_nearest_particle");
+        assert_eq!(particle_quantity.len(), self.tree.size());
+
+        interpolation_points
+            .par_iter()
+            .map(|x_i| particle_quantity[self.nearest_particle(x_i)].clone())
+            .collect()
+    }
+
+    /// Interpolates a per particle quantity to the given points by a majority vote of the particles in the kernel support, panics if the there are less per-particles values than particles
+    ///
+    /// The vote of every particle is weighted by its SPH weight `V_j W(x - x_j)`, i.e. the value with the largest
+    /// total weight is selected. Points without any particle in their kernel support get the value of the closest
+    /// particle. This is useful for quantities that cannot be averaged, e.g. integer phase or object ids.
+    pub fn interpolate_majority_vote<T: Clone + PartialEq + ThreadSafe>(
+        &self,
+        particle_quantity: &[T],
+        interpolation_points: &[Vector3<R>],
+    ) -> Vec<T> {
+        profile!("interpolate_majority_vote");
+        assert_eq!(particle_quantity.len(), self.tree.size());
+
+        let kernel = &self.kernel;
+        let squared_support = kernel.compact_support_radius() * kernel.compact_support_radius();
+
+        interpolation_points
+            .par_iter()
+            .map(|x_i| {
+                // Total weight of every distinct value in the kernel support (usually there are only a few distinct values)
+                let mut votes: Vec<(&T, R)> = Vec::new();
+
+                let query_point = bytemuck::cast::<_, [R; 3]>(*x_i);
+                for p_j in self
+                    .tree
+                    .locate_within_distance(query_point, squared_support)
+                {
+                    let x_j = bytemuck::cast_ref::<_, Vector3<R>>(p_j.geom());
+                    let r = (x_j - x_i).norm();
+                    let weight = p_j.data.volume * p_j.data.evaluate_kernel(kernel, r);
+
+                    let value = &particle_quantity[p_j.data.index];
+                    match votes.iter_mut().find(|(v, _)| *v == value) {
+                        Some((_, total_weight)) => *total_weight += weight,
+                        None => votes.push((value, weight)),
+                    }
+                }
+
+                votes
+                    .into_iter()
+                    .filter(|(_, total_weight)| *total_weight > R::zero())
+                    .reduce(|a, b| if b.1 > a.1 { b } else { a })
+                    .map(|(value, _)| value.clone())
+                    .unwrap_or_else(|| particle_quantity[self.nearest_particle(x_i)].clone())
+            })
+            .collect()
+    }
+
+    /// Returns the index of the particle closest to the given point, panics if there are no particles
+    fn nearest_particle(&self, x: &Vector3<R>) -> usize {
+        let query_point = bytemuck::cast::<_, [R; 3]>(*x);
+        self.tree
+            .nearest_neighbor(&query_point)
+            .or_else(|| self.tree.nearest_neighbor_iter(&query_point).next())
+            .expect("nearest particle query requires at least one particle")
+            .data
+            .index
+    }
+
     /// Interpolates a per particle quantity to the given points, panics if the there are less per-particles values than particles, appends to the given vector
     #[allow(non_snake_case)]
     fn interpolate_quantity_inplace<T: InterpolationQuantity<R>>(
@@ -207,8 +454,8 @@ impl<R: Real> SphInterpolator<R> {
         profile!("interpolate_quantity_inplace");
         assert_eq!(particle_quantity.len(), self.tree.size());
 
-        let squared_support = self.compact_support_radius * self.compact_support_radius;
-        let kernel = kernel::CubicSplineKernel::new(self.compact_support_radius);
+        let kernel = &self.kernel;
+        let squared_support = kernel.compact_support_radius() * kernel.compact_support_radius();
 
         let enable_correction = if first_order_correction {
             R::one()
@@ -239,7 +486,7 @@ impl<R: Real> SphInterpolator<R> {
 
                     // Unchecked access is fine as we asserted before that the slice has the correct length
                     let A_j = unsafe { particle_quantity.get_unchecked(p_j.data.index).clone() };
-                    let W_ij = kernel.evaluate(r);
+                    let W_ij = p_j.data.evaluate_kernel(kernel, r);
 
                     interpolated_value += A_j.scale(vol_j * W_ij);
                     correction += vol_j * W_ij;
@@ -253,11 +500,13 @@ impl<R: Real> SphInterpolator<R> {
     }
 }
 
-/// Constructs an R-Tree for the given particles, storing each particle's index and volume in the tree
+/// Constructs an R-Tree for the given particles, storing each particle's index, volume and kernel scale in the tree
 fn build_rtree<R: Real>(
     particle_positions: &[Vector3<R>],
     particle_densities: &[R],
     particle_rest_mass: R,
+    particle_properties: ParticleProperties<R>,
+    max_compact_support_radius: R,
 ) -> RTree<Particle<R>> {
     assert_eq!(particle_positions.len(), particle_densities.len());
 
@@ -270,7 +519,9 @@ fn build_rtree<R: Real>(
         .map(|(i, (p, rho_i))| {
             let data = ParticleData {
                 index: i,
-                volume: particle_rest_mass / rho_i,
+                volume: particle_properties.mass(i, particle_rest_mass) / rho_i,
+                kernel_scale: max_compact_support_radius
+                    / particle_properties.compact_support_radius(i, max_compact_support_radius),
             };
             Particle::new(bytemuck::cast(*p), data)
         })
@@ -284,3 +535,76 @@ fn build_rtree<R: Real>(
 
     tree
 }
+
+#[cfg(test)]
+fn test_interpolator(particle_positions: &[Vector3<f64>]) -> SphInterpolator<f64> {
+    let densities = vec![1000.0; particle_positions.len()];
+    SphInterpolator::new(
+        particle_positions,
+        &densities,
+        1.0,
+        ParticleProperties::uniform(),
+        1.0,
+        KernelType::CubicSpline,
+    )
+}
+
+#[test]
+fn test_interpolate_tensor_and_flat_vector_quantity() {
+    let particles: Vec<_> = (0..10)
+        .map(|i| Vector3::new(i as f64 * 0.25, 0.0, 0.0))
+        .collect();
+    let interpolator = test_interpolator(&particles);
+    let points = vec![Vector3::new(1.1, 0.1, 0.0), Vector3::new(0.6, 0.0, 0.2)];
+
+    // A constant tensor field is reproduced exactly with first order correction
+    let tensor = Matrix3::new(1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0);
+    let tensors = interpolator.interpolate_tensor_quantity(&vec![tensor; 10], &points, true);
+    for t in tensors {
+        assert!((t - tensor).norm() < 1e-12);
+    }
+
+    // The flat interpolation of vectors with three components matches the interpolation of `Vector3`
+    let vectors: Vec<_> = particles
+        .iter()
+        .map(|p| Vector3::new(p.x, 2.0 * p.x, -1.0))
+        .collect();
+    let expected = interpolator.interpolate_vector_quantity(&vectors, &points, true);
+    let flat_vectors: Vec<f64> = vectors.iter().flatten().copied().collect();
+    let interpolated =
+        interpolator.interpolate_flat_vector_quantity(&flat_vectors, 3, &points, true);
+    assert_eq!(interpolated.len(), 6);
+    for (v, flat_v) in expected.iter().zip(interpolated.chunks_exact(3)) {
+        assert!((v - Vector3::from_column_slice(flat_v)).norm() < 1e-12);
+    }
+}
+
+#[test]
+fn test_interpolate_integer_quantity() {
+    // Three particles of phase 1 on the left and two particles of phase 2 on the right
+    let particles = vec![
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.1, 0.0, 0.0),
+        Vector3::new(0.2, 0.0, 0.0),
+        Vector3::new(0.45, 0.0, 0.0),
+        Vector3::new(0.55, 0.0, 0.0),
+    ];
+    let phases: Vec<u64> = vec![1, 1, 1, 2, 2];
+    let interpolator = test_interpolator(&particles);
+
+    let points = vec![
+        Vector3::new(0.38, 0.0, 0.0),
+        Vector3::new(0.6, 0.0, 0.0),
+        Vector3::new(5.0, 0.0, 0.0),
+    ];
+
+    // The closest particle of the first point has phase 2 but the majority of the particles in its support has phase 1
+    assert_eq!(
+        interpolator.interpolate_nearest_particle(&phases, &points),
+        vec![2, 2, 2]
+    );
+    assert_eq!(
+        interpolator.interpolate_majority_vote(&phases, &points),
+        vec![1, 2, 2]
+    );
+}
@@ -0,0 +1,253 @@
+//! Triangulation of [`DensityMap`](crate::density_map::DensityMap)s using surface nets
+//!
+//! In contrast to marching cubes, which places vertices on the edges of the background grid,
+//! (naive) surface nets place exactly one vertex inside of every cell that is intersected by the
+//! iso-surface. The vertex is located at the average of the points where the iso-surface crosses
+//! the edges of the cell. For every edge of the grid that crosses the iso-surface, the vertices of
+//! the four cells adjacent to the edge are connected by a quad which is split into two triangles.
+//! This results in meshes with considerably fewer sliver triangles than marching cubes.
+//!
+//! Points of the grid without a value in the density map are considered to be outside of the fluid.
+//! Edges at the boundary of the grid that do not have four adjacent cells are skipped, i.e. the
+//! iso-surface should not intersect the outermost layer of cells to obtain a closed mesh.
+
+use crate::mesh::TriMesh3d;
+use crate::topology::Axis;
+use crate::{new_map, profile, DensityMap, Index, Real, UniformGrid};
+use nalgebra::Vector3;
+
+/// Local `[i, j, k]` offsets of the corners of a cell, the local index of a corner is `i + 2 * j + 4 * k`
+pub(crate) const CELL_CORNER_OFFSETS: [[usize; 3]; 8] = [
+    [0, 0, 0],
+    [1, 0, 0],
+    [0, 1, 0],
+    [1, 1, 0],
+    [0, 0, 1],
+    [1, 0, 1],
+    [0, 1, 1],
+    [1, 1, 1],
+];
+
+/// Local corner indices of the twelve edges of a cell
+const CELL_EDGES: [[usize; 2]; 12] = [
+    [0, 1],
+    [2, 3],
+    [4, 5],
+    [6, 7],
+    [0, 2],
+    [1, 3],
+    [4, 6],
+    [5, 7],
+    [0, 4],
+    [1, 5],
+    [2, 6],
+    [3, 7],
+];
+
+/// Offsets of the four cells adjacent to an edge along the two orthogonal axes of the edge, ordered counter-clockwise around the positive edge direction
+pub(crate) const EDGE_ADJACENT_CELL_OFFSETS: [[usize; 2]; 4] = [[1, 1], [0, 1], [0, 0], [1, 0]];
+
+/// Performs a surface nets triangulation of a density map on the given background grid
+pub fn triangulate_density_map<I: Index, R: Real>(
+    grid: &UniformGrid<I, R>,
+    density_map: &DensityMap<I, R>,
+    iso_surface_threshold: R,
+) -> TriMesh3d<R> {
+    profile!("surface_nets::triangulate_density_map");
+
+    let mut mesh = TriMesh3d::default();
+    triangulate_density_map_append(grid, density_map, iso_surface_threshold, &mut mesh);
+    mesh
+}
+
+/// Performs a surface nets triangulation of a density map on the given background grid, appends triangles to the given mesh
+pub fn triangulate_density_map_append<I: Index, R: Real>(
+    grid: &UniformGrid<I, R>,
+    density_map: &DensityMap<I, R>,
+    iso_surface_threshold: R,
+    mesh: &mut TriMesh3d<R>,
+) {
+    profile!("surface_nets::triangulate_density_map_append");
+
+    let point_value = |ijk: &[I; 3]| -> Option<R> {
+        let point = grid.get_point(*ijk)?;
+        Some(
+            density_map
+                .get(grid.flatten_point_index(&point))
+                .unwrap_or_else(R::zero),
+        )
+    };
+
+    // Collect all edges crossing the iso-surface, every crossing edge has at least one point above the threshold which is stored in the density map
+    let mut crossing_edges = Vec::new();
+    density_map.for_each(|flat_point_index, value| {
+        if value <= iso_surface_threshold {
+            return;
+        }
+
+        let point = grid
+            .try_unflatten_point_index(flat_point_index)
+            .expect("point of the density map has to be part of the grid");
+        let ijk = *point.index();
+
+        for &axis in Axis::all_possible() {
+            let dim = axis.dim();
+
+            // Neighbor in positive direction: edge starts at this point
+            let mut upper = ijk;
+            upper[dim] += I::one();
+            if let Some(upper_value) = point_value(&upper) {
+                if upper_value <= iso_surface_threshold {
+                    crossing_edges.push((ijk, axis, true));
+                }
+            }
+
+            // Neighbor in negative direction: edge starts at the neighbor
+            if let Some(lower_index) = ijk[dim].checked_sub(&I::one()) {
+                let mut lower = ijk;
+                lower[dim] = lower_index;
+                if let Some(lower_value) = point_value(&lower) {
+                    if lower_value <= iso_surface_threshold {
+                        crossing_edges.push((lower, axis, false));
+                    }
+                }
+            }
+        }
+    });
+
+    let mut cell_to_vertex = new_map();
+
+    for (origin_ijk, axis, origin_inside) in crossing_edges {
+        // Collect the four cells adjacent to the edge, skip edges at the boundary of the grid
+        let adjacent_cells = EDGE_ADJACENT_CELL_OFFSETS.map(|offsets| {
+            let mut cell_ijk = origin_ijk;
+            for (orth_axis, offset) in axis.orthogonal_axes().iter().zip(offsets) {
+                let dim = orth_axis.dim();
+                cell_ijk[dim] = cell_ijk[dim].checked_sub(&I::from_usize(offset).unwrap())?;
+            }
+            grid.get_cell(cell_ijk)
+        });
+
+        if adjacent_cells.iter().any(Option::is_none) {
+            continue;
+        }
+
+        let quad = adjacent_cells.map(|cell| {
+            let cell = cell.unwrap();
+            *cell_to_vertex
+                .entry(grid.flatten_cell_index(&cell))
+                .or_insert_with(|| {
+                    let cell_ijk = *cell.index();
+                    let corner_values = CELL_CORNER_OFFSETS.map(|offset| {
+                        let corner_ijk = [
+                            cell_ijk[0] + I::from_usize(offset[0]).unwrap(),
+                            cell_ijk[1] + I::from_usize(offset[1]).unwrap(),
+                            cell_ijk[2] + I::from_usize(offset[2]).unwrap(),
+                        ];
+                        point_value(&corner_ijk)
+                            .expect("corner point of existing cell has to exist")
+                    });
+
+                    mesh.vertices.push(cell_vertex_position(
+                        &grid.point_coordinates_array(&cell_ijk),
+                        grid.cell_size(),
+                        &corner_values,
+                        iso_surface_threshold,
+                    ));
+                    mesh.vertices.len() - 1
+                })
+        });
+
+        let triangles = quad_to_triangles(quad, origin_inside, &mesh.vertices);
+        mesh.triangles.extend_from_slice(&triangles);
+    }
+}
+
+/// Computes the position of the surface nets vertex of a cell as the average of all iso-surface crossings on its edges
+pub(crate) fn cell_vertex_position<R: Real>(
+    min_corner: &Vector3<R>,
+    cube_size: R,
+    corner_values: &[R; 8],
+    iso_surface_threshold: R,
+) -> Vector3<R> {
+    let corner_position = |corner: usize| -> Vector3<R> {
+        let offset = CELL_CORNER_OFFSETS[corner];
+        min_corner
+            + Vector3::new(
+                R::from_usize(offset[0]).unwrap(),
+                R::from_usize(offset[1]).unwrap(),
+                R::from_usize(offset[2]).unwrap(),
+            ) * cube_size
+    };
+
+    let mut sum = Vector3::zeros();
+    let mut count = 0;
+    for [c0, c1] in CELL_EDGES {
+        let v0 = corner_values[c0];
+        let v1 = corner_values[c1];
+        if (v0 > iso_surface_threshold) != (v1 > iso_surface_threshold) {
+            let alpha = (iso_surface_threshold - v0) / (v1 - v0);
+            sum += corner_position(c0) * (R::one() - alpha) + corner_position(c1) * alpha;
+            count += 1;
+        }
+    }
+
+    if count > 0 {
+        sum / R::from_usize(count).unwrap()
+    } else {
+        // Fallback to the cell center if the cell is not intersected by the iso-surface
+        corner_position(0) + Vector3::repeat(cube_size * R::from_f64(0.5).unwrap())
+    }
+}
+
+/// Splits the quad of an iso-surface crossing edge into two triangles along its shorter diagonal
+///
+/// The quad vertices have to be ordered counter-clockwise around the positive direction of the edge
+/// (see [`EDGE_ADJACENT_CELL_OFFSETS`]). If the origin point of the edge is inside of the fluid, the
+/// triangles are oriented in positive edge direction (i.e. pointing outwards), otherwise the orientation is flipped.
+pub(crate) fn quad_to_triangles<R: Real>(
+    quad: [usize; 4],
+    origin_inside: bool,
+    vertices: &[Vector3<R>],
+) -> [[usize; 3]; 2] {
+    let [q0, q1, q2, q3] = if origin_inside {
+        quad
+    } else {
+        [quad[3], quad[2], quad[1], quad[0]]
+    };
+
+    let diagonal_02 = (vertices[q2] - vertices[q0]).norm_squared();
+    let diagonal_13 = (vertices[q3] - vertices[q1]).norm_squared();
+    if diagonal_02 <= diagonal_13 {
+        [[q0, q1, q2], [q0, q2, q3]]
+    } else {
+        [[q1, q2, q3], [q1, q3, q0]]
+    }
+}
+
+#[test]
+fn test_surface_nets_sphere() {
+    let grid = UniformGrid::<i64, f64>::new(&Vector3::repeat(-2.0), &[20, 20, 20], 0.2).unwrap();
+
+    // Density decreasing linearly with the distance to the origin, the iso-surface at 1.0 is the unit sphere
+    let mut sparse_data = new_map();
+    for i in 0..=20 {
+        for j in 0..=20 {
+            for k in 0..=20 {
+                let value = 2.0 - grid.point_coordinates_indices(i, j, k).norm();
+                if value > 0.0 {
+                    sparse_data.insert(grid.flatten_point_indices(i, j, k), value);
+                }
+            }
+        }
+    }
+
+    let mesh = triangulate_density_map(&grid, &sparse_data.into(), 1.0);
+
+    assert!(!mesh.triangles.is_empty());
+    assert!(mesh.find_boundary_edges().is_empty());
+
+    // Triangles have to be oriented outwards
+    let sphere_volume = 4.0 / 3.0 * std::f64::consts::PI;
+    assert!((mesh.signed_volume() - sphere_volume).abs() < 0.05 * sphere_volume);
+}
@@ -0,0 +1,534 @@
+//! Tracking of a surface mesh over the frames of a sequence to obtain meshes with consistent vertices
+//!
+//! Reconstructing every frame of a sequence independently results in meshes without any correspondence between
+//! the vertices of consecutive frames. The [`SurfaceTracker`] instead advects the mesh of the previous frame with the
+//! particle velocities (interpolated using [`SphInterpolator::interpolate_vector_quantity`]) and projects the advected
+//! vertices back onto the iso-surface of the current frame using Newton iterations along the gradient of the SPH
+//! indicator function (see [`SphInterpolator::interpolate_indicator_with_gradient`]).
+//!
+//! The tracking is performed per connected component of the mesh. A tracked component is replaced by the
+//! corresponding component of the surface reconstruction of the current frame (i.e. it is re-meshed) if
+//!  - the projection of one of its vertices onto the iso-surface does not converge,
+//!  - one of its triangles is flipped or stretched beyond [`SurfaceTrackingParameters::max_edge_length`],
+//!  - its topology differs from the reconstructed surface (e.g. due to splitting or merging of fluid volumes),
+//!  - or its enclosed volume differs by more than [`SurfaceTrackingParameters::max_relative_volume_change`] from the reconstructed component.
+//!
+//! Components of the reconstruction without a corresponding tracked component (e.g. new droplets) are added to the
+//! tracked mesh. The vertices of the previous frame that are kept come first in the tracked mesh, in their original order.
+//!
+//! The tracking projects the vertices onto the iso-surface of the plain SPH indicator function, therefore it does not
+//! support anisotropic kernels, solid boundaries, boundary particles or caps at the domain boundary.
+
+use crate::mesh::TriMesh3d;
+use crate::postprocessing::components::vertex_components;
+use crate::sph_interpolation::SphInterpolator;
+use crate::uniform_grid::UniformGrid;
+use crate::{
+    new_map, profile, Index, MapType, Parameters, ParticleProperties, Real, SurfaceReconstruction,
+};
+use anyhow::anyhow;
+use log::info;
+use nalgebra::Vector3;
+
+/// Parameters for tracking a surface mesh over the frames of a sequence
+#[derive(Clone, Debug)]
+pub struct SurfaceTrackingParameters<R: Real> {
+    /// Time between two consecutive frames, the vertices are advected with the particle velocities over this time
+    pub time_step: R,
+    /// Maximum number of Newton iterations to project an advected vertex onto the iso-surface
+    pub max_projection_iterations: usize,
+    /// Maximum distance of a projected vertex to the iso-surface (in multiplies of the cube size)
+    pub projection_tolerance: R,
+    /// Maximum edge length of a triangle of a tracked component (in multiplies of the cube size), components with longer edges are re-meshed
+    pub max_edge_length: R,
+    /// Maximum relative difference between the volume of a tracked component and the volume of the corresponding reconstructed component
+    pub max_relative_volume_change: R,
+}
+
+impl<R: Real> SurfaceTrackingParameters<R> {
+    /// Returns the default tracking parameters for the given time between two consecutive frames
+    pub fn with_time_step(time_step: R) -> Self {
+        Self {
+            time_step,
+            max_projection_iterations: 10,
+            projection_tolerance: R::from_f64(0.01).unwrap(),
+            max_edge_length: R::from_f64(3.0).unwrap(),
+            max_relative_volume_change: R::from_f64(0.05).unwrap(),
+        }
+    }
+
+    /// Tries to convert the parameters from one [Real] type to another [Real] type, returns `None` if conversion fails
+    pub fn try_convert<T: Real>(&self) -> Option<SurfaceTrackingParameters<T>> {
+        Some(SurfaceTrackingParameters {
+            time_step: self.time_step.try_convert()?,
+            max_projection_iterations: self.max_projection_iterations,
+            projection_tolerance: self.projection_tolerance.try_convert()?,
+            max_edge_length: self.max_edge_length.try_convert()?,
+            max_relative_volume_change: self.max_relative_volume_change.try_convert()?,
+        })
+    }
+}
+
+/// Surface mesh of a frame resulting from the surface tracking
+#[derive(Clone, Debug)]
+pub struct TrackedSurface<R: Real> {
+    mesh: TriMesh3d<R>,
+    vertex_velocities: Vec<Vector3<R>>,
+    previous_vertices: Vec<Option<usize>>,
+    num_remeshed_components: usize,
+}
+
+impl<R: Real> TrackedSurface<R> {
+    /// Returns the tracked surface mesh
+    pub fn mesh(&self) -> &TriMesh3d<R> {
+        &self.mesh
+    }
+
+    /// Returns the velocity of every vertex of the mesh, interpolated from the particle velocities of the current frame
+    pub fn vertex_velocities(&self) -> &[Vector3<R>] {
+        &self.vertex_velocities
+    }
+
+    /// Returns for every vertex of the mesh the index of the corresponding vertex of the previous frame, `None` for re-meshed vertices
+    pub fn previous_vertices(&self) -> &[Option<usize>] {
+        &self.previous_vertices
+    }
+
+    /// Returns the number of vertices that were tracked from the previous frame, these are the first vertices of the mesh
+    pub fn num_tracked_vertices(&self) -> usize {
+        self.previous_vertices
+            .iter()
+            .filter(|v| v.is_some())
+            .count()
+    }
+
+    /// Returns the number of connected components of the mesh that were taken from the reconstruction of the current frame instead of being tracked
+    pub fn num_remeshed_components(&self) -> usize {
+        self.num_remeshed_components
+    }
+
+    /// Returns the tracked surface mesh and the velocities of its vertices, consuming the tracked surface
+    pub fn into_mesh_and_velocities(self) -> (TriMesh3d<R>, Vec<Vector3<R>>) {
+        (self.mesh, self.vertex_velocities)
+    }
+}
+
+/// Data of the previous frame that is required to advect its mesh
+struct TrackedFrame<R: Real> {
+    mesh: TriMesh3d<R>,
+    interpolator: SphInterpolator<R>,
+    particle_velocities: Vec<Vector3<R>>,
+}
+
+/// Tracks a surface mesh over the frames of a sequence, see the [module level documentation](self)
+pub struct SurfaceTracker<R: Real> {
+    parameters: SurfaceTrackingParameters<R>,
+    previous_frame: Option<TrackedFrame<R>>,
+}
+
+impl<R: Real> SurfaceTracker<R> {
+    /// Creates a tracker for a new sequence, the mesh of the first frame is taken from its reconstruction
+    pub fn new(parameters: SurfaceTrackingParameters<R>) -> Self {
+        Self {
+            parameters,
+            previous_frame: None,
+        }
+    }
+
+    /// Returns the parameters of the tracking
+    pub fn parameters(&self) -> &SurfaceTrackingParameters<R> {
+        &self.parameters
+    }
+
+    /// Tracks the surface into the next frame given the reconstruction of this frame and the particles it was reconstructed from
+    ///
+    /// The `particle_properties` have to be the same as used for the reconstruction. The particle velocities are used
+    /// to advect the returned mesh into the next frame and to compute the velocities of its vertices.
+    pub fn track_frame<I: Index>(
+        &mut self,
+        reconstruction: &SurfaceReconstruction<I, R>,
+        particle_positions: &[Vector3<R>],
+        particle_velocities: &[Vector3<R>],
+        particle_properties: ParticleProperties<R>,
+        parameters: &Parameters<R>,
+    ) -> Result<TrackedSurface<R>, anyhow::Error> {
+        profile!("track_frame");
+
+        if parameters.anisotropy.is_some()
+            || parameters.boundary.is_some()
+            || parameters.boundary_particles.is_some()
+            || parameters.cap_domain_boundary
+        {
+            return Err(anyhow!("surface tracking does not support anisotropic kernels, solid boundaries, boundary particles or caps at the domain boundary"));
+        }
+        if particle_velocities.len() != particle_positions.len() {
+            return Err(anyhow!(
+                "the number of particle velocities ({}) does not match the number of particles ({})",
+                particle_velocities.len(),
+                particle_positions.len()
+            ));
+        }
+        let particle_densities = reconstruction.particle_densities().ok_or_else(|| {
+            anyhow!("particle densities were not returned by the surface reconstruction but are required for the surface tracking")
+        })?;
+
+        let particle_rest_volume = R::from_f64((4.0 / 3.0) * std::f64::consts::PI).unwrap()
+            * parameters.particle_radius.powi(3);
+        let particle_rest_mass = particle_rest_volume * parameters.rest_density;
+        let interpolator = SphInterpolator::new(
+            particle_positions,
+            particle_densities,
+            particle_rest_mass,
+            particle_properties,
+            parameters.compact_support_radius,
+            parameters.kernel,
+        );
+
+        let (mesh, previous_vertices, num_remeshed_components) = match &self.previous_frame {
+            Some(previous_frame) => self.advect_and_project(
+                previous_frame,
+                &interpolator,
+                reconstruction.grid(),
+                reconstruction.mesh(),
+                parameters,
+            ),
+            None => (
+                reconstruction.mesh().clone(),
+                vec![None; reconstruction.mesh().vertices.len()],
+                0,
+            ),
+        };
+
+        let vertex_velocities =
+            interpolator.interpolate_vector_quantity(particle_velocities, &mesh.vertices, true);
+
+        self.previous_frame = Some(TrackedFrame {
+            mesh: mesh.clone(),
+            interpolator,
+            particle_velocities: particle_velocities.to_vec(),
+        });
+
+        Ok(TrackedSurface {
+            mesh,
+            vertex_velocities,
+            previous_vertices,
+            num_remeshed_components,
+        })
+    }
+
+    /// Advects the mesh of the previous frame, projects it onto the current iso-surface and replaces components that cannot be tracked by the reconstructed mesh
+    fn advect_and_project<I: Index>(
+        &self,
+        previous_frame: &TrackedFrame<R>,
+        interpolator: &SphInterpolator<R>,
+        grid: &UniformGrid<I, R>,
+        reconstructed_mesh: &TriMesh3d<R>,
+        parameters: &Parameters<R>,
+    ) -> (TriMesh3d<R>, Vec<Option<usize>>, usize) {
+        let previous_mesh = &previous_frame.mesh;
+        let cube_size = parameters.cube_size;
+
+        // Advect the vertices with the particle velocities of the previous frame
+        let vertex_velocities = previous_frame.interpolator.interpolate_vector_quantity(
+            &previous_frame.particle_velocities,
+            &previous_mesh.vertices,
+            true,
+        );
+        let mut vertices = previous_mesh
+            .vertices
+            .iter()
+            .zip(vertex_velocities.iter())
+            .map(|(x, v)| x + v * self.parameters.time_step)
+            .collect::<Vec<_>>();
+
+        let (converged, gradients) = project_onto_iso_surface(
+            interpolator,
+            &mut vertices,
+            parameters.iso_surface_threshold,
+            self.parameters.max_projection_iterations,
+            self.parameters.projection_tolerance * cube_size,
+            cube_size,
+        );
+        let tracked_mesh = TriMesh3d {
+            vertices,
+            triangles: previous_mesh.triangles.clone(),
+        };
+
+        let tracked_components = MeshComponents::new(&tracked_mesh);
+        let reconstructed_components = MeshComponents::new(reconstructed_mesh);
+
+        // Check the geometry of the tracked components
+        let mut tracked_valid = vec![true; tracked_components.count];
+        for (v, &is_converged) in converged.iter().enumerate() {
+            if !is_converged {
+                tracked_valid[tracked_components.vertex_component[v]] = false;
+            }
+        }
+        let max_edge_length_sq = (self.parameters.max_edge_length * cube_size).powi(2);
+        for tri in tracked_mesh.triangles.iter() {
+            let [v0, v1, v2] = tri.map(|v| tracked_mesh.vertices[v]);
+            let normal = (v1 - v0).cross(&(v2 - v0));
+            // The gradient of the indicator function points into the fluid, i.e. opposite to the outward normal
+            let inward = gradients[tri[0]] + gradients[tri[1]] + gradients[tri[2]];
+            let is_flipped = normal.dot(&inward) >= R::zero();
+            let is_stretched = [v1 - v0, v2 - v1, v0 - v2]
+                .iter()
+                .any(|e| e.norm_squared() > max_edge_length_sq);
+            if is_flipped || is_stretched {
+                tracked_valid[tracked_components.vertex_component[tri[0]]] = false;
+            }
+        }
+
+        // Match every tracked component to the reconstructed component that is closest to all of its vertices
+        let closest_component = closest_reconstructed_component(
+            grid,
+            reconstructed_mesh,
+            &reconstructed_components,
+            &tracked_mesh.vertices,
+        );
+        let mut tracked_match: Vec<Option<usize>> = vec![None; tracked_components.count];
+        let mut is_unmatched = tracked_valid
+            .iter()
+            .map(|&valid| !valid)
+            .collect::<Vec<_>>();
+        for (v, closest) in closest_component.iter().enumerate() {
+            let c = tracked_components.vertex_component[v];
+            match (*closest, tracked_match[c]) {
+                (None, _) => is_unmatched[c] = true,
+                (Some(r), None) => tracked_match[c] = Some(r),
+                (Some(r), Some(m)) if r != m => is_unmatched[c] = true,
+                _ => {}
+            }
+        }
+        for c in 0..tracked_components.count {
+            if is_unmatched[c] {
+                tracked_match[c] = None;
+            }
+        }
+
+        // Reconstructed components close to unmatched tracked components have to be re-meshed, as they may overlap
+        let mut reconstructed_contested = vec![false; reconstructed_components.count];
+        for (v, closest) in closest_component.iter().enumerate() {
+            if let (None, Some(r)) = (
+                tracked_match[tracked_components.vertex_component[v]],
+                closest,
+            ) {
+                reconstructed_contested[*r] = true;
+            }
+        }
+        let mut reconstructed_matches = vec![0; reconstructed_components.count];
+        for r in tracked_match.iter().flatten() {
+            reconstructed_matches[*r] += 1;
+        }
+
+        // A reconstructed component is replaced by a tracked component if it corresponds to exactly one tracked component with the same topology and volume
+        let mut keep_tracked = vec![false; tracked_components.count];
+        let mut keep_reconstructed = vec![true; reconstructed_components.count];
+        for c in 0..tracked_components.count {
+            if let Some(r) = tracked_match[c] {
+                let relative_volume_change = ((tracked_components.volume[c]
+                    - reconstructed_components.volume[r])
+                    / reconstructed_components.volume[r])
+                    .abs();
+                if reconstructed_matches[r] == 1
+                    && !reconstructed_contested[r]
+                    && tracked_components.euler_characteristic[c]
+                        == reconstructed_components.euler_characteristic[r]
+                    && relative_volume_change <= self.parameters.max_relative_volume_change
+                {
+                    keep_tracked[c] = true;
+                    keep_reconstructed[r] = false;
+                }
+            }
+        }
+
+        // Assemble the tracked components followed by the re-meshed components
+        let mut mesh = TriMesh3d::default();
+        let mut previous_vertices = Vec::new();
+        let mut tracked_vertex_map = vec![usize::MAX; tracked_mesh.vertices.len()];
+        for (v, vertex) in tracked_mesh.vertices.iter().enumerate() {
+            if keep_tracked[tracked_components.vertex_component[v]] {
+                tracked_vertex_map[v] = mesh.vertices.len();
+                mesh.vertices.push(*vertex);
+                previous_vertices.push(Some(v));
+            }
+        }
+        for tri in tracked_mesh.triangles.iter() {
+            if keep_tracked[tracked_components.vertex_component[tri[0]]] {
+                mesh.triangles.push(tri.map(|v| tracked_vertex_map[v]));
+            }
+        }
+
+        let mut reconstructed_vertex_map = vec![usize::MAX; reconstructed_mesh.vertices.len()];
+        for (v, vertex) in reconstructed_mesh.vertices.iter().enumerate() {
+            if keep_reconstructed[reconstructed_components.vertex_component[v]] {
+                reconstructed_vertex_map[v] = mesh.vertices.len();
+                mesh.vertices.push(*vertex);
+                previous_vertices.push(None);
+            }
+        }
+        for tri in reconstructed_mesh.triangles.iter() {
+            if keep_reconstructed[reconstructed_components.vertex_component[tri[0]]] {
+                mesh.triangles
+                    .push(tri.map(|v| reconstructed_vertex_map[v]));
+            }
+        }
+
+        let num_remeshed_components = keep_reconstructed.iter().filter(|&&keep| keep).count();
+        info!(
+            "Surface tracking: kept {} of {} tracked components, {} components were re-meshed.",
+            keep_tracked.iter().filter(|&&keep| keep).count(),
+            tracked_components.count,
+            num_remeshed_components
+        );
+
+        (mesh, previous_vertices, num_remeshed_components)
+    }
+}
+
+/// Moves the vertices onto the iso-surface of the indicator function using Newton iterations along its gradient
+///
+/// Returns for every vertex whether the projection converged and the gradient of the indicator function at the projected vertex.
+fn project_onto_iso_surface<R: Real>(
+    interpolator: &SphInterpolator<R>,
+    vertices: &mut [Vector3<R>],
+    iso_surface_threshold: R,
+    max_iterations: usize,
+    tolerance: R,
+    max_step: R,
+) -> (Vec<bool>, Vec<Vector3<R>>) {
+    profile!("project_onto_iso_surface");
+
+    let mut converged = vec![false; vertices.len()];
+    let mut gradients = vec![Vector3::zeros(); vertices.len()];
+    let mut active = (0..vertices.len()).collect::<Vec<_>>();
+    let mut points = Vec::with_capacity(vertices.len());
+
+    for iteration in 0..=max_iterations {
+        if active.is_empty() {
+            break;
+        }
+
+        points.clear();
+        points.extend(active.iter().map(|&v| vertices[v]));
+        let values = interpolator.interpolate_indicator_with_gradient(&points);
+
+        let mut still_active = Vec::with_capacity(active.len());
+        for (&v, (value, gradient)) in active.iter().zip(values) {
+            gradients[v] = gradient;
+            let gradient_norm_sq = gradient.norm_squared();
+            if gradient_norm_sq == R::zero() {
+                // The vertex is outside of the support of all particles
+                continue;
+            }
+
+            let step = gradient * ((iso_surface_threshold - value) / gradient_norm_sq);
+            let step_length = step.norm();
+            if step_length <= tolerance {
+                converged[v] = true;
+                continue;
+            }
+
+            if iteration < max_iterations {
+                vertices[v] += if step_length > max_step {
+                    step * (max_step / step_length)
+                } else {
+                    step
+                };
+                still_active.push(v);
+            }
+        }
+        active = still_active;
+    }
+
+    (converged, gradients)
+}
+
+/// Connected components of a triangle mesh with their enclosed volume and Euler characteristic
+struct MeshComponents<R: Real> {
+    count: usize,
+    vertex_component: Vec<usize>,
+    volume: Vec<R>,
+    euler_characteristic: Vec<i64>,
+}
+
+impl<R: Real> MeshComponents<R> {
+    fn new(mesh: &TriMesh3d<R>) -> Self {
+        let (count, vertex_component) = vertex_components(mesh);
+
+        let mut volume = vec![R::zero(); count];
+        let mut euler_characteristic = vec![0; count];
+        for &c in vertex_component.iter() {
+            euler_characteristic[c] += 1;
+        }
+
+        let sixth = R::from_f64(1.0 / 6.0).unwrap();
+        let mut edges = Vec::with_capacity(3 * mesh.triangles.len());
+        for tri in mesh.triangles.iter() {
+            let c = vertex_component[tri[0]];
+            let [v0, v1, v2] = tri.map(|v| mesh.vertices[v]);
+            volume[c] += v0.dot(&v1.cross(&v2)) * sixth;
+            euler_characteristic[c] += 1;
+            for (a, b) in [(tri[0], tri[1]), (tri[1], tri[2]), (tri[2], tri[0])] {
+                edges.push([a.min(b), a.max(b)]);
+            }
+        }
+        edges.sort_unstable();
+        edges.dedup();
+        for edge in edges {
+            euler_characteristic[vertex_component[edge[0]]] -= 1;
+        }
+
+        Self {
+            count,
+            vertex_component,
+            volume,
+            euler_characteristic,
+        }
+    }
+}
+
+/// Returns for every point the component of the closest vertex of the mesh in the surrounding cells of the grid, `None` if there is no vertex nearby
+fn closest_reconstructed_component<I: Index, R: Real>(
+    grid: &UniformGrid<I, R>,
+    mesh: &TriMesh3d<R>,
+    components: &MeshComponents<R>,
+    points: &[Vector3<R>],
+) -> Vec<Option<usize>> {
+    profile!("closest_reconstructed_component");
+
+    let mut cell_vertices: MapType<[I; 3], Vec<usize>> = new_map();
+    for (v, vertex) in mesh.vertices.iter().enumerate() {
+        cell_vertices
+            .entry(grid.enclosing_cell(vertex))
+            .or_default()
+            .push(v);
+    }
+
+    let offsets = [I::zero() - I::one(), I::zero(), I::one()];
+    points
+        .iter()
+        .map(|point| {
+            let cell = grid.enclosing_cell(point);
+            let mut closest: Option<(R, usize)> = None;
+            for di in offsets {
+                for dj in offsets {
+                    for dk in offsets {
+                        let neighbor = [cell[0] + di, cell[1] + dj, cell[2] + dk];
+                        for &v in cell_vertices.get(&neighbor).into_iter().flatten() {
+                            let distance_sq = (mesh.vertices[v] - point).norm_squared();
+                            let is_closer = match closest {
+                                Some((closest_distance_sq, _)) => distance_sq < closest_distance_sq,
+                                None => true,
+                            };
+                            if is_closer {
+                                closest = Some((distance_sq, v));
+                            }
+                        }
+                    }
+                }
+            }
+            closest.map(|(_, v)| components.vertex_component[v])
+        })
+        .collect()
+}
@@ -0,0 +1,383 @@
+//! Temporal filtering of particle data of simulation sequences to reduce flickering of the reconstructed surfaces
+//!
+//! Reconstructing every frame of a sequence independently can cause small surface features (thin sheets,
+//! droplets that barely reach the iso-surface threshold, etc.) to appear and disappear from one frame to the next.
+//! The function [`filter_particle_positions`] blends the positions of every particle over a window of neighboring
+//! frames using binomial weights (see [`binomial_filter_weights`]), i.e. a discrete approximation of a Gaussian
+//! low-pass filter in time. As the window is symmetric, particles moving with constant velocity are not displaced by the filter.
+//!
+//! The particles of different frames are identified by unique particle ids with [`filter_particle_frames`]. Without ids,
+//! the particles of all frames of a window have to be stored in the same order (i.e. the `i`-th particle of every frame
+//! is the same physical particle), as it is usually the case for simulations without particle emission or deletion.
+//!
+//! In addition, [`finite_difference_velocities`] estimates particle velocities from the positions of the previous and next
+//! frame for data sets without stored velocities (e.g. to compute motion vectors of the surface). Here, the particles
+//! of different frames are matched in the same way.
+
+use crate::{new_map, profile, Real};
+use anyhow::anyhow;
+use nalgebra::Vector3;
+use rayon::prelude::*;
+
+/// Returns the normalized weights of a binomial filter window with `radius` frames on each side of the center frame
+///
+/// The returned vector has `2 * radius + 1` entries that sum up to one, the entry at index `radius` is the weight of the center frame.
+pub fn binomial_filter_weights<R: Real>(radius: usize) -> Vec<R> {
+    let n = 2 * radius;
+    let mut weights = Vec::with_capacity(n + 1);
+    // Rows of Pascal's triangle, normalized by 2^n
+    let mut coefficient = 1.0_f64;
+    for k in 0..=n {
+        weights.push(coefficient);
+        coefficient = coefficient * (n - k) as f64 / (k + 1) as f64;
+    }
+    let sum = 2.0_f64.powi(n as i32);
+    weights
+        .into_iter()
+        .map(|w| R::from_f64(w / sum).unwrap())
+        .collect()
+}
+
+/// Blends the particle positions of a window of frames using a binomial filter and returns the filtered positions of the center frame
+///
+/// The `frames` have to be given in temporal order and their number has to be odd, the filtered positions
+/// correspond to the frame in the middle of the window. All frames have to contain the same number of particles
+/// in the same order. A window with a single frame returns the positions of that frame unchanged.
+pub fn filter_particle_positions<R: Real>(
+    frames: &[&[Vector3<R>]],
+    enable_multi_threading: bool,
+) -> Result<Vec<Vector3<R>>, anyhow::Error> {
+    let frames = frames
+        .iter()
+        .map(|&positions| ParticleFrame {
+            positions,
+            ids: None,
+        })
+        .collect::<Vec<_>>();
+    filter_particle_frames(&frames, enable_multi_threading)
+}
+
+/// Blends the particle positions of a window of frames using a binomial filter and returns the filtered positions of the center frame
+///
+/// The `frames` have to be given in temporal order and their number has to be odd, the filtered positions
+/// correspond to the frame in the middle of the window. The particles of the frames are matched by their ids if the
+/// center frame and the other frame have ids, otherwise both frames have to contain the same number of particles in
+/// the same order. For every particle, the window is shrunk (keeping it centered) to the largest window in which the
+/// particle exists in all frames, i.e. particles that are emitted or deleted close to the center frame are filtered less.
+pub fn filter_particle_frames<R: Real>(
+    frames: &[ParticleFrame<R>],
+    enable_multi_threading: bool,
+) -> Result<Vec<Vector3<R>>, anyhow::Error> {
+    profile!("filter_particle_frames");
+
+    if frames.len() % 2 != 1 {
+        return Err(anyhow!(
+            "the temporal filter window has to consist of an odd number of frames (got {})",
+            frames.len()
+        ));
+    }
+
+    let radius = frames.len() / 2;
+    let center = &frames[radius];
+    let num_particles = center.positions.len();
+    // Index of every particle of the center frame in all frames of the window
+    let indices = frames
+        .iter()
+        .enumerate()
+        .map(|(k, frame)| {
+            if k == radius {
+                Ok((0..num_particles).map(Some).collect())
+            } else {
+                center.match_particles(frame)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let weights = (0..=radius)
+        .map(binomial_filter_weights::<R>)
+        .collect::<Vec<_>>();
+    let filter_particle = |i: usize| {
+        let particle_radius = (1..=radius)
+            .take_while(|&r| indices[radius - r][i].is_some() && indices[radius + r][i].is_some())
+            .last()
+            .unwrap_or(0);
+        let window = radius - particle_radius..=radius + particle_radius;
+        frames[window.clone()]
+            .iter()
+            .zip(indices[window].iter())
+            .zip(weights[particle_radius].iter())
+            .fold(
+                Vector3::zeros(),
+                |sum: Vector3<R>, ((frame, indices), &w)| {
+                    sum + frame.positions[indices[i].unwrap()] * w
+                },
+            )
+    };
+
+    let filtered_positions = if enable_multi_threading {
+        (0..num_particles)
+            .into_par_iter()
+            .map(filter_particle)
+            .collect()
+    } else {
+        (0..num_particles).map(filter_particle).collect()
+    };
+
+    Ok(filtered_positions)
+}
+
+/// Particle positions of a single frame with optional ids that identify the same particle in other frames
+#[derive(Copy, Clone, Debug)]
+pub struct ParticleFrame<'a, R: Real> {
+    /// Positions of all particles of the frame
+    pub positions: &'a [Vector3<R>],
+    /// Unique ids of all particles of the frame, if not available, the particles are matched by their index
+    pub ids: Option<&'a [u64]>,
+}
+
+impl<'a, R: Real> ParticleFrame<'a, R> {
+    /// Returns the index of every particle of this frame in the other frame (if it exists in the other frame)
+    ///
+    /// Particles are matched by their ids if both frames have ids, otherwise both frames have to contain the
+    /// same number of particles in the same order.
+    fn match_particles(
+        &self,
+        other: &ParticleFrame<R>,
+    ) -> Result<Vec<Option<usize>>, anyhow::Error> {
+        match (self.ids, other.ids) {
+            (Some(ids), Some(other_ids)) => {
+                if ids.len() != self.positions.len() || other_ids.len() != other.positions.len() {
+                    return Err(anyhow!(
+                        "the number of particle ids does not match the number of particles"
+                    ));
+                }
+
+                let mut other_indices = new_map();
+                other_indices.reserve(other_ids.len());
+                for (i, &id) in other_ids.iter().enumerate() {
+                    other_indices.insert(id, i);
+                }
+                Ok(ids
+                    .iter()
+                    .map(|id| other_indices.get(id).copied())
+                    .collect())
+            }
+            _ => {
+                if self.positions.len() != other.positions.len() {
+                    return Err(anyhow!(
+                        "the frames contain different numbers of particles ({} and {}), particle ids are required to match the particles",
+                        self.positions.len(),
+                        other.positions.len()
+                    ));
+                }
+                Ok((0..self.positions.len()).map(Some).collect())
+            }
+        }
+    }
+}
+
+/// Estimates the velocities of the particles of the current frame using finite differences of the particle positions of the previous and next frame
+///
+/// Central differences are used for particles that exist in both neighboring frames, one-sided differences for particles
+/// that only exist in one of them. Particles that exist in neither of the two frames get a zero velocity. At least one
+/// neighboring frame has to be provided. The `time_step` is the time between two consecutive frames.
+pub fn finite_difference_velocities<R: Real>(
+    previous: Option<ParticleFrame<R>>,
+    current: ParticleFrame<R>,
+    next: Option<ParticleFrame<R>>,
+    time_step: R,
+    enable_multi_threading: bool,
+) -> Result<Vec<Vector3<R>>, anyhow::Error> {
+    profile!("finite_difference_velocities");
+
+    if previous.is_none() && next.is_none() {
+        return Err(anyhow!(
+            "at least one neighboring frame is required to compute finite difference velocities"
+        ));
+    }
+
+    let previous_indices = previous
+        .map(|previous| current.match_particles(&previous))
+        .transpose()?;
+    let next_indices = next
+        .map(|next| current.match_particles(&next))
+        .transpose()?;
+
+    let two = R::one() + R::one();
+    let particle_velocity = |i: usize| {
+        let x_previous = previous_indices
+            .as_ref()
+            .zip(previous.as_ref())
+            .and_then(|(indices, previous)| indices[i].map(|j| previous.positions[j]));
+        let x_next = next_indices
+            .as_ref()
+            .zip(next.as_ref())
+            .and_then(|(indices, next)| indices[i].map(|j| next.positions[j]));
+
+        match (x_previous, x_next) {
+            (Some(x_previous), Some(x_next)) => (x_next - x_previous) / (two * time_step),
+            (Some(x_previous), None) => (current.positions[i] - x_previous) / time_step,
+            (None, Some(x_next)) => (x_next - current.positions[i]) / time_step,
+            (None, None) => Vector3::zeros(),
+        }
+    };
+
+    let num_particles = current.positions.len();
+    let velocities = if enable_multi_threading {
+        (0..num_particles)
+            .into_par_iter()
+            .map(particle_velocity)
+            .collect()
+    } else {
+        (0..num_particles).map(particle_velocity).collect()
+    };
+
+    Ok(velocities)
+}
+
+#[test]
+fn test_binomial_filter_weights() {
+    assert_eq!(binomial_filter_weights::<f64>(0), vec![1.0]);
+    assert_eq!(binomial_filter_weights::<f64>(1), vec![0.25, 0.5, 0.25]);
+    assert_eq!(
+        binomial_filter_weights::<f64>(2),
+        vec![0.0625, 0.25, 0.375, 0.25, 0.0625]
+    );
+}
+
+#[test]
+fn test_filter_particle_positions() {
+    // Particles moving with constant velocity are not displaced, the jitter of the center frame is damped
+    let frames: Vec<Vec<Vector3<f64>>> = (0..5)
+        .map(|t| {
+            let jitter = if t == 2 { 0.1 } else { 0.0 };
+            vec![
+                Vector3::new(t as f64, 0.0, 0.0),
+                Vector3::new(1.0, 2.0 * t as f64, jitter),
+            ]
+        })
+        .collect();
+    let frame_refs: Vec<&[Vector3<f64>]> = frames.iter().map(Vec::as_slice).collect();
+
+    for enable_multi_threading in [false, true] {
+        let filtered = filter_particle_positions(&frame_refs, enable_multi_threading).unwrap();
+        assert_eq!(filtered[0], Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(filtered[1], Vector3::new(1.0, 4.0, 0.1 * 0.375));
+    }
+
+    assert_eq!(
+        filter_particle_positions(&frame_refs[1..2], false).unwrap(),
+        frames[1]
+    );
+    assert!(filter_particle_positions(&frame_refs[0..2], false).is_err());
+
+    let mut shorter = frames[0].clone();
+    shorter.pop();
+    assert!(filter_particle_positions(&[&frames[0], &shorter, &frames[2]], false).is_err());
+}
+
+#[test]
+fn test_filter_particle_frames() {
+    // The particles are reordered between the frames, particle 5 is emitted in the frame before the center frame
+    let positions = [
+        vec![Vector3::new(0.0, 0.0, 0.0)],
+        vec![Vector3::new(9.0, 9.0, 9.0), Vector3::new(1.0, 0.0, 0.0)],
+        vec![Vector3::new(2.0, 0.0, 0.0), Vector3::new(8.0, 8.0, 8.5)],
+        vec![Vector3::new(7.0, 7.0, 7.0), Vector3::new(3.0, 0.0, 0.0)],
+        vec![Vector3::new(4.0, 0.0, 0.0), Vector3::new(6.0, 6.0, 6.0)],
+    ];
+    let ids: [&[u64]; 5] = [&[3], &[5, 3], &[3, 5], &[5, 3], &[3, 5]];
+    let frames = positions
+        .iter()
+        .zip(ids)
+        .map(|(positions, ids)| ParticleFrame {
+            positions,
+            ids: Some(ids),
+        })
+        .collect::<Vec<_>>();
+
+    for enable_multi_threading in [false, true] {
+        let filtered = filter_particle_frames(&frames, enable_multi_threading).unwrap();
+        assert_eq!(filtered[0], Vector3::new(2.0, 0.0, 0.0));
+        assert_eq!(filtered[1], Vector3::new(8.0, 8.0, 8.25));
+    }
+
+    // Without ids, the particles are matched by index which requires the same number of particles
+    let frames_without_ids = frames
+        .iter()
+        .map(|frame| ParticleFrame {
+            ids: None,
+            ..*frame
+        })
+        .collect::<Vec<_>>();
+    assert!(filter_particle_frames(&frames_without_ids, false).is_err());
+    assert!(filter_particle_frames(&frames_without_ids[1..4], false).is_ok());
+}
+
+#[test]
+fn test_finite_difference_velocities() {
+    let previous = vec![Vector3::new(0.0, 0.0, 0.0), Vector3::new(1.0, 1.0, 1.0)];
+    let current = vec![Vector3::new(0.5, 0.0, 0.0), Vector3::new(1.0, 2.0, 1.0)];
+    let next = vec![Vector3::new(2.0, 0.0, 0.0), Vector3::new(1.0, 3.0, 1.0)];
+    let frame = |positions| ParticleFrame {
+        positions,
+        ids: None,
+    };
+
+    // Particles are matched by index without ids
+    let velocities = finite_difference_velocities(
+        Some(frame(&previous)),
+        frame(&current),
+        Some(frame(&next)),
+        0.5,
+        false,
+    )
+    .unwrap();
+    assert_eq!(
+        velocities,
+        vec![Vector3::new(2.0, 0.0, 0.0), Vector3::new(0.0, 2.0, 0.0)]
+    );
+    let velocities =
+        finite_difference_velocities(None, frame(&current), Some(frame(&next)), 0.5, true).unwrap();
+    assert_eq!(
+        velocities,
+        vec![Vector3::new(3.0, 0.0, 0.0), Vector3::new(0.0, 2.0, 0.0)]
+    );
+
+    // With ids, the particles can be reordered, deleted and emitted between the frames
+    let current_ids = [7, 3];
+    let next_reordered = vec![Vector3::new(5.0, 5.0, 5.0), Vector3::new(1.0, 3.0, 1.0)];
+    let next_ids = [42, 3];
+    let velocities = finite_difference_velocities(
+        Some(ParticleFrame {
+            positions: &previous,
+            ids: Some(&[7, 3]),
+        }),
+        ParticleFrame {
+            positions: &current,
+            ids: Some(&current_ids),
+        },
+        Some(ParticleFrame {
+            positions: &next_reordered,
+            ids: Some(&next_ids),
+        }),
+        1.0,
+        false,
+    )
+    .unwrap();
+    assert_eq!(
+        velocities,
+        vec![Vector3::new(0.5, 0.0, 0.0), Vector3::new(0.0, 1.0, 0.0)]
+    );
+
+    // Without ids, frames with different numbers of particles cannot be matched
+    assert!(finite_difference_velocities(
+        None,
+        frame(&current),
+        Some(frame(&next[..1])),
+        1.0,
+        false
+    )
+    .is_err());
+    assert!(finite_difference_velocities::<f64>(None, frame(&current), None, 1.0, false).is_err());
+}
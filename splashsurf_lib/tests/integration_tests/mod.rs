@@ -1,5 +1,12 @@
 #[cfg(feature = "io")]
+pub mod test_adaptive;
+pub mod test_boundary;
+pub mod test_domain_caps;
+#[cfg(feature = "io")]
 pub mod test_full;
+pub mod test_multiphase;
 pub mod test_neighborhood_search;
 #[cfg(feature = "io")]
 pub mod test_octree;
+pub mod test_particle_properties;
+pub mod test_surface_tracking;
@@ -0,0 +1,136 @@
+use nalgebra::Vector3;
+use splashsurf_lib::density_map::{
+    evaluate_density_at_points, sequential_generate_sparse_density_map,
+};
+use splashsurf_lib::io::particles_from_file;
+use splashsurf_lib::mesh_quality::compute_mesh_quality;
+use splashsurf_lib::{
+    reconstruct_surface, AdaptiveResolutionParameters, IsoSurfaceExtraction, KernelType,
+    Parameters, ParticleProperties,
+};
+use std::path::Path;
+
+fn params(adaptive_resolution: Option<AdaptiveResolutionParameters<f64>>) -> Parameters<f64> {
+    let particle_radius = 0.025;
+    Parameters {
+        particle_radius,
+        rest_density: 1000.0,
+        compact_support_radius: 4.0 * particle_radius,
+        cube_size: 0.75 * particle_radius,
+        iso_surface_threshold: 0.6,
+        domain_aabb: None,
+        enable_multi_threading: false,
+        subdomain_num_cubes_per_dim: None,
+        spatial_decomposition: None,
+        iso_surface_extraction: IsoSurfaceExtraction::SurfaceNets,
+        adaptive_resolution,
+        kernel: KernelType::CubicSpline,
+        anisotropy: None,
+        boundary: None,
+        boundary_particles: None,
+        cap_domain_boundary: false,
+    }
+}
+
+fn dam_break_particles() -> Vec<Vector3<f64>> {
+    particles_from_file(Path::new("../data/").join("dam_break_frame_9_6859_particles.bgeo"))
+        .unwrap()
+}
+
+#[test]
+fn surface_reconstruction_dam_break_adaptive() {
+    let particle_positions = dam_break_particles();
+
+    let uniform = reconstruct_surface::<i64, _>(&particle_positions, &params(None)).unwrap();
+    let adaptive = reconstruct_surface::<i64, _>(
+        &particle_positions,
+        &params(Some(AdaptiveResolutionParameters::default())),
+    )
+    .unwrap();
+
+    // The refined cells result in additional triangles
+    assert!(
+        adaptive.mesh().triangles.len() > uniform.mesh().triangles.len(),
+        "Adaptive mesh has probably too few triangles (uniform: {}, adaptive: {})",
+        uniform.mesh().triangles.len(),
+        adaptive.mesh().triangles.len()
+    );
+
+    let quality = compute_mesh_quality(adaptive.mesh());
+    assert!(
+        quality.is_closed_manifold(),
+        "Adaptive mesh is not closed and manifold (boundary edges: {}, non-manifold edges: {})",
+        quality.num_boundary_edges,
+        quality.num_non_manifold_edges
+    );
+}
+
+#[test]
+fn adaptive_density_samples_match_density_map() {
+    let particle_positions = dam_break_particles();
+    let parameters = params(Some(AdaptiveResolutionParameters::default()));
+
+    let reconstruction = reconstruct_surface::<i64, _>(&particle_positions, &parameters).unwrap();
+    let grid = reconstruction.grid();
+    let particle_densities = reconstruction.particle_densities().unwrap();
+
+    let particle_rest_mass = parameters.rest_density
+        * (4.0 / 3.0)
+        * std::f64::consts::PI
+        * parameters.particle_radius.powi(3);
+
+    let density_map = sequential_generate_sparse_density_map(
+        grid,
+        &particle_positions,
+        particle_densities,
+        None,
+        None,
+        particle_rest_mass,
+        ParticleProperties::uniform(),
+        parameters.compact_support_radius,
+        parameters.kernel,
+        parameters.cube_size,
+    )
+    .unwrap();
+
+    let (points, values): (Vec<_>, Vec<_>) = density_map
+        .to_vec()
+        .into_iter()
+        .map(|(flat_point_index, value)| {
+            let point = grid.try_unflatten_point_index(flat_point_index).unwrap();
+            (grid.point_coordinates(&point), value)
+        })
+        .unzip();
+    assert!(!points.is_empty());
+
+    // The adaptive refinement evaluates the density at additional points between the points of the background grid
+    let evaluate = |points: &[Vector3<f64>]| {
+        evaluate_density_at_points(
+            grid,
+            &particle_positions,
+            particle_densities,
+            particle_rest_mass,
+            ParticleProperties::uniform(),
+            parameters.compact_support_radius,
+            parameters.kernel,
+            parameters.cube_size,
+            points,
+            false,
+        )
+        .unwrap()
+    };
+
+    for ((point, value), evaluated_value) in points.iter().zip(values).zip(evaluate(&points)) {
+        assert!(
+            (value - evaluated_value).abs() < 1e-12,
+            "Evaluated density {} does not match density map value {} at {:?}",
+            evaluated_value,
+            value,
+            point
+        );
+    }
+
+    // Points without any particle in their support are outside of the fluid
+    let far_away = grid.aabb().max() + Vector3::repeat(10.0);
+    assert_eq!(evaluate(&[far_away]), vec![0.0]);
+}
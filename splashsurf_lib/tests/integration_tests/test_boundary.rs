@@ -0,0 +1,409 @@
+use nalgebra::Vector3;
+use splashsurf_lib::boundary::{BoundaryGeometry, BoundaryHandling, SignedDistanceFunction};
+use splashsurf_lib::marching_cubes::check_mesh_consistency;
+use splashsurf_lib::mesh::TriMesh3d;
+use splashsurf_lib::{
+    reconstruct_surface, Aabb3d, BoundaryParameters, BoundaryParticles, IsoSurfaceExtraction,
+    KernelType, Parameters, ParticleDensityComputationStrategy, SpatialDecompositionParameters,
+    SubdivisionCriterion,
+};
+
+const PARTICLE_RADIUS: f64 = 0.025;
+
+fn params(subdomain_grid: bool, boundary: Option<BoundaryParameters<f64>>) -> Parameters<f64> {
+    Parameters {
+        particle_radius: PARTICLE_RADIUS,
+        rest_density: 1000.0,
+        compact_support_radius: 4.0 * PARTICLE_RADIUS,
+        cube_size: 0.5 * PARTICLE_RADIUS,
+        iso_surface_threshold: 0.6,
+        domain_aabb: None,
+        enable_multi_threading: true,
+        subdomain_num_cubes_per_dim: subdomain_grid.then_some(32),
+        spatial_decomposition: None,
+        iso_surface_extraction: IsoSurfaceExtraction::MarchingCubes,
+        adaptive_resolution: None,
+        kernel: KernelType::CubicSpline,
+        anisotropy: None,
+        boundary,
+        boundary_particles: None,
+        cap_domain_boundary: false,
+    }
+}
+
+/// Samples a block of fluid with a regular lattice of particles, the lowest layer of particles is at the given height
+fn fluid_block(lowest_layer: f64) -> Vec<Vector3<f64>> {
+    let spacing = 2.0 * PARTICLE_RADIUS;
+    let mut particles = Vec::new();
+    for i in -4..4 {
+        for j in 0..6 {
+            for k in -4..4 {
+                particles.push(Vector3::new(
+                    (i as f64 + 0.5) * spacing,
+                    lowest_layer + j as f64 * spacing,
+                    (k as f64 + 0.5) * spacing,
+                ));
+            }
+        }
+    }
+    particles
+}
+
+fn floor() -> BoundaryGeometry<f64> {
+    BoundaryGeometry::SignedDistance(SignedDistanceFunction::half_space(
+        Vector3::zeros(),
+        Vector3::y(),
+    ))
+}
+
+fn reconstruct(
+    particles: &[Vector3<f64>],
+    subdomain_grid: bool,
+    geometry: BoundaryGeometry<f64>,
+    handling: BoundaryHandling,
+) -> TriMesh3d<f64> {
+    let parameters = params(
+        subdomain_grid,
+        Some(BoundaryParameters { geometry, handling }),
+    );
+    let reconstruction = reconstruct_surface::<i64, _>(particles, &parameters).unwrap();
+    if let Err(err) = check_mesh_consistency(reconstruction.grid(), reconstruction.mesh()) {
+        panic!(
+            "Mesh with boundary handling {:?} is not closed: {}",
+            handling, err
+        );
+    }
+    reconstruction.mesh().clone()
+}
+
+fn min_height(mesh: &TriMesh3d<f64>) -> f64 {
+    mesh.vertices
+        .iter()
+        .map(|v| v.y)
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Triangle mesh of the given box with all normals pointing into the box
+fn box_container_mesh(aabb: &Aabb3d<f64>) -> TriMesh3d<f64> {
+    let [min, max] = [aabb.min(), aabb.max()];
+    let vertices = (0..8)
+        .map(|corner| {
+            Vector3::new(
+                if corner & 1 == 0 { min.x } else { max.x },
+                if corner & 2 == 0 { min.y } else { max.y },
+                if corner & 4 == 0 { min.z } else { max.z },
+            )
+        })
+        .collect();
+    // Two triangles per face, oriented counter-clockwise when viewed from inside of the box
+    let triangles = vec![
+        [0, 1, 3],
+        [0, 3, 2],
+        [4, 6, 7],
+        [4, 7, 5],
+        [0, 4, 5],
+        [0, 5, 1],
+        [2, 3, 7],
+        [2, 7, 6],
+        [0, 2, 6],
+        [0, 6, 4],
+        [1, 5, 7],
+        [1, 7, 3],
+    ];
+    TriMesh3d {
+        vertices,
+        triangles,
+    }
+}
+
+#[test]
+fn test_boundary_cut() {
+    // The lowest layer of particles penetrates the floor
+    let particles = fluid_block(-PARTICLE_RADIUS);
+
+    let unbounded = reconstruct_surface::<i64, _>(&particles, &params(false, None)).unwrap();
+    assert!(min_height(unbounded.mesh()) < -PARTICLE_RADIUS);
+
+    let mut volumes = Vec::new();
+    for subdomain_grid in [false, true] {
+        let mesh = reconstruct(&particles, subdomain_grid, floor(), BoundaryHandling::Cut);
+
+        // The surface ends exactly at the floor and is closed by a cap on the floor
+        assert!(min_height(&mesh) > -1e-9);
+        let cap_vertices = mesh.vertices.iter().filter(|v| v.y.abs() < 1e-9).count();
+        assert!(cap_vertices > 100);
+
+        let volume = mesh.signed_volume();
+        assert!(volume < unbounded.mesh().signed_volume());
+        volumes.push(volume);
+    }
+
+    // The global reconstruction and the reconstruction with subdomain grid result in the same surface
+    assert!((volumes[0] - volumes[1]).abs() < 1e-2 * volumes[0]);
+}
+
+#[test]
+fn test_boundary_extend() {
+    // The fluid rests on the floor, the lowest layer of particles touches the floor
+    let particles = fluid_block(PARTICLE_RADIUS);
+
+    let cut = reconstruct(&particles, false, floor(), BoundaryHandling::Cut);
+    let extend = reconstruct(&particles, false, floor(), BoundaryHandling::Extend);
+    let extend_and_cut = reconstruct(&particles, false, floor(), BoundaryHandling::ExtendAndCut);
+
+    // The extended density field continues into the floor
+    assert!(min_height(&extend) < -PARTICLE_RADIUS);
+    assert!(min_height(&extend_and_cut) > -1e-9);
+
+    // Without the density deficiency at the floor, the surface does not peel away from the floor
+    let footprint = |mesh: &TriMesh3d<f64>| {
+        mesh.vertices
+            .iter()
+            .filter(|v| v.y.abs() < 1e-9)
+            .map(|v| v.x.abs().max(v.z.abs()))
+            .fold(0.0, f64::max)
+    };
+    assert!(footprint(&extend_and_cut) > footprint(&cut));
+    assert!(extend_and_cut.signed_volume() > cut.signed_volume());
+}
+
+#[test]
+fn test_boundary_geometries() {
+    // Container that cuts the fluid block on all sides (not aligned with the particle lattice to avoid
+    // particles with equal distances to multiple walls, where the mirroring direction is ambiguous)
+    let container = Aabb3d::new(
+        Vector3::new(-0.153, 0.0, -0.147),
+        Vector3::new(0.141, 0.25, 0.137),
+    );
+    let particles = fluid_block(-PARTICLE_RADIUS);
+
+    let sdf_mesh = reconstruct(
+        &particles,
+        false,
+        BoundaryGeometry::SignedDistance(SignedDistanceFunction::box_container(container.clone())),
+        BoundaryHandling::ExtendAndCut,
+    );
+    let mesh_mesh = reconstruct(
+        &particles,
+        false,
+        BoundaryGeometry::Mesh(box_container_mesh(&container)),
+        BoundaryHandling::ExtendAndCut,
+    );
+
+    // The surface is clipped on all sides of the container
+    let mut grown_container = container.clone();
+    grown_container.grow_uniformly(1e-9);
+    for mesh in [&sdf_mesh, &mesh_mesh] {
+        assert!(mesh
+            .vertices
+            .iter()
+            .all(|v| grown_container.contains_point(v)));
+    }
+    // The signed distance of the box mesh is identical to the analytic signed distance inside of the box
+    assert!(
+        (sdf_mesh.signed_volume() / mesh_mesh.signed_volume() - 1.0).abs() < 1e-3,
+        "volumes of the analytic box ({}) and the box mesh ({}) differ",
+        sdf_mesh.signed_volume(),
+        mesh_mesh.signed_volume()
+    );
+
+    // A dense layer of boundary particles approximates the floor
+    let spacing = 0.5 * PARTICLE_RADIUS;
+    let mut floor_particles = Vec::new();
+    for i in -40..=40 {
+        for k in -40..=40 {
+            floor_particles.push(Vector3::new(
+                i as f64 * spacing,
+                -PARTICLE_RADIUS,
+                k as f64 * spacing,
+            ));
+        }
+    }
+    let particle_floor = reconstruct(
+        &particles,
+        false,
+        BoundaryGeometry::Particles {
+            positions: floor_particles,
+            radius: PARTICLE_RADIUS,
+        },
+        BoundaryHandling::Cut,
+    );
+    let analytic_floor = reconstruct(&particles, false, floor(), BoundaryHandling::Cut);
+    assert!(min_height(&particle_floor) > -0.1 * PARTICLE_RADIUS);
+    assert!((particle_floor.signed_volume() / analytic_floor.signed_volume() - 1.0).abs() < 0.02);
+}
+
+/// Two layers of boundary particles below the floor on the same lattice as the fluid particles
+fn floor_particle_positions() -> Vec<Vector3<f64>> {
+    let spacing = 2.0 * PARTICLE_RADIUS;
+    let mut positions = Vec::new();
+    for i in -12..12 {
+        for j in 0..2 {
+            for k in -12..12 {
+                positions.push(Vector3::new(
+                    (i as f64 + 0.5) * spacing,
+                    -PARTICLE_RADIUS - j as f64 * spacing,
+                    (k as f64 + 0.5) * spacing,
+                ));
+            }
+        }
+    }
+    positions
+}
+
+#[test]
+fn test_boundary_particle_density_correction() {
+    // The fluid rests on the floor, the lowest layer of particles touches the floor
+    let particles = fluid_block(PARTICLE_RADIUS);
+    let mean_layer_density = |densities: &[f64], layer: usize| {
+        let height = PARTICLE_RADIUS + layer as f64 * 2.0 * PARTICLE_RADIUS;
+        let layer_densities = particles
+            .iter()
+            .zip(densities)
+            .filter(|(p, _)| (p.y - height).abs() < 1e-9)
+            .map(|(_, density)| *density)
+            .collect::<Vec<_>>();
+        layer_densities.iter().sum::<f64>() / layer_densities.len() as f64
+    };
+
+    let uncorrected = reconstruct_surface::<i64, _>(&particles, &params(false, None)).unwrap();
+    let uncorrected_densities = uncorrected.particle_densities().unwrap();
+    let lowest_layer_density = mean_layer_density(uncorrected_densities, 0);
+    let interior_density = mean_layer_density(uncorrected_densities, 1);
+    assert!(lowest_layer_density < 0.9 * interior_density);
+
+    // With the volume of a fluid particle, the boundary particles act like a continuation of the fluid into the floor
+    let fluid_particle_volume = (4.0 / 3.0) * std::f64::consts::PI * PARTICLE_RADIUS.powi(3);
+    let floor = floor_particle_positions();
+    let floor_volumes = vec![fluid_particle_volume; floor.len()];
+
+    let mut volumes = Vec::new();
+    for subdomain_grid in [false, true] {
+        let mut parameters = params(subdomain_grid, None);
+        parameters.boundary_particles =
+            Some(BoundaryParticles::new(floor.clone(), floor_volumes.clone()));
+        let corrected = reconstruct_surface::<i64, _>(&particles, &parameters).unwrap();
+        check_mesh_consistency(corrected.grid(), corrected.mesh()).unwrap();
+
+        // The boundary particles compensate the density deficiency of the fluid particles at the floor
+        // (only the global reconstruction returns the particle densities)
+        if !subdomain_grid {
+            let densities = corrected.particle_densities().unwrap();
+            assert_eq!(densities.len(), particles.len());
+            let corrected_density = mean_layer_density(densities, 0);
+            assert!(
+                (corrected_density - interior_density).abs()
+                    < 0.1 * (lowest_layer_density - interior_density).abs(),
+                "mean density at the floor with correction ({}) is not close to the interior density ({})",
+                corrected_density,
+                interior_density
+            );
+            // Particles without boundary particles in their neighborhood are not affected
+            assert_eq!(
+                mean_layer_density(densities, 5),
+                mean_layer_density(uncorrected_densities, 5)
+            );
+        }
+
+        // No surface is generated around the boundary particles that are not covered by the fluid
+        let footprint = |mesh: &TriMesh3d<f64>| {
+            mesh.vertices
+                .iter()
+                .map(|v| v.x.abs().max(v.z.abs()))
+                .fold(0.0, f64::max)
+        };
+        assert!(footprint(corrected.mesh()) < footprint(uncorrected.mesh()) + PARTICLE_RADIUS);
+        assert!(min_height(corrected.mesh()) > -4.0 * PARTICLE_RADIUS);
+
+        let volume = corrected.mesh().signed_volume();
+        assert!(volume > uncorrected.mesh().signed_volume());
+        volumes.push(volume);
+    }
+    assert!((volumes[0] - volumes[1]).abs() < 1e-2 * volumes[0]);
+
+    // Boundary particles far away from the fluid do not change the surface at all
+    let lifted = fluid_block(20.0 * PARTICLE_RADIUS);
+    let mut parameters = params(false, None);
+    let reference = reconstruct_surface::<i64, _>(&lifted, &parameters).unwrap();
+    parameters.boundary_particles = Some(BoundaryParticles::new(floor, floor_volumes));
+    let with_floor = reconstruct_surface::<i64, _>(&lifted, &parameters).unwrap();
+    assert_eq!(
+        reference.mesh().vertices.len(),
+        with_floor.mesh().vertices.len()
+    );
+    assert!(
+        (reference.mesh().signed_volume() - with_floor.mesh().signed_volume()).abs()
+            < 1e-12 * reference.mesh().signed_volume()
+    );
+}
+
+#[test]
+fn test_boundary_particle_volumes() {
+    let spacing = 2.0 * PARTICLE_RADIUS;
+    let floor = BoundaryParticles::with_estimated_volumes(
+        floor_particle_positions(),
+        4.0 * PARTICLE_RADIUS,
+        KernelType::CubicSpline,
+    );
+    assert_eq!(floor.volumes.len(), floor.positions.len());
+    assert!(floor.validate().is_ok());
+
+    // In the interior of the floor, the volumes of the regular sampling are close to the volume of a lattice cell
+    // (slightly larger as there are only two layers of particles)
+    let center = floor
+        .positions
+        .iter()
+        .position(|p| p.x.abs() < spacing && p.z.abs() < spacing)
+        .unwrap();
+    assert!(floor.volumes[center] > spacing.powi(3));
+    assert!(floor.volumes[center] < 2.0 * spacing.powi(3));
+
+    let invalid = BoundaryParticles::new(floor.positions.clone(), vec![1.0; 3]);
+    assert!(invalid.validate().is_err());
+
+    let mut parameters = params(false, None);
+    parameters.boundary_particles = Some(invalid);
+    assert!(reconstruct_surface::<i64, _>(&fluid_block(0.0), &parameters).is_err());
+}
+
+#[test]
+fn test_boundary_octree_unsupported() {
+    let particles = fluid_block(0.0);
+    let mut parameters = params(
+        false,
+        Some(BoundaryParameters {
+            geometry: floor(),
+            handling: BoundaryHandling::Cut,
+        }),
+    );
+    parameters.spatial_decomposition = Some(SpatialDecompositionParameters {
+        subdivision_criterion: SubdivisionCriterion::MaxParticleCountAuto,
+        ghost_particle_safety_factor: None,
+        enable_stitching: true,
+        particle_density_computation: ParticleDensityComputationStrategy::Global,
+    });
+    assert!(reconstruct_surface::<i64, _>(&particles, &parameters).is_err());
+}
+
+#[test]
+fn test_signed_distance_functions() {
+    let half_space = SignedDistanceFunction::half_space(Vector3::new(0.0, 1.0, 0.0), Vector3::y());
+    assert_eq!(half_space.evaluate(&Vector3::new(5.0, 3.0, -2.0)), 2.0);
+    assert_eq!(half_space.evaluate(&Vector3::new(0.0, 0.5, 0.0)), -0.5);
+
+    let container = SignedDistanceFunction::box_container(Aabb3d::new(
+        Vector3::zeros(),
+        Vector3::repeat(1.0f64),
+    ));
+    assert!((container.evaluate(&Vector3::new(0.5, 0.5, 0.9)) - 0.1).abs() < 1e-12);
+    assert_eq!(container.evaluate(&Vector3::new(0.5, -2.0, 0.5)), -2.0);
+    assert_eq!(container.evaluate(&Vector3::new(4.0, 5.0, 0.5)), -5.0);
+
+    let converted = BoundaryGeometry::SignedDistance(container).try_convert::<f32>();
+    match converted {
+        Some(BoundaryGeometry::SignedDistance(sdf)) => {
+            assert_eq!(sdf.evaluate(&Vector3::new(0.5, -2.0, 0.5)), -2.0f32)
+        }
+        _ => panic!("conversion of signed distance function failed"),
+    }
+}
@@ -0,0 +1,151 @@
+use nalgebra::Vector3;
+use splashsurf_lib::marching_cubes::check_mesh_consistency;
+use splashsurf_lib::mesh::TriMesh3d;
+use splashsurf_lib::multiphase::{reconstruct_multiphase_surfaces, PhaseReconstructionMode};
+use splashsurf_lib::{
+    reconstruct_surface, Aabb3d, IsoSurfaceExtraction, KernelType, Parameters, ParticleProperties,
+};
+
+const PARTICLE_RADIUS: f64 = 0.025;
+
+fn params(domain_aabb: Aabb3d<f64>, cap_domain_boundary: bool) -> Parameters<f64> {
+    Parameters {
+        particle_radius: PARTICLE_RADIUS,
+        rest_density: 1000.0,
+        compact_support_radius: 4.0 * PARTICLE_RADIUS,
+        cube_size: 0.5 * PARTICLE_RADIUS,
+        iso_surface_threshold: 0.6,
+        domain_aabb: Some(domain_aabb),
+        enable_multi_threading: true,
+        subdomain_num_cubes_per_dim: None,
+        spatial_decomposition: None,
+        iso_surface_extraction: IsoSurfaceExtraction::MarchingCubes,
+        adaptive_resolution: None,
+        kernel: KernelType::CubicSpline,
+        anisotropy: None,
+        boundary: None,
+        boundary_particles: None,
+        cap_domain_boundary,
+    }
+}
+
+/// Samples a block of fluid centered at the origin with a regular lattice of particles
+fn fluid_block() -> Vec<Vector3<f64>> {
+    let spacing = 2.0 * PARTICLE_RADIUS;
+    let mut particles = Vec::new();
+    for i in -4..4 {
+        for j in -3..3 {
+            for k in -4..4 {
+                particles.push(Vector3::new(
+                    (i as f64 + 0.5) * spacing,
+                    (j as f64 + 0.5) * spacing,
+                    (k as f64 + 0.5) * spacing,
+                ));
+            }
+        }
+    }
+    particles
+}
+
+/// Domain with the given extent in x direction, its boundaries are aligned to the marching cubes grid of [`params`]
+fn domain(min_x: f64, max_x: f64) -> Aabb3d<f64> {
+    Aabb3d::new(
+        Vector3::new(min_x, -0.5, -0.5),
+        Vector3::new(max_x, 0.5, 0.5),
+    )
+}
+
+fn reconstruct_capped(particles: &[Vector3<f64>], domain_aabb: Aabb3d<f64>) -> TriMesh3d<f64> {
+    let parameters = params(domain_aabb, true);
+    let reconstruction = reconstruct_surface::<i64, _>(particles, &parameters).unwrap();
+    if let Err(err) = check_mesh_consistency(reconstruction.grid(), reconstruction.mesh()) {
+        panic!("Capped mesh is not closed: {}", err);
+    }
+    reconstruction.mesh().clone()
+}
+
+#[test]
+fn test_caps_close_surface_at_domain_boundary() {
+    let particles = fluid_block();
+
+    // Without caps, the surface is open where it is cut by the domain
+    let parameters = params(domain(-0.5, 0.0), false);
+    let uncapped = reconstruct_surface::<i64, _>(&particles, &parameters).unwrap();
+    let capped = reconstruct_capped(&particles, domain(-0.5, 0.0));
+
+    let max_x = capped
+        .vertices
+        .iter()
+        .map(|v| v.x)
+        .fold(f64::NEG_INFINITY, f64::max);
+    assert!(
+        max_x.abs() < 1e-12,
+        "Cap has to lie on the boundary of the domain, max x is {}",
+        max_x
+    );
+    assert!(
+        capped.signed_volume() > uncapped.mesh().signed_volume(),
+        "Particles outside of the domain have to contribute to the capped surface"
+    );
+}
+
+#[test]
+fn test_caps_of_split_domain_add_up() {
+    let particles = fluid_block();
+
+    let full = reconstruct_capped(&particles, domain(-0.5, 0.5));
+    let left = reconstruct_capped(&particles, domain(-0.5, 0.0));
+    let right = reconstruct_capped(&particles, domain(0.0, 0.5));
+
+    let full_volume = full.signed_volume();
+    let split_volume = left.signed_volume() + right.signed_volume();
+    assert!(
+        ((split_volume - full_volume) / full_volume).abs() < 0.01,
+        "Volumes of the two halves ({}) do not add up to the volume of the full surface ({})",
+        split_volume,
+        full_volume
+    );
+}
+
+#[test]
+fn test_caps_multiphase() {
+    let particles = fluid_block();
+    let phases: Vec<u64> = particles
+        .iter()
+        .map(|p| if p.z < 0.0 { 0 } else { 1 })
+        .collect();
+
+    let parameters = params(domain(-0.5, 0.0), true);
+    let reconstruction = reconstruct_multiphase_surfaces::<i64, _>(
+        &particles,
+        &phases,
+        ParticleProperties::uniform(),
+        PhaseReconstructionMode::Separate,
+        &parameters,
+    )
+    .unwrap();
+
+    assert_eq!(reconstruction.phases().len(), 2);
+    for phase in reconstruction.phases() {
+        if let Err(err) = check_mesh_consistency(reconstruction.grid(), phase.mesh()) {
+            panic!(
+                "Capped mesh of phase {} is not closed: {}",
+                phase.phase_id(),
+                err
+            );
+        }
+    }
+}
+
+#[test]
+fn test_caps_unsupported_configurations() {
+    let particles = fluid_block();
+
+    let mut parameters = params(domain(-0.5, 0.0), true);
+    parameters.subdomain_num_cubes_per_dim = Some(32);
+    assert!(reconstruct_surface::<i64, _>(&particles, &parameters).is_err());
+
+    let mut parameters = params(domain(-0.5, 0.0), true);
+    parameters.iso_surface_extraction = IsoSurfaceExtraction::SurfaceNets;
+    assert!(reconstruct_surface::<i64, _>(&particles, &parameters).is_err());
+}
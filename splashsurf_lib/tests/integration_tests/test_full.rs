@@ -3,8 +3,9 @@ use splashsurf_lib::io::particles_from_file;
 use splashsurf_lib::io::vtk_format::write_vtk;
 use splashsurf_lib::marching_cubes::check_mesh_consistency;
 use splashsurf_lib::{
-    reconstruct_surface, Aabb3d, Parameters, ParticleDensityComputationStrategy, Real,
-    SpatialDecompositionParameters, SubdivisionCriterion,
+    reconstruct_surface, Aabb3d, AnisotropyParameters, IsoSurfaceExtraction, KernelType,
+    Parameters, ParticleDensityComputationStrategy, Real, SpatialDecompositionParameters,
+    SubdivisionCriterion,
 };
 use std::path::Path;
 
@@ -39,6 +40,13 @@ fn params_with_aabb<R: Real>(
         enable_multi_threading: false,
         subdomain_num_cubes_per_dim: None,
         spatial_decomposition: None,
+        iso_surface_extraction: IsoSurfaceExtraction::MarchingCubes,
+        adaptive_resolution: None,
+        kernel: KernelType::CubicSpline,
+        anisotropy: None,
+        boundary: None,
+        boundary_particles: None,
+        cap_domain_boundary: false,
     };
 
     match strategy {
@@ -100,6 +108,16 @@ fn default_params<R: Real>() -> Parameters<R> {
     default_params_with(Strategy::Global)
 }
 
+fn with_surface_nets<R: Real>(mut parameters: Parameters<R>) -> Parameters<R> {
+    parameters.iso_surface_extraction = IsoSurfaceExtraction::SurfaceNets;
+    parameters
+}
+
+fn with_anisotropy<R: Real>(mut parameters: Parameters<R>) -> Parameters<R> {
+    parameters.anisotropy = Some(AnisotropyParameters::default());
+    parameters
+}
+
 fn test_for_boundary<R: Real>(params: &Parameters<R>) -> bool {
     params
         .spatial_decomposition
@@ -175,6 +193,13 @@ generate_test!(f32, surface_reconstruction_hilbert_grid, "hilbert_46843_particle
 generate_test!(f32, surface_reconstruction_hilbert2_grid, "hilbert2_7954_particles.vtk" => "reconstruct_surface_hilbert2_par_grid.vtk", params(0.025, 4.0, 1.1, 0.6, Strategy::SubdomainGrid), 90000, 100000);
 generate_test!(f32, surface_reconstruction_octocat_grid, "octocat_32614_particles.bgeo" => "reconstruct_surface_octocat_par_grid.vtk", params(0.025, 4.0, 0.75, 0.6, Strategy::SubdomainGrid), 140000, 180000, cfg_attr(debug_assertions, ignore));
 
+generate_test!(f32, surface_reconstruction_octocat_global_surface_nets, "octocat_32614_particles.bgeo" => "reconstruct_surface_octocat_par_global_surface_nets.vtk", with_surface_nets(params(0.025, 4.0, 0.75, 0.6, Strategy::Global)), 140000, 180000, cfg_attr(debug_assertions, ignore));
+generate_test!(f32, surface_reconstruction_octocat_grid_surface_nets, "octocat_32614_particles.bgeo" => "reconstruct_surface_octocat_par_grid_surface_nets.vtk", with_surface_nets(params(0.025, 4.0, 0.75, 0.6, Strategy::SubdomainGrid)), 140000, 180000, cfg_attr(debug_assertions, ignore));
+generate_test!(f32, surface_reconstruction_knot_grid_surface_nets, "sailors_knot_19539_particles.vtk" => "reconstruct_surface_knot_par_grid_surface_nets.vtk", with_surface_nets(params(0.025, 4.0, 1.1, 0.6, Strategy::SubdomainGrid)), 40000, 70000);
+
+generate_test!(f32, surface_reconstruction_octocat_global_anisotropic, "octocat_32614_particles.bgeo" => "reconstruct_surface_octocat_par_global_anisotropic.vtk", with_anisotropy(params(0.025, 4.0, 0.75, 0.6, Strategy::Global)), 140000, 180000, cfg_attr(debug_assertions, ignore));
+generate_test!(f32, surface_reconstruction_octocat_grid_anisotropic, "octocat_32614_particles.bgeo" => "reconstruct_surface_octocat_par_grid_anisotropic.vtk", with_anisotropy(params(0.025, 4.0, 0.75, 0.6, Strategy::SubdomainGrid)), 140000, 180000, cfg_attr(debug_assertions, ignore));
+
 generate_test!(f32, surface_reconstruction_knot_global, "sailors_knot_19539_particles.vtk" => "reconstruct_surface_knot_par_global.vtk", params(0.025, 4.0, 1.1, 0.6, Strategy::Global), 40000, 70000, cfg_attr(debug_assertions, ignore));
 generate_test!(f32, surface_reconstruction_knot_stitching, "sailors_knot_19539_particles.vtk" => "reconstruct_surface_knot_par_stitching.vtk", params(0.025, 4.0, 1.1, 0.6, Strategy::OctreeStitching), 40000, 70000);
 generate_test!(f32, surface_reconstruction_knot_grid, "sailors_knot_19539_particles.vtk" => "reconstruct_surface_knot_par_grid.vtk", params(0.025, 4.0, 1.1, 0.6, Strategy::SubdomainGrid), 40000, 70000);
@@ -0,0 +1,177 @@
+use nalgebra::Vector3;
+use splashsurf_lib::marching_cubes::check_mesh_consistency;
+use splashsurf_lib::mesh::AttributeData;
+use splashsurf_lib::multiphase::{
+    phase_ids_from_attribute, reconstruct_multiphase_surfaces, PhaseReconstructionMode,
+};
+use splashsurf_lib::{
+    reconstruct_surface, Aabb3d, IsoSurfaceExtraction, KernelType, Parameters, ParticleProperties,
+};
+
+const PARTICLE_RADIUS: f64 = 0.025;
+
+fn params(iso_surface_threshold: f64) -> Parameters<f64> {
+    Parameters {
+        particle_radius: PARTICLE_RADIUS,
+        rest_density: 1000.0,
+        compact_support_radius: 4.0 * PARTICLE_RADIUS,
+        cube_size: 0.5 * PARTICLE_RADIUS,
+        iso_surface_threshold,
+        // A fixed domain ensures that all reconstructions use the same background grid
+        domain_aabb: Some(Aabb3d::new(
+            Vector3::new(-0.5, -0.5, -0.5),
+            Vector3::new(0.5, 0.5, 0.5),
+        )),
+        enable_multi_threading: true,
+        subdomain_num_cubes_per_dim: None,
+        spatial_decomposition: None,
+        iso_surface_extraction: IsoSurfaceExtraction::MarchingCubes,
+        adaptive_resolution: None,
+        kernel: KernelType::CubicSpline,
+        anisotropy: None,
+        boundary: None,
+        boundary_particles: None,
+        cap_domain_boundary: false,
+    }
+}
+
+/// Samples a box with a regular lattice of particles, the particles with negative x-coordinate get phase 0, all others phase 1
+fn two_phase_box() -> (Vec<Vector3<f64>>, Vec<u64>) {
+    let spacing = 2.0 * PARTICLE_RADIUS;
+    let mut particles = Vec::new();
+    let mut phases = Vec::new();
+    for i in -4..4 {
+        for j in -2..2 {
+            for k in -2..2 {
+                let x = Vector3::new(i as f64 + 0.5, j as f64 + 0.5, k as f64 + 0.5) * spacing;
+                particles.push(x);
+                phases.push(if x.x < 0.0 { 0 } else { 1 });
+            }
+        }
+    }
+    (particles, phases)
+}
+
+fn phase_particles(particles: &[Vector3<f64>], phases: &[u64], phase: u64) -> Vec<Vector3<f64>> {
+    particles
+        .iter()
+        .zip(phases)
+        .filter(|(_, &p)| p == phase)
+        .map(|(x, _)| *x)
+        .collect()
+}
+
+#[test]
+fn test_multiphase_separate() {
+    let (particles, phases) = two_phase_box();
+    let parameters = params(0.6);
+
+    let reconstruction = reconstruct_multiphase_surfaces::<i64, _>(
+        &particles,
+        &phases,
+        ParticleProperties::uniform(),
+        PhaseReconstructionMode::Separate,
+        &parameters,
+    )
+    .unwrap();
+
+    assert_eq!(reconstruction.phases().len(), 2);
+    assert_eq!(reconstruction.particle_densities().len(), particles.len());
+    assert!(reconstruction.phase(2).is_none());
+
+    for phase in reconstruction.phases() {
+        assert_eq!(phase.num_particles(), particles.len() / 2);
+        if let Err(err) = check_mesh_consistency(reconstruction.grid(), phase.mesh()) {
+            panic!("Mesh of phase {} is not closed: {}", phase.phase_id(), err);
+        }
+
+        // Every phase is reconstructed as if the other phase did not exist
+        let single_phase = phase_particles(&particles, &phases, phase.phase_id());
+        let reference = reconstruct_surface::<i64, _>(&single_phase, &parameters).unwrap();
+        assert_eq!(reference.mesh().vertices, phase.mesh().vertices);
+        assert_eq!(reference.mesh().triangles, phase.mesh().triangles);
+    }
+}
+
+#[test]
+fn test_multiphase_against_others() {
+    let (particles, phases) = two_phase_box();
+    let parameters = params(0.5);
+
+    let reconstruction = reconstruct_multiphase_surfaces::<i64, _>(
+        &particles,
+        &phases,
+        ParticleProperties::uniform(),
+        PhaseReconstructionMode::AgainstOthers,
+        &parameters,
+    )
+    .unwrap();
+
+    let separate = reconstruct_multiphase_surfaces::<i64, _>(
+        &particles,
+        &phases,
+        ParticleProperties::uniform(),
+        PhaseReconstructionMode::Separate,
+        &parameters,
+    )
+    .unwrap();
+
+    let combined = reconstruct_surface::<i64, _>(&particles, &parameters).unwrap();
+    let combined_volume = combined.mesh().signed_volume();
+
+    let mut total_volume = 0.0;
+    for (phase, separate_phase) in reconstruction.phases().iter().zip(separate.phases()) {
+        if let Err(err) = check_mesh_consistency(reconstruction.grid(), phase.mesh()) {
+            panic!("Mesh of phase {} is not closed: {}", phase.phase_id(), err);
+        }
+
+        // Against the other phase, the surface stops at the interface instead of enclosing the whole phase
+        let volume = phase.mesh().signed_volume();
+        assert!(volume < separate_phase.mesh().signed_volume());
+        total_volume += volume;
+    }
+
+    // The surfaces of both phases share their interface and together enclose the volume of the combined surface
+    assert!((total_volume / combined_volume - 1.0).abs() < 0.05);
+}
+
+#[test]
+fn test_multiphase_invalid_input() {
+    let (particles, phases) = two_phase_box();
+
+    let mut parameters = params(0.6);
+    assert!(reconstruct_multiphase_surfaces::<i64, _>(
+        &particles,
+        &phases[1..],
+        ParticleProperties::uniform(),
+        PhaseReconstructionMode::Separate,
+        &parameters,
+    )
+    .is_err());
+
+    parameters.subdomain_num_cubes_per_dim = Some(32);
+    assert!(reconstruct_multiphase_surfaces::<i64, _>(
+        &particles,
+        &phases,
+        ParticleProperties::uniform(),
+        PhaseReconstructionMode::Separate,
+        &parameters,
+    )
+    .is_err());
+}
+
+#[test]
+fn test_phase_ids_from_attribute() {
+    let ids = phase_ids_from_attribute(&AttributeData::<f32>::ScalarU64(vec![0, 3, 1])).unwrap();
+    assert_eq!(ids, vec![0, 3, 1]);
+
+    let ids = phase_ids_from_attribute(&AttributeData::ScalarReal(vec![2.0f32, 0.0])).unwrap();
+    assert_eq!(ids, vec![2, 0]);
+
+    assert!(phase_ids_from_attribute(&AttributeData::ScalarReal(vec![0.5f32])).is_err());
+    assert!(phase_ids_from_attribute(&AttributeData::ScalarReal(vec![-1.0f32])).is_err());
+    assert!(
+        phase_ids_from_attribute(&AttributeData::Vector3Real(vec![Vector3::<f32>::zeros()]))
+            .is_err()
+    );
+}
@@ -0,0 +1,163 @@
+use nalgebra::Vector3;
+use splashsurf_lib::marching_cubes::check_mesh_consistency;
+use splashsurf_lib::{
+    reconstruct_surface, reconstruct_surface_with_properties, IsoSurfaceExtraction, KernelType,
+    Parameters, ParticleProperties,
+};
+
+const PARTICLE_RADIUS: f64 = 0.025;
+const REST_DENSITY: f64 = 1000.0;
+const SPHERE_RADIUS: f64 = 0.2;
+
+fn params(subdomain_grid: bool) -> Parameters<f64> {
+    Parameters {
+        particle_radius: PARTICLE_RADIUS,
+        rest_density: REST_DENSITY,
+        compact_support_radius: 4.0 * PARTICLE_RADIUS,
+        cube_size: 0.5 * PARTICLE_RADIUS,
+        iso_surface_threshold: 0.6,
+        domain_aabb: None,
+        enable_multi_threading: false,
+        subdomain_num_cubes_per_dim: subdomain_grid.then_some(32),
+        spatial_decomposition: None,
+        iso_surface_extraction: IsoSurfaceExtraction::MarchingCubes,
+        adaptive_resolution: None,
+        kernel: KernelType::CubicSpline,
+        anisotropy: None,
+        boundary: None,
+        boundary_particles: None,
+        cap_domain_boundary: false,
+    }
+}
+
+fn particle_mass(particle_radius: f64) -> f64 {
+    REST_DENSITY * 4.0 / 3.0 * std::f64::consts::PI * particle_radius.powi(3)
+}
+
+/// Samples a sphere with a regular lattice of particles with the given radius, optionally restricted to one half of the sphere
+fn sample_sphere(particle_radius: f64, half: Option<bool>, particles: &mut Vec<Vector3<f64>>) {
+    let spacing = 2.0 * particle_radius;
+    let n = (SPHERE_RADIUS / spacing).ceil() as i32;
+    for i in -n..=n {
+        for j in -n..=n {
+            for k in -n..=n {
+                // Offset by half a spacing to avoid particles exactly on the dividing plane
+                let x = Vector3::new(i as f64 + 0.5, j as f64, k as f64) * spacing;
+                let in_half = half.map_or(true, |positive| (x.x > 0.0) == positive);
+                if in_half && x.norm() <= SPHERE_RADIUS - particle_radius {
+                    particles.push(x);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_uniform_particle_properties() {
+    let mut particles = Vec::new();
+    sample_sphere(PARTICLE_RADIUS, None, &mut particles);
+
+    let parameters = params(false);
+    let masses = vec![particle_mass(PARTICLE_RADIUS); particles.len()];
+    let radii = vec![parameters.compact_support_radius; particles.len()];
+
+    let reference = reconstruct_surface::<i64, _>(&particles, &parameters).unwrap();
+    let reconstruction = reconstruct_surface_with_properties::<i64, _>(
+        &particles,
+        ParticleProperties::uniform()
+            .with_masses(&masses)
+            .with_compact_support_radii(&radii),
+        &parameters,
+    )
+    .unwrap();
+
+    assert_eq!(
+        reference.mesh().triangles.len(),
+        reconstruction.mesh().triangles.len()
+    );
+    for (a, b) in reference
+        .particle_densities()
+        .unwrap()
+        .iter()
+        .zip(reconstruction.particle_densities().unwrap())
+    {
+        assert!((a - b).abs() < 1e-10 * a);
+    }
+}
+
+#[test]
+fn test_mixed_particle_sizes() {
+    // One half of the sphere is sampled with particles of half the radius
+    let fine_radius = 0.5 * PARTICLE_RADIUS;
+    let mut particles = Vec::new();
+    sample_sphere(PARTICLE_RADIUS, Some(false), &mut particles);
+    let num_coarse = particles.len();
+    sample_sphere(fine_radius, Some(true), &mut particles);
+    let num_fine = particles.len() - num_coarse;
+
+    let masses = [
+        vec![particle_mass(PARTICLE_RADIUS); num_coarse],
+        vec![particle_mass(fine_radius); num_fine],
+    ]
+    .concat();
+    let radii = [
+        vec![4.0 * PARTICLE_RADIUS; num_coarse],
+        vec![4.0 * fine_radius; num_fine],
+    ]
+    .concat();
+    let properties = ParticleProperties::uniform()
+        .with_masses(&masses)
+        .with_compact_support_radii(&radii);
+
+    // Volumes of the sphere sampled only with particles of one size
+    let uniform_volume = |particle_radius: f64| {
+        let mut uniform_particles = Vec::new();
+        sample_sphere(particle_radius, None, &mut uniform_particles);
+        let mut parameters = params(false);
+        parameters.particle_radius = particle_radius;
+        parameters.compact_support_radius = 4.0 * particle_radius;
+        reconstruct_surface::<i64, _>(&uniform_particles, &parameters)
+            .unwrap()
+            .mesh()
+            .signed_volume()
+    };
+    let coarse_volume = uniform_volume(PARTICLE_RADIUS);
+    let fine_volume = uniform_volume(fine_radius);
+
+    let mut volumes = Vec::new();
+    for subdomain_grid in [false, true] {
+        let parameters = params(subdomain_grid);
+        let reconstruction =
+            reconstruct_surface_with_properties::<i64, _>(&particles, properties, &parameters)
+                .unwrap();
+        let mesh = reconstruction.mesh();
+
+        if let Err(err) = check_mesh_consistency(reconstruction.grid(), mesh) {
+            panic!("Mesh of mixed particle sizes is not closed: {}", err);
+        }
+
+        // The particle densities of both halves are close to the rest density
+        let densities = reconstruction
+            .particle_densities()
+            .map_or(&[][..], Vec::as_slice);
+        for (i, &density) in densities.iter().enumerate() {
+            let x = particles[i];
+            if x.norm() < SPHERE_RADIUS - 4.0 * PARTICLE_RADIUS && x.x.abs() > 4.0 * PARTICLE_RADIUS
+            {
+                assert!(
+                    (density / (0.524 * REST_DENSITY) - 1.0).abs() < 0.1,
+                    "unexpected density {} of particle {}",
+                    density,
+                    i
+                );
+            }
+        }
+
+        let volume = mesh.signed_volume();
+        assert!(volume > coarse_volume && volume < fine_volume);
+        volumes.push(volume);
+    }
+
+    // The global reconstruction and the reconstruction with subdomain grid result in the same surface
+    assert!((volumes[0] - volumes[1]).abs() < 1e-2 * volumes[0]);
+}
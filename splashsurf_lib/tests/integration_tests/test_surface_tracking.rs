@@ -0,0 +1,194 @@
+use nalgebra::Vector3;
+use splashsurf_lib::marching_cubes::check_mesh_consistency;
+use splashsurf_lib::surface_tracking::{SurfaceTracker, SurfaceTrackingParameters};
+use splashsurf_lib::{
+    reconstruct_surface, Aabb3d, IsoSurfaceExtraction, KernelType, Parameters, ParticleProperties,
+};
+
+const PARTICLE_RADIUS: f64 = 0.025;
+const TIME_STEP: f64 = 0.01;
+
+fn params() -> Parameters<f64> {
+    Parameters {
+        particle_radius: PARTICLE_RADIUS,
+        rest_density: 1000.0,
+        compact_support_radius: 4.0 * PARTICLE_RADIUS,
+        cube_size: 0.5 * PARTICLE_RADIUS,
+        iso_surface_threshold: 0.6,
+        // A fixed domain ensures that all frames use the same background grid
+        domain_aabb: Some(Aabb3d::new(
+            Vector3::new(-0.5, -0.5, -0.5),
+            Vector3::new(0.5, 0.5, 0.5),
+        )),
+        enable_multi_threading: true,
+        subdomain_num_cubes_per_dim: None,
+        spatial_decomposition: None,
+        iso_surface_extraction: IsoSurfaceExtraction::MarchingCubes,
+        adaptive_resolution: None,
+        kernel: KernelType::CubicSpline,
+        anisotropy: None,
+        boundary: None,
+        boundary_particles: None,
+        cap_domain_boundary: false,
+    }
+}
+
+/// Samples a block of fluid with a regular lattice of particles centered at the given position
+fn fluid_block(center: Vector3<f64>) -> Vec<Vector3<f64>> {
+    let spacing = 2.0 * PARTICLE_RADIUS;
+    let mut particles = Vec::new();
+    for i in -3..3 {
+        for j in -2..2 {
+            for k in -2..2 {
+                particles.push(
+                    center + Vector3::new(i as f64 + 0.5, j as f64 + 0.5, k as f64 + 0.5) * spacing,
+                );
+            }
+        }
+    }
+    particles
+}
+
+#[test]
+fn test_surface_tracking_translation() {
+    let parameters = params();
+    let velocity = Vector3::new(1.0, 0.5, 0.0);
+    let mut tracker = SurfaceTracker::new(SurfaceTrackingParameters::with_time_step(TIME_STEP));
+
+    let mut first_mesh = None;
+    for frame in 0..3 {
+        let particles = fluid_block(velocity * (frame as f64 * TIME_STEP));
+        let velocities = vec![velocity; particles.len()];
+        let reconstruction = reconstruct_surface::<i64, _>(&particles, &parameters).unwrap();
+        let tracked = tracker
+            .track_frame(
+                &reconstruction,
+                &particles,
+                &velocities,
+                ParticleProperties::uniform(),
+                &parameters,
+            )
+            .unwrap();
+
+        let mesh = tracked.mesh();
+        if let Err(err) = check_mesh_consistency(reconstruction.grid(), mesh) {
+            panic!("Tracked mesh of frame {} is not closed: {}", frame, err);
+        }
+        for v in tracked.vertex_velocities() {
+            assert!((v - velocity).norm() < 1e-9);
+        }
+
+        match &first_mesh {
+            None => {
+                assert_eq!(tracked.num_tracked_vertices(), 0);
+                first_mesh = Some(mesh.clone());
+            }
+            Some(first_mesh) => {
+                // All vertices are tracked and translated, apart from the projection of the marching cubes vertices onto the exact iso-surface
+                assert_eq!(tracked.num_remeshed_components(), 0);
+                assert_eq!(tracked.num_tracked_vertices(), first_mesh.vertices.len());
+                assert_eq!(mesh.triangles, first_mesh.triangles);
+                for (i, (v, v0)) in mesh
+                    .vertices
+                    .iter()
+                    .zip(first_mesh.vertices.iter())
+                    .enumerate()
+                {
+                    assert_eq!(tracked.previous_vertices()[i], Some(i));
+                    let displacement = v - v0 - velocity * (frame as f64 * TIME_STEP);
+                    assert!(displacement.norm() < 0.1 * parameters.cube_size);
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_surface_tracking_rotation() {
+    let parameters = params();
+    let angular_velocity = Vector3::new(0.0, 0.0, 2.0);
+    let mut tracker = SurfaceTracker::new(SurfaceTrackingParameters::with_time_step(TIME_STEP));
+
+    let initial_particles = fluid_block(Vector3::zeros());
+    let mut num_vertices = 0;
+    for frame in 0..4 {
+        let rotation = nalgebra::Rotation3::new(angular_velocity * (frame as f64 * TIME_STEP));
+        let particles = initial_particles
+            .iter()
+            .map(|x| rotation * x)
+            .collect::<Vec<_>>();
+        let velocities = particles
+            .iter()
+            .map(|x| angular_velocity.cross(x))
+            .collect::<Vec<_>>();
+        let reconstruction = reconstruct_surface::<i64, _>(&particles, &parameters).unwrap();
+        let tracked = tracker
+            .track_frame(
+                &reconstruction,
+                &particles,
+                &velocities,
+                ParticleProperties::uniform(),
+                &parameters,
+            )
+            .unwrap();
+
+        if let Err(err) = check_mesh_consistency(reconstruction.grid(), tracked.mesh()) {
+            panic!("Tracked mesh of frame {} is not closed: {}", frame, err);
+        }
+
+        if frame == 0 {
+            num_vertices = tracked.mesh().vertices.len();
+        } else {
+            // The rotated block keeps its vertices although it is not aligned with the grid anymore
+            assert_eq!(tracked.num_remeshed_components(), 0);
+            assert_eq!(tracked.num_tracked_vertices(), num_vertices);
+
+            let volume = tracked.mesh().signed_volume();
+            let reconstructed_volume = reconstruction.mesh().signed_volume();
+            assert!(((volume - reconstructed_volume) / reconstructed_volume).abs() < 0.05);
+        }
+    }
+}
+
+#[test]
+fn test_surface_tracking_topology_change() {
+    let parameters = params();
+    let mut tracker = SurfaceTracker::new(SurfaceTrackingParameters::with_time_step(TIME_STEP));
+
+    // A single block splits into two separate blocks
+    let frames = [
+        fluid_block(Vector3::zeros()),
+        [
+            fluid_block(Vector3::new(-0.2, 0.0, 0.0)),
+            fluid_block(Vector3::new(0.2, 0.0, 0.0)),
+        ]
+        .concat(),
+    ];
+
+    for (frame, particles) in frames.iter().enumerate() {
+        let velocities = vec![Vector3::zeros(); particles.len()];
+        let reconstruction = reconstruct_surface::<i64, _>(particles, &parameters).unwrap();
+        let tracked = tracker
+            .track_frame(
+                &reconstruction,
+                particles,
+                &velocities,
+                ParticleProperties::uniform(),
+                &parameters,
+            )
+            .unwrap();
+
+        if frame == 1 {
+            // Both blocks are re-meshed, i.e. the tracked mesh is the reconstructed mesh
+            assert_eq!(tracked.num_remeshed_components(), 2);
+            assert_eq!(tracked.num_tracked_vertices(), 0);
+            assert_eq!(
+                tracked.mesh().vertices.len(),
+                reconstruction.mesh().vertices.len()
+            );
+            if let Err(err) = check_mesh_consistency(reconstruction.grid(), tracked.mesh()) {
+                panic!("Re-meshed surface is not closed: {}", err);
+            }
+        }
+    }
+}